@@ -7,6 +7,7 @@ use compositor_task::{Msg, CompositorTask, Exit, ChangeReadyState, SetIds, Layer
 use compositor_task::{GetGraphicsMetadata, CreateOrUpdateRootLayer, CreateOrUpdateDescendantLayer};
 use compositor_task::{SetLayerOrigin, Paint, ScrollFragmentPoint, LoadComplete};
 use compositor_task::{ShutdownComplete, ChangeRenderState, RenderMsgDiscarded};
+use compositor_task::PromptForCredentials;
 use constellation::SendableFrameTree;
 use events;
 use events::ScrollPositionChanged;
@@ -341,6 +342,10 @@ impl IOCompositor {
                     self.got_load_complete_message = true;
                 }
 
+                (Ok(PromptForCredentials(request, response_chan)), NotShuttingDown) => {
+                    let _ = response_chan.send_opt(self.window.prompt_for_credentials(&request));
+                }
+
                 // When we are shutting_down, we need to avoid performing operations
                 // such as Paint that may crash because we have begun tearing down
                 // the rest of our resources.
@@ -446,6 +451,7 @@ impl IOCompositor {
             rect: Rect::zero(),
             background_color: azure_hl::Color::new(0., 0., 0., 0.),
             scroll_policy: Scrollable,
+            opacity: 1.0,
         };
         let root_layer = CompositorData::new_layer(frame_tree.pipeline.clone(),
                                                    layer_properties,
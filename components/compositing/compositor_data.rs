@@ -67,7 +67,9 @@ impl CompositorData {
         };
 
         Rc::new(Layer::new(Rect::from_untyped(&layer_properties.rect),
-                           tile_size, new_compositor_data))
+                           tile_size,
+                           layer_properties.opacity,
+                           new_compositor_data))
     }
 
     pub fn update_layer_except_size(layer: Rc<Layer<CompositorData>>,
@@ -75,6 +77,7 @@ impl CompositorData {
         layer.extra_data.borrow_mut().epoch = layer_properties.epoch;
         layer.extra_data.borrow_mut().scroll_policy = layer_properties.scroll_policy;
         layer.extra_data.borrow_mut().background_color = layer_properties.background_color;
+        *layer.opacity.borrow_mut() = layer_properties.opacity;
         layer.contents_changed();
     }
 
@@ -19,6 +19,8 @@ use layers::layers::LayerBufferSet;
 use servo_msg::compositor_msg::{Epoch, LayerId, LayerMetadata, ReadyState};
 use servo_msg::compositor_msg::{RenderListener, RenderState, ScriptListener, ScrollPolicy};
 use servo_msg::constellation_msg::{ConstellationChan, PipelineId};
+use servo_net::http_auth::{AuthRequest, Credentials};
+use servo_net::resource_task::AuthPrompt;
 use servo_util::memory::MemoryProfilerChan;
 use servo_util::opts::Opts;
 use servo_util::time::TimeProfilerChan;
@@ -68,6 +70,7 @@ pub struct LayerProperties {
     pub rect: Rect<f32>,
     pub background_color: Color,
     pub scroll_policy: ScrollPolicy,
+    pub opacity: f32,
 }
 
 impl LayerProperties {
@@ -82,6 +85,7 @@ impl LayerProperties {
                               metadata.position.size.height as f32)),
             background_color: metadata.background_color,
             scroll_policy: metadata.scroll_policy,
+            opacity: metadata.opacity,
         }
     }
 }
@@ -129,6 +133,14 @@ impl RenderListener for CompositorChan {
     }
 }
 
+/// Implementation of the `AuthPrompt` interface, which asks the user through the window.
+impl AuthPrompt for CompositorChan {
+    fn prompt_for_credentials(&self, request: AuthRequest,
+                              response_chan: Sender<Option<Credentials>>) {
+        self.chan.send(PromptForCredentials(request, response_chan));
+    }
+}
+
 impl CompositorChan {
     pub fn new() -> (Receiver<Msg>, CompositorChan) {
         let (chan, port) = channel();
@@ -181,6 +193,8 @@ pub enum Msg {
     SetIds(SendableFrameTree, Sender<()>, ConstellationChan),
     /// The load of a page for a given URL has completed.
     LoadComplete(PipelineId, Url),
+    /// Asks the user for credentials for a server or proxy on behalf of the resource task.
+    PromptForCredentials(AuthRequest, Sender<Option<Credentials>>),
 }
 
 pub enum CompositorMode {
@@ -11,7 +11,7 @@ use gfx::render_task;
 use libc;
 use pipeline::{Pipeline, CompositionPipeline};
 use layout_traits::{LayoutControlChan, LayoutTaskFactory, ExitNowMsg};
-use script_traits::{ResizeMsg, ResizeInactiveMsg, ExitPipelineMsg};
+use script_traits::{ResizeMsg, ResizeInactiveMsg, ExitPipelineMsg, ReportCSPViolationsMsg};
 use script_traits::{ScriptControlChan, ScriptTaskFactory};
 use servo_msg::compositor_msg::LayerId;
 use servo_msg::constellation_msg::{ConstellationChan, ExitMsg, FailureMsg, Failure, FrameRectMsg};
@@ -20,7 +20,11 @@ use servo_msg::constellation_msg::{LoadCompleteMsg, LoadIframeUrlMsg, LoadUrlMsg
 use servo_msg::constellation_msg::{LoadData, NavigationType, PipelineId, RendererReadyMsg, ResizedWindowMsg};
 use servo_msg::constellation_msg::{SubpageId, WindowSizeData};
 use servo_msg::constellation_msg;
+use servo_net::csp::ContentSecurityPolicy;
+use servo_net::fetch::request::IFrame;
 use servo_net::image_cache_task::{ImageCacheTask, ImageCacheTaskClient};
+use servo_net::mixed_content;
+use servo_net::referrer_policy::ReferrerPolicy;
 use gfx::font_cache_task::FontCacheTask;
 use servo_net::resource_task::ResourceTask;
 use servo_net::resource_task;
@@ -357,9 +361,11 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
                 debug!("constellation got frame rect message");
                 self.handle_frame_rect_msg(pipeline_id, subpage_id, Rect::from_untyped(&rect));
             }
-            LoadIframeUrlMsg(url, source_pipeline_id, subpage_id, sandbox) => {
+            LoadIframeUrlMsg(url, source_pipeline_id, subpage_id, sandbox, policy,
+                             referrer_policy) => {
                 debug!("constellation got iframe URL load message");
-                self.handle_load_iframe_url_msg(url, source_pipeline_id, subpage_id, sandbox);
+                self.handle_load_iframe_url_msg(url, source_pipeline_id, subpage_id, sandbox,
+                                                policy, referrer_policy);
             }
             // Load a new page, usually -- but not always -- from a mouse click or typed url
             // If there is already a pending page (self.pending_frames), it will not be overridden;
@@ -550,7 +556,9 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
                                   url: Url,
                                   source_pipeline_id: PipelineId,
                                   subpage_id: SubpageId,
-                                  sandbox: IFrameSandboxState) {
+                                  sandbox: IFrameSandboxState,
+                                  policy: ContentSecurityPolicy,
+                                  referrer_policy: Option<ReferrerPolicy>) {
         // A message from the script associated with pipeline_id that it has
         // parsed an iframe during html parsing. This iframe will result in a
         // new pipeline being spawned and a frame tree being added to pipeline_id's
@@ -565,16 +573,35 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
                    impossible.");
         }
 
-        let next_pipeline_id = self.get_next_pipeline_id();
-
-        // Compare the pipeline's url to the new url. If the origin is the same,
-        // then reuse the script task in creating the new pipeline
         let source_pipeline = self.pipelines.find(&source_pipeline_id).expect("Constellation:
             source Id of LoadIframeUrlMsg does have an associated pipeline in
             constellation. This should be impossible.").clone();
 
+        // The frame-src directive of the parent document decides whether the frame is loaded
+        // at all. Violations are reported to the parent's console.
+        let result = policy.check_load(IFrame, &url);
+        if !result.violations.is_empty() {
+            let ScriptControlChan(ref script_chan) = source_pipeline.script_chan;
+            script_chan.send(ReportCSPViolationsMsg(source_pipeline_id, result.violations));
+        }
+        if !result.allowed {
+            debug!("Constellation: Content Security Policy blocked iframe at {:?}", url);
+            return
+        }
+
         let source_url = source_pipeline.load_data.url.clone();
 
+        // Insecure frames aren't loaded into secure documents.
+        match mixed_content::check(&source_url, &url, IFrame) {
+            Ok(()) => {}
+            Err(_) => return,
+        }
+
+        let next_pipeline_id = self.get_next_pipeline_id();
+
+        // Compare the pipeline's url to the new url. If the origin is the same,
+        // then reuse the script task in creating the new pipeline
+
         let same_script = (source_url.host() == url.host() &&
                            source_url.port() == url.port()) && sandbox == IFrameUnsandboxed;
         // FIXME(tkuehn): Need to follow the standardized spec for checking same-origin
@@ -587,11 +614,16 @@ impl<LTF: LayoutTaskFactory, STF: ScriptTaskFactory> Constellation<LTF, STF> {
             None
         };
 
+        // The parent document is the frame's referrer.
+        let mut load_data = LoadData::new(url);
+        load_data.referrer = Some(policy.document_url().clone());
+        load_data.referrer_policy = referrer_policy;
+
         let pipeline = self.new_pipeline(
             next_pipeline_id,
             Some(subpage_id),
             new_pipeline,
-            LoadData::new(url)
+            load_data
         );
 
         let rect = self.pending_sizes.pop(&(source_pipeline_id, subpage_id));
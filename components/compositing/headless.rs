@@ -5,6 +5,7 @@
 use compositor_task::{Msg, Exit, ChangeReadyState, SetIds};
 use compositor_task::{GetGraphicsMetadata, CreateOrUpdateRootLayer, CreateOrUpdateDescendantLayer};
 use compositor_task::{SetLayerOrigin, Paint, ScrollFragmentPoint, LoadComplete};
+use compositor_task::PromptForCredentials;
 use compositor_task::{ShutdownComplete, ChangeRenderState, RenderMsgDiscarded};
 
 use geom::scale_factor::ScaleFactor;
@@ -84,6 +85,11 @@ impl NullCompositor {
                     response_chan.send(());
                 }
 
+                // There is nobody to ask.
+                PromptForCredentials(_, response_chan) => {
+                    let _ = response_chan.send_opt(None);
+                }
+
                 // Explicitly list ignored messages so that when we add a new one,
                 // we'll notice and think about whether it needs a response, like
                 // SetIds.
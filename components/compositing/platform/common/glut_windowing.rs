@@ -20,6 +20,7 @@ use geom::size::TypedSize2D;
 use layers::geometry::DevicePixel;
 use servo_msg::compositor_msg::{IdleRenderState, RenderState, RenderingRenderState};
 use servo_msg::compositor_msg::{FinishedLoading, Blank, ReadyState};
+use servo_net::http_auth::{AuthRequest, Credentials};
 use servo_util::geometry::ScreenPx;
 
 use glut::glut::{ACTIVE_SHIFT, DOUBLE, WindowHeight};
@@ -196,6 +197,20 @@ impl WindowMethods<Application> for Window {
         //FIXME: Do nothing in GLUT now.
         ScaleFactor(1.0)
     }
+
+    fn prompt_for_credentials(&self, request: &AuthRequest) -> Option<Credentials> {
+        let server = if request.is_proxy { "The proxy" } else { "The server" };
+        let message = format!("{:s} {:s} requires a username and password for \"{:s}\". \
+                               Username:",
+                              server, request.url.serialize_host().unwrap_or(String::new()),
+                              request.realm);
+        let username = prompt(message.as_slice());
+        if username.is_empty() {
+            return None
+        }
+        let password = prompt("Password:");
+        Some(Credentials { username: username, password: password })
+    }
 }
 
 impl Window {
@@ -277,10 +292,7 @@ impl Window {
 
     /// Helper function to pop up an alert box prompting the user to load a URL.
     fn load_url(&self) {
-        let mut alert: Alert = AlertMethods::new("Navigate to:");
-        alert.add_prompt();
-        alert.run();
-        let value = alert.prompt_value();
+        let value = prompt("Navigate to:");
         if "" == value.as_slice() {    // To avoid crashing on Linux.
             self.event_queue.borrow_mut().push(LoadUrlWindowEvent("http://purple.com/".to_string()))
         } else {
@@ -302,3 +314,11 @@ fn drop_local_window() {
 fn local_window() -> Rc<Window> {
     TLS_KEY.get().unwrap().clone()
 }
+
+/// Pops up an alert box with a text field, and returns what the user entered.
+fn prompt(message: &str) -> String {
+    let mut alert: Alert = AlertMethods::new(message);
+    alert.add_prompt();
+    alert.run();
+    alert.prompt_value()
+}
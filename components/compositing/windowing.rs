@@ -9,6 +9,7 @@ use geom::scale_factor::ScaleFactor;
 use geom::size::TypedSize2D;
 use layers::geometry::DevicePixel;
 use servo_msg::compositor_msg::{ReadyState, RenderState};
+use servo_net::http_auth::{AuthRequest, Credentials};
 use servo_util::geometry::ScreenPx;
 use std::rc::Rc;
 
@@ -79,5 +80,9 @@ pub trait WindowMethods<A> {
 
     /// Returns the hidpi factor of the monitor.
     fn hidpi_factor(&self) -> ScaleFactor<ScreenPx, DevicePixel, f32>;
+
+    /// Asks the user for a user name and password for a server or proxy. Returns `None` if the
+    /// user declines.
+    fn prompt_for_credentials(&self, request: &AuthRequest) -> Option<Credentials>;
 }
 
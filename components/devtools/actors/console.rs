@@ -10,13 +10,15 @@ use actor::{Actor, ActorRegistry};
 use protocol::JsonPacketSender;
 
 use devtools_traits::{EvaluateJS, NullValue, VoidValue, NumberValue, StringValue, BooleanValue};
-use devtools_traits::{ActorValue, DevtoolScriptControlMsg};
+use devtools_traits::{ActorValue, DevtoolScriptControlMsg, PageError};
 use servo_msg::constellation_msg::PipelineId;
 
 use collections::TreeMap;
 use serialize::json;
 use serialize::json::ToJson;
+use std::cell::RefCell;
 use std::io::TcpStream;
+use time;
 
 #[deriving(Encodable)]
 struct StartedListenersTraits {
@@ -53,6 +55,13 @@ struct PageErrorMessage {
     private: bool,
 }
 
+#[deriving(Encodable)]
+pub struct PageErrorPacket {
+    from: String,
+    __type__: String,
+    pageError: PageErrorMessage,
+}
+
 #[deriving(Encodable)]
 struct LogMessage {
     _type: String, //FIXME: should this be __type__ instead?
@@ -101,6 +110,41 @@ pub struct ConsoleActor {
     pub name: String,
     pub pipeline: PipelineId,
     pub script_chan: Sender<DevtoolScriptControlMsg>,
+    /// The errors reported about the page so far, with the time they were reported at.
+    pub page_errors: RefCell<Vec<(PageError, u64)>>,
+}
+
+impl ConsoleActor {
+    fn page_error_message(error: &PageError, timestamp: u64) -> PageErrorMessage {
+        PageErrorMessage {
+            _type: "PageError".to_string(),
+            errorMessage: error.message.clone(),
+            sourceName: error.source_name.clone(),
+            lineText: "".to_string(),
+            lineNumber: 0,
+            columnNumber: 0,
+            category: error.category.clone(),
+            timeStamp: timestamp as uint,
+            warning: error.warning,
+            error: !error.warning,
+            exception: false,
+            strict: false,
+            private: false,
+        }
+    }
+
+    /// Remember an error about the page, and return the packet that announces it to clients.
+    pub fn page_error(&self, error: PageError) -> PageErrorPacket {
+        let now = time::get_time();
+        let timestamp = now.sec as u64 * 1000 + now.nsec as u64 / 1000000;
+        let msg = PageErrorPacket {
+            from: self.name(),
+            __type__: "pageError".to_string(),
+            pageError: ConsoleActor::page_error_message(&error, timestamp),
+        };
+        self.page_errors.borrow_mut().push((error, timestamp));
+        msg
+    }
 }
 
 impl Actor for ConsoleActor {
@@ -116,7 +160,7 @@ impl Actor for ConsoleActor {
         match msg_type.as_slice() {
             "getCachedMessages" => {
                 let types = msg.find(&"messageTypes".to_string()).unwrap().as_list().unwrap();
-                let /*mut*/ messages = vec!();
+                let mut messages = vec!();
                 for msg_type in types.iter() {
                     let msg_type = msg_type.as_string().unwrap();
                     match msg_type.as_slice() {
@@ -127,23 +171,10 @@ impl Actor for ConsoleActor {
                         "PageError" => {
                             //TODO: make script error reporter pass all reported errors
                             //      to devtools and cache them for returning here.
-
-                            /*let message = PageErrorMessage {
-                                _type: msg_type.to_string(),
-                                sourceName: "".to_string(),
-                                lineText: "".to_string(),
-                                lineNumber: 0,
-                                columnNumber: 0,
-                                category: "".to_string(),
-                                warning: false,
-                                error: true,
-                                exception: false,
-                                strict: false,
-                                private: false,
-                                timeStamp: 0,
-                                errorMessage: "page error test".to_string(),
-                            };
-                            messages.push(json::from_str(json::encode(&message).as_slice()).unwrap().as_object().unwrap().clone());*/
+                            for &(ref error, timestamp) in self.page_errors.borrow().iter() {
+                                let message = ConsoleActor::page_error_message(error, timestamp);
+                                messages.push(json::from_str(json::encode(&message).as_slice()).unwrap().as_object().unwrap().clone());
+                            }
                         }
 
                         "LogMessage" => {
@@ -174,7 +205,8 @@ impl Actor for ConsoleActor {
                     from: self.name(),
                     nativeConsoleAPI: true,
                     startedListeners:
-                        vec!("PageError".to_string(), "ConsoleAPI".to_string()),
+                        vec!("PageError".to_string(), "ConsoleAPI".to_string(),
+                             "NetworkActivity".to_string()),
                     traits: StartedListenersTraits {
                         customNetworkRequest: true,
                     }
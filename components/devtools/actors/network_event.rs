@@ -0,0 +1,352 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/// Liberally derived from the [Firefox JS implementation](http://mxr.mozilla.org/mozilla-central/source/toolkit/devtools/server/actors/webconsole.js).
+/// A single request made by the resource task, as shown by the network monitor. The actor is
+/// announced to clients with a `networkEvent` packet from a console actor, and is then updated
+/// with `networkEventUpdate` packets as the load progresses.
+
+use actor::{Actor, ActorRegistry};
+use protocol::JsonPacketSender;
+
+use servo_net::network_events::{NetworkRequest, NetworkResponse};
+
+use serialize::json;
+use std::io::TcpStream;
+use time;
+
+#[deriving(Encodable)]
+pub struct EventActor {
+    actor: String,
+    startedDateTime: String,
+    url: String,
+    method: String,
+    isXHR: bool,
+}
+
+#[deriving(Encodable)]
+pub struct NetworkEventPacket {
+    from: String,
+    __type__: String,
+    eventActor: EventActor,
+}
+
+#[deriving(Encodable)]
+pub struct ResponseStartMsg {
+    httpVersion: String,
+    remoteAddress: String,
+    remotePort: uint,
+    status: String,
+    statusText: String,
+    headersSize: uint,
+    discardResponseBody: bool,
+}
+
+#[deriving(Encodable)]
+pub struct RequestHeadersUpdateMsg {
+    from: String,
+    __type__: String,
+    updateType: String,
+    headers: uint,
+    headersSize: uint,
+}
+
+#[deriving(Encodable)]
+pub struct ResponseStartUpdateMsg {
+    from: String,
+    __type__: String,
+    updateType: String,
+    response: ResponseStartMsg,
+}
+
+#[deriving(Encodable)]
+pub struct EventTimingsUpdateMsg {
+    from: String,
+    __type__: String,
+    updateType: String,
+    totalTime: f64,
+}
+
+#[deriving(Encodable)]
+pub struct ResponseContentUpdateMsg {
+    from: String,
+    __type__: String,
+    updateType: String,
+    mimeType: String,
+    contentSize: uint,
+    transferredSize: uint,
+    discardResponseBody: bool,
+}
+
+#[deriving(Encodable)]
+struct Header {
+    name: String,
+    value: String,
+}
+
+#[deriving(Encodable)]
+struct GetHeadersReply {
+    from: String,
+    headers: Vec<Header>,
+    headersSize: uint,
+    rawHeaders: String,
+}
+
+#[deriving(Encodable)]
+struct GetCookiesReply {
+    from: String,
+    cookies: Vec<Header>,
+}
+
+#[deriving(Encodable)]
+struct PostData {
+    text: String,
+}
+
+#[deriving(Encodable)]
+struct GetPostDataReply {
+    from: String,
+    postData: PostData,
+    postDataDiscarded: bool,
+}
+
+#[deriving(Encodable)]
+struct Content {
+    mimeType: String,
+    size: uint,
+    text: String,
+}
+
+#[deriving(Encodable)]
+struct GetContentReply {
+    from: String,
+    content: Content,
+    contentDiscarded: bool,
+}
+
+#[deriving(Encodable)]
+struct Timings {
+    blocked: f64,
+    dns: f64,
+    connect: f64,
+    send: f64,
+    wait: f64,
+    receive: f64,
+}
+
+#[deriving(Encodable)]
+struct GetTimingsReply {
+    from: String,
+    timings: Timings,
+    totalTime: f64,
+}
+
+pub struct NetworkEventActor {
+    pub name: String,
+    pub request: NetworkRequest,
+    pub response: Option<NetworkResponse>,
+    /// The number of bytes of the response body received.
+    pub size: uint,
+    /// When the request started, when its response started and when it finished, as given by
+    /// `time::precise_time_ns`.
+    pub start: u64,
+    pub response_start: Option<u64>,
+    pub end: Option<u64>,
+}
+
+fn headers(headers: &Vec<(String, String)>) -> Vec<Header> {
+    headers.iter().map(|&(ref name, ref value)| {
+        Header {
+            name: name.clone(),
+            value: value.clone(),
+        }
+    }).collect()
+}
+
+fn raw_headers(headers: &Vec<(String, String)>) -> String {
+    headers.iter().map(|&(ref name, ref value)| {
+        format!("{:s}: {:s}\r\n", *name, *value)
+    }).collect::<Vec<String>>().concat()
+}
+
+fn milliseconds(from: u64, to: u64) -> f64 {
+    (to - from) as f64 / 1000000.0
+}
+
+impl Actor for NetworkEventActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(&self,
+                      _registry: &ActorRegistry,
+                      msg_type: &String,
+                      _msg: &json::JsonObject,
+                      stream: &mut TcpStream) -> bool {
+        match msg_type.as_slice() {
+            "getRequestHeaders" => {
+                let raw = raw_headers(&self.request.headers);
+                let msg = GetHeadersReply {
+                    from: self.name(),
+                    headers: headers(&self.request.headers),
+                    headersSize: raw.len(),
+                    rawHeaders: raw,
+                };
+                stream.write_json_packet(&msg);
+                true
+            }
+
+            "getResponseHeaders" => {
+                let response_headers = self.response.as_ref().map_or(vec!(), |response| {
+                    response.headers.clone()
+                });
+                let raw = raw_headers(&response_headers);
+                let msg = GetHeadersReply {
+                    from: self.name(),
+                    headers: headers(&response_headers),
+                    headersSize: raw.len(),
+                    rawHeaders: raw,
+                };
+                stream.write_json_packet(&msg);
+                true
+            }
+
+            //TODO: report the cookies sent and stored by the HTTP loader.
+            "getRequestCookies" | "getResponseCookies" => {
+                let msg = GetCookiesReply {
+                    from: self.name(),
+                    cookies: vec!(),
+                };
+                stream.write_json_packet(&msg);
+                true
+            }
+
+            "getRequestPostData" => {
+                let msg = GetPostDataReply {
+                    from: self.name(),
+                    postData: PostData {
+                        text: String::new(),
+                    },
+                    postDataDiscarded: self.request.body_size > 0,
+                };
+                stream.write_json_packet(&msg);
+                true
+            }
+
+            // Response bodies aren't kept.
+            "getResponseContent" => {
+                let msg = GetContentReply {
+                    from: self.name(),
+                    content: Content {
+                        mimeType: self.mime_type(),
+                        size: self.size,
+                        text: String::new(),
+                    },
+                    contentDiscarded: true,
+                };
+                stream.write_json_packet(&msg);
+                true
+            }
+
+            "getEventTimings" => {
+                let end = self.end.unwrap_or(time::precise_time_ns());
+                let response_start = self.response_start.unwrap_or(end);
+                let msg = GetTimingsReply {
+                    from: self.name(),
+                    timings: Timings {
+                        blocked: 0.0,
+                        dns: 0.0,
+                        connect: 0.0,
+                        send: 0.0,
+                        wait: milliseconds(self.start, response_start),
+                        receive: milliseconds(response_start, end),
+                    },
+                    totalTime: milliseconds(self.start, end),
+                };
+                stream.write_json_packet(&msg);
+                true
+            }
+
+            _ => false
+        }
+    }
+}
+
+impl NetworkEventActor {
+    fn mime_type(&self) -> String {
+        match self.response {
+            Some(NetworkResponse { content_type: Some((ref type_, ref subtype)), .. }) => {
+                format!("{:s}/{:s}", *type_, *subtype)
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// The packet announcing this actor to clients, sent from the given console actor.
+    pub fn network_event(&self, console: String) -> NetworkEventPacket {
+        NetworkEventPacket {
+            from: console,
+            __type__: "networkEvent".to_string(),
+            eventActor: EventActor {
+                actor: self.name(),
+                startedDateTime: time::at_utc(self.request.started).rfc3339(),
+                url: self.request.url.serialize(),
+                method: format!("{}", self.request.method),
+                isXHR: false,
+            },
+        }
+    }
+
+    pub fn request_headers_update(&self) -> RequestHeadersUpdateMsg {
+        RequestHeadersUpdateMsg {
+            from: self.name(),
+            __type__: "networkEventUpdate".to_string(),
+            updateType: "requestHeaders".to_string(),
+            headers: self.request.headers.len(),
+            headersSize: raw_headers(&self.request.headers).len(),
+        }
+    }
+
+    pub fn response_start_update(&self) -> ResponseStartUpdateMsg {
+        let (status, headers_size) = match self.response {
+            Some(ref response) => (response.status, raw_headers(&response.headers).len()),
+            None => (0, 0),
+        };
+        ResponseStartUpdateMsg {
+            from: self.name(),
+            __type__: "networkEventUpdate".to_string(),
+            updateType: "responseStart".to_string(),
+            response: ResponseStartMsg {
+                httpVersion: "HTTP/1.1".to_string(),
+                remoteAddress: String::new(),
+                remotePort: 0,
+                status: status.to_string(),
+                statusText: String::new(),
+                headersSize: headers_size,
+                discardResponseBody: true,
+            },
+        }
+    }
+
+    pub fn event_timings_update(&self) -> EventTimingsUpdateMsg {
+        EventTimingsUpdateMsg {
+            from: self.name(),
+            __type__: "networkEventUpdate".to_string(),
+            updateType: "eventTimings".to_string(),
+            totalTime: milliseconds(self.start, self.end.unwrap_or(self.start)),
+        }
+    }
+
+    pub fn response_content_update(&self) -> ResponseContentUpdateMsg {
+        ResponseContentUpdateMsg {
+            from: self.name(),
+            __type__: "networkEventUpdate".to_string(),
+            updateType: "responseContent".to_string(),
+            mimeType: self.mime_type(),
+            contentSize: self.size,
+            transferredSize: self.size,
+            discardResponseBody: true,
+        }
+    }
+}
@@ -26,26 +26,35 @@ extern crate devtools_traits;
 extern crate debug;
 extern crate serialize;
 extern crate sync;
+extern crate time;
 extern crate "msg" as servo_msg;
+extern crate "net" as servo_net;
 
 use actor::{Actor, ActorRegistry};
 use actors::console::ConsoleActor;
 use actors::inspector::InspectorActor;
+use actors::network_event::NetworkEventActor;
 use actors::root::RootActor;
 use actors::tab::TabActor;
 use protocol::JsonPacketSender;
 
 use devtools_traits::{ServerExitMsg, DevtoolsControlMsg, NewGlobal, DevtoolScriptControlMsg};
+use devtools_traits::{NetworkEventMsg, ReportPageErrorMsg, PageError};
 use servo_msg::constellation_msg::PipelineId;
+use servo_net::network_events::{NetworkEvent, NetworkRequest, NetworkResponse, RequestStarted};
+use servo_net::network_events::{Redirected, ResponseStarted, FirstByte, LoadCompleted};
+use servo_net::resource_task::{LoadGroupId, LoadId};
 
 use std::cell::RefCell;
+use std::collections::hashmap::HashMap;
 use std::comm;
 use std::comm::{Disconnected, Empty};
 use std::io::{TcpListener, TcpStream};
 use std::io::{Acceptor, Listener, EndOfFile, TimedOut};
 use std::num;
 use std::task::TaskBuilder;
-use serialize::json;
+use serialize::{json, Encodable};
+use std::io::IoError;
 use sync::{Arc, Mutex};
 
 mod actor;
@@ -53,6 +62,7 @@ mod actor;
 mod actors {
     pub mod console;
     pub mod inspector;
+    pub mod network_event;
     pub mod root;
     pub mod tab;
 }
@@ -144,6 +154,7 @@ fn run_server(receiver: Receiver<DevtoolsControlMsg>, port: u16) {
                 name: actors.new_name("console"),
                 script_chan: sender.clone(),
                 pipeline: pipeline,
+                page_errors: RefCell::new(vec!()),
             };
             let inspector = InspectorActor {
                 name: actors.new_name("inspector"),
@@ -172,6 +183,124 @@ fn run_server(receiver: Receiver<DevtoolsControlMsg>, port: u16) {
         actors.register(box inspector);
     }
 
+    /// Write a packet to every connected client, forgetting the ones that have disconnected.
+    fn broadcast<'a, T: Encodable<json::Encoder<'a>, IoError>>(clients: &mut Vec<TcpStream>,
+                                                                msg: &T) {
+        clients.retain(|client| client.clone().try_write_json_packet(msg).is_ok());
+    }
+
+    /// The console actors of the tabs that a load made on behalf of `group` belongs to: every
+    /// tab, if the load isn't part of a group.
+    fn console_actors(actors: &ActorRegistry, group: Option<LoadGroupId>) -> Vec<String> {
+        let root = actors.find::<RootActor>("root");
+        root.tabs.iter().map(|tab| {
+            actors.find::<TabActor>(tab.as_slice()).console.clone()
+        }).filter(|console| {
+            match group {
+                Some(LoadGroupId(id)) => {
+                    actors.find::<ConsoleActor>(console.as_slice()).pipeline == PipelineId(id)
+                }
+                None => true,
+            }
+        }).collect()
+    }
+
+    /// Show an error about a page in the consoles of its tabs, and remember it for the clients
+    /// that connect later.
+    fn handle_page_error(actors: &ActorRegistry,
+                         clients: &mut Vec<TcpStream>,
+                         pipeline: PipelineId,
+                         error: PageError) {
+        let PipelineId(id) = pipeline;
+        for console in console_actors(actors, Some(LoadGroupId(id))).into_iter() {
+            let actor = actors.find::<ConsoleActor>(console.as_slice());
+            broadcast(clients, &actor.page_error(error.clone()));
+        }
+    }
+
+    // Each request made by the resource task gets an actor, which is announced to the clients
+    // and updated as the load progresses. The packets are written while the registry is
+    // locked, so that they aren't interleaved with replies to the clients' requests.
+    fn handle_network_event(actors: &mut ActorRegistry,
+                            clients: &mut Vec<TcpStream>,
+                            loads: &mut HashMap<LoadId, String>,
+                            event: NetworkEvent) {
+        let NetworkEvent { load, time: event_time, kind } = event;
+        match kind {
+            RequestStarted(request) => {
+                let actor = NetworkEventActor {
+                    name: actors.new_name("netEvent"),
+                    request: request,
+                    response: None,
+                    size: 0,
+                    start: event_time,
+                    response_start: None,
+                    end: None,
+                };
+                for console in console_actors(actors, actor.request.group).into_iter() {
+                    broadcast(clients, &actor.network_event(console));
+                }
+                broadcast(clients, &actor.request_headers_update());
+                loads.insert(load, actor.name());
+                actors.register(box actor);
+            }
+            Redirected(url, status, headers) => {
+                // The redirect ends the current request, and the loader makes a new one.
+                let request = match loads.find(&load) {
+                    Some(name) => {
+                        let actor = actors.find_mut::<NetworkEventActor>(name.as_slice());
+                        actor.response = Some(NetworkResponse {
+                            url: actor.request.url.clone(),
+                            status: status,
+                            headers: headers,
+                            content_type: None,
+                        });
+                        actor.response_start = Some(event_time);
+                        actor.end = Some(event_time);
+                        broadcast(clients, &actor.response_start_update());
+                        broadcast(clients, &actor.event_timings_update());
+                        NetworkRequest {
+                            url: url,
+                            body_size: 0,
+                            started: time::get_time(),
+                            .. actor.request.clone()
+                        }
+                    }
+                    None => return,
+                };
+                handle_network_event(actors, clients, loads, NetworkEvent {
+                    load: load,
+                    time: event_time,
+                    kind: RequestStarted(request),
+                });
+            }
+            ResponseStarted(response) => {
+                match loads.find(&load) {
+                    Some(name) => {
+                        let actor = actors.find_mut::<NetworkEventActor>(name.as_slice());
+                        actor.response = Some(response);
+                        actor.response_start = Some(event_time);
+                        broadcast(clients, &actor.response_start_update());
+                    }
+                    None => {}
+                }
+            }
+            FirstByte => {}
+            LoadCompleted(size, _) => {
+                match loads.pop(&load) {
+                    Some(name) => {
+                        let actor = actors.find_mut::<NetworkEventActor>(name.as_slice());
+                        actor.size = size;
+                        actor.end = Some(event_time);
+                        broadcast(clients, &actor.event_timings_update());
+                        broadcast(clients, &actor.response_content_update());
+                    }
+                    None => {}
+                }
+            }
+        }
+    }
+
     //TODO: figure out some system that allows us to watch for new connections,
     //      shut down existing ones at arbitrary times, and also watch for messages
     //      from multiple script tasks simultaneously. Polling for new connections
@@ -180,6 +309,11 @@ fn run_server(receiver: Receiver<DevtoolsControlMsg>, port: u16) {
 
     //TODO: make constellation send ServerExitMsg on shutdown.
 
+    // The connected clients, for sending them packets that aren't replies to their requests.
+    let mut clients = vec!();
+    // The actor for each load in progress.
+    let mut loads = HashMap::new();
+
     // accept connections and process them, spawning a new tasks for each one
     loop {
         match acceptor.accept() {
@@ -187,11 +321,19 @@ fn run_server(receiver: Receiver<DevtoolsControlMsg>, port: u16) {
                 match receiver.try_recv() {
                     Ok(ServerExitMsg) | Err(Disconnected) => break,
                     Ok(NewGlobal(id, sender)) => handle_new_global(actors.clone(), id, sender),
+                    Ok(NetworkEventMsg(event)) => {
+                        handle_network_event(&mut *actors.lock(), &mut clients, &mut loads,
+                                             event)
+                    }
+                    Ok(ReportPageErrorMsg(id, error)) => {
+                        handle_page_error(&*actors.lock(), &mut clients, id, error)
+                    }
                     Err(Empty) => acceptor.set_timeout(Some(POLL_TIMEOUT)),
                 }
             }
             Err(_e) => { /* connection failed */ }
             Ok(stream) => {
+                clients.push(stream.clone());
                 let actors = actors.clone();
                 spawn(proc() {
                     // connection succeeded
@@ -11,12 +11,14 @@
 #![allow(non_snake_case)]
 
 extern crate "msg" as servo_msg;
+extern crate "net" as servo_net;
 
 /// This module contains shared types and messages for use by devtools/script.
 /// The traits are here instead of in script so that the devtools crate can be
 /// modified independently of the rest of Servo.
 
 use servo_msg::constellation_msg::PipelineId;
+use servo_net::network_events::NetworkEvent;
 
 pub type DevtoolsControlChan = Sender<DevtoolsControlMsg>;
 pub type DevtoolsControlPort = Receiver<DevtoolScriptControlMsg>;
@@ -25,9 +27,25 @@ pub type DevtoolsControlPort = Receiver<DevtoolScriptControlMsg>;
 /// according to changes in the browser.
 pub enum DevtoolsControlMsg {
     NewGlobal(PipelineId, Sender<DevtoolScriptControlMsg>),
+    /// Report the progress of a load made by the resource task to the network monitor
+    NetworkEventMsg(NetworkEvent),
+    /// Report an error or warning about a page to its console
+    ReportPageErrorMsg(PipelineId, PageError),
     ServerExitMsg
 }
 
+/// An error or warning about a page that isn't a script exception, such as a Content Security
+/// Policy violation.
+#[deriving(Clone)]
+pub struct PageError {
+    pub message: String,
+    /// The URL of the document or resource the error is about.
+    pub source_name: String,
+    /// The kind of error, such as "CSP".
+    pub category: String,
+    pub warning: bool,
+}
+
 /// Serialized JS return values
 /// TODO: generalize this beyond the EvaluateJS message?
 pub enum EvaluateJSReply {
@@ -438,6 +438,8 @@ pub enum DisplayItem {
     BorderDisplayItemClass(Box<BorderDisplayItem>),
     LineDisplayItemClass(Box<LineDisplayItem>),
     ClipDisplayItemClass(Box<ClipDisplayItem>),
+    TransformDisplayItemClass(Box<TransformDisplayItem>),
+    OpacityDisplayItemClass(Box<OpacityDisplayItem>),
 
     /// A pseudo-display item that exists only so that queries like `ContentBoxQuery` and
     /// `ContentBoxesQuery` can be answered.
@@ -563,6 +565,69 @@ impl ClipDisplayItem {
     }
 }
 
+/// Transforms a list of child display items, which form a stacking context of their own.
+#[deriving(Clone)]
+pub struct TransformDisplayItem {
+    /// The base information. The bounds are those of the transformed children, in page
+    /// coordinates.
+    pub base: BaseDisplayItem,
+
+    /// The transform from the coordinates of the children to page coordinates, in pixels.
+    pub transform: Matrix2D<AzFloat>,
+
+    /// The child nodes, which must already have been flattened.
+    pub children: DisplayList,
+}
+
+impl TransformDisplayItem {
+    pub fn new(base: BaseDisplayItem, transform: Matrix2D<AzFloat>, children: DisplayList)
+               -> TransformDisplayItem {
+        TransformDisplayItem {
+            base: base,
+            transform: transform,
+            children: children,
+        }
+    }
+
+    /// Maps a point in page coordinates to the coordinates of the children, or returns `None` if
+    /// the transform isn't invertible.
+    pub fn untransform_point(&self, point: &Point2D<Au>) -> Option<Point2D<Au>> {
+        let m = &self.transform;
+        let determinant = m.m11 * m.m22 - m.m12 * m.m21;
+        if determinant == 0. {
+            return None
+        }
+        let x = point.x.to_subpx() as AzFloat - m.m31;
+        let y = point.y.to_subpx() as AzFloat - m.m32;
+        Some(Point2D(Au::from_frac_px(((x * m.m22 - y * m.m21) / determinant) as f64),
+                     Au::from_frac_px(((y * m.m11 - x * m.m12) / determinant) as f64)))
+    }
+}
+
+/// Renders a list of child display items as a group, which is composited with the given opacity.
+#[deriving(Clone)]
+pub struct OpacityDisplayItem {
+    /// The base information.
+    pub base: BaseDisplayItem,
+
+    /// The opacity of the group, between 0 and 1.
+    pub opacity: AzFloat,
+
+    /// The child nodes, which must already have been flattened.
+    pub children: DisplayList,
+}
+
+impl OpacityDisplayItem {
+    pub fn new(base: BaseDisplayItem, opacity: AzFloat, children: DisplayList)
+               -> OpacityDisplayItem {
+        OpacityDisplayItem {
+            base: base,
+            opacity: opacity,
+            children: children,
+        }
+    }
+}
+
 pub enum DisplayItemIterator<'a> {
     EmptyDisplayItemIterator,
     ParentDisplayItemIterator(dlist::Items<'a,DisplayItem>),
@@ -598,6 +663,19 @@ impl DisplayItem {
                 render_context.draw_pop_clip();
             }
 
+            TransformDisplayItemClass(ref transform) => {
+                let new_transform = current_transform.mul(&transform.transform);
+                render_context.draw_target.set_transform(&new_transform);
+                transform.children.draw_into_context(render_context, &new_transform);
+                render_context.draw_target.set_transform(current_transform);
+            }
+
+            OpacityDisplayItemClass(ref opacity) => {
+                render_context.draw_with_opacity(opacity.opacity, current_transform, |context| {
+                    opacity.children.draw_into_context(context, current_transform)
+                })
+            }
+
             TextDisplayItemClass(ref text) => {
                 debug!("Drawing text at {}.", text.base.bounds);
 
@@ -698,6 +776,8 @@ impl DisplayItem {
             BorderDisplayItemClass(ref border) => &border.base,
             LineDisplayItemClass(ref line) => &line.base,
             ClipDisplayItemClass(ref clip) => &clip.base,
+            TransformDisplayItemClass(ref transform) => &transform.base,
+            OpacityDisplayItemClass(ref opacity) => &opacity.base,
             PseudoDisplayItemClass(ref base) => &**base,
         }
     }
@@ -710,6 +790,8 @@ impl DisplayItem {
             BorderDisplayItemClass(ref mut border) => &mut border.base,
             LineDisplayItemClass(ref mut line) => &mut line.base,
             ClipDisplayItemClass(ref mut clip) => &mut clip.base,
+            TransformDisplayItemClass(ref mut transform) => &mut transform.base,
+            OpacityDisplayItemClass(ref mut opacity) => &mut opacity.base,
             PseudoDisplayItemClass(ref mut base) => &mut **base,
         }
     }
@@ -721,6 +803,12 @@ impl DisplayItem {
     pub fn children<'a>(&'a self) -> DisplayItemIterator<'a> {
         match *self {
             ClipDisplayItemClass(ref clip) => ParentDisplayItemIterator(clip.children.list.iter()),
+            TransformDisplayItemClass(ref transform) => {
+                ParentDisplayItemIterator(transform.children.list.iter())
+            }
+            OpacityDisplayItemClass(ref opacity) => {
+                ParentDisplayItemIterator(opacity.children.list.iter())
+            }
             SolidColorDisplayItemClass(..) |
             TextDisplayItemClass(..) |
             ImageDisplayItemClass(..) |
@@ -734,6 +822,8 @@ impl DisplayItem {
     fn mut_sublist<'a>(&'a mut self) -> Option<&'a mut DisplayList> {
         match *self {
             ClipDisplayItemClass(ref mut clip) => Some(&mut clip.children),
+            TransformDisplayItemClass(ref mut transform) => Some(&mut transform.children),
+            OpacityDisplayItemClass(ref mut opacity) => Some(&mut opacity.children),
             SolidColorDisplayItemClass(..) |
             TextDisplayItemClass(..) |
             ImageDisplayItemClass(..) |
@@ -765,6 +855,8 @@ impl fmt::Show for DisplayItem {
                 BorderDisplayItemClass(_) => "Border",
                 LineDisplayItemClass(_) => "Line",
                 ClipDisplayItemClass(_) => "Clip",
+                TransformDisplayItemClass(_) => "Transform",
+                OpacityDisplayItemClass(_) => "Opacity",
                 PseudoDisplayItemClass(_) => "Pseudo",
             },
             self.base().bounds,
@@ -4,7 +4,9 @@
 
 use display_list::{BorderDisplayItemClass, ClipDisplayItem, ClipDisplayItemClass, DisplayItem};
 use display_list::{DisplayList, ImageDisplayItemClass, LineDisplayItemClass};
+use display_list::{OpacityDisplayItem, OpacityDisplayItemClass};
 use display_list::{PseudoDisplayItemClass, SolidColorDisplayItemClass, TextDisplayItemClass};
+use display_list::TransformDisplayItemClass;
 
 use collections::dlist::DList;
 use geom::rect::Rect;
@@ -62,9 +64,21 @@ impl DisplayListOptimizer {
                 }))
             }
 
+            OpacityDisplayItemClass(ref opacity) => {
+                let new_children = self.process_display_list(&opacity.children);
+                if new_children.is_empty() {
+                    return None
+                }
+                Some(OpacityDisplayItemClass(box OpacityDisplayItem::new(opacity.base.clone(),
+                                                                         opacity.opacity,
+                                                                         new_children)))
+            }
+
+            // The children of a transform aren't in page coordinates, so they can't be culled
+            // against the visible rect.
             BorderDisplayItemClass(_) | ImageDisplayItemClass(_) | LineDisplayItemClass(_) |
             PseudoDisplayItemClass(_) | SolidColorDisplayItemClass(_) |
-            TextDisplayItemClass(_) => {
+            TextDisplayItemClass(_) | TransformDisplayItemClass(_) => {
                 Some((*display_item).clone())
             }
         }
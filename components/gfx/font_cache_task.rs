@@ -12,7 +12,7 @@ use std::collections::HashMap;
 use sync::Arc;
 use font_template::{FontTemplate, FontTemplateDescriptor};
 use platform::font_template::FontTemplateData;
-use servo_net::resource_task::{ResourceTask, load_whole_resource};
+use servo_net::resource_task::{FontPriority, LoadData, ResourceTask, load_whole_resource_with};
 use url::Url;
 
 /// A list of font templates that make up a given font family.
@@ -117,7 +117,9 @@ impl FontCache {
                     result.send(GetFontTemplateReply(font_template));
                 }
                 AddWebFont(family_name, url, result) => {
-                    let maybe_resource = load_whole_resource(&self.resource_task, url.clone());
+                    let mut load_data = LoadData::new(url.clone());
+                    load_data.priority = FontPriority;
+                    let maybe_resource = load_whole_resource_with(&self.resource_task, load_data);
                     match maybe_resource {
                         Ok((_, bytes)) => {
                             if !self.web_families.contains_key(&family_name) {
@@ -9,6 +9,7 @@ use azure::azure_hl::{B8G8R8A8, A8, Color, ColorPattern, DrawOptions, DrawSurfac
 use azure::azure_hl::{Linear, SourceOp, StrokeOptions};
 use azure::AZ_CAP_BUTT;
 use azure::AzFloat;
+use geom::matrix2d::Matrix2D;
 use geom::point::Point2D;
 use geom::rect::Rect;
 use geom::size::Size2D;
@@ -126,6 +127,42 @@ impl<'a> RenderContext<'a>  {
                                      draw_options);
     }
 
+    /// Draws into an offscreen surface the size of this context with `draw`, then composites that
+    /// surface onto this context with the given opacity, so that the drawing forms a single group.
+    pub fn draw_with_opacity(&mut self,
+                             opacity: AzFloat,
+                             current_transform: &Matrix2D<AzFloat>,
+                             draw: |&mut RenderContext|) {
+        let size = Size2D(self.screen_rect.size.width as i32,
+                          self.screen_rect.size.height as i32);
+        self.draw_target.make_current();
+        let group_draw_target = self.draw_target.create_similar_draw_target(&size, B8G8R8A8);
+        group_draw_target.set_transform(current_transform);
+        {
+            let mut group_context = RenderContext {
+                draw_target: &group_draw_target,
+                font_ctx: &mut *self.font_ctx,
+                opts: self.opts,
+                page_rect: self.page_rect,
+                screen_rect: self.screen_rect,
+            };
+            draw(&mut group_context);
+        }
+
+        // The surface is in device pixels, so composite it without the page transform.
+        self.draw_target.make_current();
+        let surface = group_draw_target.snapshot();
+        let rect = Rect(Point2D(0.0 as AzFloat, 0.0 as AzFloat),
+                        Size2D(size.width as AzFloat, size.height as AzFloat));
+        self.draw_target.set_transform(&Matrix2D::identity());
+        self.draw_target.draw_surface(surface,
+                                      rect,
+                                      rect,
+                                      DrawSurfaceOptions::new(Linear, true),
+                                      DrawOptions::new(opacity, 0));
+        self.draw_target.set_transform(current_transform);
+    }
+
     pub fn clear(&self) {
         let pattern = ColorPattern::new(Color::new(0.0, 0.0, 0.0, 0.0));
         let rect = Rect(Point2D(self.page_rect.origin.x as AzFloat,
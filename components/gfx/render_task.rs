@@ -47,6 +47,8 @@ pub struct RenderLayer {
     pub background_color: Color,
     /// The scrolling policy of this layer.
     pub scroll_policy: ScrollPolicy,
+    /// The opacity with which the compositor draws this layer.
+    pub opacity: f32,
 }
 
 pub struct RenderRequest {
@@ -141,6 +143,7 @@ fn initialize_layers<C:RenderListener>(
             position: render_layer.position,
             background_color: render_layer.background_color,
             scroll_policy: render_layer.scroll_policy,
+            opacity: render_layer.opacity,
         }
     }).collect();
     compositor.initialize_layers_for_pipeline(pipeline_id, metadata, epoch);
@@ -30,11 +30,13 @@ use style::ComputedValues;
 use style::computed_values::{clear, position};
 
 use collections::dlist::DList;
-use geom::{Size2D, Point2D, Rect};
+use geom::{Size2D, Point2D, Rect, Matrix2D};
 use gfx::color;
-use gfx::display_list::{BackgroundAndBorderLevel, BlockLevel, ContentStackingLevel, DisplayList};
-use gfx::display_list::{FloatStackingLevel, PositionedDescendantStackingLevel};
-use gfx::display_list::{RootOfStackingContextLevel};
+use gfx::display_list::{BackgroundAndBorderLevel, BaseDisplayItem, BlockLevel};
+use gfx::display_list::{ContentStackingLevel, DisplayList, FloatStackingLevel};
+use gfx::display_list::{OpacityDisplayItem, OpacityDisplayItemClass};
+use gfx::display_list::{PositionedDescendantStackingLevel, RootOfStackingContextLevel};
+use gfx::display_list::{StackingLevel, TransformDisplayItem, TransformDisplayItemClass};
 use gfx::render_task::RenderLayer;
 use servo_msg::compositor_msg::{FixedPosition, LayerId, Scrollable};
 use servo_util::geometry::{Au, MAX_AU};
@@ -44,7 +46,7 @@ use std::fmt;
 use std::mem;
 use style::computed_values::{LPA_Auto, LPA_Length, LPA_Percentage, LPN_Length, LPN_None};
 use style::computed_values::{LPN_Percentage, LP_Length, LP_Percentage, box_sizing};
-use style::computed_values::{display, float, overflow};
+use style::computed_values::{display, float, overflow, transform};
 use sync::Arc;
 
 /// Information specific to floated blocks.
@@ -478,7 +480,7 @@ enum FormattingContextType {
 // the positioned descendants are actually on top of the fixed kids.
 //
 // TODO(#1244, #2007, pcwalton): Do this for CSS transforms and opacity too, at least if they're
-// animating.
+// animating. Blocks that get a layer anyway already leave their opacity to the compositor.
 fn propagate_layer_flag_from_child(layers_needed_for_descendants: &mut bool, kid: &mut Flow) {
     if kid.is_absolute_containing_block() {
         let kid_base = flow::mut_base(kid);
@@ -1120,6 +1122,16 @@ impl BlockFlow {
             self.build_display_list_float(layout_context)
         } else if self.is_absolutely_positioned() {
             self.build_display_list_abs(layout_context)
+        } else if self.fragment.is_transformed() || self.fragment.needs_opacity_group() {
+            // A transform or an opacity makes this block a stacking context, which is painted
+            // along with the positioned descendants of its parent stacking context.
+            self.build_display_list_block_common(layout_context, RootOfStackingContextLevel);
+            let z_index = self.fragment.style().get_box().z_index.number_or_zero();
+            let level = PositionedDescendantStackingLevel(z_index);
+            self.base.display_list = mem::replace(&mut self.base.display_list,
+                                                  DisplayList::new()).flatten(level);
+            self.transform_display_list_if_necessary(level);
+            self.group_display_list_for_opacity_if_necessary(level)
         } else {
             self.build_display_list_block_common(layout_context, BlockLevel)
         }
@@ -1128,7 +1140,91 @@ impl BlockFlow {
     pub fn build_display_list_float(&mut self, layout_context: &LayoutContext) {
         self.build_display_list_block_common(layout_context, RootOfStackingContextLevel);
         self.base.display_list = mem::replace(&mut self.base.display_list,
-                                              DisplayList::new()).flatten(FloatStackingLevel)
+                                              DisplayList::new()).flatten(FloatStackingLevel);
+        self.transform_display_list_if_necessary(FloatStackingLevel);
+        self.group_display_list_for_opacity_if_necessary(FloatStackingLevel)
+    }
+
+    /// Wraps the flattened display list of this block in an opacity display item at the given
+    /// stacking level, if the block needs to be rendered as a group with its opacity.
+    fn group_display_list_for_opacity_if_necessary(&mut self, level: StackingLevel) {
+        if !self.fragment.needs_opacity_group() || self.base.display_list.list.is_empty() {
+            return
+        }
+
+        let display_list = mem::replace(&mut self.base.display_list, DisplayList::new());
+        let bounds = {
+            let mut items = display_list.iter();
+            let first_bounds = items.next().unwrap().bounds();
+            items.fold(first_bounds, |bounds, item| bounds.union(&item.bounds()))
+        };
+        let base = BaseDisplayItem::new(bounds, self.fragment.node, level);
+        let opacity = self.fragment.style().get_effects().opacity as f32;
+        self.base.display_list.push(OpacityDisplayItemClass(
+            box OpacityDisplayItem::new(base, opacity, display_list)))
+    }
+
+    /// Wraps the flattened display list of this block in a transform display item at the given
+    /// stacking level, if the block has a transform.
+    fn transform_display_list_if_necessary(&mut self, level: StackingLevel) {
+        if !self.fragment.is_transformed() {
+            return
+        }
+
+        // The border box in page coordinates, as `Fragment::build_display_list()` computes it.
+        let rel_offset =
+            self.fragment.relative_position(&self.base
+                                             .absolute_position_info
+                                             .relative_containing_block_size);
+        let flow_origin =
+            self.base.abs_position.add_size(&rel_offset.to_physical(self.base.writing_mode));
+        let border_box = self.fragment.border_box.to_physical(self.base.writing_mode,
+                                                              Size2D::zero());
+        let border_box = Rect(border_box.origin + flow_origin, border_box.size);
+
+        // The transform applies around the transform origin, which is relative to the border box.
+        let effects = self.fragment.style().get_effects();
+        let origin_x = border_box.origin.x + specified(effects.transform_origin.horizontal,
+                                                      border_box.size.width);
+        let origin_y = border_box.origin.y + specified(effects.transform_origin.vertical,
+                                                       border_box.size.height);
+        let (origin_x, origin_y) = (origin_x.to_subpx(), origin_y.to_subpx());
+        let matrix = transform::ComputedMatrix::translation(-origin_x, -origin_y, 0.)
+            .then(&transform::to_matrix(&effects.transform, &border_box.size))
+            .then(&transform::ComputedMatrix::translation(origin_x, origin_y, 0.));
+
+        // Only the 2D part of the matrix is used for painting.
+        let matrix = Matrix2D::new(matrix.m11 as f32, matrix.m12 as f32,
+                                   matrix.m21 as f32, matrix.m22 as f32,
+                                   matrix.m41 as f32, matrix.m42 as f32);
+
+        // The bounds are those of the transformed children, for invalidation and hit testing.
+        let display_list = mem::replace(&mut self.base.display_list, DisplayList::new());
+        let children_bounds = display_list.iter().fold(border_box, |bounds, item| {
+            bounds.union(&item.bounds())
+        });
+        let corners = [
+            children_bounds.origin,
+            Point2D(children_bounds.max_x(), children_bounds.origin.y),
+            Point2D(children_bounds.origin.x, children_bounds.max_y()),
+            Point2D(children_bounds.max_x(), children_bounds.max_y()),
+        ];
+        let (mut min_x, mut min_y) = (MAX_AU, MAX_AU);
+        let (mut max_x, mut max_y) = (-MAX_AU, -MAX_AU);
+        for corner in corners.iter() {
+            let (x, y) = (corner.x.to_subpx() as f32, corner.y.to_subpx() as f32);
+            let (x, y) = (Au::from_frac_px((x * matrix.m11 + y * matrix.m21 + matrix.m31) as f64),
+                          Au::from_frac_px((x * matrix.m12 + y * matrix.m22 + matrix.m32) as f64));
+            min_x = min(min_x, x);
+            min_y = min(min_y, y);
+            max_x = max(max_x, x);
+            max_y = max(max_y, y);
+        }
+        let bounds = Rect(Point2D(min_x, min_y), Size2D(max_x - min_x, max_y - min_y));
+
+        let base = BaseDisplayItem::new(bounds, self.fragment.node, level);
+        self.base.display_list.push(TransformDisplayItemClass(
+            box TransformDisplayItem::new(base, matrix, display_list)))
     }
 
     /// Calculate and set the block-size, offsets, etc. for absolutely positioned flow.
@@ -1243,6 +1339,8 @@ impl BlockFlow {
             let level = PositionedDescendantStackingLevel(z_index);
             self.base.display_list = mem::replace(&mut self.base.display_list,
                                                   DisplayList::new()).flatten(level);
+            self.transform_display_list_if_necessary(level);
+            self.group_display_list_for_opacity_if_necessary(level);
             return
         }
 
@@ -1258,13 +1356,19 @@ impl BlockFlow {
         } else {
             Scrollable
         };
+        self.base.display_list = mem::replace(&mut self.base.display_list,
+                                              DisplayList::new()).flatten(ContentStackingLevel);
+        self.transform_display_list_if_necessary(ContentStackingLevel);
         let display_list = mem::replace(&mut self.base.display_list, DisplayList::new());
+
+        // The compositor applies the opacity of the layer, rather than an opacity display item.
         let new_layer = RenderLayer {
             id: self.layer_id(0),
-            display_list: Arc::new(display_list.flatten(ContentStackingLevel)),
+            display_list: Arc::new(display_list),
             position: Rect(origin, size),
             background_color: color::rgba(1.0, 1.0, 1.0, 0.0),
             scroll_policy: scroll_policy,
+            opacity: self.fragment.style().get_effects().opacity as f32,
         };
         self.base.layers.push(new_layer)
     }
@@ -1781,11 +1885,18 @@ impl Flow for BlockFlow {
         self.fragment.style.get_box().position
     }
 
+    /// Returns true if this flow is positioned or has a transform, either of which makes it the
+    /// containing block of its absolutely-positioned descendants.
+    fn is_positioned(&self) -> bool {
+        self.is_relatively_positioned() || self.is_absolutely_positioned() ||
+            self.fragment.is_transformed()
+    }
+
     /// Return true if this is the root of an Absolute flow tree.
     ///
-    /// It has to be either relatively positioned or the Root flow.
+    /// It has to be either relatively positioned, transformed, or the Root flow.
     fn is_root_of_absolute_flow_tree(&self) -> bool {
-        self.is_relatively_positioned() || self.is_root()
+        (self.is_positioned() && !self.is_absolutely_positioned()) || self.is_root()
     }
 
     /// Return the dimensions of the containing block generated by this flow for absolutely-
@@ -1721,6 +1721,38 @@ impl Fragment {
         }
     }
 
+    /// Returns true if this fragment has a transform, which makes it a stacking context and the
+    /// containing block of its absolutely-positioned descendants.
+    pub fn is_transformed(&self) -> bool {
+        !self.style().get_effects().transform.is_empty()
+    }
+
+    /// Returns true if this fragment must be rendered as a group with its opacity, which also makes
+    /// it a stacking context.
+    pub fn needs_opacity_group(&self) -> bool {
+        self.style().get_effects().opacity < 1.0
+    }
+
+    /// Returns the opacity with which the display items of this fragment must be grouped when it
+    /// is laid out inline: the product of the opacities of its enclosing inline elements and, for
+    /// replaced content, of its own. Text fragments share the style of their parent element, and
+    /// inline blocks group their own display lists, so their own opacity is not counted here.
+    pub fn inline_opacity(&self) -> f32 {
+        let mut opacity = match self.specific {
+            ScannedTextFragment(_) | UnscannedTextFragment(_) | InlineBlockFragment(_) => 1.0,
+            _ => self.style().get_effects().opacity as f32,
+        };
+        match self.inline_context {
+            Some(ref inline_context) => {
+                for style in inline_context.styles.iter() {
+                    opacity *= style.get_effects().opacity as f32
+                }
+            }
+            None => {}
+        }
+        opacity
+    }
+
     /// Returns true if this fragment can merge with another adjacent fragment or false otherwise.
     pub fn can_merge_with_fragment(&self, other: &Fragment) -> bool {
         match (&self.specific, &other.specific) {
@@ -93,7 +93,8 @@ pub fn compute_damage(old: &Option<Arc<ComputedValues>>, new: &ComputedValues) -
     add_if_not_equal!(old, new, damage, [ Repaint ],
         [ get_color.color, get_background.background_color,
           get_border.border_top_color, get_border.border_right_color,
-          get_border.border_bottom_color, get_border.border_left_color ]);
+          get_border.border_bottom_color, get_border.border_left_color,
+          get_effects.opacity, get_effects.transform_origin ]);
 
     add_if_not_equal!(old, new, damage, [ Repaint, BubbleISizes, Reflow ],
         [ get_border.border_top_width, get_border.border_right_width,
@@ -104,7 +105,8 @@ pub fn compute_damage(old: &Option<Arc<ComputedValues>>, new: &ComputedValues) -
           get_padding.padding_bottom, get_padding.padding_left,
           get_box.position, get_box.width, get_box.height, get_box.float, get_box.display,
           get_font.font_family, get_font.font_size, get_font.font_style, get_font.font_weight,
-          get_inheritedtext.text_align, get_text.text_decoration, get_inheritedbox.line_height ]);
+          get_inheritedtext.text_align, get_text.text_decoration, get_inheritedbox.line_height,
+          get_effects.transform ]);
 
     // FIXME: test somehow that we checked every CSS property
 
@@ -18,7 +18,8 @@ use wrapper::ThreadSafeLayoutNode;
 
 use collections::{Deque, RingBuf};
 use geom::Rect;
-use gfx::display_list::ContentLevel;
+use gfx::display_list::{BaseDisplayItem, ContentLevel, ContentStackingLevel, DisplayList};
+use gfx::display_list::{OpacityDisplayItem, OpacityDisplayItemClass};
 use gfx::font::FontMetrics;
 use gfx::font_context::FontContext;
 use geom::Size2D;
@@ -786,18 +787,42 @@ impl InlineFlow {
             let rel_offset = fragment.relative_position(&self.base
                                                              .absolute_position_info
                                                              .relative_containing_block_size);
-            let mut accumulator = fragment.build_display_list(&mut self.base.display_list,
-                                             layout_context,
-                                             self.base.abs_position.add_size(
-                                                &rel_offset.to_physical(self.base.writing_mode)),
-                                             ContentLevel);
-            match fragment.specific {
-                InlineBlockFragment(ref mut block_flow) => {
-                    let block_flow = block_flow.flow_ref.get_mut();
-                    accumulator.push_child(&mut self.base.display_list, block_flow);
+            // Content inside inline elements with opacity is grouped fragment by fragment, since
+            // inline elements do not have display lists of their own.
+            let opacity = fragment.inline_opacity();
+            let mut fragment_display_list = DisplayList::new();
+            {
+                let display_list = if opacity < 1.0 {
+                    &mut fragment_display_list
+                } else {
+                    &mut self.base.display_list
+                };
+                let flow_origin = self.base.abs_position.add_size(
+                    &rel_offset.to_physical(self.base.writing_mode));
+                let mut accumulator = fragment.build_display_list(display_list,
+                                                                  layout_context,
+                                                                  flow_origin,
+                                                                  ContentLevel);
+                match fragment.specific {
+                    InlineBlockFragment(ref mut block_flow) => {
+                        let block_flow = block_flow.flow_ref.get_mut();
+                        accumulator.push_child(display_list, block_flow);
+                    }
+                    _ => {}
                 }
-                _ => {}
             }
+
+            if fragment_display_list.list.is_empty() {
+                continue
+            }
+            let bounds = {
+                let mut items = fragment_display_list.iter();
+                let first_bounds = items.next().unwrap().bounds();
+                items.fold(first_bounds, |bounds, item| bounds.union(&item.bounds()))
+            };
+            let base = BaseDisplayItem::new(bounds, fragment.node, ContentStackingLevel);
+            self.base.display_list.push(OpacityDisplayItemClass(
+                box OpacityDisplayItem::new(base, opacity, fragment_display_list)))
         }
 
         // TODO(#225): Should `inline-block` elements have flows as children of the inline flow or
@@ -22,10 +22,12 @@ use wrapper::{LayoutNode, TLayoutNode, ThreadSafeLayoutNode};
 use collections::dlist::DList;
 use encoding::EncodingRef;
 use encoding::all::UTF_8;
+use encoding::label::encoding_from_whatwg_label;
 use geom::point::Point2D;
 use geom::rect::Rect;
 use geom::size::Size2D;
 use gfx::display_list::{ClipDisplayItemClass, ContentStackingLevel, DisplayItem};
+use gfx::display_list::{OpacityDisplayItemClass, TransformDisplayItemClass};
 use gfx::display_list::{DisplayItemIterator, DisplayList, OpaqueNode};
 use gfx::render_task::{RenderInitMsg, RenderChan, RenderLayer};
 use gfx::{render_task, color};
@@ -39,16 +41,21 @@ use script::layout_interface::{TrustedNodeAddress, ContentBoxesResponse, ExitNow
 use script::layout_interface::{ContentBoxResponse, HitTestResponse, MouseOverResponse};
 use script::layout_interface::{LayoutChan, Msg, PrepareToExitMsg};
 use script::layout_interface::{GetRPCMsg, LayoutRPC, ReapLayoutDataMsg, Reflow};
-use script::layout_interface::{ReflowForDisplay, ReflowMsg};
+use script::layout_interface::{ReflowForDisplay, ReflowMsg, RemoveStylesheetMsg, StylesheetId};
+use script::layout_interface::ComputedStyleResponse;
 use script_traits::{SendEventMsg, ReflowEvent, ReflowCompleteMsg, OpaqueScriptLayoutChannel};
-use script_traits::{ScriptControlChan, UntrustedNodeAddress};
+use script_traits::{ScriptControlChan, UntrustedNodeAddress, ReportCSPViolationsMsg};
+use script_traits::StylesheetLoadedMsg;
 use servo_msg::compositor_msg::Scrollable;
 use servo_msg::constellation_msg::{ConstellationChan, PipelineId, Failure, FailureMsg};
+use servo_net::csp::ContentSecurityPolicy;
+use servo_net::fetch::request::{Request, RefererUrl, Style};
 use servo_net::image_cache_task::{ImageCacheTask, ImageResponseMsg};
 use gfx::font_cache_task::{FontCacheTask};
 use servo_net::local_image_cache::{ImageResponder, LocalImageCache};
+use servo_net::referrer_policy::ReferrerPolicy;
 use servo_util::bloom::BloomFilter;
-use servo_net::resource_task::{ResourceTask, load_bytes_iter};
+use servo_net::resource_task::{LoadGroupId, ResourceTask};
 use servo_util::geometry::Au;
 use servo_util::geometry;
 use servo_util::logical_geometry::LogicalPoint;
@@ -63,8 +70,9 @@ use std::comm::{channel, Sender, Receiver, Select};
 use std::mem;
 use std::ptr;
 use style;
-use style::{TNode, AuthorOrigin, Stylesheet, Stylist};
-use style::iter_font_face_rules;
+use style::{TNode, AuthorOrigin, Stylesheet, Stylist, PseudoElement, Before, After};
+use style::{Device, Screen};
+use style::{decode_stylesheet, iter_font_face_rules};
 use sync::{Arc, Mutex, MutexGuard};
 use url::Url;
 
@@ -96,6 +104,10 @@ pub struct LayoutTaskData {
     /// True if a style sheet was added since the last reflow. Currently, this causes all nodes to
     /// be dirtied at the next reflow.
     pub stylesheet_dirty: bool,
+
+    /// The author stylesheets, in the order they were added. Those that script can change or
+    /// remove have an id.
+    pub author_stylesheets: Vec<(Option<StylesheetId>, Stylesheet)>,
 }
 
 /// Information needed by the layout task.
@@ -380,11 +392,13 @@ impl LayoutTask {
                     local_image_cache: local_image_cache,
                     screen_size: screen_size,
                     display_list: None,
-                    stylist: box Stylist::new(),
+                    // The real viewport is only known at the first reflow.
+                    stylist: box Stylist::new(Device::new(Screen, screen_size, 1.)),
                     parallel_traversal: parallel_traversal,
                     dirty: Rect::zero(),
                     generation: 0,
                     stylesheet_dirty: false,
+                    author_stylesheets: vec!(),
               })),
         }
     }
@@ -481,8 +495,19 @@ impl LayoutTask {
     /// Receives and dispatches messages from the script task.
     fn handle_script_request<'a>(&'a self, request: Msg, possibly_locked_rw_data: &mut Option<MutexGuard<'a, LayoutTaskData>>) -> bool {
         match request {
-            AddStylesheetMsg(sheet) => self.handle_add_stylesheet(sheet, possibly_locked_rw_data),
-            LoadStylesheetMsg(url) => self.handle_load_stylesheet(url, possibly_locked_rw_data),
+            AddStylesheetMsg(id, sheet, policy, environment_encoding, referrer_policy) => {
+                let environment_encoding = LayoutTask::environment_encoding(environment_encoding);
+                let url = policy.document_url().clone();
+                self.handle_add_stylesheet(Some(id), sheet, &url, &policy, environment_encoding,
+                                           referrer_policy, possibly_locked_rw_data)
+            }
+            RemoveStylesheetMsg(id) => {
+                self.handle_remove_stylesheet(id, possibly_locked_rw_data)
+            }
+            LoadStylesheetMsg(id, url, policy, environment_encoding, referrer_policy) => {
+                self.handle_load_stylesheet(id, url, policy, environment_encoding, referrer_policy,
+                                            possibly_locked_rw_data)
+            }
             GetRPCMsg(response_chan) => {
                 response_chan.send(
                     box LayoutRPCImpl(
@@ -557,30 +582,146 @@ impl LayoutTask {
         response_port.recv()
     }
 
-    fn handle_load_stylesheet<'a>(&'a self, url: Url, possibly_locked_rw_data: &mut Option<MutexGuard<'a, LayoutTaskData>>) {
-        // TODO: Get the actual value. http://dev.w3.org/csswg/css-syntax/#environment-encoding
-        let environment_encoding = UTF_8 as EncodingRef;
+    fn handle_load_stylesheet<'a>(&'a self,
+                                  id: StylesheetId,
+                                  url: Url,
+                                  policy: ContentSecurityPolicy,
+                                  environment_encoding: String,
+                                  referrer_policy: Option<ReferrerPolicy>,
+                                  possibly_locked_rw_data: &mut Option<MutexGuard<'a, LayoutTaskData>>) {
+        let environment_encoding = LayoutTask::environment_encoding(environment_encoding);
+        let (css, final_url) = match self.fetch_css(&url, &policy, environment_encoding,
+                                                    referrer_policy) {
+            Some(result) => result,
+            // The stylesheet the element loaded before no longer applies.
+            None => return self.handle_remove_stylesheet(id, possibly_locked_rw_data),
+        };
+        let sheet = Stylesheet::from_str(css.as_slice(), final_url.clone());
+        // Script keeps its own copy of the rules, for the CSSOM.
+        let ScriptControlChan(ref chan) = self.script_chan;
+        chan.send(StylesheetLoadedMsg(self.id, id.clone(), final_url, css));
+        self.handle_add_stylesheet(Some(id), sheet, &url, &policy, environment_encoding,
+                                   referrer_policy, possibly_locked_rw_data);
+    }
+
+    /// http://dev.w3.org/csswg/css-syntax/#environment-encoding
+    fn environment_encoding(label: String) -> EncodingRef {
+        encoding_from_whatwg_label(label.as_slice()).unwrap_or(UTF_8 as EncodingRef)
+    }
+
+    /// Fetches and parses the stylesheet at `url`, unless the document's Content Security Policy
+    /// blocks it or it can't be loaded.
+    fn fetch_stylesheet(&self,
+                        url: &Url,
+                        policy: &ContentSecurityPolicy,
+                        environment_encoding: EncodingRef,
+                        referrer_policy: Option<ReferrerPolicy>)
+                        -> Option<Stylesheet> {
+        self.fetch_css(url, policy, environment_encoding, referrer_policy).map(|(css, final_url)| {
+            Stylesheet::from_str(css.as_slice(), final_url)
+        })
+    }
+
+    /// Fetches the text of the stylesheet at `url` and the URL it was finally loaded from,
+    /// unless the document's Content Security Policy blocks it or it can't be loaded.
+    fn fetch_css(&self,
+                 url: &Url,
+                 policy: &ContentSecurityPolicy,
+                 environment_encoding: EncodingRef,
+                 referrer_policy: Option<ReferrerPolicy>)
+                 -> Option<(String, Url)> {
+        // Violations are reported to the document's console by the script task, whether or not
+        // the stylesheet was blocked.
+        let result = policy.check_load(Style, url);
+        if !result.violations.is_empty() {
+            let ScriptControlChan(ref chan) = self.script_chan;
+            chan.send(ReportCSPViolationsMsg(self.id, result.violations));
+        }
+        if !result.allowed {
+            return None
+        }
 
-        let (metadata, iter) = load_bytes_iter(&self.resource_task, url);
+        let mut request = Request::new(url.clone(), Style);
+        let PipelineId(id) = self.id;
+        request.group = Some(LoadGroupId(id));
+        request.origin = Some(policy.document_url().clone());
+        request.referer = RefererUrl(policy.document_url().clone());
+        request.referrer_policy = referrer_policy;
+        let loader = request.fetch(&self.resource_task);
+        if loader.response.is_network_error() {
+            return None
+        }
+        let metadata = loader.metadata.clone();
         let protocol_encoding_label = metadata.charset.as_ref().map(|s| s.as_slice());
-        let final_url = metadata.final_url;
+        let final_url = metadata.final_url.clone();
 
-        let sheet = Stylesheet::from_bytes_iter(iter, final_url, protocol_encoding_label, Some(environment_encoding));
-        self.handle_add_stylesheet(sheet, possibly_locked_rw_data);
+        let css = decode_stylesheet(loader, protocol_encoding_label, Some(environment_encoding));
+        Some((css, final_url))
     }
 
-    fn handle_add_stylesheet<'a>(&'a self, sheet: Stylesheet, possibly_locked_rw_data: &mut Option<MutexGuard<'a, LayoutTaskData>>) {
+    /// Adds a stylesheet loaded from `url` to the document, once the stylesheets it imports have
+    /// been fetched under the same policies. A stylesheet with the `id` of one that was already
+    /// added replaces it.
+    fn handle_add_stylesheet<'a>(&'a self,
+                                 id: Option<StylesheetId>,
+                                 mut sheet: Stylesheet,
+                                 url: &Url,
+                                 policy: &ContentSecurityPolicy,
+                                 environment_encoding: EncodingRef,
+                                 referrer_policy: Option<ReferrerPolicy>,
+                                 possibly_locked_rw_data: &mut Option<MutexGuard<'a, LayoutTaskData>>) {
+        sheet.load_imports(url, |import_url| {
+            self.fetch_stylesheet(import_url, policy, environment_encoding, referrer_policy)
+        });
+
+        let mut rw_data = self.lock_rw_data(possibly_locked_rw_data);
+
         // Find all font-face rules and notify the font cache of them.
         // GWTODO: Need to handle unloading web fonts (when we handle unloading stylesheets!)
-        iter_font_face_rules(&sheet, |family, url| {
+        iter_font_face_rules(&sheet, rw_data.stylist.device(), |family, url| {
             self.font_cache_task.add_web_font(family.to_string(), url.clone());
         });
-        let mut rw_data = self.lock_rw_data(possibly_locked_rw_data);
-        rw_data.stylist.add_stylesheet(sheet, AuthorOrigin);
+        let position = rw_data.author_stylesheets.iter().position(|&(ref other_id, _)| {
+            id.is_some() && *other_id == id
+        });
+        match position {
+            Some(position) => {
+                *rw_data.author_stylesheets.get_mut(position) = (id, sheet);
+                LayoutTask::rebuild_author_stylesheets(&mut *rw_data);
+            }
+            None => {
+                rw_data.stylist.add_stylesheet(&sheet, AuthorOrigin);
+                rw_data.author_stylesheets.push((id, sheet));
+            }
+        }
         rw_data.stylesheet_dirty = true;
         LayoutTask::return_rw_data(possibly_locked_rw_data, rw_data);
     }
 
+    /// Removes the stylesheet with the given id, when its owner leaves the document.
+    fn handle_remove_stylesheet<'a>(&'a self,
+                                    id: StylesheetId,
+                                    possibly_locked_rw_data: &mut Option<MutexGuard<'a, LayoutTaskData>>) {
+        let mut rw_data = self.lock_rw_data(possibly_locked_rw_data);
+        let id = Some(id);
+        let length = rw_data.author_stylesheets.len();
+        rw_data.author_stylesheets.retain(|&(ref other_id, _)| *other_id != id);
+        if rw_data.author_stylesheets.len() != length {
+            LayoutTask::rebuild_author_stylesheets(&mut *rw_data);
+            rw_data.stylesheet_dirty = true;
+        }
+        LayoutTask::return_rw_data(possibly_locked_rw_data, rw_data);
+    }
+
+    /// Adds the author stylesheets to the stylist again, after one of them was replaced or
+    /// removed.
+    fn rebuild_author_stylesheets(rw_data: &mut LayoutTaskData) {
+        rw_data.stylist.clear_author_stylesheets();
+        for &(_, ref sheet) in rw_data.author_stylesheets.iter() {
+            rw_data.stylist.add_stylesheet(sheet, AuthorOrigin);
+        }
+    }
+
     /// Retrieves the flow tree root from the root node.
     fn get_layout_root(&self, node: LayoutNode) -> FlowRef {
         let mut layout_data_ref = node.mutate_layout_data();
@@ -715,6 +856,20 @@ impl LayoutTask {
                                          Au::from_frac32_px(viewport_size.height.get()));
         let old_screen_size = mem::replace(&mut rw_data.screen_size, current_screen_size);
 
+        // Media queries are evaluated again when the viewport changes.
+        let device = Device::new(Screen,
+                                 current_screen_size,
+                                 data.window_size.device_pixel_ratio.get() as f64);
+        let media_queries_changed = {
+            let rw_data = &mut *rw_data;
+            let author_stylesheets: Vec<&Stylesheet> =
+                rw_data.author_stylesheets.iter().map(|&(_, ref sheet)| sheet).collect();
+            rw_data.stylist.set_device(device, author_stylesheets.as_slice())
+        };
+        if media_queries_changed {
+            LayoutTask::rebuild_author_stylesheets(&mut *rw_data);
+        }
+
         // Create a layout context for use throughout the following passes.
         let mut shared_layout_ctx =
             self.build_shared_layout_context(
@@ -726,6 +881,7 @@ impl LayoutTask {
         let mut needs_dirtying = false;
 
         needs_dirtying |= current_screen_size != old_screen_size;
+        needs_dirtying |= media_queries_changed;
         needs_dirtying |= rw_data.stylesheet_dirty;
 
         unsafe {
@@ -868,6 +1024,7 @@ impl LayoutTask {
                     position: Rect(Point2D(0u, 0u), root_size),
                     background_color: color,
                     scroll_policy: Scrollable,
+                    opacity: 1.0,
                 };
 
                 rw_data.display_list = Some(display_list.clone());
@@ -1024,6 +1181,28 @@ impl LayoutRPC for LayoutRPCImpl {
                         }
                         continue
                     }
+                    OpacityDisplayItemClass(ref opacity) => {
+                        let ret = hit_test(x, y, opacity.children.list.iter().rev());
+                        if !ret.is_none() {
+                            return ret
+                        }
+                        continue
+                    }
+                    TransformDisplayItemClass(ref transform) => {
+                        if geometry::rect_contains_point(transform.base.bounds, Point2D(x, y)) {
+                            let ret = match transform.untransform_point(&Point2D(x, y)) {
+                                Some(point) => {
+                                    hit_test(point.x, point.y,
+                                             transform.children.list.iter().rev())
+                                }
+                                None => None,
+                            };
+                            if !ret.is_none() {
+                                return ret
+                            }
+                        }
+                        continue
+                    }
                     _ => {}
                 }
 
@@ -1073,6 +1252,20 @@ impl LayoutRPC for LayoutRPCImpl {
                     ClipDisplayItemClass(ref cc) => {
                         mouse_over_test(x, y, cc.children.list.iter().rev(), result);
                     }
+                    OpacityDisplayItemClass(ref opacity) => {
+                        mouse_over_test(x, y, opacity.children.list.iter().rev(), result);
+                    }
+                    TransformDisplayItemClass(ref transform) => {
+                        match transform.untransform_point(&Point2D(x, y)) {
+                            Some(point) => {
+                                mouse_over_test(point.x,
+                                                point.y,
+                                                transform.children.list.iter().rev(),
+                                                result)
+                            }
+                            None => {}
+                        }
+                    }
                     _ => {
                         let bounds = item.bounds();
 
@@ -1114,4 +1307,27 @@ impl LayoutRPC for LayoutRPCImpl {
             Ok(MouseOverResponse(mouse_over_list))
         }
     }
+
+    /// Requests the computed value of `property` for a node or one of its pseudo-elements, as in
+    /// the `getComputedStyle()` call. Nodes that weren't styled, such as those that aren't
+    /// rendered, have no computed values yet.
+    fn computed_style(&self, node: TrustedNodeAddress, pseudo_element: Option<PseudoElement>,
+                      property: &str) -> ComputedStyleResponse {
+        let node: JS<Node> = unsafe { JS::from_trusted_node_address(node) };
+        let node: &LayoutNode = unsafe { mem::transmute(&node) };
+
+        // Holding the lock keeps layout from restyling the node while its style is read.
+        let &LayoutRPCImpl(ref rw_data) = self;
+        let _rw_data = rw_data.lock();
+        let layout_data_ref = node.borrow_layout_data();
+        let value = layout_data_ref.as_ref().and_then(|layout_data| {
+            let style = match pseudo_element {
+                None => layout_data.shared_data.style.as_ref(),
+                Some(Before) => layout_data.data.before_style.as_ref(),
+                Some(After) => layout_data.data.after_style.as_ref(),
+            };
+            style.and_then(|style| style::computed_value_to_css(&**style, property))
+        });
+        ComputedStyleResponse(value)
+    }
 }
@@ -80,6 +80,8 @@ pub struct LayerMetadata {
     pub background_color: Color,
     /// The scrolling policy of this layer.
     pub scroll_policy: ScrollPolicy,
+    /// The opacity of the layer.
+    pub opacity: f32,
 }
 
 /// The interface used by the renderer to acquire draw targets for each render frame and
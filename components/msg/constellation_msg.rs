@@ -11,6 +11,8 @@ use geom::scale_factor::ScaleFactor;
 use http::headers::request::HeaderCollection as RequestHeaderCollection;
 use http::method::{Method, Get};
 use layers::geometry::DevicePixel;
+use servo_net::csp::ContentSecurityPolicy;
+use servo_net::referrer_policy::ReferrerPolicy;
 use servo_util::geometry::{PagePx, ViewportPx};
 use std::comm::{channel, Sender, Receiver};
 use url::Url;
@@ -58,7 +60,10 @@ pub enum Msg {
     LoadCompleteMsg(PipelineId, Url),
     FrameRectMsg(PipelineId, SubpageId, Rect<f32>),
     LoadUrlMsg(PipelineId, LoadData),
-    LoadIframeUrlMsg(Url, PipelineId, SubpageId, IFrameSandboxState),
+    /// Load an iframe, if the Content Security Policy of its parent document allows it.
+    /// The iframe's document is requested with the parent document's referrer policy.
+    LoadIframeUrlMsg(Url, PipelineId, SubpageId, IFrameSandboxState, ContentSecurityPolicy,
+                     Option<ReferrerPolicy>),
     NavigateMsg(NavigationDirection),
     RendererReadyMsg(PipelineId),
     ResizedWindowMsg(WindowSizeData),
@@ -73,6 +78,9 @@ pub struct LoadData {
     pub method: Method,
     pub headers: RequestHeaderCollection,
     pub data: Option<Vec<u8>>,
+    /// The URL of the document that started the navigation, and its referrer policy.
+    pub referrer: Option<Url>,
+    pub referrer_policy: Option<ReferrerPolicy>,
 }
 
 impl LoadData {
@@ -82,6 +90,8 @@ impl LoadData {
             method: Get,
             headers: RequestHeaderCollection::new(),
             data: None,
+            referrer: None,
+            referrer_policy: None,
         }
     }
 }
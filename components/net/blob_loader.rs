@@ -0,0 +1,126 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The [blob URL store](http://dev.w3.org/2006/webapi/FileAPI/#url) filled by
+//! `URL.createObjectURL`, and the loader that answers `blob:` URLs from it.
+
+use resource_task::{Done, Payload, Metadata, LoadData, LoadResponse, start_sending};
+
+use http::headers::test_utils::from_stream_with_str;
+use http::headers::content_type::MediaType;
+use std::collections::hashmap::HashMap;
+use std::sync::Arc;
+use url::Url;
+
+/// The contents of a `Blob`, shared by the script task and the loads of its URLs.
+#[deriving(Clone)]
+pub struct BlobData {
+    pub bytes: Arc<Vec<u8>>,
+    /// The `type` of the blob, which is empty if it is unknown.
+    pub content_type: String,
+}
+
+/// The blob URLs that haven't been revoked.
+pub struct BlobURLStore {
+    entries: HashMap<String, BlobData>,
+}
+
+/// Blob URLs are looked up without their fragment.
+fn key(url: &Url) -> String {
+    url.serialize_no_fragment()
+}
+
+impl BlobURLStore {
+    pub fn new() -> BlobURLStore {
+        BlobURLStore {
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, url: &Url, data: BlobData) {
+        self.entries.insert(key(url), data);
+    }
+
+    pub fn revoke(&mut self, url: &Url) {
+        self.entries.remove(&key(url));
+    }
+
+    pub fn get(&self, url: &Url) -> Option<BlobData> {
+        self.entries.find(&key(url)).map(|data| data.clone())
+    }
+}
+
+/// Answer a load of a blob URL with the contents of its blob, or with a network error if it
+/// was never created or has been revoked.
+pub fn factory(load_data: LoadData, start_chan: Sender<LoadResponse>, data: Option<BlobData>) {
+    let url = load_data.url;
+    assert!("blob" == url.scheme.as_slice());
+
+    let mut metadata = Metadata::default(url.clone());
+    let data = match data {
+        Some(data) => data,
+        None => {
+            start_sending(start_chan, metadata).send(Done(Err("invalid blob URL".to_string())));
+            return
+        }
+    };
+
+    if !data.content_type.is_empty() {
+        let content_type: Option<MediaType> = from_stream_with_str(data.content_type.as_slice());
+        metadata.set_content_type(&content_type);
+    }
+    let progress_chan = start_sending(start_chan, metadata);
+    progress_chan.send(Payload(data.bytes.deref().clone()));
+    progress_chan.send(Done(Ok(())));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlobData, BlobURLStore, factory};
+    use resource_task::{LoadData, Payload, Done};
+    use std::comm;
+    use std::sync::Arc;
+    use url::Url;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    fn blob(bytes: &[u8], content_type: &str) -> BlobData {
+        BlobData {
+            bytes: Arc::new(bytes.to_vec()),
+            content_type: content_type.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_store() {
+        let blob_url = url("blob:https://example.com/1d6a9a1e-2cbc-4f51-9e9b-3a0f5bb1e3c1");
+        let mut store = BlobURLStore::new();
+        store.add(&blob_url, blob(b"abc", "text/plain"));
+        let with_fragment = url("blob:https://example.com/1d6a9a1e-2cbc-4f51-9e9b-3a0f5bb1e3c1#x");
+        assert!(store.get(&with_fragment).is_some());
+        store.revoke(&blob_url);
+        assert!(store.get(&blob_url).is_none());
+    }
+
+    #[test]
+    fn test_load() {
+        let (start_chan, start_port) = comm::channel();
+        let load_data = LoadData::new(url("blob:null/2a5e1b4c-51e2-4c0b-8a8b-0e6f4e0c1f2d"));
+        factory(load_data, start_chan, Some(blob(b"<p>hi", "text/html;charset=utf-8")));
+        let response = start_port.recv();
+        assert_eq!(response.metadata.content_type,
+                   Some(("text".to_string(), "html".to_string())));
+        assert_eq!(response.metadata.charset, Some("utf-8".to_string()));
+        assert_eq!(response.progress_port.recv(), Payload(b"<p>hi".to_vec()));
+        assert_eq!(response.progress_port.recv(), Done(Ok(())));
+
+        let (start_chan, start_port) = comm::channel();
+        let load_data = LoadData::new(url("blob:null/2a5e1b4c-51e2-4c0b-8a8b-0e6f4e0c1f2d"));
+        factory(load_data, start_chan, None);
+        let response = start_port.recv();
+        assert_eq!(response.progress_port.recv(), Done(Err("invalid blob URL".to_string())));
+    }
+}
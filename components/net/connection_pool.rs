@@ -0,0 +1,351 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The pool of persistent HTTP connections owned by the resource task.
+//!
+//! A loader checks a connection out of the pool before sending each request. The pool either
+//! hands back an idle connection to the same server, or allows a new one to be opened as long
+//! as the server's limit of concurrent connections hasn't been reached; otherwise the loader
+//! waits for another load to finish. When the response has been read, the connection is
+//! returned to the pool if it can carry another request, and closed if not.
+
+use proxy::ProxiedStream;
+use resource_task::{ResourceTask, ReleaseConnection};
+
+use http::connecter::Connecter;
+use servo_util::opts::Opts;
+use std::collections::hashmap::HashMap;
+use std::io::IoResult;
+use std::io::net::ip::SocketAddr;
+use time;
+use time::Timespec;
+use url::Url;
+
+/// The answer to a loader asking for a connection.
+pub enum Checkout {
+    /// Send the request on this idle connection.
+    ReuseConnection(ProxiedStream),
+    /// Open a new connection.
+    NewConnection,
+}
+
+struct IdleConnection {
+    stream: ProxiedStream,
+    /// When the connection was returned to the pool.
+    since: Timespec,
+}
+
+/// The connections to a single server, or to a single proxy route.
+struct HostConnections {
+    /// The number of connections checked out by loaders.
+    in_use: uint,
+    idle: Vec<IdleConnection>,
+    /// Loaders waiting for the number of connections in use to drop below the limit.
+    waiting: Vec<Sender<Checkout>>,
+}
+
+pub struct ConnectionPool {
+    max_connections_per_host: uint,
+    /// How long, in seconds, an idle connection is kept open.
+    idle_timeout: i64,
+    hosts: HashMap<String, HostConnections>,
+}
+
+impl ConnectionPool {
+    pub fn new(opts: &Opts) -> ConnectionPool {
+        ConnectionPool {
+            max_connections_per_host: opts.max_connections_per_host,
+            idle_timeout: opts.idle_connection_timeout as i64,
+            hosts: HashMap::new(),
+        }
+    }
+
+    /// Answer a loader asking for a connection to the server identified by `key`, now if a
+    /// connection is available and otherwise when one is released.
+    pub fn checkout(&mut self, key: String, consumer: Sender<Checkout>) {
+        self.close_expired_connections();
+        let max_connections_per_host = self.max_connections_per_host;
+        let host = self.hosts.find_or_insert_with(key, |_| {
+            HostConnections {
+                in_use: 0,
+                idle: vec!(),
+                waiting: vec!(),
+            }
+        });
+        if host.in_use < max_connections_per_host {
+            host.hand_out(consumer);
+        } else {
+            debug!("connection_pool: waiting for a connection");
+            host.waiting.push(consumer);
+        }
+    }
+
+    /// Take back a connection checked out for `key`. `None` means that the connection has been
+    /// closed, rather than being left ready for another request.
+    pub fn release(&mut self, key: String, stream: Option<ProxiedStream>) {
+        let max_connections_per_host = self.max_connections_per_host;
+        match self.hosts.find_mut(&key) {
+            Some(host) => {
+                host.in_use -= 1;
+                match stream {
+                    Some(stream) => {
+                        host.idle.push(IdleConnection {
+                            stream: stream,
+                            since: time::get_time(),
+                        })
+                    }
+                    None => {}
+                }
+                while host.in_use < max_connections_per_host && !host.waiting.is_empty() {
+                    let consumer = host.waiting.remove(0).unwrap();
+                    host.hand_out(consumer);
+                }
+            }
+            None => {}
+        }
+        self.close_expired_connections();
+    }
+
+    /// Close connections that have been idle for longer than the timeout, and forget servers
+    /// with no connections left.
+    fn close_expired_connections(&mut self) {
+        let oldest = time::get_time().sec - self.idle_timeout;
+        let mut unused = vec!();
+        for (key, host) in self.hosts.iter_mut() {
+            host.idle.retain(|connection| connection.since.sec > oldest);
+            if host.in_use == 0 && host.idle.is_empty() && host.waiting.is_empty() {
+                unused.push(key.clone());
+            }
+        }
+        for key in unused.iter() {
+            self.hosts.remove(key);
+        }
+    }
+}
+
+impl HostConnections {
+    fn hand_out(&mut self, consumer: Sender<Checkout>) {
+        // The most recently used connection is the least likely to have been closed by the
+        // server.
+        let checkout = match self.idle.pop() {
+            Some(connection) => ReuseConnection(connection.stream),
+            None => NewConnection,
+        };
+        match consumer.send_opt(checkout) {
+            Ok(()) => self.in_use += 1,
+            Err(ReuseConnection(stream)) => {
+                self.idle.push(IdleConnection {
+                    stream: stream,
+                    since: time::get_time(),
+                })
+            }
+            Err(NewConnection) => {}
+        }
+    }
+}
+
+/// The key identifying the connections that a request for `url` through `proxy` may use.
+pub fn pool_key(url: &Url, proxy: Option<&Url>) -> String {
+    let origin = format!("{:s}://{:s}:{}", url.scheme,
+                         url.serialize_host().unwrap_or(String::new()),
+                         url.port_or_default().unwrap_or(0));
+    match proxy {
+        // Plain HTTP requests to any server can share connections to the proxy, but tunnels
+        // lead to a single server.
+        Some(proxy) if url.scheme.as_slice() == "http" => {
+            format!("proxy {:s}", proxy.serialize_no_fragment())
+        }
+        Some(proxy) => format!("{:s} via {:s}", origin, proxy.serialize_no_fragment()),
+        None => origin,
+    }
+}
+
+/// A connection checked out of the pool for the load running on the current task.
+struct Lease {
+    key: String,
+    resource_task: ResourceTask,
+    stream: Option<ProxiedStream>,
+}
+
+local_data_key!(current_lease: Lease)
+local_data_key!(current_connection_reusable: bool)
+
+/// Make the connection checked out for `key` available to the next `PooledStream` opened on the
+/// current task. Returns whether an existing connection is being reused.
+pub fn lease_connection(key: String, resource_task: ResourceTask, checkout: Checkout) -> bool {
+    let stream = match checkout {
+        ReuseConnection(stream) => Some(stream),
+        NewConnection => None,
+    };
+    let reused = stream.is_some();
+    release_unused_lease();
+    current_connection_reusable.replace(None);
+    current_lease.replace(Some(Lease {
+        key: key,
+        resource_task: resource_task,
+        stream: stream,
+    }));
+    reused
+}
+
+/// Give back a connection that was checked out but never used.
+pub fn release_unused_lease() {
+    match current_lease.replace(None) {
+        Some(lease) => lease.resource_task.send(ReleaseConnection(lease.key, lease.stream)),
+        None => {}
+    }
+}
+
+/// Note that the response on the current task's connection has been read completely, and that
+/// the connection may carry another request.
+pub fn set_connection_reusable() {
+    current_connection_reusable.replace(Some(true));
+}
+
+/// A connection that goes back to the pool it was checked out of when it is dropped.
+pub struct PooledStream {
+    stream: Option<ProxiedStream>,
+    lease: Option<(String, ResourceTask)>,
+}
+
+impl Connecter for PooledStream {
+    fn connect(addr: SocketAddr, host: &str, use_ssl: bool) -> IoResult<PooledStream> {
+        let (stream, lease) = match current_lease.replace(None) {
+            Some(Lease { key, resource_task, stream: Some(stream) }) => {
+                (stream.reuse(), Some((key, resource_task)))
+            }
+            Some(Lease { key, resource_task, stream: None }) => {
+                let connected: IoResult<ProxiedStream> = Connecter::connect(addr, host, use_ssl);
+                match connected {
+                    Ok(stream) => (stream, Some((key, resource_task))),
+                    Err(e) => {
+                        resource_task.send(ReleaseConnection(key, None));
+                        return Err(e);
+                    }
+                }
+            }
+            None => {
+                let stream: ProxiedStream = try!(Connecter::connect(addr, host, use_ssl));
+                (stream, None)
+            }
+        };
+        Ok(PooledStream {
+            stream: Some(stream),
+            lease: lease,
+        })
+    }
+}
+
+impl Reader for PooledStream {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        self.stream.as_mut().unwrap().read(buf)
+    }
+}
+
+impl Writer for PooledStream {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        self.stream.as_mut().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.stream.as_mut().unwrap().flush()
+    }
+}
+
+impl Drop for PooledStream {
+    fn drop(&mut self) {
+        match self.lease.take() {
+            Some((key, resource_task)) => {
+                let reusable = current_connection_reusable.replace(None).map_or(false, |r| r);
+                let stream = if reusable { self.stream.take() } else { None };
+                let _ = resource_task.send_opt(ReleaseConnection(key, stream));
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConnectionPool, NewConnection, pool_key};
+    use resource_task::{new_resource_task, load_whole_resource, ResourceHandlers, Exit};
+    use servo_util::opts::default_opts;
+    use url::{Url, UrlParser};
+
+    #[test]
+    fn test_max_connections_per_host() {
+        let mut opts = default_opts();
+        opts.max_connections_per_host = 1;
+        let mut pool = ConnectionPool::new(&opts);
+        let key = "http://example.com:80".to_string();
+
+        let (first, first_port) = channel();
+        pool.checkout(key.clone(), first);
+        assert!(match first_port.try_recv() { Ok(NewConnection) => true, _ => false });
+
+        // A second load waits until the first connection is closed.
+        let (second, second_port) = channel();
+        pool.checkout(key.clone(), second);
+        assert!(second_port.try_recv().is_err());
+        pool.release(key.clone(), None);
+        assert!(match second_port.try_recv() { Ok(NewConnection) => true, _ => false });
+
+        // Other servers are unaffected.
+        let (other, other_port) = channel();
+        pool.checkout("http://example.org:80".to_string(), other);
+        assert!(other_port.try_recv().is_ok());
+    }
+
+    #[test]
+    fn test_pool_key() {
+        let url = Url::parse("http://example.com/foo").unwrap();
+        let secure_url = Url::parse("https://example.com:8443/foo").unwrap();
+        let proxy = Url::parse("http://proxy.example:3128/").unwrap();
+        assert_eq!(pool_key(&url, None), "http://example.com:80".to_string());
+        assert_eq!(pool_key(&secure_url, None), "https://example.com:8443".to_string());
+        assert_eq!(pool_key(&url, Some(&proxy)), "proxy http://proxy.example:3128/".to_string());
+        assert_eq!(pool_key(&secure_url, Some(&proxy)),
+                   "https://example.com:8443 via http://proxy.example:3128/".to_string());
+    }
+
+    #[test]
+    fn test_connection_reuse() {
+        use std::io::{Acceptor, Listener};
+        use std::io::net::tcp::TcpListener;
+        use std::str;
+
+        // The server answers two requests on a single connection, the first with a chunked body.
+        let mut listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+        let port = listener.socket_name().unwrap().port;
+        spawn(proc() {
+            let mut acceptor = listener.listen().unwrap();
+            let mut stream = acceptor.accept().unwrap();
+            let responses = [
+                "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nworld",
+            ];
+            for response in responses.iter() {
+                let mut request = vec!();
+                while !str::from_utf8(request.as_slice())
+                           .map_or(false, |r| r.contains("\r\n\r\n")) {
+                    let mut buf = [0u8, ..1024];
+                    match stream.read(&mut buf) {
+                        Ok(len) => request.push_all(buf.slice_to(len)),
+                        Err(_) => break,
+                    }
+                }
+                stream.write(response.as_bytes()).unwrap();
+            }
+        });
+
+        let resource_task = new_resource_task(&default_opts(), ResourceHandlers::new());
+        let url = Url::parse(format!("http://127.0.0.1:{}/", port).as_slice()).unwrap();
+        let (_, body) = load_whole_resource(&resource_task, url.clone()).unwrap();
+        assert_eq!(body.as_slice(), b"hello");
+        let url = UrlParser::new().base_url(&url).parse("other").unwrap();
+        let (_, body) = load_whole_resource(&resource_task, url).unwrap();
+        assert_eq!(body.as_slice(), b"world");
+        resource_task.send(Exit);
+    }
+}
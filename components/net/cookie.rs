@@ -0,0 +1,448 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Implementation of cookie parsing and matching as described in
+//! [RFC 6265](http://tools.ietf.org/html/rfc6265).
+
+use std::ascii::{StrAsciiExt, OwnedStrAsciiExt};
+use std::io::net::ip::IpAddr;
+use time;
+use time::{Timespec, Tm};
+use url::Url;
+
+/// The source of a cookie operation, used to enforce the `HttpOnly` flag.
+#[deriving(Clone, PartialEq)]
+pub enum CookieSource {
+    /// An HTTP API, such as `Set-Cookie` and `Cookie` headers.
+    HTTP,
+    /// A non-HTTP API, such as `document.cookie`.
+    NonHTTP,
+}
+
+/// A cookie as stored by the user agent.
+/// http://tools.ietf.org/html/rfc6265#section-5.3
+#[deriving(Clone)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    pub path: String,
+    /// `None` for a session cookie, which lives until the resource task exits.
+    pub expiry_time: Option<Timespec>,
+    pub creation_time: Timespec,
+    pub last_access_time: Timespec,
+    pub host_only: bool,
+    pub secure_only: bool,
+    pub http_only: bool,
+}
+
+impl Cookie {
+    /// Parse a `Set-Cookie` string received from `request` and apply the storage model
+    /// checks. Returns `None` if the cookie must be ignored.
+    /// http://tools.ietf.org/html/rfc6265#section-5.2
+    pub fn new_wrapped(set_cookie_string: &str, request: &Url, source: CookieSource)
+                       -> Option<Cookie> {
+        let request_host = match canonical_host(request) {
+            Some(host) => host,
+            None => return None,
+        };
+
+        // Steps 1-2: split off the name-value pair.
+        let (name_value_pair, unparsed_attributes) = match set_cookie_string.find(';') {
+            Some(index) => (set_cookie_string.slice_to(index),
+                            set_cookie_string.slice_from(index + 1)),
+            None => (set_cookie_string, ""),
+        };
+
+        // Steps 3-5: an empty name or a missing '=' means the cookie is ignored.
+        let (name, value) = match name_value_pair.find('=') {
+            Some(index) => (name_value_pair.slice_to(index).trim(),
+                            name_value_pair.slice_from(index + 1).trim()),
+            None => return None,
+        };
+        if name.is_empty() {
+            return None
+        }
+
+        let now = time::get_time();
+        let mut expires = None;
+        let mut max_age = None;
+        let mut domain = None;
+        let mut path = None;
+        let mut secure_only = false;
+        let mut http_only = false;
+
+        // http://tools.ietf.org/html/rfc6265#section-5.2 (attribute processing)
+        for cookie_av in unparsed_attributes.split(';') {
+            let (attribute_name, attribute_value) = match cookie_av.find('=') {
+                Some(index) => (cookie_av.slice_to(index).trim(),
+                                cookie_av.slice_from(index + 1).trim()),
+                None => (cookie_av.trim(), ""),
+            };
+            match attribute_name.to_ascii_lower().as_slice() {
+                // http://tools.ietf.org/html/rfc6265#section-5.2.1
+                "expires" => {
+                    match parse_cookie_date(attribute_value) {
+                        Some(date) => expires = Some(date),
+                        None => {}
+                    }
+                }
+                // http://tools.ietf.org/html/rfc6265#section-5.2.2
+                "max-age" => {
+                    let valid = attribute_value.chars().enumerate().all(|(i, c)| {
+                        c.is_digit() || (i == 0 && c == '-')
+                    });
+                    if !valid || attribute_value.is_empty() {
+                        continue
+                    }
+                    match from_str::<i64>(attribute_value) {
+                        Some(delta) if delta <= 0 => max_age = Some(Timespec::new(0, 0)),
+                        Some(delta) => max_age = Some(Timespec::new(now.sec + delta, now.nsec)),
+                        None => {}
+                    }
+                }
+                // http://tools.ietf.org/html/rfc6265#section-5.2.3
+                "domain" => {
+                    if attribute_value.is_empty() {
+                        continue
+                    }
+                    let value = if attribute_value.starts_with(".") {
+                        attribute_value.slice_from(1)
+                    } else {
+                        attribute_value
+                    };
+                    domain = Some(value.to_string().into_ascii_lower());
+                }
+                // http://tools.ietf.org/html/rfc6265#section-5.2.4
+                "path" => {
+                    if attribute_value.is_empty() || !attribute_value.starts_with("/") {
+                        path = None;
+                    } else {
+                        path = Some(attribute_value.to_string());
+                    }
+                }
+                // http://tools.ietf.org/html/rfc6265#section-5.2.5
+                "secure" => secure_only = true,
+                // http://tools.ietf.org/html/rfc6265#section-5.2.6
+                "httponly" => http_only = true,
+                _ => {}
+            }
+        }
+
+        // http://tools.ietf.org/html/rfc6265#section-5.3
+        // Step 3: Max-Age takes precedence over Expires.
+        let expiry_time = max_age.or(expires);
+
+        // Steps 4-6: domain matching. We do not yet consult a public suffix list.
+        let (domain, host_only) = match domain {
+            Some(ref domain) if !domain.is_empty() => {
+                if !domain_match(request_host.as_slice(), domain.as_slice()) {
+                    return None
+                }
+                (domain.clone(), false)
+            }
+            _ => (request_host, true),
+        };
+
+        // Step 7
+        let path = path.unwrap_or_else(|| default_path(request));
+
+        // Step 10
+        if http_only && source == NonHTTP {
+            return None
+        }
+
+        Some(Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: domain,
+            path: path,
+            expiry_time: expiry_time,
+            creation_time: now,
+            last_access_time: now,
+            host_only: host_only,
+            secure_only: secure_only,
+            http_only: http_only,
+        })
+    }
+
+    /// Whether this cookie has passed its expiry time.
+    pub fn is_expired(&self, now: Timespec) -> bool {
+        match self.expiry_time {
+            Some(expiry_time) => expiry_time <= now,
+            None => false,
+        }
+    }
+
+    /// Whether this cookie should be included when retrieving cookies for `url`.
+    /// http://tools.ietf.org/html/rfc6265#section-5.4 step 1
+    pub fn appropriate_for_url(&self, url: &Url, source: CookieSource) -> bool {
+        let host = match canonical_host(url) {
+            Some(host) => host,
+            None => return false,
+        };
+        if self.host_only {
+            if self.domain != host {
+                return false
+            }
+        } else if !domain_match(host.as_slice(), self.domain.as_slice()) {
+            return false
+        }
+
+        let path = url.serialize_path().unwrap_or_else(|| "/".to_string());
+        if !path_match(path.as_slice(), self.path.as_slice()) {
+            return false
+        }
+
+        if self.secure_only && url.scheme.as_slice() != "https" {
+            return false
+        }
+        if self.http_only && source == NonHTTP {
+            return false
+        }
+        true
+    }
+
+    /// Whether `other` would replace this cookie in the cookie store.
+    /// http://tools.ietf.org/html/rfc6265#section-5.3 step 11
+    pub fn is_same_cookie(&self, other: &Cookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+}
+
+/// The lowercased host of `url`, or `None` if it has no host.
+fn canonical_host(url: &Url) -> Option<String> {
+    url.host().map(|host| host.serialize().into_ascii_lower())
+}
+
+/// http://tools.ietf.org/html/rfc6265#section-5.1.3
+pub fn domain_match(string: &str, domain_string: &str) -> bool {
+    if string == domain_string {
+        return true
+    }
+    if !string.ends_with(domain_string) || string.len() <= domain_string.len() {
+        return false
+    }
+    let boundary = string.len() - domain_string.len() - 1;
+    string.as_bytes()[boundary] == b'.' && from_str::<IpAddr>(string).is_none()
+}
+
+/// http://tools.ietf.org/html/rfc6265#section-5.1.4
+pub fn default_path(url: &Url) -> String {
+    let path = url.serialize_path().unwrap_or_else(|| "/".to_string());
+    if !path.as_slice().starts_with("/") {
+        return "/".to_string()
+    }
+    match path.as_slice().rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => path.as_slice().slice_to(index).to_string(),
+    }
+}
+
+/// http://tools.ietf.org/html/rfc6265#section-5.1.4
+pub fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    if request_path == cookie_path {
+        return true
+    }
+    request_path.starts_with(cookie_path) &&
+        (cookie_path.ends_with("/") ||
+         request_path.as_bytes()[cookie_path.len()] == b'/')
+}
+
+/// http://tools.ietf.org/html/rfc6265#section-5.1.1
+fn is_date_delimiter(c: char) -> bool {
+    c == '\x09' ||
+        (c >= '\x20' && c <= '\x2F') ||
+        (c >= '\x3B' && c <= '\x40') ||
+        (c >= '\x5B' && c <= '\x60') ||
+        (c >= '\x7B' && c <= '\x7E')
+}
+
+/// Parses a token consisting of between `min` and `max` leading digits, ignoring any trailing
+/// non-digit characters.
+fn parse_digits(token: &str, min: uint, max: uint) -> Option<i32> {
+    let count = token.chars().take_while(|c| c.is_digit()).count();
+    if count < min || count > max {
+        return None
+    }
+    from_str(token.slice_to(count))
+}
+
+fn parse_time(token: &str) -> Option<(i32, i32, i32)> {
+    let parts: Vec<&str> = token.splitn(2, ':').collect();
+    if parts.len() != 3 {
+        return None
+    }
+    match (parse_digits(parts[0], 1, 2), parse_digits(parts[1], 1, 2), parse_digits(parts[2], 1, 2)) {
+        (Some(hour), Some(minute), Some(second)) => Some((hour, minute, second)),
+        _ => None,
+    }
+}
+
+fn parse_month(token: &str) -> Option<i32> {
+    if token.len() < 3 {
+        return None
+    }
+    static MONTHS: [&'static str, ..12] = ["jan", "feb", "mar", "apr", "may", "jun",
+                                           "jul", "aug", "sep", "oct", "nov", "dec"];
+    let prefix = token.slice_to(3).to_ascii_lower();
+    MONTHS.iter().position(|&month| month == prefix.as_slice()).map(|index| index as i32)
+}
+
+/// Parse a cookie date as described in
+/// http://tools.ietf.org/html/rfc6265#section-5.1.1
+pub fn parse_cookie_date(date: &str) -> Option<Timespec> {
+    let mut time = None;
+    let mut day_of_month = None;
+    let mut month = None;
+    let mut year = None;
+
+    for token in date.split(is_date_delimiter).filter(|token| !token.is_empty()) {
+        if time.is_none() {
+            time = parse_time(token);
+            if time.is_some() {
+                continue
+            }
+        }
+        if day_of_month.is_none() {
+            day_of_month = parse_digits(token, 1, 2);
+            if day_of_month.is_some() {
+                continue
+            }
+        }
+        if month.is_none() {
+            month = parse_month(token);
+            if month.is_some() {
+                continue
+            }
+        }
+        if year.is_none() {
+            year = parse_digits(token, 2, 4);
+        }
+    }
+
+    let (hour, minute, second) = match time {
+        Some(time) => time,
+        None => return None,
+    };
+    let (day_of_month, month, year) = match (day_of_month, month, year) {
+        (Some(d), Some(m), Some(y)) => (d, m, y),
+        _ => return None,
+    };
+    let year = match year {
+        70..99 => year + 1900,
+        0..69 => year + 2000,
+        _ => year,
+    };
+    if day_of_month < 1 || day_of_month > 31 || year < 1601 ||
+            hour > 23 || minute > 59 || second > 59 {
+        return None
+    }
+
+    let tm = Tm {
+        tm_sec: second,
+        tm_min: minute,
+        tm_hour: hour,
+        tm_mday: day_of_month,
+        tm_mon: month,
+        tm_year: year - 1900,
+        tm_wday: 0,
+        tm_yday: 0,
+        tm_isdst: 0,
+        tm_gmtoff: 0,
+        tm_nsec: 0,
+    };
+    Some(tm.to_timespec())
+}
+
+#[test]
+fn test_domain_match() {
+    assert!(domain_match("foo.com", "foo.com"));
+    assert!(domain_match("bar.foo.com", "foo.com"));
+    assert!(domain_match("baz.bar.foo.com", "foo.com"));
+
+    assert!(!domain_match("bar.foo.com", "bar.com"));
+    assert!(!domain_match("bar.com", "baz.bar.com"));
+    assert!(!domain_match("foo.com", "bar.com"));
+    assert!(!domain_match("barfoo.com", "foo.com"));
+    assert!(!domain_match("127.0.0.1", "0.0.1"));
+}
+
+#[test]
+fn test_path_match() {
+    assert!(path_match("/", "/"));
+    assert!(path_match("/index.html", "/"));
+    assert!(path_match("/w/index.html", "/"));
+    assert!(path_match("/w/index.html", "/w/index.html"));
+    assert!(path_match("/w/index.html", "/w/"));
+    assert!(path_match("/w/index.html", "/w"));
+
+    assert!(!path_match("/", "/w/"));
+    assert!(!path_match("/a", "/w/"));
+    assert!(!path_match("/", "/w"));
+    assert!(!path_match("/wa", "/w"));
+}
+
+#[test]
+fn test_default_path() {
+    assert_eq!(default_path(&Url::parse("http://example.com/foo/bar").unwrap()).as_slice(), "/foo");
+    assert_eq!(default_path(&Url::parse("http://example.com/foo/").unwrap()).as_slice(), "/foo");
+    assert_eq!(default_path(&Url::parse("http://example.com/foo").unwrap()).as_slice(), "/");
+    assert_eq!(default_path(&Url::parse("http://example.com/").unwrap()).as_slice(), "/");
+}
+
+#[test]
+fn test_parse_cookie_date() {
+    let expected = Some(Timespec::new(1445412480, 0));
+    assert!(parse_cookie_date("Wed, 21 Oct 2015 07:28:00 GMT") == expected);
+    assert!(parse_cookie_date("Wednesday, 21-Oct-15 07:28:00 GMT") == expected);
+    assert!(parse_cookie_date("Wed Oct 21 07:28:00 2015") == expected);
+    assert!(parse_cookie_date("Wed, 21 Oct 2015") == None);
+    assert!(parse_cookie_date("Wed, 32 Oct 2015 07:28:00 GMT") == None);
+}
+
+#[test]
+fn test_cookie_parsing() {
+    let url = Url::parse("http://example.com/foo/bar").unwrap();
+
+    assert!(Cookie::new_wrapped("=value", &url, HTTP).is_none());
+    assert!(Cookie::new_wrapped("novalue", &url, HTTP).is_none());
+    assert!(Cookie::new_wrapped("a=b; Domain=other.com", &url, HTTP).is_none());
+    assert!(Cookie::new_wrapped("a=b; HttpOnly", &url, NonHTTP).is_none());
+
+    let cookie = Cookie::new_wrapped(" a = b ; Secure; HttpOnly; Domain=.Example.com; Path=/",
+                                     &url, HTTP).unwrap();
+    assert_eq!(cookie.name.as_slice(), "a");
+    assert_eq!(cookie.value.as_slice(), "b");
+    assert_eq!(cookie.domain.as_slice(), "example.com");
+    assert_eq!(cookie.path.as_slice(), "/");
+    assert!(!cookie.host_only);
+    assert!(cookie.secure_only);
+    assert!(cookie.http_only);
+    assert!(cookie.expiry_time.is_none());
+
+    let cookie = Cookie::new_wrapped("a=b; Path=relative", &url, HTTP).unwrap();
+    assert_eq!(cookie.domain.as_slice(), "example.com");
+    assert_eq!(cookie.path.as_slice(), "/foo");
+    assert!(cookie.host_only);
+
+    let cookie = Cookie::new_wrapped("a=b; Max-Age=0; Expires=Wed, 21 Oct 2037 07:28:00 GMT",
+                                     &url, HTTP).unwrap();
+    assert!(cookie.is_expired(time::get_time()));
+}
+
+#[test]
+fn test_appropriate_for_url() {
+    let url = Url::parse("http://example.com/foo/bar").unwrap();
+    let cookie = Cookie::new_wrapped("a=b; Domain=example.com", &url, HTTP).unwrap();
+    assert!(cookie.appropriate_for_url(&Url::parse("http://sub.example.com/foo").unwrap(), HTTP));
+    assert!(!cookie.appropriate_for_url(&Url::parse("http://example.com/").unwrap(), HTTP));
+    assert!(!cookie.appropriate_for_url(&Url::parse("http://example.org/foo").unwrap(), HTTP));
+
+    let cookie = Cookie::new_wrapped("a=b; Path=/; Secure; HttpOnly", &url, HTTP).unwrap();
+    assert!(!cookie.appropriate_for_url(&Url::parse("http://sub.example.com/").unwrap(), HTTP));
+    assert!(!cookie.appropriate_for_url(&Url::parse("http://example.com/").unwrap(), HTTP));
+    assert!(cookie.appropriate_for_url(&Url::parse("https://example.com/").unwrap(), HTTP));
+    assert!(!cookie.appropriate_for_url(&Url::parse("https://example.com/").unwrap(), NonHTTP));
+}
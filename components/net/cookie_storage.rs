@@ -0,0 +1,130 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The cookie store owned by the resource task, implementing the storage model in
+//! [RFC 6265](http://tools.ietf.org/html/rfc6265#section-5.3).
+
+use cookie::{Cookie, CookieSource, NonHTTP};
+use time;
+use url::Url;
+
+pub struct CookieStorage {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieStorage {
+    pub fn new() -> CookieStorage {
+        CookieStorage {
+            cookies: Vec::new(),
+        }
+    }
+
+    /// Parse a `Set-Cookie` string received from `url` and store the resulting cookie.
+    pub fn set_cookie_for_url(&mut self, url: &Url, set_cookie_string: &str,
+                              source: CookieSource) {
+        match Cookie::new_wrapped(set_cookie_string, url, source) {
+            Some(cookie) => self.push(cookie, source),
+            None => debug!("cookie_storage: ignoring cookie {:s}", set_cookie_string),
+        }
+    }
+
+    /// Insert a cookie, replacing any existing cookie with the same name, domain and path.
+    /// http://tools.ietf.org/html/rfc6265#section-5.3 steps 11-12
+    pub fn push(&mut self, mut cookie: Cookie, source: CookieSource) {
+        let position = self.cookies.iter().position(|c| c.is_same_cookie(&cookie));
+        match position {
+            Some(index) => {
+                if self.cookies[index].http_only && source == NonHTTP {
+                    return
+                }
+                let old_cookie = self.cookies.remove(index).unwrap();
+                cookie.creation_time = old_cookie.creation_time;
+            }
+            None => {}
+        }
+
+        // A cookie that is already expired only serves to evict the old one.
+        if cookie.is_expired(time::get_time()) {
+            return
+        }
+        self.cookies.push(cookie);
+    }
+
+    /// Remove all expired cookies.
+    pub fn remove_expired(&mut self) {
+        let now = time::get_time();
+        self.cookies.retain(|cookie| !cookie.is_expired(now));
+    }
+
+    /// Serialize the cookies that should be sent to `url` into a `Cookie` header value.
+    /// http://tools.ietf.org/html/rfc6265#section-5.4
+    pub fn cookies_for_url(&mut self, url: &Url, source: CookieSource) -> Option<String> {
+        self.remove_expired();
+
+        let now = time::get_time();
+        let mut matching: Vec<&mut Cookie> = self.cookies.iter_mut().filter(|cookie| {
+            cookie.appropriate_for_url(url, source)
+        }).collect();
+
+        // Step 2: longer paths first, then earlier creation times first.
+        matching.sort_by(|a, b| {
+            match b.path.len().cmp(&a.path.len()) {
+                Equal => a.creation_time.cmp(&b.creation_time),
+                ordering => ordering,
+            }
+        });
+
+        let pairs: Vec<String> = matching.into_iter().map(|cookie| {
+            // Step 3
+            cookie.last_access_time = now;
+            format!("{:s}={:s}", cookie.name, cookie.value)
+        }).collect();
+
+        if pairs.is_empty() {
+            None
+        } else {
+            Some(pairs.connect("; "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cookie::{HTTP, NonHTTP};
+    use super::CookieStorage;
+    use url::Url;
+
+    #[test]
+    fn test_cookies_for_url_ordering() {
+        let url = Url::parse("http://example.com/foo/bar").unwrap();
+        let mut storage = CookieStorage::new();
+        storage.set_cookie_for_url(&url, "a=1; Path=/", HTTP);
+        storage.set_cookie_for_url(&url, "b=2; Path=/foo", HTTP);
+        storage.set_cookie_for_url(&url, "c=3; Path=/", HTTP);
+        assert_eq!(storage.cookies_for_url(&url, HTTP), Some("b=2; a=1; c=3".to_string()));
+        assert_eq!(storage.cookies_for_url(&Url::parse("http://example.org/").unwrap(), HTTP), None);
+    }
+
+    #[test]
+    fn test_cookie_replacement_and_expiry() {
+        let url = Url::parse("http://example.com/").unwrap();
+        let mut storage = CookieStorage::new();
+        storage.set_cookie_for_url(&url, "a=1", HTTP);
+        storage.set_cookie_for_url(&url, "a=2", HTTP);
+        assert_eq!(storage.cookies_for_url(&url, HTTP), Some("a=2".to_string()));
+
+        storage.set_cookie_for_url(&url, "a=3; Max-Age=0", HTTP);
+        assert_eq!(storage.cookies_for_url(&url, HTTP), None);
+    }
+
+    #[test]
+    fn test_http_only() {
+        let url = Url::parse("http://example.com/").unwrap();
+        let mut storage = CookieStorage::new();
+        storage.set_cookie_for_url(&url, "a=1; HttpOnly", HTTP);
+        storage.set_cookie_for_url(&url, "a=2", NonHTTP);
+        assert_eq!(storage.cookies_for_url(&url, NonHTTP), None);
+        assert_eq!(storage.cookies_for_url(&url, HTTP), Some("a=1".to_string()));
+    }
+}
@@ -0,0 +1,631 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! [Content Security Policy](http://www.w3.org/TR/CSP2/) parsing and matching.
+//!
+//! A document's policies come from its `Content-Security-Policy` and
+//! `Content-Security-Policy-Report-Only` headers, and from `<meta http-equiv>` elements. The
+//! tasks that load or run content on behalf of the document check it against the policies,
+//! and report the violations they find to the document's script task.
+
+use fetch::request::{Context, Script, Worker, SharedWorker, ServiceWorker, Style, Import};
+use fetch::request::{Image, ImageSet, Favicon, Frame, IFrame, Font, Audio, Video, Track};
+use fetch::request::{Object, Embed, Plugin, XMLHttpRequest, Eventsource, Fetch, Beacon, Ping};
+use fetch::request::is_same_origin;
+
+use openssl::crypto::hash::{hash, HashType, SHA256, SHA384, SHA512};
+use serialize::base64::{ToBase64, STANDARD};
+use std::ascii::StrAsciiExt;
+use url::Url;
+
+/// A [source expression](http://www.w3.org/TR/CSP2/#source-list-syntax).
+#[deriving(Clone, PartialEq, Show)]
+enum SourceExpression {
+    SelfSource,
+    UnsafeInline,
+    UnsafeEval,
+    /// `*`, which matches any URL whose scheme isn't `data`, `blob` or `filesystem`.
+    WildcardSource,
+    /// A scheme, such as `https:`.
+    SchemeSource(String),
+    HostSource(HostExpression),
+    /// A nonce that inline content must carry to run.
+    NonceSource(String),
+    /// The base64 digest that inline content must have to run.
+    HashSource(HashAlgorithm, String),
+}
+
+#[deriving(Clone, PartialEq, Show)]
+enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    fn hash_type(&self) -> HashType {
+        match *self {
+            Sha256 => SHA256,
+            Sha384 => SHA384,
+            Sha512 => SHA512,
+        }
+    }
+}
+
+/// A host source, such as `https://*.example.com:443/path`.
+#[deriving(Clone, PartialEq, Show)]
+struct HostExpression {
+    scheme: Option<String>,
+    /// The host, which is `*` for any host, or starts with `*.` for any subdomain.
+    host: String,
+    /// The port, which is `None` for `*`. Without a port, only the default port of the scheme
+    /// matches.
+    port: Option<Option<u16>>,
+    path: Option<String>,
+}
+
+#[deriving(Clone, Show)]
+struct Directive {
+    /// The lowercase name of the directive.
+    name: String,
+    /// The directive as written, for violation reports.
+    text: String,
+    sources: Vec<SourceExpression>,
+}
+
+/// A single policy.
+#[deriving(Clone, Show)]
+struct Policy {
+    /// The policy as written, for violation reports.
+    text: String,
+    /// Whether violations are only reported, rather than blocked.
+    report_only: bool,
+    directives: Vec<Directive>,
+}
+
+/// The policies that apply to a document.
+#[deriving(Clone, Show)]
+pub struct ContentSecurityPolicy {
+    /// The URL of the document, which `'self'` stands for.
+    document_url: Url,
+    policies: Vec<Policy>,
+}
+
+/// A load or an inline script or style that a policy doesn't allow.
+#[deriving(Clone, Show)]
+pub struct Violation {
+    pub document_url: Url,
+    /// The URL of the blocked load, or `None` for inline content.
+    pub blocked_url: Option<Url>,
+    /// The directive that was checked, such as `script-src`.
+    pub effective_directive: String,
+    /// The directive that was violated, as written. This is `default-src` when the policy has
+    /// no effective directive.
+    pub violated_directive: String,
+    pub original_policy: String,
+    /// Whether the violated policy is report-only, in which case nothing was blocked.
+    pub report_only: bool,
+    /// Whether a string was to be evaluated as code, with `eval()` and the like.
+    pub eval: bool,
+}
+
+/// The outcome of a check against the policies of a document.
+pub struct CheckResult {
+    /// Whether the load or the inline content is allowed by every enforced policy.
+    pub allowed: bool,
+    /// The violations to report, including those of report-only policies.
+    pub violations: Vec<Violation>,
+}
+
+/// The directive that governs loads made for a request context, if any.
+/// http://www.w3.org/TR/CSP2/#directives
+fn directive_for(context: Context) -> Option<&'static str> {
+    match context {
+        Script | Worker | SharedWorker | ServiceWorker => Some("script-src"),
+        Style | Import => Some("style-src"),
+        Image | ImageSet | Favicon => Some("img-src"),
+        Frame | IFrame => Some("frame-src"),
+        Font => Some("font-src"),
+        Audio | Video | Track => Some("media-src"),
+        Object | Embed | Plugin => Some("object-src"),
+        XMLHttpRequest | Eventsource | Fetch | Beacon | Ping => Some("connect-src"),
+        _ => None,
+    }
+}
+
+fn is_scheme(value: &str) -> bool {
+    let mut chars = value.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || c == '+' || c == '-' || c == '.')
+}
+
+fn is_host(value: &str) -> bool {
+    let value = if value.starts_with("*.") { value.slice_from(2) } else { value };
+    !value.is_empty() && value.split('.').all(|label| {
+        !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+    })
+}
+
+fn parse_host_source(value: &str) -> Option<HostExpression> {
+    let (scheme, rest) = match value.find_str("://") {
+        Some(index) => {
+            let scheme = value.slice_to(index);
+            if !is_scheme(scheme) {
+                return None
+            }
+            (Some(scheme.to_ascii_lower()), value.slice_from(index + 3))
+        }
+        None => (None, value),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (rest.slice_to(index), Some(rest.slice_from(index).to_string())),
+        None => (rest, None),
+    };
+    let (host, port) = match authority.rfind(':') {
+        Some(index) => {
+            let port = match authority.slice_from(index + 1) {
+                "*" => None,
+                port => match from_str::<u16>(port) {
+                    Some(port) => Some(port),
+                    None => return None,
+                },
+            };
+            (authority.slice_to(index), Some(port))
+        }
+        None => (authority, None),
+    };
+    if host != "*" && !is_host(host) {
+        return None
+    }
+    Some(HostExpression {
+        scheme: scheme,
+        host: host.to_ascii_lower(),
+        port: port,
+        path: path,
+    })
+}
+
+/// Parse a source expression, returning `None` for `'none'` and invalid expressions.
+fn parse_source_expression(token: &str) -> Option<SourceExpression> {
+    let lower = token.to_ascii_lower();
+    match lower.as_slice() {
+        "'self'" => return Some(SelfSource),
+        "'unsafe-inline'" => return Some(UnsafeInline),
+        "'unsafe-eval'" => return Some(UnsafeEval),
+        "'none'" => return None,
+        "*" => return Some(WildcardSource),
+        _ => {}
+    }
+    if token.len() > 2 && token.starts_with("'") && token.ends_with("'") {
+        let inner = token.slice(1, token.len() - 1);
+        let lower_inner = lower.as_slice().slice(1, token.len() - 1);
+        let hashes = [("sha256-", Sha256), ("sha384-", Sha384), ("sha512-", Sha512)];
+        if lower_inner.starts_with("nonce-") {
+            return Some(NonceSource(inner.slice_from("nonce-".len()).to_string()))
+        }
+        for &(prefix, algorithm) in hashes.iter() {
+            if lower_inner.starts_with(prefix) {
+                return Some(HashSource(algorithm, inner.slice_from(prefix.len()).to_string()))
+            }
+        }
+        return None
+    }
+    if token.ends_with(":") && is_scheme(token.slice_to(token.len() - 1)) {
+        return Some(SchemeSource(lower.as_slice().slice_to(token.len() - 1).to_string()))
+    }
+    parse_host_source(token).map(HostSource)
+}
+
+fn parse_policy(text: &str, report_only: bool) -> Policy {
+    let mut directives: Vec<Directive> = vec!();
+    for directive in text.split(';') {
+        let directive = directive.trim();
+        let mut tokens = directive.split(|c: char| c.is_whitespace()).filter(|t| !t.is_empty());
+        let name = match tokens.next() {
+            Some(name) => name.to_ascii_lower(),
+            None => continue,
+        };
+        if !name.as_slice().chars().all(|c| c.is_alphanumeric() || c == '-') {
+            continue
+        }
+        // Only the first occurrence of a directive counts.
+        if directives.iter().any(|d| d.name == name) {
+            continue
+        }
+        directives.push(Directive {
+            name: name,
+            text: directive.to_string(),
+            sources: tokens.filter_map(parse_source_expression).collect(),
+        });
+    }
+    Policy {
+        text: text.trim().to_string(),
+        report_only: report_only,
+        directives: directives,
+    }
+}
+
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        "ftp" => Some(21),
+        _ => None,
+    }
+}
+
+/// The HTTP scheme that a WebSocket scheme is matched as, so that sources for a host also
+/// allow WebSocket connections to it.
+fn http_scheme(scheme: &str) -> &str {
+    match scheme {
+        "ws" => "http",
+        "wss" => "https",
+        scheme => scheme,
+    }
+}
+
+/// Whether a URL with scheme `scheme` matches a source expecting `expected`. Sources for
+/// `http` also match `https`, and both match the corresponding WebSocket schemes.
+fn scheme_matches(expected: &str, scheme: &str) -> bool {
+    let scheme = http_scheme(scheme);
+    expected == scheme || (expected == "http" && scheme == "https")
+}
+
+impl HostExpression {
+    /// http://www.w3.org/TR/CSP2/#match-source-expression
+    fn matches(&self, url: &Url, document_url: &Url) -> bool {
+        let scheme = url.scheme.as_slice();
+        let expected_scheme = match self.scheme {
+            Some(ref expected) => expected.as_slice(),
+            None => document_url.scheme.as_slice(),
+        };
+        if !scheme_matches(expected_scheme, scheme) {
+            return false
+        }
+
+        let host = match url.serialize_host() {
+            Some(host) => host.into_ascii_lower(),
+            None => return false,
+        };
+        let host_matches = if self.host.as_slice() == "*" {
+            true
+        } else if self.host.as_slice().starts_with("*.") {
+            host.as_slice().ends_with(self.host.as_slice().slice_from(1))
+        } else {
+            host == self.host
+        };
+        if !host_matches {
+            return false
+        }
+
+        let port_matches = match self.port {
+            Some(None) => true,
+            Some(Some(port)) => url.port_or_default() == Some(port),
+            None => url.port().is_none() || url.port() == default_port(scheme),
+        };
+        if !port_matches {
+            return false
+        }
+
+        match self.path {
+            Some(ref path) => {
+                let url_path = url.serialize_path().unwrap_or(String::new());
+                if path.as_slice().ends_with("/") {
+                    url_path.as_slice().starts_with(path.as_slice())
+                } else {
+                    url_path == *path
+                }
+            }
+            None => true,
+        }
+    }
+}
+
+impl SourceExpression {
+    fn matches(&self, url: &Url, document_url: &Url) -> bool {
+        match *self {
+            SelfSource => {
+                let mut url = url.clone();
+                url.scheme = http_scheme(url.scheme.as_slice()).to_string();
+                is_same_origin(&url, document_url)
+            }
+            WildcardSource => !["data", "blob", "filesystem"].contains(&url.scheme.as_slice()),
+            SchemeSource(ref scheme) => url.scheme == *scheme,
+            HostSource(ref source) => source.matches(url, document_url),
+            UnsafeInline | UnsafeEval | NonceSource(_) | HashSource(..) => false,
+        }
+    }
+}
+
+impl Policy {
+    /// The directive that applies to `name`, falling back to `default-src`.
+    fn directive(&self, name: &str) -> Option<&Directive> {
+        let fallbacks: &[&str] = match name {
+            // CSP 2 replaces frame-src with child-src, but still honours frame-src.
+            "frame-src" => &["frame-src", "child-src", "default-src"],
+            name => return self.directives.iter().find(|d| d.name.as_slice() == name).or_else(|| {
+                self.directives.iter().find(|d| d.name.as_slice() == "default-src")
+            }),
+        };
+        fallbacks.iter().filter_map(|&fallback| {
+            self.directives.iter().find(|d| d.name.as_slice() == fallback)
+        }).next()
+    }
+}
+
+impl Directive {
+    fn allows_inline(&self, nonce: Option<&str>, content: Option<&str>) -> bool {
+        let mut has_nonce_or_hash = false;
+        for source in self.sources.iter() {
+            match *source {
+                NonceSource(ref expected) => {
+                    has_nonce_or_hash = true;
+                    if nonce == Some(expected.as_slice()) {
+                        return true
+                    }
+                }
+                HashSource(algorithm, ref expected) => {
+                    has_nonce_or_hash = true;
+                    match content {
+                        Some(content) => {
+                            let digest = hash(algorithm.hash_type(), content.as_bytes());
+                            if digest.as_slice().to_base64(STANDARD) == *expected {
+                                return true
+                            }
+                        }
+                        None => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        // 'unsafe-inline' is ignored when the directive lists nonces or hashes.
+        !has_nonce_or_hash && self.sources.contains(&UnsafeInline)
+    }
+}
+
+impl Violation {
+    /// A description of the violation for the console.
+    pub fn message(&self) -> String {
+        let what = match (self.effective_directive.as_slice(), &self.blocked_url) {
+            _ if self.eval => "evaluate a string as JavaScript".to_string(),
+            ("script-src", &None) => "execute inline script".to_string(),
+            ("style-src", &None) => "apply inline style".to_string(),
+            (directive, &Some(ref url)) => {
+                let noun = match directive {
+                    "script-src" => "script",
+                    "style-src" => "stylesheet",
+                    "img-src" => "image",
+                    "frame-src" => "frame",
+                    "font-src" => "font",
+                    "media-src" => "media",
+                    "object-src" => "plugin data",
+                    "connect-src" => "resource",
+                    _ => "resource",
+                };
+                format!("load the {:s} '{:s}'", noun, url.serialize())
+            }
+            (_, &None) => "run inline content".to_string(),
+        };
+        format!("{:s}Refused to {:s} because it violates the following Content Security Policy \
+                 directive: \"{:s}\".",
+                if self.report_only { "[Report Only] " } else { "" },
+                what,
+                self.violated_directive)
+    }
+}
+
+impl ContentSecurityPolicy {
+    /// No policies, for the document at `document_url`.
+    pub fn new(document_url: Url) -> ContentSecurityPolicy {
+        ContentSecurityPolicy {
+            document_url: document_url,
+            policies: vec!(),
+        }
+    }
+
+    /// The URL of the document the policies belong to.
+    pub fn document_url<'a>(&'a self) -> &'a Url {
+        &self.document_url
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    /// Add the policies of a `Content-Security-Policy` header, or of a
+    /// `Content-Security-Policy-Report-Only` header if `report_only` is set.
+    pub fn add_header(&mut self, value: &str, report_only: bool) {
+        for text in value.split(',') {
+            if !text.trim().is_empty() {
+                self.policies.push(parse_policy(text, report_only));
+            }
+        }
+    }
+
+    /// Add the policy of a `<meta http-equiv="Content-Security-Policy">` element. Such policies
+    /// are always enforced, and can't use `report-uri`, `frame-ancestors` or `sandbox`.
+    pub fn add_meta_policy(&mut self, value: &str) {
+        let mut policy = parse_policy(value, false);
+        policy.directives.retain(|d| {
+            !["report-uri", "frame-ancestors", "sandbox"].contains(&d.name.as_slice())
+        });
+        self.policies.push(policy);
+    }
+
+    fn check(&self, directive: &str, blocked_url: Option<&Url>, allows: |&Directive| -> bool)
+             -> CheckResult {
+        let mut result = CheckResult {
+            allowed: true,
+            violations: vec!(),
+        };
+        for policy in self.policies.iter() {
+            let violated = match policy.directive(directive) {
+                Some(violated) if !allows(violated) => violated,
+                _ => continue,
+            };
+            if !policy.report_only {
+                result.allowed = false;
+            }
+            result.violations.push(Violation {
+                document_url: self.document_url.clone(),
+                blocked_url: blocked_url.map(|url| url.clone()),
+                effective_directive: directive.to_string(),
+                violated_directive: violated.text.clone(),
+                original_policy: policy.text.clone(),
+                report_only: policy.report_only,
+                eval: false,
+            });
+        }
+        result
+    }
+
+    /// Check a load of `url` made for `context`.
+    pub fn check_load(&self, context: Context, url: &Url) -> CheckResult {
+        let directive = match directive_for(context) {
+            Some(directive) => directive,
+            None => return CheckResult {
+                allowed: true,
+                violations: vec!(),
+            },
+        };
+        self.check(directive, Some(url), |directive| {
+            directive.sources.iter().any(|source| source.matches(url, &self.document_url))
+        })
+    }
+
+    /// Check inline script or style, with the nonce it carries. Hashes are only checked when
+    /// `content` is given, which isn't the case for event handler attributes.
+    pub fn check_inline(&self, context: Context, nonce: Option<&str>, content: Option<&str>)
+                        -> CheckResult {
+        let directive = directive_for(context).unwrap_or("script-src");
+        self.check(directive, None, |directive| directive.allows_inline(nonce, content))
+    }
+
+    /// Check a WebSocket connection to `url`.
+    pub fn check_connect(&self, url: &Url) -> CheckResult {
+        self.check("connect-src", Some(url), |directive| {
+            directive.sources.iter().any(|source| source.matches(url, &self.document_url))
+        })
+    }
+
+    /// Whether strings can be evaluated as code, with `eval()` and the like.
+    pub fn check_eval(&self) -> CheckResult {
+        let mut result = self.check("script-src", None, |directive| {
+            directive.sources.contains(&UnsafeEval)
+        });
+        for violation in result.violations.iter_mut() {
+            violation.eval = true;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ContentSecurityPolicy;
+    use fetch::request::{Script, Style, Image, IFrame, Form, XMLHttpRequest};
+    use url::Url;
+
+    fn policy(header: &str) -> ContentSecurityPolicy {
+        let mut csp = ContentSecurityPolicy::new(Url::parse("https://example.com/page").unwrap());
+        csp.add_header(header, false);
+        csp
+    }
+
+    fn allows(csp: &ContentSecurityPolicy, context: super::Context, url: &str) -> bool {
+        csp.check_load(context, &Url::parse(url).unwrap()).allowed
+    }
+
+    #[test]
+    fn test_self_and_hosts() {
+        let csp = policy("default-src 'self'; img-src *.cdn.com https://images.org:8443/pics/");
+        assert!(allows(&csp, Script, "https://example.com/app.js"));
+        assert!(!allows(&csp, Script, "http://example.com/app.js"));
+        assert!(!allows(&csp, Script, "https://evil.com/app.js"));
+        assert!(allows(&csp, Image, "https://a.cdn.com/x.png"));
+        assert!(!allows(&csp, Image, "https://cdn.com/x.png"));
+        assert!(allows(&csp, Image, "https://images.org:8443/pics/cat.png"));
+        assert!(!allows(&csp, Image, "https://images.org/pics/cat.png"));
+        assert!(!allows(&csp, Image, "https://example.com/x.png"));
+        // form-action doesn't fall back to default-src.
+        assert!(allows(&csp, Form, "https://evil.com/"));
+    }
+
+    #[test]
+    fn test_schemes_and_none() {
+        let csp = policy("style-src https: data:; frame-src 'none'");
+        assert!(allows(&csp, Style, "https://evil.com/a.css"));
+        assert!(allows(&csp, Style, "data:text/css,a{}"));
+        assert!(!allows(&csp, Style, "http://example.com/a.css"));
+        assert!(!allows(&csp, IFrame, "https://example.com/frame.html"));
+        assert!(allows(&csp, Script, "http://anything.com/"));
+    }
+
+    #[test]
+    fn test_wildcard_excludes_data() {
+        let csp = policy("img-src *");
+        assert!(allows(&csp, Image, "http://a.com/x.png"));
+        assert!(allows(&csp, Image, "ftp://a.com:2121/x.png"));
+        assert!(!allows(&csp, Image, "data:image/png;base64,AAAA"));
+    }
+
+    #[test]
+    fn test_inline() {
+        let csp = policy("script-src 'self' 'nonce-abc123' \
+                          'sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng='; \
+                          style-src 'unsafe-inline'");
+        assert!(csp.check_inline(Script, Some("abc123"), Some("foo()")).allowed);
+        assert!(!csp.check_inline(Script, Some("wrong"), Some("foo()")).allowed);
+        // The SHA-256 digest of "alert('Hello, world.');".
+        assert!(csp.check_inline(Script, None, Some("alert('Hello, world.');")).allowed);
+        assert!(!csp.check_inline(Script, None, None).allowed);
+        assert!(csp.check_inline(Style, None, Some("a {}")).allowed);
+        assert!(!csp.check_eval().allowed);
+    }
+
+    #[test]
+    fn test_connect() {
+        let csp = policy("connect-src 'self' chat.com; script-src 'unsafe-eval'");
+        assert!(allows(&csp, XMLHttpRequest, "https://example.com/data.json"));
+        assert!(!allows(&csp, XMLHttpRequest, "https://evil.com/data.json"));
+        assert!(csp.check_connect(&Url::parse("wss://example.com/socket").unwrap()).allowed);
+        assert!(csp.check_connect(&Url::parse("wss://chat.com/").unwrap()).allowed);
+        assert!(!csp.check_connect(&Url::parse("ws://chat.com/").unwrap()).allowed);
+        assert!(csp.check_eval().allowed);
+    }
+
+    #[test]
+    fn test_report_only() {
+        let mut csp = policy("img-src 'self'");
+        csp.add_header("script-src 'none'", true);
+        let result = csp.check_load(Script, &Url::parse("https://example.com/a.js").unwrap());
+        assert!(result.allowed);
+        assert_eq!(result.violations.len(), 1);
+        assert!(result.violations[0].report_only);
+        assert_eq!(result.violations[0].violated_directive, "script-src 'none'".to_string());
+        assert!(result.violations[0].message().as_slice().starts_with("[Report Only] Refused"));
+    }
+
+    #[test]
+    fn test_multiple_policies() {
+        let csp = policy("img-src 'self' https://a.com, img-src https://a.com");
+        assert!(allows(&csp, Image, "https://a.com/x.png"));
+        let result = csp.check_load(Image, &Url::parse("https://example.com/x.png").unwrap());
+        assert!(!result.allowed);
+        assert_eq!(result.violations.len(), 1);
+    }
+
+    #[test]
+    fn test_meta_policy_ignores_report_uri() {
+        let mut csp = ContentSecurityPolicy::new(Url::parse("http://example.com/").unwrap());
+        csp.add_meta_policy("report-uri /r; default-src 'none'");
+        let result = csp.check_load(Image, &Url::parse("http://example.com/x.png").unwrap());
+        assert!(!result.allowed);
+        assert_eq!(result.violations[0].violated_directive, "default-src 'none'".to_string());
+    }
+}
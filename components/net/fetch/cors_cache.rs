@@ -3,11 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 //! An implementation of the [CORS preflight cache](http://fetch.spec.whatwg.org/#cors-preflight-cache)
-//! For now this library is XHR-specific.
-//! For stuff involving `<img>`, `<iframe>`, `<form>`, etc please check what
-//! the request mode should be and compare with the fetch spec
-//! This library will eventually become the core of the Fetch crate
-//! with CORSRequest being expanded into FetchRequest (etc)
+//! The resource task runs a `CORSCacheTask`, which is shared by all fetches.
 
 use http::method::Method;
 use std::ascii::StrAsciiExt;
@@ -66,6 +62,7 @@ impl CORSCacheEntry {
 }
 
 /// Properties of Request required to cache match.
+#[deriving(Clone)]
 pub struct CacheRequestDetails {
     pub origin: Url,
     pub destination: Url,
@@ -136,7 +133,7 @@ impl CORSCache for BasicCORSCache {
     #[allow(dead_code)]
     fn clear (&mut self, request: CacheRequestDetails) {
         let BasicCORSCache(buf) = self.clone();
-        let new_buf: Vec<CORSCacheEntry> = buf.into_iter().filter(|e| e.origin == request.origin && request.destination == e.url).collect();
+        let new_buf: Vec<CORSCacheEntry> = buf.into_iter().filter(|e| e.origin != request.origin || request.destination != e.url).collect();
         *self = BasicCORSCache(new_buf);
     }
 
@@ -144,7 +141,7 @@ impl CORSCache for BasicCORSCache {
     fn cleanup(&mut self) {
         let BasicCORSCache(buf) = self.clone();
         let now = time::now().to_timespec();
-        let new_buf: Vec<CORSCacheEntry> = buf.into_iter().filter(|e| now.sec > e.created.sec + e.max_age as i64).collect();
+        let new_buf: Vec<CORSCacheEntry> = buf.into_iter().filter(|e| now.sec < e.created.sec + e.max_age as i64).collect();
         *self = BasicCORSCache(new_buf);
     }
 
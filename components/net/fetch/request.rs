@@ -2,13 +2,26 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use url::Url;
-use http::method::{Get, Method};
+use fetch::cors_cache::{CORSCache, CacheRequestDetails};
+use fetch::response;
+use fetch::response::{Response, ResponseLoader};
+use http_cache::get_header;
+use referrer_policy::ReferrerPolicy;
+use resource_task::{ResourceTask, LoadData, Load, GetCORSCache, LoadGroupId, LoadPriority};
+use resource_task::{DocumentPriority, StylesheetPriority, ScriptPriority, FontPriority};
+use resource_task::{ImagePriority, PrefetchPriority};
+use url::{Url, UrlParser};
+use http::method::{Get, Head, Method, Options, Post};
+use http::headers::HeaderEnum;
+use http::headers::content_type::MediaType;
 use http::headers::request::HeaderCollection;
-use fetch::cors_cache::CORSCache;
-use fetch::response::Response;
+use http::headers::request::Header as RequestHeader;
+use http::headers::request::{Accept, AcceptLanguage, ContentLanguage, ContentType};
+use std::ascii::{OwnedStrAsciiExt, StrAsciiExt};
+use std::comm::channel;
 
 /// A [request context](http://fetch.spec.whatwg.org/#concept-request-context)
+#[deriving(Clone, PartialEq, Show)]
 pub enum Context {
     Audio, Beacon, CSPreport, Download, Embed, Eventsource,
     Favicon, Fetch, Font, Form, Frame, Hyperlink, IFrame, Image,
@@ -17,6 +30,20 @@ pub enum Context {
     Style, Track, Video, Worker, XMLHttpRequest, XSLT
 }
 
+impl Context {
+    /// The priority the resource task gives to loads for this context.
+    pub fn priority(&self) -> LoadPriority {
+        match *self {
+            Style | Import | XSLT => StylesheetPriority,
+            Script | Worker | SharedWorker | ServiceWorker => ScriptPriority,
+            Font => FontPriority,
+            Image | ImageSet | Favicon => ImagePriority,
+            Prefetch => PrefetchPriority,
+            _ => DocumentPriority,
+        }
+    }
+}
+
 /// A [request context frame type](http://fetch.spec.whatwg.org/#concept-request-context-frame-type)
 pub enum ContextFrameType {
     Auxiliary,
@@ -26,6 +53,7 @@ pub enum ContextFrameType {
 }
 
 /// A [referer](http://fetch.spec.whatwg.org/#concept-request-referrer)
+#[deriving(Clone)]
 pub enum Referer {
     RefererNone,
     Client,
@@ -33,6 +61,7 @@ pub enum Referer {
 }
 
 /// A [request mode](http://fetch.spec.whatwg.org/#concept-request-mode)
+#[deriving(PartialEq)]
 pub enum RequestMode {
     SameOrigin,
     NoCORS,
@@ -41,6 +70,7 @@ pub enum RequestMode {
 }
 
 /// Request [credentials mode](http://fetch.spec.whatwg.org/#concept-request-credentials-mode)
+#[deriving(PartialEq)]
 pub enum CredentialsMode {
     Omit,
     CredentialsSameOrigin,
@@ -71,6 +101,8 @@ pub struct Request {
     pub force_origin_header: bool,
     pub same_origin_data: bool,
     pub referer: Referer,
+    /// How much of the referer is sent. The default policy is used if not set.
+    pub referrer_policy: Option<ReferrerPolicy>,
     pub authentication: bool,
     pub sync: bool,
     pub mode: RequestMode,
@@ -79,7 +111,12 @@ pub struct Request {
     pub manual_redirect: bool,
     pub redirect_count: uint,
     pub response_tainting: ResponseTainting,
-    pub cache: Option<Box<CORSCache+'static>>
+    /// The CORS preflight cache. If not set, the resource task's cache is used.
+    pub cache: Option<Box<CORSCache+'static>>,
+    /// The priority of the loads made by the resource task. Set from the context by default.
+    pub priority: LoadPriority,
+    /// The group to cancel the loads made by the resource task with, if any.
+    pub group: Option<LoadGroupId>,
 }
 
 impl Request {
@@ -98,52 +135,465 @@ impl Request {
             force_origin_header: false,
             same_origin_data: false,
             referer: Client,
+            referrer_policy: None,
             authentication: false,
             sync: false,
             mode: NoCORS,
-            credentials_mode: Omit,
+            credentials_mode: Include,
             use_url_credentials: false,
             manual_redirect: false,
             redirect_count: 0,
             response_tainting: Basic,
-            cache: None
+            cache: None,
+            priority: context.priority(),
+            group: None,
+        }
+    }
+
+    /// [Fetch](http://fetch.spec.whatwg.org#concept-fetch)
+    pub fn fetch(&mut self, resource_task: &ResourceTask) -> ResponseLoader {
+        let url = self.url.clone();
+        let same_origin = self.origin.as_ref().map_or(false, |origin| is_same_origin(origin, &url));
+        let scheme = url.scheme.clone();
+        let mut loader = if same_origin ||
+                (scheme.as_slice() == "data" && self.same_origin_data) ||
+                scheme.as_slice() == "about" {
+            // Step 3, substep 1
+            self.response_tainting = Basic;
+            self.basic_fetch(resource_task)
+        } else {
+            match self.mode {
+                // Substep 2
+                SameOrigin => return ResponseLoader::network_error(url),
+                // Substep 3
+                NoCORS => {
+                    self.response_tainting = Opaque;
+                    self.basic_fetch(resource_task)
+                }
+                // Substep 4
+                _ if scheme.as_slice() != "http" && scheme.as_slice() != "https" => {
+                    return ResponseLoader::network_error(url)
+                }
+                // Substeps 5 and 6
+                _ => {
+                    self.response_tainting = CORSTainting;
+                    let preflight = self.mode == ForcedPreflightMode ||
+                        !is_simple_method(&self.method) ||
+                        self.headers.iter().any(|h| !is_simple_header(&h));
+                    self.http_fetch(resource_task, true, preflight, false)
+                }
+            }
+        };
+
+        // Step 4. The response to a redirected request has already been filtered.
+        if loader.response.is_network_error() || loader.response.internal_response.is_some() {
+            return loader;
         }
+        let filter_type = match self.response_tainting {
+            Basic => response::Basic,
+            CORSTainting => response::CORS,
+            Opaque => response::Opaque,
+        };
+        loader.response = loader.response.to_filtered(filter_type);
+        loader
     }
 
     /// [Basic fetch](http://fetch.spec.whatwg.org#basic-fetch)
-    pub fn basic_fetch(&mut self) -> Response {
+    pub fn basic_fetch(&mut self, resource_task: &ResourceTask) -> ResponseLoader {
         match self.url.scheme.as_slice() {
-            "about" => match self.url.non_relative_scheme_data() {
-                Some(s) if s.as_slice() == "blank" => {
-                    let mut response = Response::new();
-                    let _ = response.headers.insert_raw("Content-Type".to_string(), b"text/html;charset=utf-8");
-                    response
-                },
-                _ => Response::network_error()
-            },
-            "http" | "https" => {
-                self.http_fetch(false, false, false)
-            },
-            "blob" | "data" | "file" | "ftp" => {
-                // XXXManishearth handle these
-                fail!("Unimplemented scheme for Fetch")
-            },
-
-            _ => Response::network_error()
-        }
-    }
-
-    // [HTTP fetch](http://fetch.spec.whatwg.org#http-fetch)
-    pub fn http_fetch(&mut self, _cors_flag: bool, cors_preflight_flag: bool, _authentication_fetch_flag: bool) -> Response {
-        let response = Response::new();
+            "http" | "https" => self.http_fetch(resource_task, false, false, false),
+            "about" | "blob" | "data" | "file" => self.network_fetch(resource_task, false, false),
+            // XXXManishearth handle ftp
+            _ => ResponseLoader::network_error(self.url.clone()),
+        }
+    }
+
+    /// [HTTP fetch](http://fetch.spec.whatwg.org#http-fetch)
+    pub fn http_fetch(&mut self, resource_task: &ResourceTask, cors_flag: bool,
+                      cors_preflight_flag: bool, _authentication_fetch_flag: bool)
+                      -> ResponseLoader {
         // TODO: Service worker fetch
         // Step 3
         // Substep 1
         self.skip_service_worker = true;
         // Substep 2
         if cors_preflight_flag {
-            // XXXManishearth stuff goes here
+            let method = self.method.clone();
+            let headers: Vec<String> = self.headers.iter().filter(|h| !is_simple_header(h))
+                                                          .map(|h| h.header_name())
+                                                          .collect();
+            let (method_match, headers_match) = match self.cache_request_details() {
+                Some(details) => {
+                    let cache = self.cors_cache(resource_task);
+                    let method_match = cache.match_method(details.clone(), method);
+                    let headers_match = headers.iter().all(|name| {
+                        cache.match_header(details.clone(), name.as_slice())
+                    });
+                    (method_match, headers_match)
+                }
+                None => (false, false),
+            };
+            let needs_preflight = (!method_match && (!is_simple_method(&self.method) ||
+                                                     self.mode == ForcedPreflightMode)) ||
+                                  !headers_match;
+            if needs_preflight && !self.preflight_fetch(resource_task) {
+                return ResponseLoader::network_error(self.url.clone());
+            }
+        }
+        // Substep 3
+        let credentials = match self.credentials_mode {
+            Include => true,
+            CredentialsSameOrigin => !cors_flag,
+            Omit => false,
+        };
+        let loader = self.network_fetch(resource_task, credentials, cors_flag);
+        // Substep 4
+        if loader.response.is_network_error() ||
+                (cors_flag && !self.cors_check(&loader.response)) {
+            return ResponseLoader::network_error(self.url.clone());
+        }
+
+        // Step 4: TODO: 401 and 407 responses
+        // Step 5
+        let status = loader.response.status.code();
+        match status {
+            301 | 302 | 303 | 307 | 308 if !self.manual_redirect => {}
+            _ => return loader,
+        }
+        // Substeps 1 and 2
+        let location = match get_header(&loader.response.headers, "location") {
+            Some(location) => location,
+            None => return loader,
+        };
+        let location = match UrlParser::new().base_url(&self.url).parse(location.as_slice()) {
+            Ok(location) => location,
+            Err(_) => return ResponseLoader::network_error(self.url.clone()),
+        };
+        // Substeps 3 and 4
+        if self.redirect_count == 20 {
+            return ResponseLoader::network_error(self.url.clone());
+        }
+        self.redirect_count += 1;
+        // Substep 5
+        if cors_flag {
+            let has_credentials = location.username().map_or(false, |u| !u.is_empty()) ||
+                                  location.password().is_some();
+            if has_credentials {
+                return ResponseLoader::network_error(self.url.clone());
+            }
+            if !is_same_origin(&location, &self.url) {
+                self.origin = None;
+            }
+        }
+        // Substep 6
+        if status == 303 || ((status == 301 || status == 302) && self.method == Post) {
+            self.method = Get;
+            self.body = None;
+        }
+        // Substeps 7 and 8
+        info!("fetch: redirecting to {:s}", location.serialize());
+        self.url = location;
+        self.fetch(resource_task)
+    }
+
+    /// [CORS preflight fetch](http://fetch.spec.whatwg.org#cors-preflight-fetch). Returns
+    /// whether the actual request may be made.
+    fn preflight_fetch(&mut self, resource_task: &ResourceTask) -> bool {
+        // Step 1
+        let mut preflight = Request::new(self.url.clone(), self.context);
+        preflight.method = Options;
+        preflight.origin = self.origin.clone();
+        preflight.referer = self.referer.clone();
+        preflight.referrer_policy = self.referrer_policy;
+        // Step 2
+        let _ = preflight.headers.insert_raw("Access-Control-Request-Method".to_string(),
+                                             self.method.to_string().as_bytes());
+        // Steps 3 - 5
+        let mut header_names: Vec<String> = self.headers.iter()
+                                                .filter(|h| !is_simple_header(h))
+                                                .map(|h| h.header_name().into_ascii_lower())
+                                                .collect();
+        header_names.sort();
+        header_names.dedup();
+        if !header_names.is_empty() {
+            let header_list = header_names.connect(", ");
+            let _ = preflight.headers.insert_raw("Access-Control-Request-Headers".to_string(),
+                                                 header_list.as_bytes());
         }
-        response
+
+        // Step 6
+        let loader = preflight.network_fetch(resource_task, false, true);
+        let response = &loader.response;
+
+        // Step 7
+        if response.is_network_error() || response.status.code() / 100 != 2 ||
+                !self.cors_check(response) {
+            return false;
+        }
+        // Substeps 1 - 3
+        let split = |value: Option<String>| -> Vec<String> {
+            value.map_or(vec!(), |value| {
+                value.as_slice().split(',').map(|s| s.trim().to_string())
+                                           .filter(|s| !s.is_empty())
+                                           .collect()
+            })
+        };
+        let mut methods = split(get_header(&response.headers, "access-control-allow-methods"));
+        let headers = split(get_header(&response.headers, "access-control-allow-headers"));
+        // Substep 4
+        if methods.is_empty() || self.mode == ForcedPreflightMode {
+            methods = vec!(self.method.to_string());
+        }
+        // Substep 5
+        if !is_simple_method(&self.method) &&
+                !methods.iter().any(|m| *m == self.method.to_string()) {
+            return false;
+        }
+        // Substep 6
+        let allowed = header_names.iter().all(|name| {
+            headers.iter().any(|h| h.as_slice().eq_ignore_ascii_case(name.as_slice()))
+        });
+        if !allowed {
+            return false;
+        }
+        // Substeps 7 and 8. TODO: Substep 9, limiting max-age.
+        let max_age: uint = get_header(&response.headers, "access-control-max-age")
+                                .and_then(|h| from_str(h.as_slice().trim()))
+                                .unwrap_or(0);
+        // Substeps 10 - 12
+        match self.cache_request_details() {
+            Some(details) => {
+                let cache = self.cors_cache(resource_task);
+                for method in methods.iter() {
+                    match Method::from_str_or_new(method.as_slice()) {
+                        Some(method) => {
+                            cache.match_method_and_update(details.clone(), method, max_age);
+                        }
+                        None => {}
+                    }
+                }
+                for header in headers.iter() {
+                    cache.match_header_and_update(details.clone(), header.as_slice(), max_age);
+                }
+            }
+            None => {}
+        }
+        true
+    }
+
+    /// [CORS check](http://fetch.spec.whatwg.org#cors-check)
+    fn cors_check(&self, response: &Response) -> bool {
+        let allow_origin = match get_header(&response.headers, "access-control-allow-origin") {
+            Some(allow_origin) => allow_origin,
+            None => return false,
+        };
+        let allow_origin = allow_origin.as_slice().trim();
+        if self.credentials_mode != Include && allow_origin == "*" {
+            return true;
+        }
+        if allow_origin != self.serialized_origin().as_slice() {
+            return false;
+        }
+        if self.credentials_mode != Include {
+            return true;
+        }
+        get_header(&response.headers, "access-control-allow-credentials").map_or(false, |value| {
+            value.as_slice().trim() == "true"
+        })
+    }
+
+    /// Load the request's URL with the resource task, without following redirects.
+    fn network_fetch(&self, resource_task: &ResourceTask, credentials: bool, cors_flag: bool)
+                     -> ResponseLoader {
+        let mut load_data = LoadData::new(self.url.clone());
+        load_data.method = self.method.clone();
+        load_data.headers = self.headers.clone();
+        load_data.data = self.body.clone();
+        load_data.follow_redirects = false;
+        load_data.send_credentials = credentials;
+        load_data.priority = self.priority;
+        load_data.group = self.group;
+        // Only the scheme of the origin matters to the mixed content check.
+        load_data.client_url = self.origin.clone();
+        load_data.context = Some(self.context);
+        if cors_flag || self.force_origin_header {
+            let _ = load_data.headers.insert_raw("Origin".to_string(),
+                                                 self.serialized_origin().as_bytes());
+        }
+        match self.referer {
+            RefererUrl(ref referer) => {
+                load_data.referrer = Some(referer.clone());
+                load_data.referrer_policy = self.referrer_policy;
+            }
+            RefererNone | Client => {}
+        }
+
+        let (start_chan, start_port) = channel();
+        resource_task.send(Load(load_data, start_chan));
+        match start_port.recv_opt() {
+            Ok(load_response) => ResponseLoader::from_load_response(load_response),
+            Err(()) => ResponseLoader::network_error(self.url.clone()),
+        }
+    }
+
+    /// The ASCII serialization of the request's origin. A request without an origin has a
+    /// globally unique one, which serializes as `null`.
+    fn serialized_origin(&self) -> String {
+        match self.origin {
+            Some(ref origin) => serialize_origin(origin),
+            None => "null".to_string(),
+        }
+    }
+
+    fn cache_request_details(&self) -> Option<CacheRequestDetails> {
+        self.origin.as_ref().map(|origin| {
+            CacheRequestDetails {
+                origin: origin.clone(),
+                destination: self.url.clone(),
+                credentials: self.credentials_mode == Include,
+            }
+        })
+    }
+
+    fn cors_cache<'a>(&'a mut self, resource_task: &ResourceTask) -> &'a mut Box<CORSCache+'static> {
+        if self.cache.is_none() {
+            let (tx, rx) = channel();
+            resource_task.send(GetCORSCache(tx));
+            self.cache = Some(box rx.recv() as Box<CORSCache+'static>);
+        }
+        self.cache.as_mut().unwrap()
+    }
+}
+
+/// The URL that `url` takes its origin from: a blob URL has the origin of the document that
+/// created it, which is serialized at the start of its path.
+/// http://dev.w3.org/2006/webapi/FileAPI/#originOfBlobURL
+fn origin_url(url: &Url) -> Url {
+    if url.scheme.as_slice() != "blob" {
+        return url.clone();
+    }
+    url.non_relative_scheme_data().and_then(|data| Url::parse(data).ok()).unwrap_or(url.clone())
+}
+
+/// Whether two URLs have the same [origin](http://tools.ietf.org/html/rfc6454).
+pub fn is_same_origin(a: &Url, b: &Url) -> bool {
+    let (a, b) = (origin_url(a), origin_url(b));
+    a.scheme == b.scheme && a.host() == b.host() && a.port_or_default() == b.port_or_default()
+}
+
+/// The [ASCII serialization](http://tools.ietf.org/html/rfc6454#section-6.2) of the origin of
+/// `url`.
+pub fn serialize_origin(url: &Url) -> String {
+    let host = url.serialize_host().unwrap_or(String::new());
+    match url.port() {
+        Some(port) => format!("{:s}://{:s}:{}", url.scheme, host, port),
+        None => format!("{:s}://{:s}", url.scheme, host),
+    }
+}
+
+/// A [simple header](http://fetch.spec.whatwg.org/#simple-header)
+pub fn is_simple_header(h: &RequestHeader) -> bool {
+    match *h {
+        Accept(_) | AcceptLanguage(_) | ContentLanguage(_) => true,
+        ContentType(MediaType {type_: ref t, subtype: ref s, ..}) => match (t.as_slice(), s.as_slice()) {
+            ("text", "plain") | ("application", "x-www-form-urlencoded") | ("multipart", "form-data") => true,
+            _ => false
+        },
+        _ => false
+    }
+}
+
+/// A [simple method](http://fetch.spec.whatwg.org/#simple-method)
+pub fn is_simple_method(m: &Method) -> bool {
+    match *m {
+        Get | Head | Post => true,
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Request, Fetch, CORSMode, NoCORS, Image, is_same_origin};
+    use cookie::HTTP;
+    use fetch::response;
+    use http::headers::HeaderEnum;
+    use resource_task::{new_resource_task, spawn_test_server, ResourceHandlers, Exit};
+    use resource_task::{GetCookiesForUrl, SetCookieForUrl};
+    use servo_util::opts::default_opts;
+    use url::Url;
+
+    fn ok_response(headers: &str) -> Vec<u8> {
+        format!("HTTP/1.1 200 OK\r\n{:s}Content-Length: 5\r\nConnection: close\r\n\r\nhello",
+                headers).into_bytes()
+    }
+
+    #[test]
+    fn test_cors_fetch() {
+        let (url, requests) = spawn_test_server(vec!(
+            ok_response("Access-Control-Allow-Origin: http://example.org\r\nX-Secret: 1\r\n"),
+            ok_response("")));
+        let resource_task = new_resource_task(&default_opts(), ResourceHandlers::new());
+        let origin = Url::parse("http://example.org/page.html").unwrap();
+
+        let mut request = Request::new(url.clone(), Fetch);
+        request.mode = CORSMode;
+        request.origin = Some(origin.clone());
+        let loader = request.fetch(&resource_task);
+        assert!(loader.response.response_type == response::CORS);
+        assert!(loader.response.headers.iter().all(|h| h.header_name().as_slice() != "X-Secret"));
+        assert!(requests.recv().as_slice().contains("\r\nOrigin: http://example.org\r\n"));
+        let (_, body) = loader.into_bytes().unwrap();
+        assert_eq!(body.as_slice(), b"hello");
+
+        // Without Access-Control-Allow-Origin, the response is a network error.
+        let mut request = Request::new(url, Fetch);
+        request.mode = CORSMode;
+        request.origin = Some(origin);
+        assert!(request.fetch(&resource_task).response.is_network_error());
+        resource_task.send(Exit);
+    }
+
+    #[test]
+    fn test_no_cors_fetch() {
+        let (url, _requests) = spawn_test_server(vec!(ok_response("")));
+        let resource_task = new_resource_task(&default_opts(), ResourceHandlers::new());
+
+        let mut request = Request::new(url, Fetch);
+        request.mode = NoCORS;
+        request.origin = Some(Url::parse("http://example.org/").unwrap());
+        let loader = request.fetch(&resource_task);
+        assert!(loader.response.response_type == response::Opaque);
+        assert_eq!(loader.response.status.code(), 0);
+        let (_, body) = loader.into_bytes().unwrap();
+        assert_eq!(body.as_slice(), b"hello");
+        resource_task.send(Exit);
+    }
+
+    #[test]
+    fn test_blob_url_origin() {
+        let page = Url::parse("https://example.com/page.html").unwrap();
+        let blob = Url::parse("blob:https://example.com/2a5e1b4c-51e2-4c0b-8a8b-0e6f4e0c1f2d").unwrap();
+        assert!(is_same_origin(&page, &blob));
+        let blob = Url::parse("blob:null/2a5e1b4c-51e2-4c0b-8a8b-0e6f4e0c1f2d").unwrap();
+        assert!(!is_same_origin(&page, &blob));
+    }
+
+    #[test]
+    fn test_subresource_fetch_cookies() {
+        let response = b"HTTP/1.1 200 OK\r\nSet-Cookie: b=2\r\nContent-Length: 0\r\n\
+                         Connection: close\r\n\r\n".to_vec();
+        let (url, requests) = spawn_test_server(vec!(response));
+        let resource_task = new_resource_task(&default_opts(), ResourceHandlers::new());
+        resource_task.send(SetCookieForUrl(url.clone(), "a=1".to_string(), HTTP));
+
+        // Images are fetched in no-cors mode, which sends and stores cookies.
+        let loader = Request::new(url.clone(), Image).fetch(&resource_task);
+        assert!(loader.into_bytes().is_ok());
+        assert!(requests.recv().as_slice().contains("\r\nCookie: a=1\r\n"));
+
+        let (tx, rx) = channel();
+        resource_task.send(GetCookiesForUrl(url, tx, HTTP));
+        assert_eq!(rx.recv(), Some("a=1; b=2".to_string()));
+        resource_task.send(Exit);
     }
 }
@@ -2,13 +2,16 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use http_cache::get_header;
+use resource_task;
+use resource_task::{LoadResponse, Metadata, ProgressMsg, Payload};
 use url::Url;
 use http::status::{Status, UnregisteredStatus};
 use http::status::Ok as StatusOk;
 use http::headers::HeaderEnum;
 use http::headers::response::HeaderCollection;
-use std::ascii::OwnedStrAsciiExt;
-use std::comm::Receiver;
+use std::ascii::{OwnedStrAsciiExt, StrAsciiExt};
+use std::comm::{channel, Receiver};
 
 /// [Response type](http://fetch.spec.whatwg.org/#concept-response-type)
 #[deriving(Clone, PartialEq)]
@@ -38,19 +41,6 @@ pub enum ResponseBody {
     Done(Vec<u8>),
 }
 
-#[unstable = "I haven't yet decided exactly how the interface for this will be"]
-pub enum ResponseMsg {
-    Chunk(Vec<u8>),
-    Finished,
-    Errored
-}
-
-#[unstable = "I haven't yet decided exactly how the interface for this will be"]
-pub struct ResponseLoader {
-    response: Response,
-    chan: Receiver<ResponseMsg>
-}
-
 /// A [Response](http://fetch.spec.whatwg.org/#concept-response) as defined by the Fetch spec
 #[deriving(Clone)]
 pub struct Response {
@@ -121,13 +111,23 @@ impl Response {
                 response.response_type = filter_type;
             },
             CORS => {
+                // Access-Control-Expose-Headers names further headers that may be exposed.
+                let exposed: Vec<String> = match get_header(&old_headers, "access-control-expose-headers") {
+                    Some(names) => {
+                        names.as_slice().split(',').map(|name| name.trim().to_ascii_lower())
+                                                   .filter(|name| !name.is_empty())
+                                                   .collect()
+                    }
+                    None => vec!(),
+                };
                 let mut headers = HeaderCollection::new();
                 for h in old_headers.iter() {
-                    match h.header_name().into_ascii_lower().as_slice() {
+                    let name = h.header_name().into_ascii_lower();
+                    match name.as_slice() {
                         "cache-control" | "content-language" |
-                        "content-type" | "expires" | "last-modified" | "Pragma" => {},
-                        // XXXManishearth handle Access-Control-Expose-Headers
-                        _ => headers.insert(h)
+                        "content-type" | "expires" | "last-modified" | "pragma" => headers.insert(h),
+                        _ if exposed.contains(&name) => headers.insert(h),
+                        _ => {}
                     }
                 }
                 response.headers = headers;
@@ -137,8 +137,96 @@ impl Response {
                 response.headers = HeaderCollection::new();
                 response.status = UnregisteredStatus(0, "".to_string());
                 response.body = Empty;
+                response.response_type = filter_type;
             }
         }
         response
     }
 }
+
+/// The result of a fetch: a response whose body is still being received from the resource task.
+pub struct ResponseLoader {
+    /// The response, which may be filtered.
+    pub response: Response,
+    /// The metadata reported by the loader. Like the body, this belongs to the internal
+    /// response, and must only be used by consumers that are allowed to see it.
+    pub metadata: Metadata,
+    /// A message received from `progress_port` while checking for a network error.
+    pending: Option<ProgressMsg>,
+    progress_port: Receiver<ProgressMsg>,
+}
+
+impl ResponseLoader {
+    /// Wrap the response of a load, which is a network error if the load fails before any of
+    /// the body is received.
+    pub fn from_load_response(load_response: LoadResponse) -> ResponseLoader {
+        let LoadResponse { metadata, progress_port } = load_response;
+        let pending = match progress_port.recv_opt() {
+            Ok(resource_task::Done(Err(e))) => {
+                debug!("fetch: load of {:s} failed: {:s}", metadata.final_url.serialize(), e);
+                return ResponseLoader::network_error(metadata.final_url);
+            }
+            Err(()) => return ResponseLoader::network_error(metadata.final_url),
+            Ok(msg) => msg,
+        };
+
+        let mut response = Response::new();
+        response.url = Some(metadata.final_url.clone());
+        response.status = metadata.status.clone();
+        response.headers = metadata.headers.clone().unwrap_or_else(|| HeaderCollection::new());
+        ResponseLoader {
+            response: response,
+            metadata: metadata,
+            pending: Some(pending),
+            progress_port: progress_port,
+        }
+    }
+
+    pub fn network_error(url: Url) -> ResponseLoader {
+        let (progress_chan, progress_port) = channel();
+        progress_chan.send(resource_task::Done(Err("network error".to_string())));
+        ResponseLoader {
+            response: Response::network_error(),
+            metadata: Metadata::default(url),
+            pending: None,
+            progress_port: progress_port,
+        }
+    }
+
+    /// Receive the next part of the body.
+    pub fn recv(&mut self) -> ProgressMsg {
+        match self.pending.take() {
+            Some(msg) => msg,
+            None => {
+                self.progress_port.recv_opt().unwrap_or_else(|_| {
+                    resource_task::Done(Err("load ended unexpectedly".to_string()))
+                })
+            }
+        }
+    }
+
+    /// Receive the whole body.
+    pub fn into_bytes(mut self) -> Result<(Metadata, Vec<u8>), String> {
+        let mut buf = vec!();
+        loop {
+            match self.recv() {
+                Payload(data) => buf.push_all(data.as_slice()),
+                resource_task::Done(Ok(())) => return Ok((self.metadata, buf)),
+                resource_task::Done(Err(e)) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Iterator<Vec<u8>> for ResponseLoader {
+    fn next(&mut self) -> Option<Vec<u8>> {
+        match self.recv() {
+            Payload(data) => Some(data),
+            resource_task::Done(Ok(())) => None,
+            resource_task::Done(Err(e)) => {
+                error!("error receiving bytes: {}", e);
+                None
+            }
+        }
+    }
+}
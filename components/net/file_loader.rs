@@ -2,12 +2,27 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use resource_task::{ProgressMsg, Metadata, Payload, Done, LoadData, start_sending};
+//! Loads `file:` URLs.
+//!
+//! Files are given a content type from their extension, which the sniffer task may refine, and
+//! a `Last-Modified` header from their modification time. Directories are loaded as a generated
+//! HTML listing, and paths that can't be read as an HTML error page with a matching HTTP status.
+
+use load_scheduler::LOAD_CANCELLED;
+use resource_task::{ProgressMsg, Metadata, Payload, Done, LoadData, start_sending_opt};
 use resource_task::{LoadResponse};
 
+use http::headers::response::HeaderCollection as ResponseHeaderCollection;
+use http::status::{Status, NotFound, Forbidden, InternalServerError};
+use std::ascii::StrAsciiExt;
+use std::cmp::Equal;
 use std::io;
-use std::io::File;
+use std::io::{File, FileStat, IoError, IoResult};
+use std::io::fs;
 use servo_util::task::spawn_named;
+use time;
+use time::Timespec;
+use url::Url;
 
 static READ_SIZE: uint = 8192;
 
@@ -16,11 +31,15 @@ fn read_all(reader: &mut io::Stream, progress_chan: &Sender<ProgressMsg>)
     loop {
         let mut buf = vec!();
         match reader.push_at_least(READ_SIZE, READ_SIZE, &mut buf) {
-            Ok(_) => progress_chan.send(Payload(buf)),
+            Ok(_) => {
+                if progress_chan.send_opt(Payload(buf)).is_err() {
+                    return Err(LOAD_CANCELLED.to_string());
+                }
+            }
             Err(e) => match e.kind {
                 io::EndOfFile => {
                     if buf.len() > 0 {
-                        progress_chan.send(Payload(buf));
+                        let _ = progress_chan.send_opt(Payload(buf));
                     }
                     return Ok(());
                 }
@@ -30,27 +49,362 @@ fn read_all(reader: &mut io::Stream, progress_chan: &Sender<ProgressMsg>)
     }
 }
 
+/// The content type of a file, guessed from its extension.
+fn content_type_for_path(path: &Path) -> Option<(String, String)> {
+    let extension = match path.extension_str() {
+        Some(extension) => extension.to_ascii_lower(),
+        None => return None,
+    };
+    let content_type = match extension.as_slice() {
+        "html" | "htm" => ("text", "html"),
+        "xhtml" | "xht" => ("application", "xhtml+xml"),
+        "xml" => ("text", "xml"),
+        "css" => ("text", "css"),
+        "js" => ("application", "javascript"),
+        "json" => ("application", "json"),
+        "txt" => ("text", "plain"),
+        "svg" => ("image", "svg+xml"),
+        "png" => ("image", "png"),
+        "jpg" | "jpeg" => ("image", "jpeg"),
+        "gif" => ("image", "gif"),
+        "bmp" => ("image", "bmp"),
+        "ico" => ("image", "x-icon"),
+        "webp" => ("image", "webp"),
+        "ttf" => ("application", "x-font-ttf"),
+        "otf" => ("application", "x-font-otf"),
+        "woff" => ("application", "font-woff"),
+        "mp3" => ("audio", "mpeg"),
+        "ogg" => ("audio", "ogg"),
+        "wav" => ("audio", "wav"),
+        "mp4" => ("video", "mp4"),
+        "webm" => ("video", "webm"),
+        "pdf" => ("application", "pdf"),
+        _ => return None,
+    };
+    Some((content_type.val0().to_string(), content_type.val1().to_string()))
+}
+
+/// A modification time from `FileStat`, in milliseconds since the epoch.
+fn timespec(modified: u64) -> Timespec {
+    Timespec::new((modified / 1000) as i64, ((modified % 1000) * 1000000) as i32)
+}
+
+fn metadata(url: Url, stat: &FileStat, content_type: Option<(String, String)>) -> Metadata {
+    let mut headers = ResponseHeaderCollection::new();
+    let last_modified = time::at_utc(timespec(stat.modified)).rfc822();
+    let _ = headers.insert_raw("Last-Modified".to_string(), last_modified.as_bytes());
+    let mut metadata = Metadata::default(url);
+    metadata.content_type = content_type;
+    metadata.headers = Some(headers);
+    metadata
+}
+
+/// Escape text for inclusion in a generated HTML page.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push_char(c),
+        }
+    }
+    escaped
+}
+
+/// Answer a load with a generated HTML page.
+pub fn send_html(start_chan: Sender<LoadResponse>, mut metadata: Metadata, html: String) {
+    metadata.content_type = Some(("text".to_string(), "html".to_string()));
+    metadata.charset = Some("utf-8".to_string());
+    let progress_chan = match start_sending_opt(start_chan, metadata) {
+        Ok(progress_chan) => progress_chan,
+        Err(()) => return,
+    };
+    let _ = progress_chan.send_opt(Payload(html.into_bytes()));
+    let _ = progress_chan.send_opt(Done(Ok(())));
+}
+
+/// Answer the load with an error page, as an HTTP server would.
+fn send_error_page(url: Url, start_chan: Sender<LoadResponse>, status: Status, message: &str) {
+    let html = format!("<!DOCTYPE html>\n<html>\n<head>\n<title>{status}</title>\n</head>\n\
+                        <body>\n<h1>{status}</h1>\n<p>{url}: {message}</p>\n</body>\n</html>\n",
+                       status = escape_html(status.to_string().as_slice()),
+                       url = escape_html(url.serialize().as_slice()),
+                       message = escape_html(message));
+    let mut metadata = Metadata::default(url);
+    metadata.status = status;
+    send_html(start_chan, metadata, html);
+}
+
+fn send_io_error(url: Url, start_chan: Sender<LoadResponse>, error: IoError) {
+    let status = match error.kind {
+        io::FileNotFound => NotFound,
+        io::PermissionDenied => Forbidden,
+        _ => InternalServerError,
+    };
+    debug!("file_loader: couldn't load {:s}: {}", url.serialize(), error);
+    send_error_page(url, start_chan, status, error.desc);
+}
+
+/// The column a directory listing is sorted by, given by the `sort` query parameter.
+#[deriving(PartialEq, Show)]
+enum SortKey {
+    SortByName,
+    SortBySize,
+    SortByModified,
+}
+
+impl SortKey {
+    fn parameter(&self) -> &'static str {
+        match *self {
+            SortByName => "name",
+            SortBySize => "size",
+            SortByModified => "modified",
+        }
+    }
+}
+
+struct DirectoryEntry {
+    name: String,
+    url: Url,
+    is_directory: bool,
+    size: u64,
+    modified: u64,
+}
+
+/// The sort order requested by the query of a directory's URL, as a key and whether it is
+/// descending.
+fn sort_order(url: &Url) -> (SortKey, bool) {
+    let mut key = SortByName;
+    let mut descending = false;
+    for (name, value) in url.query_pairs().unwrap_or(vec!()).into_iter() {
+        match (name.as_slice(), value.as_slice()) {
+            ("sort", "name") => key = SortByName,
+            ("sort", "size") => key = SortBySize,
+            ("sort", "modified") => key = SortByModified,
+            ("order", "asc") => descending = false,
+            ("order", "desc") => descending = true,
+            _ => {}
+        }
+    }
+    (key, descending)
+}
+
+fn read_directory(path: &Path) -> IoResult<Vec<DirectoryEntry>> {
+    let paths = try!(fs::readdir(path));
+    Ok(paths.into_iter().filter_map(|path| {
+        // Skip entries that disappear or can't be examined while the listing is made.
+        let stat = match fs::stat(&path) {
+            Ok(stat) => stat,
+            Err(_) => return None,
+        };
+        let url = match Url::from_file_path(&path) {
+            Ok(url) => url,
+            Err(()) => return None,
+        };
+        Some(DirectoryEntry {
+            name: path.filename_display().to_string(),
+            url: url,
+            is_directory: stat.kind == io::TypeDirectory,
+            size: stat.size,
+            modified: stat.modified,
+        })
+    }).collect())
+}
+
+fn sort_entries(entries: &mut Vec<DirectoryEntry>, key: SortKey, descending: bool) {
+    entries.sort_by(|a, b| {
+        let (a, b) = if descending { (b, a) } else { (a, b) };
+        let order = match key {
+            SortByName => Equal,
+            SortBySize => a.size.cmp(&b.size),
+            SortByModified => a.modified.cmp(&b.modified),
+        };
+        // Entries that compare equal are ordered by name.
+        match order {
+            Equal => a.name.cmp(&b.name),
+            order => order,
+        }
+    });
+}
+
+fn directory_listing(path: &Path, entries: &Vec<DirectoryEntry>, key: SortKey, descending: bool)
+                     -> String {
+    let title = escape_html(format!("Index of {}", path.display()).as_slice());
+    let mut html = format!("<!DOCTYPE html>\n<html>\n<head>\n<title>{title}</title>\n</head>\n\
+                            <body>\n<h1>{title}</h1>\n<table>\n<tr>",
+                           title = title);
+    for &(column, label) in [(SortByName, "Name"), (SortBySize, "Size"),
+                             (SortByModified, "Last modified")].iter() {
+        // Clicking the column the listing is sorted by reverses the order.
+        let order = if column == key && !descending { "desc" } else { "asc" };
+        html.push_str(format!("<th><a href=\"?sort={}&amp;order={}\">{}</a></th>",
+                              column.parameter(), order, label).as_slice());
+    }
+    html.push_str("</tr>\n");
+
+    let parent = path.dir_path();
+    if parent != *path {
+        match Url::from_file_path(&parent) {
+            Ok(url) => {
+                html.push_str(format!("<tr><td><a href=\"{}\">..</a></td><td></td><td></td></tr>\n",
+                                      escape_html(url.serialize().as_slice())).as_slice());
+            }
+            Err(()) => {}
+        }
+    }
+
+    for entry in entries.iter() {
+        let (name, size) = if entry.is_directory {
+            (format!("{}/", entry.name), "-".to_string())
+        } else {
+            (entry.name.clone(), entry.size.to_string())
+        };
+        let modified = time::at(timespec(entry.modified)).strftime("%Y-%m-%d %H:%M");
+        html.push_str(format!("<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td></tr>\n",
+                              escape_html(entry.url.serialize().as_slice()),
+                              escape_html(name.as_slice()),
+                              size,
+                              modified).as_slice());
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+fn load_directory(url: Url, path: &Path, stat: &FileStat, start_chan: Sender<LoadResponse>) {
+    let mut entries = match read_directory(path) {
+        Ok(entries) => entries,
+        Err(e) => return send_io_error(url, start_chan, e),
+    };
+    let (key, descending) = sort_order(&url);
+    sort_entries(&mut entries, key, descending);
+    let html = directory_listing(path, &entries, key, descending);
+    send_html(start_chan, metadata(url, stat, None), html);
+}
+
+fn load_file(url: Url, path: &Path, stat: &FileStat, start_chan: Sender<LoadResponse>) {
+    let mut reader = match File::open_mode(path, io::Open, io::Read) {
+        Ok(reader) => reader,
+        Err(e) => return send_io_error(url, start_chan, e),
+    };
+    let metadata = metadata(url, stat, content_type_for_path(path));
+    let progress_chan = match start_sending_opt(start_chan, metadata) {
+        Ok(progress_chan) => progress_chan,
+        Err(()) => return,
+    };
+    let res = read_all(&mut reader as &mut io::Stream, &progress_chan);
+    let _ = progress_chan.send_opt(Done(res));
+}
+
 pub fn factory(load_data: LoadData, start_chan: Sender<LoadResponse>) {
     let url = load_data.url;
     assert!("file" == url.scheme.as_slice());
-    let progress_chan = start_sending(start_chan, Metadata::default(url.clone()));
     spawn_named("file_loader", proc() {
-        let file_path: Result<Path, ()> = url.to_file_path();
-        match file_path {
-            Ok(file_path) => {
-                match File::open_mode(&Path::new(file_path), io::Open, io::Read) {
-                    Ok(ref mut reader) => {
-                        let res = read_all(reader as &mut io::Stream, &progress_chan);
-                        progress_chan.send(Done(res));
-                    }
-                    Err(e) => {
-                        progress_chan.send(Done(Err(e.desc.to_string())));
-                    }
+        let file_path = match url.to_file_path() {
+            Ok(file_path) => file_path,
+            Err(()) => return send_error_page(url, start_chan, NotFound, "not a local file"),
+        };
+        let stat = match fs::stat(&file_path) {
+            Ok(stat) => stat,
+            Err(e) => return send_io_error(url, start_chan, e),
+        };
+        if stat.kind == io::TypeDirectory {
+            load_directory(url, &file_path, &stat, start_chan)
+        } else {
+            load_file(url, &file_path, &stat, start_chan)
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{content_type_for_path, factory};
+    use resource_task::{LoadData, Payload, Done};
+
+    use http::headers::HeaderEnum;
+    use std::io::{File, TempDir};
+    use std::io::fs;
+    use std::io::UserRWX;
+    use std::str;
+    use url::Url;
+
+    fn load(url: Url) -> (u16, Option<(String, String)>, Vec<String>, String) {
+        let (start_chan, start_port) = channel();
+        factory(LoadData::new(url), start_chan);
+        let response = start_port.recv();
+        let mut body = vec!();
+        loop {
+            match response.progress_port.recv() {
+                Payload(data) => body.push_all(data.as_slice()),
+                Done(result) => {
+                    assert!(result.is_ok());
+                    break
                 }
             }
-            Err(_) => {
-                progress_chan.send(Done(Err(url.to_string())));
-            }
         }
-    });
+        let header_names = response.metadata.headers.as_ref().map_or(vec!(), |headers| {
+            headers.iter().map(|header| header.header_name()).collect()
+        });
+        (response.metadata.status.code(),
+         response.metadata.content_type.clone(),
+         header_names,
+         str::from_utf8(body.as_slice()).unwrap().to_string())
+    }
+
+    #[test]
+    fn test_content_type_for_path() {
+        assert_eq!(content_type_for_path(&Path::new("/a/style.CSS")),
+                   Some(("text".to_string(), "css".to_string())));
+        assert_eq!(content_type_for_path(&Path::new("/a/page.htm")),
+                   Some(("text".to_string(), "html".to_string())));
+        assert_eq!(content_type_for_path(&Path::new("/a/README")), None);
+    }
+
+    #[test]
+    fn test_file_metadata() {
+        let dir = TempDir::new("file-loader").unwrap();
+        let path = dir.path().join("style.css");
+        File::create(&path).write_str("p { color: red }").unwrap();
+        let (status, content_type, headers, body) = load(Url::from_file_path(&path).unwrap());
+        assert_eq!(status, 200);
+        assert_eq!(content_type, Some(("text".to_string(), "css".to_string())));
+        assert!(headers.contains(&"Last-Modified".to_string()));
+        assert_eq!(body, "p { color: red }".to_string());
+    }
+
+    #[test]
+    fn test_directory_listing() {
+        let dir = TempDir::new("file-loader").unwrap();
+        File::create(&dir.path().join("b.txt")).write_str("1").unwrap();
+        File::create(&dir.path().join("a.txt")).write_str("123").unwrap();
+        fs::mkdir(&dir.path().join("c"), UserRWX).unwrap();
+        let url = Url::from_file_path(dir.path()).unwrap();
+
+        let (status, content_type, _, body) = load(url.clone());
+        assert_eq!(status, 200);
+        assert_eq!(content_type, Some(("text".to_string(), "html".to_string())));
+        let a = body.as_slice().find_str(">a.txt<").unwrap();
+        let b = body.as_slice().find_str(">b.txt<").unwrap();
+        let c = body.as_slice().find_str(">c/<").unwrap();
+        assert!(a < b && b < c);
+
+        let mut by_size = url.clone();
+        by_size.query = Some("sort=size&order=desc".to_string());
+        let (_, _, _, body) = load(by_size);
+        let a = body.as_slice().find_str(">a.txt<").unwrap();
+        let b = body.as_slice().find_str(">b.txt<").unwrap();
+        assert!(a < b);
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let dir = TempDir::new("file-loader").unwrap();
+        let url = Url::from_file_path(&dir.path().join("missing.html")).unwrap();
+        let (status, content_type, _, body) = load(url);
+        assert_eq!(status, 404);
+        assert_eq!(content_type, Some(("text".to_string(), "html".to_string())));
+        assert!(body.as_slice().contains("missing.html"));
+    }
 }
@@ -0,0 +1,320 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A log of the loads made by the resource task, written in the
+//! [HAR 1.2](http://www.softwareishard.com/blog/har-12-spec/) format when Servo exits.
+//!
+//! Each request is a separate entry, including each step of a redirect chain followed by the
+//! HTTP loader.
+
+#![allow(non_snake_case)]
+
+use about_loader::SERVO_VERSION;
+use network_events::{NetworkEvent, NetworkRequest, RequestStarted, Redirected};
+use network_events::{ResponseStarted, FirstByte, LoadCompleted};
+use resource_task::LoadId;
+
+use serialize::json;
+use std::collections::hashmap::HashMap;
+use std::io::{File, IoResult};
+use time;
+use url::Url;
+
+#[deriving(Clone, Encodable)]
+struct HarFile {
+    log: Log,
+}
+
+#[deriving(Clone, Encodable)]
+struct Log {
+    version: String,
+    creator: Creator,
+    entries: Vec<Entry>,
+}
+
+#[deriving(Clone, Encodable)]
+struct Creator {
+    name: String,
+    version: String,
+}
+
+#[deriving(Clone, Encodable)]
+struct Header {
+    name: String,
+    value: String,
+}
+
+#[deriving(Clone, Encodable)]
+struct Entry {
+    startedDateTime: String,
+    /// The total time of the request, in milliseconds.
+    time: f64,
+    request: Request,
+    response: Response,
+    cache: Cache,
+    timings: Timings,
+}
+
+#[deriving(Clone, Encodable)]
+struct Request {
+    method: String,
+    url: String,
+    httpVersion: String,
+    cookies: Vec<Header>,
+    headers: Vec<Header>,
+    queryString: Vec<Header>,
+    headersSize: int,
+    bodySize: int,
+}
+
+#[deriving(Clone, Encodable)]
+struct Response {
+    status: u16,
+    statusText: String,
+    httpVersion: String,
+    cookies: Vec<Header>,
+    headers: Vec<Header>,
+    content: Content,
+    redirectURL: String,
+    headersSize: int,
+    bodySize: int,
+    /// Not part of HAR: the error the load failed with, if any.
+    _error: Option<String>,
+}
+
+#[deriving(Clone, Encodable)]
+struct Content {
+    size: int,
+    mimeType: String,
+}
+
+#[deriving(Clone, Encodable)]
+struct Cache;
+
+/// The phases of the request, in milliseconds. `-1` means that the phase doesn't apply or
+/// wasn't measured.
+#[deriving(Clone, Encodable)]
+struct Timings {
+    blocked: f64,
+    dns: f64,
+    connect: f64,
+    send: f64,
+    wait: f64,
+    receive: f64,
+    ssl: f64,
+}
+
+/// An entry being filled in from events, with the times the events happened.
+struct PendingEntry {
+    request: NetworkRequest,
+    entry: Entry,
+    start: u64,
+    response_start: Option<u64>,
+}
+
+pub struct HarLog {
+    path: Path,
+    entries: Vec<PendingEntry>,
+    /// The index of the current entry of each load in progress.
+    loads: HashMap<LoadId, uint>,
+}
+
+fn headers(headers: &Vec<(String, String)>) -> Vec<Header> {
+    headers.iter().map(|&(ref name, ref value)| {
+        Header {
+            name: name.clone(),
+            value: value.clone(),
+        }
+    }).collect()
+}
+
+fn milliseconds(from: u64, to: u64) -> f64 {
+    (to - from) as f64 / 1000000.0
+}
+
+fn query_string(url: &Url) -> Vec<Header> {
+    url.query_pairs().unwrap_or(vec!()).into_iter().map(|(name, value)| {
+        Header {
+            name: name,
+            value: value,
+        }
+    }).collect()
+}
+
+impl HarLog {
+    /// Start a log to be written to `path`.
+    pub fn new(path: Path) -> HarLog {
+        HarLog {
+            path: path,
+            entries: vec!(),
+            loads: HashMap::new(),
+        }
+    }
+
+    pub fn observe(&mut self, event: &NetworkEvent) {
+        match event.kind {
+            RequestStarted(ref request) => self.start_entry(event.load, event.time, request),
+            Redirected(ref url, status, ref redirect_headers) => {
+                // The redirect ends the current entry, and the loader makes a new request.
+                let request = match self.loads.find(&event.load) {
+                    Some(&index) => {
+                        let pending = &mut self.entries.as_mut_slice()[index];
+                        pending.entry.response.status = status;
+                        pending.entry.response.headers = headers(redirect_headers);
+                        pending.entry.response.redirectURL = url.serialize();
+                        pending.finish(event.time);
+                        NetworkRequest {
+                            url: url.clone(),
+                            body_size: 0,
+                            started: time::get_time(),
+                            .. pending.request.clone()
+                        }
+                    }
+                    None => return,
+                };
+                self.start_entry(event.load, event.time, &request);
+            }
+            ResponseStarted(ref response) => {
+                self.with_entry(event.load, |pending| {
+                    pending.response_start = Some(event.time);
+                    let entry = &mut pending.entry;
+                    entry.response.status = response.status;
+                    entry.response.headers = headers(&response.headers);
+                    entry.response.content.mimeType = match response.content_type {
+                        Some((ref type_, ref subtype)) => format!("{:s}/{:s}", *type_, *subtype),
+                        None => String::new(),
+                    };
+                })
+            }
+            FirstByte => {}
+            LoadCompleted(size, ref result) => {
+                self.with_entry(event.load, |pending| {
+                    pending.entry.response.content.size = size as int;
+                    pending.entry.response.bodySize = size as int;
+                    pending.entry.response._error = result.clone().err();
+                    pending.finish(event.time);
+                });
+                self.loads.remove(&event.load);
+            }
+        }
+    }
+
+    fn start_entry(&mut self, load: LoadId, time: u64, request: &NetworkRequest) {
+        let started = time::at_utc(request.started).rfc3339();
+        self.loads.insert(load, self.entries.len());
+        self.entries.push(PendingEntry {
+            request: request.clone(),
+            entry: Entry {
+                startedDateTime: started,
+                time: 0.0,
+                request: Request {
+                    method: format!("{}", request.method),
+                    url: request.url.serialize(),
+                    httpVersion: "HTTP/1.1".to_string(),
+                    cookies: vec!(),
+                    headers: headers(&request.headers),
+                    queryString: query_string(&request.url),
+                    headersSize: -1,
+                    bodySize: request.body_size as int,
+                },
+                response: Response {
+                    status: 0,
+                    statusText: String::new(),
+                    httpVersion: "HTTP/1.1".to_string(),
+                    cookies: vec!(),
+                    headers: vec!(),
+                    content: Content {
+                        size: 0,
+                        mimeType: String::new(),
+                    },
+                    redirectURL: String::new(),
+                    headersSize: -1,
+                    bodySize: -1,
+                    _error: None,
+                },
+                cache: Cache,
+                timings: Timings {
+                    blocked: -1.0,
+                    dns: -1.0,
+                    connect: -1.0,
+                    send: 0.0,
+                    wait: 0.0,
+                    receive: 0.0,
+                    ssl: -1.0,
+                },
+            },
+            start: time,
+            response_start: None,
+        });
+    }
+
+    fn with_entry(&mut self, load: LoadId, f: |&mut PendingEntry|) {
+        match self.loads.find(&load) {
+            Some(&index) => f(&mut self.entries.as_mut_slice()[index]),
+            None => {}
+        }
+    }
+
+    /// Write the log to its file.
+    pub fn write(&self) -> IoResult<()> {
+        let har = HarFile {
+            log: Log {
+                version: "1.2".to_string(),
+                creator: Creator {
+                    name: "Servo".to_string(),
+                    version: SERVO_VERSION.to_string(),
+                },
+                entries: self.entries.iter().map(|pending| pending.entry.clone()).collect(),
+            },
+        };
+        File::create(&self.path).write_str(json::encode(&har).as_slice())
+    }
+}
+
+impl PendingEntry {
+    fn finish(&mut self, time: u64) {
+        let response_start = self.response_start.unwrap_or(time);
+        self.entry.timings.wait = milliseconds(self.start, response_start);
+        self.entry.timings.receive = milliseconds(response_start, time);
+        self.entry.time = milliseconds(self.start, time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HarLog;
+    use network_events::{NetworkEvent, NetworkRequest, NetworkResponse, RequestStarted};
+    use network_events::{Redirected, ResponseStarted, LoadCompleted};
+    use resource_task::{LoadData, LoadId};
+    use url::Url;
+
+    #[test]
+    fn test_redirect_chain() {
+        let load = LoadId(1);
+        let url = Url::parse("http://example.com/old?a=1").unwrap();
+        let new_url = Url::parse("http://example.com/new").unwrap();
+        let mut log = HarLog::new(Path::new("unused.har"));
+        let request = NetworkRequest::new(&LoadData::new(url));
+        log.observe(&NetworkEvent::new(load, RequestStarted(request)));
+        log.observe(&NetworkEvent::new(load, Redirected(new_url.clone(), 301, vec!())));
+        log.observe(&NetworkEvent::new(load, ResponseStarted(NetworkResponse {
+            url: new_url.clone(),
+            status: 200,
+            headers: vec!(("Content-Type".to_string(), "text/html".to_string())),
+            content_type: Some(("text".to_string(), "html".to_string())),
+        })));
+        log.observe(&NetworkEvent::new(load, LoadCompleted(5, Ok(()))));
+
+        assert_eq!(log.entries.len(), 2);
+        let redirect = &log.entries.as_slice()[0].entry;
+        assert_eq!(redirect.request.queryString.as_slice()[0].value, "1".to_string());
+        assert_eq!(redirect.response.status, 301);
+        assert_eq!(redirect.response.redirectURL, new_url.serialize());
+        let response = &log.entries.as_slice()[1].entry;
+        assert_eq!(response.request.url, new_url.serialize());
+        assert_eq!(response.response.content.mimeType, "text/html".to_string());
+        assert_eq!(response.response.bodySize, 5);
+        assert!(log.loads.is_empty());
+    }
+}
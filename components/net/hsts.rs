@@ -0,0 +1,250 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The [HTTP Strict Transport Security](http://tools.ietf.org/html/rfc6797) store owned by the
+//! resource task. Requests to the hosts it knows are upgraded from `http` to `https`.
+
+use serialize::json;
+use std::ascii::StrAsciiExt;
+use std::io::File;
+use std::io::net::ip::IpAddr;
+use time;
+use url::Url;
+
+/// A host that must only be reached over a secure connection.
+#[deriving(Clone, PartialEq, Show)]
+pub struct HSTSEntry {
+    pub host: String,
+    pub include_subdomains: bool,
+    /// When the entry expires, in seconds since the epoch. Preloaded entries never expire.
+    pub expires_at: Option<i64>,
+}
+
+impl HSTSEntry {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.map_or(false, |expires_at| expires_at <= now)
+    }
+
+    /// http://tools.ietf.org/html/rfc6797#section-8.2
+    fn matches_host(&self, host: &str) -> bool {
+        host == self.host.as_slice() ||
+            (self.include_subdomains && host.ends_with(format!(".{:s}", self.host).as_slice()))
+    }
+}
+
+/// The format of the preload list file: `{"entries": [{"host": "example.com",
+/// "include_subdomains": true}, ...]}`.
+#[deriving(Decodable)]
+struct PreloadEntry {
+    host: String,
+    include_subdomains: bool,
+}
+
+#[deriving(Decodable)]
+struct PreloadList {
+    entries: Vec<PreloadEntry>,
+}
+
+/// A `Strict-Transport-Security` header value.
+#[deriving(PartialEq, Show)]
+pub struct HSTSHeader {
+    pub max_age: u64,
+    pub include_subdomains: bool,
+}
+
+/// Parse a `Strict-Transport-Security` header value, returning `None` if it is invalid.
+/// http://tools.ietf.org/html/rfc6797#section-6.1
+pub fn parse_header(value: &str) -> Option<HSTSHeader> {
+    let mut max_age = None;
+    let mut include_subdomains = false;
+    for directive in value.split(';') {
+        let directive = directive.trim();
+        if directive.is_empty() {
+            continue
+        }
+        let (name, value) = match directive.find('=') {
+            Some(index) => {
+                let value = directive.slice_from(index + 1).trim();
+                let value = if value.len() >= 2 && value.starts_with("\"") && value.ends_with("\"") {
+                    value.slice(1, value.len() - 1)
+                } else {
+                    value
+                };
+                (directive.slice_to(index).trim().to_ascii_lower(), Some(value))
+            }
+            None => (directive.to_ascii_lower(), None),
+        };
+        // Directives may not appear twice.
+        match (name.as_slice(), value) {
+            ("max-age", Some(value)) if max_age.is_none() => {
+                match from_str::<u64>(value) {
+                    Some(seconds) => max_age = Some(seconds),
+                    None => return None,
+                }
+            }
+            ("max-age", _) => return None,
+            ("includesubdomains", None) if !include_subdomains => include_subdomains = true,
+            ("includesubdomains", _) => return None,
+            // Unknown directives are ignored.
+            _ => {}
+        }
+    }
+    max_age.map(|max_age| HSTSHeader {
+        max_age: max_age,
+        include_subdomains: include_subdomains,
+    })
+}
+
+/// The serialized host of `url`, if it has a domain name rather than an IP address.
+fn domain_name(url: &Url) -> Option<String> {
+    url.serialize_host().and_then(|host| {
+        let host = host.into_ascii_lower();
+        let is_ip = from_str::<IpAddr>(host.as_slice()).is_some() || host.as_slice().starts_with("[");
+        if is_ip { None } else { Some(host) }
+    })
+}
+
+pub struct HSTSList {
+    entries: Vec<HSTSEntry>,
+}
+
+impl HSTSList {
+    pub fn new() -> HSTSList {
+        HSTSList {
+            entries: vec!(),
+        }
+    }
+
+    /// A list holding the entries of a preload list file.
+    pub fn from_preload_file(path: &Path) -> Result<HSTSList, String> {
+        let contents = try!(File::open(path).read_to_string().map_err(|e| e.to_string()));
+        let preload: PreloadList = try!(json::decode(contents.as_slice())
+                                            .map_err(|e| format!("{}", e)));
+        Ok(HSTSList {
+            entries: preload.entries.into_iter().map(|entry| {
+                HSTSEntry {
+                    host: entry.host.into_ascii_lower(),
+                    include_subdomains: entry.include_subdomains,
+                    expires_at: None,
+                }
+            }).collect(),
+        })
+    }
+
+    /// Whether requests to `host` must be made securely.
+    pub fn is_host_secure(&self, host: &str) -> bool {
+        let now = time::get_time().sec;
+        self.entries.iter().any(|entry| !entry.is_expired(now) && entry.matches_host(host))
+    }
+
+    /// Store or remove the entry for the host of `url`, which sent a `Strict-Transport-Security`
+    /// header. The header is ignored unless it was received over a secure connection.
+    /// http://tools.ietf.org/html/rfc6797#section-8.1
+    pub fn update_from_header(&mut self, url: &Url, value: &str) {
+        if url.scheme.as_slice() != "https" {
+            return
+        }
+        let host = match domain_name(url) {
+            Some(host) => host,
+            None => return,
+        };
+        let header = match parse_header(value) {
+            Some(header) => header,
+            None => {
+                debug!("hsts: ignoring invalid header {:s}", value);
+                return
+            }
+        };
+
+        // Preloaded entries can't be changed by the host.
+        let preloaded = self.entries.iter().any(|entry| {
+            entry.host == host && entry.expires_at.is_none()
+        });
+        if preloaded {
+            return
+        }
+        self.entries.retain(|entry| entry.host != host);
+        if header.max_age == 0 {
+            return
+        }
+        let now = time::get_time().sec;
+        self.entries.push(HSTSEntry {
+            host: host,
+            include_subdomains: header.include_subdomains,
+            expires_at: Some(now + header.max_age as i64),
+        });
+    }
+
+    /// The secure equivalent of `url` if its host is known to require secure connections, or
+    /// `url` itself.
+    /// http://tools.ietf.org/html/rfc6797#section-8.3
+    pub fn secure_url(&self, url: &Url) -> Url {
+        if url.scheme.as_slice() != "http" {
+            return url.clone()
+        }
+        match domain_name(url) {
+            Some(ref host) if self.is_host_secure(host.as_slice()) => {}
+            _ => return url.clone(),
+        }
+        // Reparsing with the new scheme leaves an explicit port alone, and replaces the default
+        // port of http with that of https.
+        let serialized = url.serialize();
+        match Url::parse(format!("https{:s}", serialized.as_slice().slice_from(4)).as_slice()) {
+            Ok(secure_url) => secure_url,
+            Err(_) => url.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HSTSHeader, HSTSList, parse_header};
+    use url::Url;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(parse_header("max-age=31536000"),
+                   Some(HSTSHeader { max_age: 31536000, include_subdomains: false }));
+        assert_eq!(parse_header(" Max-Age=\"10\" ; includeSubDomains; preload"),
+                   Some(HSTSHeader { max_age: 10, include_subdomains: true }));
+        assert_eq!(parse_header("includeSubDomains"), None);
+        assert_eq!(parse_header("max-age=10; max-age=20"), None);
+        assert_eq!(parse_header("max-age=ten"), None);
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let mut list = HSTSList::new();
+        list.update_from_header(&url("https://example.com/"), "max-age=100; includeSubDomains");
+        list.update_from_header(&url("https://other.org/"), "max-age=100");
+        assert_eq!(list.secure_url(&url("http://example.com/a?b")), url("https://example.com/a?b"));
+        assert_eq!(list.secure_url(&url("http://www.example.com:8080/")),
+                   url("https://www.example.com:8080/"));
+        assert_eq!(list.secure_url(&url("http://other.org/")), url("https://other.org/"));
+        assert_eq!(list.secure_url(&url("http://www.other.org/")), url("http://www.other.org/"));
+        assert_eq!(list.secure_url(&url("http://example.org/")), url("http://example.org/"));
+    }
+
+    #[test]
+    fn test_ignored_headers() {
+        let mut list = HSTSList::new();
+        list.update_from_header(&url("http://example.com/"), "max-age=100");
+        list.update_from_header(&url("https://127.0.0.1/"), "max-age=100");
+        assert!(!list.is_host_secure("example.com"));
+        assert!(!list.is_host_secure("127.0.0.1"));
+    }
+
+    #[test]
+    fn test_max_age_zero_removes_entry() {
+        let mut list = HSTSList::new();
+        list.update_from_header(&url("https://example.com/"), "max-age=100");
+        assert!(list.is_host_secure("example.com"));
+        list.update_from_header(&url("https://example.com/"), "max-age=0");
+        assert!(!list.is_host_secure("example.com"));
+    }
+}
@@ -0,0 +1,419 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! An in-memory HTTP cache owned by the resource task, following
+//! [RFC 7234](http://tools.ietf.org/html/rfc7234).
+//!
+//! The cache only stores complete responses to `GET` requests. Lookups and stores are performed
+//! by `http_loader` through the resource task's control channel, which replays cached responses
+//! to the consumer as if they had come from the network.
+
+use cookie::parse_cookie_date;
+use resource_task::{Metadata, Payload, Done, LoadResponse, start_sending_opt};
+
+use http::headers::HeaderEnum;
+use http::headers::request::HeaderCollection as RequestHeaderCollection;
+use http::headers::response::HeaderCollection as ResponseHeaderCollection;
+use http::method::{Method, Get};
+use std::ascii::StrAsciiExt;
+use std::cmp;
+use std::collections::hashmap::HashMap;
+use time;
+use time::Timespec;
+use url::Url;
+
+/// The maximum number of body bytes kept in the cache before old entries are evicted.
+static MAX_CACHE_SIZE: uint = 32 * 1024 * 1024;
+
+/// Status codes that are cacheable by default.
+/// http://tools.ietf.org/html/rfc7231#section-6.1
+static HEURISTICALLY_CACHEABLE_STATUSES: [u16, ..11] = [
+    200, 203, 204, 206, 300, 301, 404, 405, 410, 414, 501
+];
+
+/// Returns the value of the first header named `name`, ignoring case.
+pub fn get_header(headers: &ResponseHeaderCollection, name: &str) -> Option<String> {
+    headers.iter().find(|header| {
+        header.header_name().as_slice().eq_ignore_ascii_case(name)
+    }).map(|header| header.header_value())
+}
+
+/// The parsed `Cache-Control` directives that this cache understands.
+/// http://tools.ietf.org/html/rfc7234#section-5.2.2
+struct CacheControl {
+    max_age: Option<i64>,
+    no_store: bool,
+    no_cache: bool,
+    must_revalidate: bool,
+}
+
+impl CacheControl {
+    fn parse(headers: &ResponseHeaderCollection) -> CacheControl {
+        let mut cache_control = CacheControl {
+            max_age: None,
+            no_store: false,
+            no_cache: false,
+            must_revalidate: false,
+        };
+        let value = match get_header(headers, "cache-control") {
+            Some(value) => value,
+            None => return cache_control,
+        };
+        for directive in value.as_slice().split(',') {
+            let directive = directive.trim().to_ascii_lower();
+            let (name, argument) = match directive.as_slice().find('=') {
+                Some(index) => (directive.as_slice().slice_to(index).trim(),
+                                Some(directive.as_slice().slice_from(index + 1).trim_chars('"'))),
+                None => (directive.as_slice(), None),
+            };
+            match name {
+                "max-age" => cache_control.max_age = argument.and_then(|a| from_str(a)),
+                "no-store" => cache_control.no_store = true,
+                "no-cache" => cache_control.no_cache = true,
+                "must-revalidate" => cache_control.must_revalidate = true,
+                _ => {}
+            }
+        }
+        cache_control
+    }
+}
+
+/// Whether a response may be stored at all.
+/// http://tools.ietf.org/html/rfc7234#section-3
+pub fn is_storable(method: &Method, status: u16, headers: &ResponseHeaderCollection) -> bool {
+    if *method != Get {
+        return false
+    }
+    let cache_control = CacheControl::parse(headers);
+    if cache_control.no_store {
+        return false
+    }
+    // We do not keep the request headers that a `Vary` header refers to.
+    if get_header(headers, "vary").is_some() {
+        return false
+    }
+    if status == 200 {
+        return true
+    }
+    HEURISTICALLY_CACHEABLE_STATUSES.iter().any(|&s| s == status) &&
+        (cache_control.max_age.is_some() || get_header(headers, "expires").is_some())
+}
+
+/// A response held in the cache, with its body split in the chunks it was received in.
+#[deriving(Clone)]
+pub struct CachedResource {
+    pub metadata: Metadata,
+    pub body: Vec<Vec<u8>>,
+    /// When the response was received.
+    response_time: Timespec,
+    /// The age the response had when it was received.
+    /// http://tools.ietf.org/html/rfc7234#section-4.2.3
+    corrected_initial_age: i64,
+    /// http://tools.ietf.org/html/rfc7234#section-4.2.1
+    freshness_lifetime: i64,
+}
+
+impl CachedResource {
+    pub fn new(metadata: Metadata, body: Vec<Vec<u8>>) -> CachedResource {
+        let mut resource = CachedResource {
+            metadata: metadata,
+            body: body,
+            response_time: time::get_time(),
+            corrected_initial_age: 0,
+            freshness_lifetime: 0,
+        };
+        resource.update_freshness();
+        resource
+    }
+
+    fn headers<'a>(&'a self) -> Option<&'a ResponseHeaderCollection> {
+        self.metadata.headers.as_ref()
+    }
+
+    fn update_freshness(&mut self) {
+        let now = time::get_time();
+        let (age, lifetime) = match self.headers() {
+            Some(headers) => {
+                let age = get_header(headers, "age").and_then(|a| from_str(a.as_slice()));
+                (age.unwrap_or(0), freshness_lifetime(headers, self.metadata.status.code(), now))
+            }
+            None => (0, 0),
+        };
+        self.response_time = now;
+        self.corrected_initial_age = age;
+        self.freshness_lifetime = lifetime;
+    }
+
+    /// http://tools.ietf.org/html/rfc7234#section-4.2.3
+    pub fn current_age(&self, now: Timespec) -> i64 {
+        let resident_time = cmp::max(now.sec - self.response_time.sec, 0);
+        self.corrected_initial_age + resident_time
+    }
+
+    /// Whether the response can be served without contacting the server.
+    /// http://tools.ietf.org/html/rfc7234#section-4.2
+    pub fn is_fresh(&self, now: Timespec) -> bool {
+        self.freshness_lifetime > self.current_age(now)
+    }
+
+    /// The total number of body bytes held by this entry.
+    pub fn size(&self) -> uint {
+        self.body.iter().fold(0, |size, chunk| size + chunk.len())
+    }
+
+    /// Add conditional request headers so the server can validate this response.
+    /// http://tools.ietf.org/html/rfc7234#section-4.3.1
+    pub fn add_validators(&self, request_headers: &mut RequestHeaderCollection) {
+        let headers = match self.headers() {
+            Some(headers) => headers,
+            None => return,
+        };
+        match get_header(headers, "etag") {
+            Some(etag) => {
+                let _ = request_headers.insert_raw("If-None-Match".to_string(), etag.as_bytes());
+            }
+            None => {}
+        }
+        match get_header(headers, "last-modified") {
+            Some(last_modified) => {
+                let _ = request_headers.insert_raw("If-Modified-Since".to_string(),
+                                                   last_modified.as_bytes());
+            }
+            None => {}
+        }
+    }
+
+    /// Whether there is anything to validate the response with.
+    pub fn has_validators(&self) -> bool {
+        match self.headers() {
+            Some(headers) => get_header(headers, "etag").is_some() ||
+                             get_header(headers, "last-modified").is_some(),
+            None => false,
+        }
+    }
+
+    /// Update the stored headers after a `304 Not Modified` response.
+    /// http://tools.ietf.org/html/rfc7234#section-4.3.4
+    pub fn refresh(&mut self, not_modified_headers: &ResponseHeaderCollection) {
+        match self.metadata.headers {
+            Some(ref mut headers) => {
+                for header in not_modified_headers.iter() {
+                    match header.header_name().as_slice().to_ascii_lower().as_slice() {
+                        "content-length" | "content-encoding" | "transfer-encoding" => {}
+                        _ => headers.insert(header),
+                    }
+                }
+            }
+            None => {}
+        }
+        self.update_freshness();
+    }
+
+    /// Send the cached response to a consumer as if it was coming from the network.
+    pub fn replay(self, start_chan: Sender<LoadResponse>) {
+        let progress_chan = match start_sending_opt(start_chan, self.metadata) {
+            Ok(p) => p,
+            _ => return
+        };
+        for chunk in self.body.into_iter() {
+            if progress_chan.send_opt(Payload(chunk)).is_err() {
+                return
+            }
+        }
+        let _ = progress_chan.send_opt(Done(Ok(())));
+    }
+}
+
+/// http://tools.ietf.org/html/rfc7234#section-4.2.1
+fn freshness_lifetime(headers: &ResponseHeaderCollection, status: u16, now: Timespec) -> i64 {
+    let cache_control = CacheControl::parse(headers);
+    if cache_control.no_cache {
+        return 0
+    }
+    match cache_control.max_age {
+        Some(max_age) => return max_age,
+        None => {}
+    }
+
+    // HTTP dates share their formats with cookie dates, so the lenient cookie date parser
+    // handles all three of them.
+    let date = get_header(headers, "date").and_then(|d| parse_cookie_date(d.as_slice()))
+                                          .unwrap_or(now);
+    match get_header(headers, "expires") {
+        // An invalid date, such as "0", means the response has already expired.
+        Some(expires) => {
+            return match parse_cookie_date(expires.as_slice()) {
+                Some(expires) => cmp::max(expires.sec - date.sec, 0),
+                None => 0,
+            }
+        }
+        None => {}
+    }
+
+    // http://tools.ietf.org/html/rfc7234#section-4.2.2
+    if cache_control.must_revalidate || !HEURISTICALLY_CACHEABLE_STATUSES.iter().any(|&s| s == status) {
+        return 0
+    }
+    match get_header(headers, "last-modified").and_then(|l| parse_cookie_date(l.as_slice())) {
+        Some(last_modified) => cmp::max((date.sec - last_modified.sec) / 10, 0),
+        None => 0,
+    }
+}
+
+/// The cache itself, keyed by the final URL of each response.
+pub struct HttpCache {
+    entries: HashMap<Url, CachedResource>,
+    size: uint,
+}
+
+impl HttpCache {
+    pub fn new() -> HttpCache {
+        HttpCache {
+            entries: HashMap::new(),
+            size: 0,
+        }
+    }
+
+    pub fn get(&self, url: &Url) -> Option<CachedResource> {
+        self.entries.find(url).map(|resource| resource.clone())
+    }
+
+    pub fn store(&mut self, url: Url, resource: CachedResource) {
+        self.invalidate(&url);
+        let size = resource.size();
+        if size > MAX_CACHE_SIZE {
+            return
+        }
+        while self.size + size > MAX_CACHE_SIZE {
+            self.evict_oldest();
+        }
+        self.size += size;
+        self.entries.insert(url, resource);
+    }
+
+    /// Remove any entry for `url`, e.g. after an unsafe request to it.
+    /// http://tools.ietf.org/html/rfc7234#section-4.4
+    pub fn invalidate(&mut self, url: &Url) {
+        match self.entries.pop(url) {
+            Some(resource) => self.size -= resource.size(),
+            None => {}
+        }
+    }
+
+    /// The URL, content type, size and freshness of each entry, e.g. for about:cache.
+    pub fn summary(&self) -> Vec<(Url, Option<(String, String)>, uint, bool)> {
+        let now = time::get_time();
+        self.entries.iter().map(|(url, resource)| {
+            (url.clone(), resource.metadata.content_type.clone(), resource.size(),
+             resource.is_fresh(now))
+        }).collect()
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self.entries.iter().min_by(|&(_, resource)| resource.response_time)
+                                        .map(|(url, _)| url.clone());
+        match oldest {
+            Some(url) => self.invalidate(&url),
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CachedResource, freshness_lifetime, is_storable};
+    use resource_task::Metadata;
+    use resource_task::{new_resource_task, load_whole_resource, spawn_test_server};
+    use resource_task::{ResourceHandlers, Exit};
+
+    use http::headers::response::HeaderCollection;
+    use http::method::{Get, Post};
+    use servo_util::opts::default_opts;
+    use time;
+    use url::Url;
+
+    fn headers(raw: &[(&str, &str)]) -> HeaderCollection {
+        let mut headers = HeaderCollection::new();
+        for &(name, value) in raw.iter() {
+            let _ = headers.insert_raw(name.to_string(), value.as_bytes());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_freshness_lifetime() {
+        let now = time::get_time();
+        assert_eq!(freshness_lifetime(&headers(&[("Cache-Control", "max-age=60")]), 200, now), 60);
+        assert_eq!(freshness_lifetime(&headers(&[("Cache-Control", "no-cache, max-age=60")]), 200, now), 0);
+        assert_eq!(freshness_lifetime(&headers(&[("Date", "Wed, 21 Oct 2015 07:28:00 GMT"),
+                                                ("Expires", "Wed, 21 Oct 2015 08:28:00 GMT")]), 200, now),
+                   3600);
+        assert_eq!(freshness_lifetime(&headers(&[("Expires", "0")]), 200, now), 0);
+        assert_eq!(freshness_lifetime(&headers(&[("Date", "Wed, 21 Oct 2015 07:28:00 GMT"),
+                                                ("Last-Modified", "Wed, 21 Oct 2015 07:18:00 GMT")]), 200, now),
+                   60);
+        assert_eq!(freshness_lifetime(&headers(&[]), 200, now), 0);
+    }
+
+    #[test]
+    fn test_is_storable() {
+        assert!(is_storable(&Get, 200, &headers(&[])));
+        assert!(!is_storable(&Post, 200, &headers(&[])));
+        assert!(!is_storable(&Get, 200, &headers(&[("Cache-Control", "no-store")])));
+        assert!(!is_storable(&Get, 200, &headers(&[("Vary", "Accept-Encoding")])));
+        assert!(!is_storable(&Get, 302, &headers(&[("Cache-Control", "max-age=60")])));
+        assert!(is_storable(&Get, 404, &headers(&[("Cache-Control", "max-age=60")])));
+    }
+
+    #[test]
+    fn test_refresh_after_revalidation() {
+        let mut metadata = Metadata::default(Url::parse("http://example.com/").unwrap());
+        metadata.headers = Some(headers(&[("Cache-Control", "no-cache"), ("ETag", "\"a\"")]));
+        let mut resource = CachedResource::new(metadata, vec!(vec!(1, 2, 3)));
+        assert!(!resource.is_fresh(time::get_time()));
+        assert!(resource.has_validators());
+
+        resource.refresh(&headers(&[("Cache-Control", "max-age=60")]));
+        assert!(resource.is_fresh(time::get_time()));
+        assert_eq!(resource.size(), 3);
+    }
+
+    #[test]
+    fn test_http_cache_fresh_response() {
+        let response = "HTTP/1.1 200 OK\r\nCache-Control: max-age=3600\r\nContent-Length: 5\r\n\
+                        Connection: close\r\n\r\nhello".to_string().into_bytes();
+        let (url, requests) = spawn_test_server(vec!(response));
+        let resource_task = new_resource_task(&default_opts(), ResourceHandlers::new());
+
+        let (_, body) = load_whole_resource(&resource_task, url.clone()).unwrap();
+        assert_eq!(body.as_slice(), b"hello");
+        requests.recv();
+
+        // The second load is answered without contacting the server.
+        let (metadata, body) = load_whole_resource(&resource_task, url).unwrap();
+        assert_eq!(body.as_slice(), b"hello");
+        assert_eq!(metadata.status.code(), 200);
+        assert!(requests.try_recv().is_err());
+        resource_task.send(Exit);
+    }
+
+    #[test]
+    fn test_http_cache_revalidation() {
+        let first = "HTTP/1.1 200 OK\r\nCache-Control: no-cache\r\nETag: \"v1\"\r\n\
+                     Content-Length: 5\r\nConnection: close\r\n\r\nhello".to_string().into_bytes();
+        let second = b"HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_vec();
+        let (url, requests) = spawn_test_server(vec!(first, second));
+        let resource_task = new_resource_task(&default_opts(), ResourceHandlers::new());
+
+        let (_, body) = load_whole_resource(&resource_task, url.clone()).unwrap();
+        assert_eq!(body.as_slice(), b"hello");
+        assert!(!requests.recv().as_slice().contains("If-None-Match"));
+
+        let (metadata, body) = load_whole_resource(&resource_task, url).unwrap();
+        assert_eq!(body.as_slice(), b"hello");
+        assert_eq!(metadata.status.code(), 200);
+        assert!(requests.recv().as_slice().contains("If-None-Match: \"v1\""));
+        resource_task.send(Exit);
+    }
+}
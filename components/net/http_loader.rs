@@ -2,17 +2,246 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use connection_pool::{PooledStream, lease_connection, pool_key, release_unused_lease};
+use connection_pool::set_connection_reusable;
+use cookie::HTTP;
+use http_auth::{AuthChallenge, parse_challenges};
+use http_cache::{CachedResource, get_header, is_storable};
+use mixed_content;
+use network_events::{NetworkEvent, Redirected, response_headers};
+use proxy;
+use proxy::{ProxyConfig, request_writer};
+use referrer_policy::determine_referrer;
 use resource_task::{Metadata, Payload, Done, LoadResponse, LoadData, start_sending_opt};
+use resource_task::{ResourceTask, GetCookiesForUrl, SetCookieForUrl};
+use resource_task::{GetAuthorization, Authenticate, GetConnection, ReportNetworkEvent};
+use resource_task::{GetCachedResource, StoreCachedResource, InvalidateCachedResource};
+use resource_task::{SetHSTSEntryForUrl, GetSecureUrl};
 
+use flate2::reader::{GzDecoder, ZlibDecoder};
+use std::ascii::StrAsciiExt;
+use std::cmp;
 use std::collections::hashmap::HashSet;
-use http::client::{RequestWriter, NetworkStream};
 use http::headers::HeaderEnum;
-use std::io::Reader;
+use http::headers::response::HeaderCollection as ResponseHeaderCollection;
+use http::method::{Get, Head, Method};
+use std::io;
+use std::io::{IoError, IoResult, Reader};
+use std::num;
 use servo_util::task::spawn_named;
+use time;
 use url::Url;
 
-pub fn factory(load_data: LoadData, start_chan: Sender<LoadResponse>) {
-    spawn_named("http_loader", proc() load(load_data, start_chan))
+pub fn factory(load_data: LoadData, start_chan: Sender<LoadResponse>, resource_mgr_chan: ResourceTask,
+               proxy_config: ProxyConfig) {
+    spawn_named("http_loader", proc() load(load_data, start_chan, resource_mgr_chan, proxy_config))
+}
+
+/// The content codings that can be decoded by `decode_response`.
+static SUPPORTED_CONTENT_CODINGS: [&'static str, ..3] = ["gzip", "x-gzip", "deflate"];
+
+/// Build an `Accept-Encoding` value from the configured content codings, dropping any that we
+/// are unable to decode.
+pub fn accept_encoding(codings: &[String]) -> String {
+    let supported: Vec<&str> = codings.iter().map(|coding| coding.as_slice()).filter(|coding| {
+        let supported = SUPPORTED_CONTENT_CODINGS.iter().any(|s| coding.eq_ignore_ascii_case(*s));
+        if !supported {
+            warn!("http_loader: ignoring unsupported content coding {:s}", *coding);
+        }
+        supported
+    }).collect();
+    if supported.is_empty() {
+        "identity".to_string()
+    } else {
+        supported.connect(", ")
+    }
+}
+
+/// Wrap the response body in a decoder for its `Content-Encoding`, so that consumers receive
+/// the decoded bytes chunk by chunk.
+fn decode_response<R: Reader + 'static>(response: R, content_encoding: Option<String>)
+                                        -> Result<Box<Reader + 'static>, String> {
+    let content_encoding = match content_encoding {
+        Some(content_encoding) => content_encoding.as_slice().trim().to_ascii_lower(),
+        None => return Ok(box response as Box<Reader>),
+    };
+    match content_encoding.as_slice() {
+        "gzip" | "x-gzip" => match GzDecoder::new(response) {
+            Ok(decoder) => Ok(box decoder as Box<Reader>),
+            Err(e) => Err(e.desc.to_string()),
+        },
+        "deflate" => Ok(box ZlibDecoder::new(response) as Box<Reader>),
+        "identity" | "" => Ok(box response as Box<Reader>),
+        _ => Err(format!("unsupported content coding {:s}", content_encoding)),
+    }
+}
+
+/// How the end of a response body is found.
+/// http://tools.ietf.org/html/rfc7230#section-3.3.3
+enum BodyLength {
+    /// The body has this many bytes left.
+    KnownLength(u64),
+    /// The body uses the chunked transfer coding. Holds the number of bytes left in the current
+    /// chunk, or `None` if the size of the next chunk is to be read.
+    Chunked(Option<u64>),
+    /// The body ends when the server closes the connection.
+    UntilClose,
+}
+
+fn body_length(method: &Method, status: u16, headers: &ResponseHeaderCollection) -> BodyLength {
+    if *method == Head || status / 100 == 1 || status == 204 || status == 304 {
+        return KnownLength(0)
+    }
+    match get_header(headers, "transfer-encoding") {
+        Some(codings) => {
+            let codings = codings.as_slice().trim().to_ascii_lower();
+            return if codings.as_slice().ends_with("chunked") { Chunked(None) } else { UntilClose }
+        }
+        None => {}
+    }
+    match get_header(headers, "content-length").and_then(|length| {
+        from_str::<u64>(length.as_slice().trim())
+    }) {
+        Some(length) => KnownLength(length),
+        None => UntilClose,
+    }
+}
+
+/// Whether the server will keep the connection open after the response.
+/// http://tools.ietf.org/html/rfc7230#section-6.3
+fn is_persistent(version: (uint, uint), headers: &ResponseHeaderCollection) -> bool {
+    let connection = get_header(headers, "connection").unwrap_or(String::new()).to_ascii_lower();
+    let has_option = |option: &str| connection.as_slice().split(',').any(|o| o.trim() == option);
+    match version {
+        (1, 0) => has_option("keep-alive"),
+        (1, _) => !has_option("close"),
+        _ => false,
+    }
+}
+
+/// Reads a response body up to the end given by its `BodyLength`, rather than until the server
+/// closes the connection, and marks a persistent connection as reusable once the end is reached.
+struct BodyReader<R> {
+    response: R,
+    length: BodyLength,
+    persistent: bool,
+}
+
+impl<R: Reader> BodyReader<R> {
+    fn finish(&mut self) {
+        if self.persistent {
+            set_connection_reusable();
+            self.persistent = false;
+        }
+    }
+
+    /// Reads from the response, treating the end of the connection as an error, since the body
+    /// hasn't ended yet.
+    fn read_body(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        self.response.read(buf).map_err(|e| {
+            if e.kind != io::EndOfFile {
+                return e
+            }
+            IoError {
+                kind: io::ConnectionAborted,
+                desc: "connection closed before the end of the response body",
+                detail: None,
+            }
+        })
+    }
+
+    fn read_line(&mut self) -> IoResult<String> {
+        let mut line = vec!();
+        loop {
+            let mut byte = [0u8];
+            if try!(self.read_body(&mut byte)) == 0 {
+                continue
+            }
+            match byte[0] {
+                b'\n' => break,
+                byte => line.push(byte),
+            }
+        }
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(String::from_utf8_lossy(line.as_slice()).into_string())
+    }
+
+    /// http://tools.ietf.org/html/rfc7230#section-4.1
+    fn read_chunk_size(&mut self) -> IoResult<u64> {
+        let line = try!(self.read_line());
+        let size = line.as_slice().split(';').next().unwrap_or("").trim();
+        match num::from_str_radix::<u64>(size, 16) {
+            Some(size) => Ok(size),
+            None => Err(IoError {
+                kind: io::InvalidInput,
+                desc: "invalid chunk size",
+                detail: Some(line.clone()),
+            }),
+        }
+    }
+}
+
+impl<R: Reader> Reader for BodyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        let length = self.length;
+        match length {
+            UntilClose => self.response.read(buf),
+            KnownLength(0) => {
+                self.finish();
+                Err(io::standard_error(io::EndOfFile))
+            }
+            KnownLength(remaining) => {
+                let max = cmp::min(buf.len() as u64, remaining) as uint;
+                let len = try!(self.read_body(buf.slice_to_mut(max)));
+                self.length = KnownLength(remaining - len as u64);
+                if len as u64 == remaining {
+                    self.finish();
+                }
+                Ok(len)
+            }
+            Chunked(None) => {
+                let size = try!(self.read_chunk_size());
+                if size == 0 {
+                    // Skip the trailer.
+                    while !try!(self.read_line()).is_empty() {}
+                    self.length = KnownLength(0);
+                    return self.read(buf)
+                }
+                self.length = Chunked(Some(size));
+                self.read(buf)
+            }
+            Chunked(Some(remaining)) => {
+                let max = cmp::min(buf.len() as u64, remaining) as uint;
+                let len = try!(self.read_body(buf.slice_to_mut(max)));
+                if len as u64 == remaining {
+                    // The chunk data is followed by a CRLF.
+                    try!(self.read_line());
+                    self.length = Chunked(None);
+                } else {
+                    self.length = Chunked(Some(remaining - len as u64));
+                }
+                Ok(len)
+            }
+        }
+    }
+}
+
+/// The values of all `WWW-Authenticate` or `Proxy-Authenticate` headers in a response.
+fn challenge_headers(headers: &ResponseHeaderCollection, name: &str) -> Vec<String> {
+    headers.iter().filter(|header| header.header_name().as_slice().eq_ignore_ascii_case(name))
+                  .map(|header| header.header_value())
+                  .collect()
+}
+
+/// Ask the resource task for the stored credentials to send with a request for `url`, to the
+/// origin server or, if given, to the proxy.
+fn stored_authorization(resource_mgr_chan: &ResourceTask, url: &Url, proxy: Option<Url>,
+                        method: &Method) -> Option<String> {
+    let (tx, rx) = channel();
+    resource_mgr_chan.send(GetAuthorization(url.clone(), proxy, method.clone(), tx));
+    rx.recv()
 }
 
 fn send_error(url: Url, err: String, start_chan: Sender<LoadResponse>) {
@@ -22,7 +251,8 @@ fn send_error(url: Url, err: String, start_chan: Sender<LoadResponse>) {
     };
 }
 
-fn load(load_data: LoadData, start_chan: Sender<LoadResponse>) {
+fn load(load_data: LoadData, start_chan: Sender<LoadResponse>, resource_mgr_chan: ResourceTask,
+        proxy_config: ProxyConfig) {
     // FIXME: At the time of writing this FIXME, servo didn't have any central
     //        location for configuration. If you're reading this and such a
     //        repository DOES exist, please update this constant to use it.
@@ -30,6 +260,12 @@ fn load(load_data: LoadData, start_chan: Sender<LoadResponse>) {
     let mut iters = 0u;
     let mut url = load_data.url.clone();
     let mut redirected_to = HashSet::new();
+    // The credentials sent with the current request, and the number of times we've retried it
+    // after a 401 or 407.
+    let max_auth_attempts = 3u;
+    let mut auth_attempts = 0u;
+    let mut authorization: Option<String> = None;
+    let mut proxy_authorization: Option<String> = None;
 
     // Loop to handle redirects.
     loop {
@@ -56,9 +292,34 @@ fn load(load_data: LoadData, start_chan: Sender<LoadResponse>) {
             }
         }
 
+        // Only plain GET requests are answered from the HTTP cache.
+        let cached = if load_data.method == Get && load_data.data.is_none() {
+            let (tx, rx) = channel();
+            resource_mgr_chan.send(GetCachedResource(url.clone(), tx));
+            rx.recv()
+        } else {
+            None
+        };
+        let cached = match cached {
+            Some(cached) => {
+                if cached.is_fresh(time::get_time()) {
+                    info!("using cached response for {:s}", url.serialize());
+                    cached.replay(start_chan);
+                    return;
+                }
+                if cached.has_validators() { Some(cached) } else { None }
+            }
+            None => None
+        };
+
         info!("requesting {:s}", url.serialize());
 
-        let request = RequestWriter::<NetworkStream>::new(load_data.method.clone(), url.clone());
+        let proxy = proxy_config.proxy_for_url(&url);
+        // HTTPS requests are tunnelled, so only plain HTTP requests are seen by the proxy.
+        let forward_proxy = if url.scheme.as_slice() == "http" { proxy.clone() } else { None };
+        let connection_key = pool_key(&url, proxy.as_ref());
+        let request = request_writer::<PooledStream>(proxy, load_data.method.clone(), &url,
+                                                     load_data.headers.clone());
         let mut writer = match request {
             Ok(w) => box w,
             Err(e) => {
@@ -67,30 +328,98 @@ fn load(load_data: LoadData, start_chan: Sender<LoadResponse>) {
             }
         };
 
-        // Preserve the `host` header set automatically by RequestWriter.
-        let host = writer.headers.host.clone();
-        writer.headers = load_data.headers.clone();
-        writer.headers.host = host;
-        if writer.headers.accept_encoding.is_none() {
-            // We currently don't support HTTP Compression (FIXME #2587)
-            writer.headers.accept_encoding = Some(String::from_str("identity".as_slice()))
-        }
-        match load_data.data {
-            Some(ref data) => {
-                writer.headers.content_length = Some(data.len());
-                match writer.write(data.as_slice()) {
-                    Err(e) => {
-                        send_error(url, e.desc.to_string(), start_chan);
-                        return;
+        // The referrer is reconsidered for each URL in a redirect chain, since a redirect may
+        // downgrade the request or change its origin.
+        match load_data.referrer {
+            Some(ref referrer) => {
+                match determine_referrer(referrer, &url, load_data.referrer_policy) {
+                    Some(referrer) => {
+                        let _ = writer.headers.insert_raw("Referer".to_string(),
+                                                          referrer.serialize().as_bytes());
                     }
-                    _ => {}
+                    None => {}
                 }
-            },
+            }
+            None => {}
+        }
+
+        if load_data.send_credentials {
+            let (tx, rx) = channel();
+            resource_mgr_chan.send(GetCookiesForUrl(url.clone(), tx, HTTP));
+            match rx.recv() {
+                Some(cookies) => {
+                    let _ = writer.headers.insert_raw("Cookie".to_string(), cookies.as_bytes());
+                }
+                None => {}
+            }
+        }
+
+        if load_data.send_credentials {
+            if authorization.is_none() {
+                authorization = stored_authorization(&resource_mgr_chan, &url, None,
+                                                     &load_data.method);
+            }
+            match authorization {
+                Some(ref authorization) => {
+                    let _ = writer.headers.insert_raw("Authorization".to_string(),
+                                                      authorization.as_bytes());
+                }
+                None => {}
+            }
+        }
+        match forward_proxy {
+            // The credentials in the proxy's URL are sent unless the proxy has rejected them.
+            Some(ref forward_proxy) if proxy_authorization.is_none() => {
+                proxy_authorization = proxy::proxy_authorization(forward_proxy).or_else(|| {
+                    stored_authorization(&resource_mgr_chan, &url, Some(forward_proxy.clone()),
+                                         &load_data.method)
+                });
+            }
             _ => {}
         }
-        let mut response = match writer.read_response() {
+        if forward_proxy.is_some() {
+            match proxy_authorization {
+                Some(ref authorization) => {
+                    let _ = writer.headers.insert_raw("Proxy-Authorization".to_string(),
+                                                      authorization.as_bytes());
+                }
+                None => {}
+            }
+        }
+
+        match cached {
+            Some(ref cached) => cached.add_validators(&mut writer.headers),
+            None => {}
+        }
+
+        // Send the request on a connection from the pool, waiting for one if necessary.
+        let (tx, rx) = channel();
+        resource_mgr_chan.send(GetConnection(connection_key.clone(), tx));
+        let reused = lease_connection(connection_key, resource_mgr_chan.clone(), rx.recv());
+        let _ = writer.headers.insert_raw("Connection".to_string(), b"keep-alive");
+
+        let written = match load_data.data {
+            Some(ref data) => {
+                writer.headers.content_length = Some(data.len());
+                writer.write(data.as_slice())
+            },
+            None => Ok(()),
+        };
+        let response = match written {
+            Ok(()) => writer.read_response().map_err(|(_, e)| e),
+            Err(e) => Err(e),
+        };
+        release_unused_lease();
+        let response = match response {
             Ok(r) => r,
-            Err((_, e)) => {
+            Err(ref e) if reused => {
+                // The server may have closed the idle connection; try again on another one.
+                info!("retrying {:s} after error on reused connection: {:s}",
+                      url.serialize(), e.desc);
+                redirected_to.remove(&url);
+                continue;
+            }
+            Err(e) => {
                 send_error(url, e.desc.to_string(), start_chan);
                 return;
             }
@@ -103,47 +432,173 @@ fn load(load_data: LoadData, start_chan: Sender<LoadResponse>) {
                 info!(" - {:s}: {:s}", header.header_name(), header.header_value());
             });
 
-        if 3 == (response.status.code() / 100) {
+        // Store cookies before following any redirect, so that they are sent to the new location.
+        if load_data.send_credentials {
+            for header in response.headers.iter() {
+                if header.header_name().as_slice().eq_ignore_ascii_case("set-cookie") {
+                    resource_mgr_chan.send(SetCookieForUrl(url.clone(), header.header_value(), HTTP));
+                }
+            }
+        }
+
+        // Only headers received over a secure connection count, which the store checks.
+        // http://tools.ietf.org/html/rfc6797#section-8.1
+        for header in response.headers.iter() {
+            if header.header_name().as_slice().eq_ignore_ascii_case("strict-transport-security") {
+                resource_mgr_chan.send(SetHSTSEntryForUrl(url.clone(), header.header_value()));
+            }
+        }
+
+        // http://tools.ietf.org/html/rfc2617#section-1.2
+        let challenge = match response.status.code() {
+            _ if auth_attempts >= max_auth_attempts => None,
+            401 if load_data.send_credentials => {
+                Some((None, challenge_headers(&response.headers, "www-authenticate"),
+                      authorization.is_some()))
+            }
+            407 if forward_proxy.is_some() => {
+                let headers = challenge_headers(&response.headers, "proxy-authenticate");
+                Some((forward_proxy.clone(), headers, proxy_authorization.is_some()))
+            }
+            _ => None,
+        };
+        match challenge {
+            Some((proxy, headers, rejected)) => {
+                let is_proxy = proxy.is_some();
+                let challenge = AuthChallenge {
+                    url: url.clone(),
+                    method: load_data.method.clone(),
+                    proxy: proxy,
+                    challenges: headers.iter().flat_map(|header| {
+                        parse_challenges(header.as_slice()).into_iter()
+                    }).collect(),
+                    rejected: rejected,
+                };
+                let (tx, rx) = channel();
+                resource_mgr_chan.send(Authenticate(challenge, tx));
+                match rx.recv() {
+                    Some(credentials) => {
+                        info!("retrying {:s} with credentials", url.serialize());
+                        if is_proxy {
+                            proxy_authorization = Some(credentials);
+                        } else {
+                            authorization = Some(credentials);
+                        }
+                        auth_attempts += 1;
+                        // The retry is not a redirect loop.
+                        redirected_to.remove(&url);
+                        continue;
+                    }
+                    // Hand the challenge response to the consumer.
+                    None => {}
+                }
+            }
+            None => {}
+        }
+
+        // http://tools.ietf.org/html/rfc7234#section-4.3.4
+        if response.status.code() == 304 {
+            match cached {
+                Some(mut cached) => {
+                    info!("cached response for {:s} was validated", url.serialize());
+                    cached.refresh(&response.headers);
+                    resource_mgr_chan.send(StoreCachedResource(url.clone(), cached.clone()));
+                    cached.replay(start_chan);
+                    return;
+                }
+                None => {}
+            }
+        }
+
+        // http://tools.ietf.org/html/rfc7234#section-4.4
+        let status_class = response.status.code() / 100;
+        if load_data.method != Get && load_data.method != Head &&
+                (status_class == 2 || status_class == 3) {
+            resource_mgr_chan.send(InvalidateCachedResource(url.clone()));
+        }
+
+        if load_data.follow_redirects && 3 == (response.status.code() / 100) {
+            let redirect_headers = response_headers(&response.headers);
             match response.headers.location {
                 Some(new_url) => {
-                    // CORS (http://fetch.spec.whatwg.org/#http-fetch, status section, point 9, 10)
-                    match load_data.cors {
-                        Some(ref c) => {
-                            if c.preflight {
-                                // The preflight lied
-                                send_error(url, "Preflight fetch inconsistent with main fetch".to_string(), start_chan);
-                                return;
-                            } else {
-                                // XXXManishearth There are some CORS-related steps here,
-                                // but they don't seem necessary until credentials are implemented
+                    // The new location is upgraded if its host requires HTTPS, and is subject
+                    // to the same mixed content rules as the original request.
+                    let (tx, rx) = channel();
+                    resource_mgr_chan.send(GetSecureUrl(new_url, tx));
+                    let new_url = rx.recv();
+                    match (&load_data.client_url, load_data.context) {
+                        (&Some(ref client_url), Some(context)) => {
+                            match mixed_content::check(client_url, &new_url, context) {
+                                Ok(()) => {}
+                                Err(message) => {
+                                    send_error(new_url, message, start_chan);
+                                    return;
+                                }
                             }
                         }
                         _ => {}
                     }
                     info!("redirecting to {:s}", new_url.serialize());
+                    let redirect = Redirected(new_url.clone(), response.status.code(),
+                                              redirect_headers);
+                    resource_mgr_chan.send(ReportNetworkEvent(NetworkEvent::new(load_data.id,
+                                                                                redirect)));
                     url = new_url;
+                    authorization = None;
+                    proxy_authorization = None;
+                    auth_attempts = 0;
                     continue;
                 }
                 None => ()
             }
         }
 
-        let mut metadata = Metadata::default(url);
+        let content_encoding = get_header(&response.headers, "content-encoding");
+        let length = body_length(&load_data.method, response.status.code(), &response.headers);
+        let persistent = is_persistent(response.version, &response.headers);
+
+        let mut metadata = Metadata::default(url.clone());
         metadata.set_content_type(&response.headers.content_type);
         metadata.headers = Some(response.headers.clone());
         metadata.status = response.status.clone();
 
+        let storable = load_data.data.is_none() &&
+            is_storable(&load_data.method, response.status.code(), &response.headers);
+        let cached_metadata = if storable { Some(metadata.clone()) } else { None };
+        let mut cached_body = vec!();
+
         let progress_chan = match start_sending_opt(start_chan, metadata) {
             Ok(p) => p,
             _ => return
         };
+        // Responses without a body, such as those to HEAD requests, have nothing to decode
+        // even if they name the content coding of the representation.
+        let content_encoding = match length {
+            KnownLength(0) => None,
+            _ => content_encoding,
+        };
+        let body = BodyReader {
+            response: response,
+            length: length,
+            persistent: persistent,
+        };
+        let mut body = match decode_response(body, content_encoding) {
+            Ok(body) => body,
+            Err(e) => {
+                let _ = progress_chan.send_opt(Done(Err(e)));
+                return;
+            }
+        };
         loop {
             let mut buf = Vec::with_capacity(1024);
 
             unsafe { buf.set_len(1024); }
-            match response.read(buf.as_mut_slice()) {
+            match body.read(buf.as_mut_slice()) {
                 Ok(len) => {
                     unsafe { buf.set_len(len); }
+                    if storable {
+                        cached_body.push(buf.clone());
+                    }
                     if progress_chan.send_opt(Payload(buf)).is_err() {
                         // The send errors when the receiver is out of scope,
                         // which will happen if the fetch has timed out (or has been aborted)
@@ -151,14 +606,103 @@ fn load(load_data: LoadData, start_chan: Sender<LoadResponse>) {
                         return;
                     }
                 }
-                Err(_) => {
-                    let _ = progress_chan.send_opt(Done(Ok(())));
-                    break;
+                Err(ref e) if e.kind == io::EndOfFile => break,
+                // The body is incomplete: it is neither cached nor reported as a success.
+                // http://tools.ietf.org/html/rfc7234#section-3.1
+                Err(e) => {
+                    let _ = progress_chan.send_opt(Done(Err(e.desc.to_string())));
+                    return;
                 }
             }
         }
 
+        // Return the connection to the pool before reporting that the load is done, so that
+        // the consumer's next load can use it.
+        drop(body);
+        match cached_metadata {
+            Some(metadata) => {
+                let resource = CachedResource::new(metadata, cached_body);
+                resource_mgr_chan.send(StoreCachedResource(url.clone(), resource));
+            }
+            None => {}
+        }
+        let _ = progress_chan.send_opt(Done(Ok(())));
+
         // We didn't get redirected.
         break;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use http::method::Head;
+    use resource_task::{new_resource_task, load_whole_resource, spawn_test_server};
+    use resource_task::{load_whole_resource_with, LoadData, ResourceHandlers, Exit};
+    use servo_util::opts::default_opts;
+
+    #[test]
+    fn test_content_decoding() {
+        let hello_gzip = [0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48,
+                          0xcd, 0xc9, 0xc9, 0x57, 0x28, 0xcf, 0x2f, 0xca, 0x49, 0x01, 0x00, 0x85,
+                          0x11, 0x4a, 0x0d, 0x0b, 0x00, 0x00, 0x00];
+        let hello_deflate = [0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0x28, 0xcf, 0x2f,
+                             0xca, 0x49, 0x01, 0x00, 0x1a, 0x0b, 0x04, 0x5d];
+        let mut gzip_response = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\n\
+                                  Connection: close\r\n\r\n".to_vec();
+        gzip_response.push_all(&hello_gzip);
+        let mut deflate_response = b"HTTP/1.1 200 OK\r\nContent-Encoding: deflate\r\n\
+                                     Connection: close\r\n\r\n".to_vec();
+        deflate_response.push_all(&hello_deflate);
+
+        let (url, requests) = spawn_test_server(vec!(gzip_response, deflate_response));
+        let resource_task = new_resource_task(&default_opts(), ResourceHandlers::new());
+
+        let (_, body) = load_whole_resource(&resource_task, url.clone()).unwrap();
+        assert_eq!(body.as_slice(), b"hello world");
+        assert!(requests.recv().as_slice().contains("Accept-Encoding: gzip, deflate"));
+
+        let (_, body) = load_whole_resource(&resource_task, url).unwrap();
+        assert_eq!(body.as_slice(), b"hello world");
+        resource_task.send(Exit);
+    }
+
+    #[test]
+    fn test_truncated_body() {
+        let truncated = b"HTTP/1.1 200 OK\r\nCache-Control: max-age=3600\r\n\
+                          Content-Length: 10\r\n\r\nhello".to_vec();
+        let complete = b"HTTP/1.1 200 OK\r\nCache-Control: max-age=3600\r\n\
+                         Content-Length: 5\r\nConnection: close\r\n\r\nhello".to_vec();
+        let (url, requests) = spawn_test_server(vec!(truncated, complete));
+        let resource_task = new_resource_task(&default_opts(), ResourceHandlers::new());
+
+        // The server closes the connection before the end of the body.
+        assert!(load_whole_resource(&resource_task, url.clone()).is_err());
+        requests.recv();
+
+        // The truncated response wasn't cached, so the server is asked again.
+        let (_, body) = load_whole_resource(&resource_task, url).unwrap();
+        assert_eq!(body.as_slice(), b"hello");
+        requests.recv();
+        resource_task.send(Exit);
+    }
+
+    #[test]
+    fn test_content_coding_without_body() {
+        let head = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: 31\r\n\
+                     Connection: close\r\n\r\n".to_vec();
+        let no_content = b"HTTP/1.1 204 No Content\r\nContent-Encoding: gzip\r\n\
+                           Connection: close\r\n\r\n".to_vec();
+        let (url, _requests) = spawn_test_server(vec!(head, no_content));
+        let resource_task = new_resource_task(&default_opts(), ResourceHandlers::new());
+
+        let mut load_data = LoadData::new(url.clone());
+        load_data.method = Head;
+        let (_, body) = load_whole_resource_with(&resource_task, load_data).unwrap();
+        assert!(body.is_empty());
+
+        let (metadata, body) = load_whole_resource(&resource_task, url).unwrap();
+        assert_eq!(metadata.status.code(), 204);
+        assert!(body.is_empty());
+        resource_task.send(Exit);
+    }
+}
@@ -2,15 +2,15 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use fetch;
+use fetch::request::Request;
 use image::base::{Image, load_from_memory};
-use resource_task;
-use resource_task::{LoadData, ResourceTask};
+use resource_task::{ResourceTask, PrioritizeLoad};
 
 use std::comm::{channel, Receiver, Sender};
 use std::collections::hashmap::HashMap;
 use std::mem::replace;
 use std::task::spawn;
-use std::result;
 use sync::{Arc, Mutex};
 use serialize::{Encoder, Encodable};
 use url::Url;
@@ -39,6 +39,10 @@ pub enum Msg {
     /// Used by the decoder tasks to post decoded images back to the cache
     StoreImage(Url, Option<Arc<Box<Image>>>),
 
+    /// Report the URL of each image in the cache along with a description of its state,
+    /// e.g. for about:cache
+    GetCacheEntries(Sender<Vec<(Url, String)>>),
+
     /// For testing
     WaitForStore(Sender<()>),
 
@@ -191,6 +195,7 @@ impl ImageCache {
                 WaitForImage(url, response) => {
                     self.wait_for_image(url, response)
                 }
+                GetCacheEntries(response) => response.send(self.describe_entries()),
                 WaitForStore(chan) => store_chan = Some(chan),
                 WaitForStorePrefetched(chan) => store_prefetched_chan = Some(chan),
                 Exit(response) => {
@@ -227,6 +232,20 @@ impl ImageCache {
         }
     }
 
+    fn describe_entries(&self) -> Vec<(Url, String)> {
+        self.state_map.iter().map(|(url, state)| {
+            let description = match *state {
+                Init => "waiting".to_string(),
+                Prefetching(..) => "loading".to_string(),
+                Prefetched(ref data) => format!("loaded, {} bytes", data.len()),
+                Decoding => "decoding".to_string(),
+                Decoded(ref image) => format!("decoded, {}x{}", image.width, image.height),
+                Failed => "failed".to_string(),
+            };
+            (url.clone(), description)
+        }).collect()
+    }
+
     fn get_state(&self, url: &Url) -> ImageState {
         match self.state_map.find(url) {
             Some(state) => state.clone(),
@@ -302,7 +321,10 @@ impl ImageCache {
             Init => fail!("decoding image before prefetch"),
 
             Prefetching(DoNotDecode) => {
-                // We don't have the data yet, queue up the decode
+                // We don't have the data yet, queue up the decode. The image is needed now, so
+                // its load shouldn't wait behind other images any longer than an image would.
+                let priority = fetch::request::Image.priority();
+                self.resource_task.send(PrioritizeLoad(url.clone(), priority));
                 self.set_state(url, Prefetching(DoDecode))
             }
 
@@ -452,24 +474,13 @@ impl ImageCacheTask {
 }
 
 fn load_image_data(url: Url, resource_task: ResourceTask) -> Result<Vec<u8>, ()> {
-    let (response_chan, response_port) = channel();
-    resource_task.send(resource_task::Load(LoadData::new(url), response_chan));
-
-    let mut image_data = vec!();
-
-    let progress_port = response_port.recv().progress_port;
-    loop {
-        match progress_port.recv() {
-            resource_task::Payload(data) => {
-                image_data.push_all(data.as_slice());
-            }
-            resource_task::Done(result::Ok(..)) => {
-                return Ok(image_data.into_iter().collect());
-            }
-            resource_task::Done(result::Err(..)) => {
-                return Err(());
-            }
-        }
+    // Images may come from any origin. The body of the resulting opaque response is only used
+    // to decode the image. The image isn't known to be needed until it is decoded, so it is
+    // loaded as a prefetch until then.
+    let mut request = Request::new(url, fetch::request::Prefetch);
+    match request.fetch(&resource_task).into_bytes() {
+        Ok((_, image_data)) => Ok(image_data),
+        Err(..) => Err(()),
     }
 }
 
@@ -490,6 +501,7 @@ pub fn spawn_listener<A: Send>(f: proc(Receiver<A>):Send) -> Sender<A> {
 mod tests {
     use super::*;
 
+    use fetch;
     use resource_task;
     use resource_task::{ResourceTask, Metadata, start_sending};
     use image::base::test_image_bin;
@@ -571,7 +583,8 @@ mod tests {
                             Url::parse("file:///fake").unwrap()));
                         on_load.invoke(chan);
                     }
-                    resource_task::Exit => break
+                    resource_task::Exit => break,
+                    _ => {}
                 }
             }
         })
@@ -728,6 +741,7 @@ mod tests {
                         resource_task_exited_chan.send(());
                         break
                     }
+                    _ => {}
                 }
             }
         });
@@ -775,6 +789,7 @@ mod tests {
                         resource_task_exited_chan.send(());
                         break
                     }
+                    _ => {}
                 }
             }
         });
@@ -990,4 +1005,36 @@ mod tests {
         image_cache_task.exit();
         mock_resource_task.send(resource_task::Exit);
     }
+
+    #[test]
+    fn should_prefetch_with_prefetch_context_and_raise_priority_on_decode() {
+        let (loads_chan, loads) = channel();
+        let (priorities_chan, priorities) = channel();
+        let mock_resource_task = spawn_listener(proc(port: Receiver<resource_task::ControlMsg>) {
+            loop {
+                match port.recv() {
+                    resource_task::Load(load_data, _) => {
+                        loads_chan.send((load_data.context, load_data.priority));
+                    }
+                    resource_task::PrioritizeLoad(url, priority) => {
+                        priorities_chan.send((url, priority));
+                    }
+                    resource_task::Exit => break,
+                    _ => {}
+                }
+            }
+        });
+
+        let image_cache_task = ImageCacheTask::new(mock_resource_task.clone());
+        let url = Url::parse("file:///").unwrap();
+
+        image_cache_task.send(Prefetch(url.clone()));
+        image_cache_task.send(Decode(url.clone()));
+        assert_eq!(loads.recv(),
+                   (Some(fetch::request::Prefetch), resource_task::PrefetchPriority));
+        assert_eq!(priorities.recv(), (url, resource_task::ImagePriority));
+
+        image_cache_task.exit();
+        mock_resource_task.send(resource_task::Exit);
+    }
 }
@@ -8,11 +8,13 @@
 
 extern crate debug;
 extern crate collections;
+extern crate flate2;
 extern crate geom;
 extern crate http;
 extern crate png;
 #[phase(plugin, link)]
 extern crate log;
+extern crate openssl;
 extern crate serialize;
 extern crate "util" as servo_util;
 extern crate stb_image;
@@ -31,16 +33,34 @@ pub mod image {
 }
 
 pub mod about_loader;
+pub mod blob_loader;
+pub mod connection_pool;
+pub mod cookie;
+pub mod cookie_storage;
+pub mod csp;
 pub mod file_loader;
+pub mod har;
+pub mod hsts;
+pub mod http_auth;
+pub mod http_cache;
 pub mod http_loader;
+pub mod load_scheduler;
 pub mod data_loader;
 pub mod image_cache_task;
 pub mod local_image_cache;
+pub mod mime_classifier;
+pub mod mixed_content;
+pub mod network_archive;
+pub mod network_events;
+pub mod proxy;
+pub mod referrer_policy;
 pub mod resource_task;
+pub mod sniffer_task;
+pub mod tls;
+pub mod websocket;
 
 /// An implementation of the [Fetch spec](http://fetch.spec.whatwg.org/)
 pub mod fetch {
-    #![allow(dead_code)] // XXXManishearth this is only temporary until the Fetch mod starts being used
     pub mod request;
     pub mod response;
     pub mod cors_cache;
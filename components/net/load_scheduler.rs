@@ -0,0 +1,255 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Decides when the loads sent to the resource task start.
+//!
+//! Loads that block rendering start as soon as they arrive. Image loads are limited to a few
+//! at a time, and prefetches only start when nothing else is loading, so that a page full of
+//! images doesn't hold up its stylesheets and scripts. Waiting loads start in priority order.
+
+use resource_task::{LoadData, LoadGroupId, LoadId, LoadPriority, LoadResponse, Metadata, Done};
+use resource_task::{ImagePriority, PrefetchPriority, start_sending_opt};
+
+use std::comm::{channel, Receiver, Sender};
+use std::mem;
+use url::Url;
+
+/// The number of image loads that may be in progress at once.
+static MAX_IMAGE_LOADS: uint = 6;
+/// The number of prefetches that may be in progress at once, when nothing else is loading.
+static MAX_PREFETCH_LOADS: uint = 2;
+
+/// The error that a cancelled load finishes with.
+pub static LOAD_CANCELLED: &'static str = "load cancelled";
+
+struct PendingLoad {
+    load_data: LoadData,
+    start_chan: Sender<LoadResponse>,
+}
+
+struct ActiveLoad {
+    id: LoadId,
+    priority: LoadPriority,
+    group: Option<LoadGroupId>,
+    /// Tells the load's sniffer task to stop forwarding data.
+    cancel_chan: Sender<()>,
+}
+
+pub struct LoadScheduler {
+    /// Loads waiting to start, highest priority first and otherwise in order of arrival.
+    pending: Vec<PendingLoad>,
+    active: Vec<ActiveLoad>,
+}
+
+impl LoadScheduler {
+    pub fn new() -> LoadScheduler {
+        LoadScheduler {
+            pending: vec!(),
+            active: vec!(),
+        }
+    }
+
+    /// Queue a load. It is started by a later call to `next_load`.
+    pub fn schedule(&mut self, load_data: LoadData, start_chan: Sender<LoadResponse>) {
+        let index = self.pending.iter().position(|load| {
+            load.load_data.priority > load_data.priority
+        }).unwrap_or(self.pending.len());
+        self.pending.insert(index, PendingLoad {
+            load_data: load_data,
+            start_chan: start_chan,
+        });
+    }
+
+    /// Raise the priority of the waiting loads of `url` to `priority`, e.g. when a prefetched
+    /// resource turns out to be needed.
+    pub fn prioritize(&mut self, url: &Url, priority: LoadPriority) {
+        let pending = mem::replace(&mut self.pending, vec!());
+        for load in pending.into_iter() {
+            let PendingLoad { mut load_data, start_chan } = load;
+            if load_data.url == *url && load_data.priority > priority {
+                load_data.priority = priority;
+            }
+            self.schedule(load_data, start_chan);
+        }
+    }
+
+    /// Take the next load that may start now, along with the port on which it will be told to
+    /// stop if it is cancelled.
+    pub fn next_load(&mut self) -> Option<(LoadData, Sender<LoadResponse>, Receiver<()>)> {
+        let index = match self.pending.iter().position(|load| {
+            self.may_start(load.load_data.priority)
+        }) {
+            Some(index) => index,
+            None => return None,
+        };
+        let PendingLoad { load_data, start_chan } = self.pending.remove(index).unwrap();
+        let (cancel_chan, cancel_port) = channel();
+        self.active.push(ActiveLoad {
+            id: load_data.id,
+            priority: load_data.priority,
+            group: load_data.group,
+            cancel_chan: cancel_chan,
+        });
+        Some((load_data, start_chan, cancel_port))
+    }
+
+    /// Forget a load that has finished, making room for the loads waiting behind it.
+    pub fn finished(&mut self, id: LoadId) {
+        self.active.retain(|load| load.id != id);
+    }
+
+    pub fn cancel(&mut self, id: LoadId) {
+        self.cancel_matching(|load_id, _| load_id == id);
+    }
+
+    pub fn cancel_group(&mut self, group: LoadGroupId) {
+        self.cancel_matching(|_, load_group| load_group == Some(group));
+    }
+
+    /// Fail the waiting loads matching `predicate`, and stop the ones in progress. Loads in
+    /// progress stay active until their sniffer task reports that they have finished.
+    fn cancel_matching(&mut self, predicate: |LoadId, Option<LoadGroupId>| -> bool) {
+        let pending = mem::replace(&mut self.pending, vec!());
+        for load in pending.into_iter() {
+            if !predicate(load.load_data.id, load.load_data.group) {
+                self.pending.push(load);
+                continue
+            }
+            let PendingLoad { load_data, start_chan } = load;
+            debug!("load_scheduler: cancelled {:s} before it started", load_data.url.serialize());
+            match start_sending_opt(start_chan, Metadata::default(load_data.url)) {
+                Ok(progress_chan) => {
+                    let _ = progress_chan.send_opt(Done(Err(LOAD_CANCELLED.to_string())));
+                }
+                Err(()) => {}
+            }
+        }
+
+        for load in self.active.iter() {
+            if predicate(load.id, load.group) {
+                let _ = load.cancel_chan.send_opt(());
+            }
+        }
+    }
+
+    fn may_start(&self, priority: LoadPriority) -> bool {
+        let count = |priority: LoadPriority| {
+            self.active.iter().filter(|load| load.priority == priority).count()
+        };
+        match priority {
+            ImagePriority => count(ImagePriority) < MAX_IMAGE_LOADS,
+            PrefetchPriority => {
+                let prefetches = count(PrefetchPriority);
+                prefetches == self.active.len() && prefetches < MAX_PREFETCH_LOADS
+            }
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LoadScheduler, LOAD_CANCELLED, MAX_IMAGE_LOADS};
+    use resource_task::{LoadData, LoadGroupId, LoadPriority, Done};
+    use resource_task::{ImagePriority, PrefetchPriority, ScriptPriority};
+    use url::Url;
+
+    fn load_data(priority: LoadPriority) -> LoadData {
+        let mut load_data = LoadData::new(Url::parse("http://example.com/").unwrap());
+        load_data.priority = priority;
+        load_data
+    }
+
+    #[test]
+    fn test_throttle_images() {
+        let mut scheduler = LoadScheduler::new();
+        for _ in range(0, MAX_IMAGE_LOADS + 1) {
+            scheduler.schedule(load_data(ImagePriority), channel().val0());
+        }
+        scheduler.schedule(load_data(PrefetchPriority), channel().val0());
+        let script = load_data(ScriptPriority);
+        let script_id = script.id;
+        scheduler.schedule(script, channel().val0());
+
+        // The script load jumps the queue, and only some of the images start.
+        let mut started = vec!();
+        loop {
+            match scheduler.next_load() {
+                Some((load_data, _, _)) => started.push(load_data),
+                None => break,
+            }
+        }
+        assert_eq!(started.len(), MAX_IMAGE_LOADS + 1);
+        assert_eq!(started.as_slice()[0].id, script_id);
+
+        // The prefetch waits until everything else has finished.
+        for load_data in started.iter() {
+            scheduler.finished(load_data.id);
+        }
+        let (last_image, _, _) = scheduler.next_load().unwrap();
+        assert_eq!(last_image.priority, ImagePriority);
+        assert!(scheduler.next_load().is_none());
+        scheduler.finished(last_image.id);
+        let (prefetch, _, _) = scheduler.next_load().unwrap();
+        assert_eq!(prefetch.priority, PrefetchPriority);
+    }
+
+    #[test]
+    fn test_prioritize() {
+        let mut scheduler = LoadScheduler::new();
+        let mut active = vec!();
+        for _ in range(0, MAX_IMAGE_LOADS) {
+            scheduler.schedule(load_data(ImagePriority), channel().val0());
+            active.push(scheduler.next_load().unwrap());
+        }
+        let mut prefetch = load_data(PrefetchPriority);
+        prefetch.url = Url::parse("http://example.com/needed.png").unwrap();
+        let prefetch_id = prefetch.id;
+        scheduler.schedule(load_data(ImagePriority), channel().val0());
+        scheduler.schedule(prefetch, channel().val0());
+
+        // The prefetch becomes an image load, and starts after the image queued before it.
+        scheduler.prioritize(&Url::parse("http://example.com/needed.png").unwrap(),
+                             ImagePriority);
+        for &(ref load_data, _, _) in active.iter() {
+            scheduler.finished(load_data.id);
+        }
+        let (first, _, _) = scheduler.next_load().unwrap();
+        let (second, _, _) = scheduler.next_load().unwrap();
+        assert!(first.id != prefetch_id);
+        assert_eq!(second.id, prefetch_id);
+        assert_eq!(second.priority, ImagePriority);
+    }
+
+    #[test]
+    fn test_cancel_group() {
+        let mut scheduler = LoadScheduler::new();
+        let group = LoadGroupId(1);
+        let mut active = load_data(ScriptPriority);
+        active.group = Some(group);
+        scheduler.schedule(active, channel().val0());
+        let (_, _, cancel_port) = scheduler.next_load().unwrap();
+
+        let mut throttled = vec!();
+        for _ in range(0, MAX_IMAGE_LOADS) {
+            scheduler.schedule(load_data(ImagePriority), channel().val0());
+            throttled.push(scheduler.next_load().unwrap());
+        }
+        let mut queued = load_data(ImagePriority);
+        queued.group = Some(group);
+        let (start_chan, start_port) = channel();
+        scheduler.schedule(queued, start_chan);
+        assert!(scheduler.next_load().is_none());
+
+        // The active load is told to stop, and the queued one fails without starting.
+        scheduler.cancel_group(group);
+        assert!(cancel_port.try_recv().is_ok());
+        let response = start_port.recv();
+        assert_eq!(response.progress_port.recv(), Done(Err(LOAD_CANCELLED.to_string())));
+        for &(ref load_data, _, _) in throttled.iter() {
+            scheduler.finished(load_data.id);
+        }
+        assert!(scheduler.next_load().is_none());
+    }
+}
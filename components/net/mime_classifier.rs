@@ -0,0 +1,490 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! An implementation of the [MIME Sniffing standard](http://mimesniff.spec.whatwg.org/).
+
+use std::ascii::StrAsciiExt;
+
+/// The number of bytes of a resource that are examined when sniffing.
+/// http://mimesniff.spec.whatwg.org/#resource-header
+pub static RESOURCE_HEADER_SIZE: uint = 512;
+
+/// A content type, as a (type, subtype) pair.
+pub type ContentType = (String, String);
+
+fn content_type(type_: &str, subtype: &str) -> ContentType {
+    (type_.to_string(), subtype.to_string())
+}
+
+/// http://mimesniff.spec.whatwg.org/#whitespace-byte
+fn is_whitespace_byte(byte: u8) -> bool {
+    byte == 0x09 || byte == 0x0A || byte == 0x0C || byte == 0x0D || byte == 0x20
+}
+
+/// http://mimesniff.spec.whatwg.org/#tag-terminating-byte
+fn is_tag_terminating_byte(byte: u8) -> bool {
+    byte == 0x20 || byte == 0x3E
+}
+
+/// http://mimesniff.spec.whatwg.org/#binary-data-byte
+fn is_binary_data_byte(byte: u8) -> bool {
+    byte <= 0x08 || byte == 0x0B || (byte >= 0x0E && byte <= 0x1A) || (byte >= 0x1C && byte <= 0x1F)
+}
+
+/// A byte pattern with a mask, as used by the
+/// [pattern matching algorithm](http://mimesniff.spec.whatwg.org/#pattern-matching-algorithm).
+struct ByteMatcher {
+    pattern: &'static [u8],
+    mask: &'static [u8],
+    /// Whether leading whitespace bytes are skipped before matching.
+    skip_whitespace: bool,
+    content_type: (&'static str, &'static str),
+}
+
+impl ByteMatcher {
+    fn new(pattern: &'static [u8], mask: &'static [u8], content_type: (&'static str, &'static str))
+           -> ByteMatcher {
+        assert!(pattern.len() == mask.len());
+        ByteMatcher {
+            pattern: pattern,
+            mask: mask,
+            skip_whitespace: false,
+            content_type: content_type,
+        }
+    }
+
+    /// Returns the length of the match, or `None` if the pattern does not match.
+    fn matches(&self, data: &[u8]) -> Option<uint> {
+        let start = if self.skip_whitespace {
+            data.iter().take_while(|&&byte| is_whitespace_byte(byte)).count()
+        } else {
+            0
+        };
+        if data.len() < start + self.pattern.len() {
+            return None
+        }
+        let matched = self.pattern.iter().zip(self.mask.iter()).enumerate().all(|(i, (&p, &m))| {
+            data[start + i] & m == p
+        });
+        if matched { Some(start + self.pattern.len()) } else { None }
+    }
+
+    fn classify(&self, data: &[u8]) -> Option<ContentType> {
+        self.matches(data).map(|_| content_type(self.content_type.val0(), self.content_type.val1()))
+    }
+}
+
+/// A case-insensitive tag pattern that must be followed by a tag-terminating byte.
+/// http://mimesniff.spec.whatwg.org/#rules-for-identifying-an-unknown-mime-type step 1
+struct TagTerminatedMatcher {
+    matcher: ByteMatcher,
+}
+
+impl TagTerminatedMatcher {
+    fn new(pattern: &'static [u8], mask: &'static [u8]) -> TagTerminatedMatcher {
+        let mut matcher = ByteMatcher::new(pattern, mask, ("text", "html"));
+        matcher.skip_whitespace = true;
+        TagTerminatedMatcher {
+            matcher: matcher,
+        }
+    }
+
+    fn classify(&self, data: &[u8]) -> Option<ContentType> {
+        match self.matcher.matches(data) {
+            Some(end) if end < data.len() && is_tag_terminating_byte(data[end]) => {
+                Some(content_type("text", "html"))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Classifies resources according to the MIME sniffing standard.
+pub struct MIMEClassifier {
+    html_patterns: Vec<TagTerminatedMatcher>,
+    scriptable_patterns: Vec<ByteMatcher>,
+    unknown_patterns: Vec<ByteMatcher>,
+    image_patterns: Vec<ByteMatcher>,
+    audio_video_patterns: Vec<ByteMatcher>,
+    font_patterns: Vec<ByteMatcher>,
+    archive_patterns: Vec<ByteMatcher>,
+}
+
+impl MIMEClassifier {
+    pub fn new() -> MIMEClassifier {
+        MIMEClassifier {
+            // http://mimesniff.spec.whatwg.org/#rules-for-identifying-an-unknown-mime-type
+            html_patterns: vec!(
+                TagTerminatedMatcher::new(b"<!DOCTYPE HTML",
+                    b"\xFF\xFF\xDF\xDF\xDF\xDF\xDF\xDF\xDF\xFF\xDF\xDF\xDF\xDF"),
+                TagTerminatedMatcher::new(b"<HTML", b"\xFF\xDF\xDF\xDF\xDF"),
+                TagTerminatedMatcher::new(b"<HEAD", b"\xFF\xDF\xDF\xDF\xDF"),
+                TagTerminatedMatcher::new(b"<SCRIPT", b"\xFF\xDF\xDF\xDF\xDF\xDF\xDF"),
+                TagTerminatedMatcher::new(b"<IFRAME", b"\xFF\xDF\xDF\xDF\xDF\xDF\xDF"),
+                TagTerminatedMatcher::new(b"<H1", b"\xFF\xDF\xFF"),
+                TagTerminatedMatcher::new(b"<DIV", b"\xFF\xDF\xDF\xDF"),
+                TagTerminatedMatcher::new(b"<FONT", b"\xFF\xDF\xDF\xDF\xDF"),
+                TagTerminatedMatcher::new(b"<TABLE", b"\xFF\xDF\xDF\xDF\xDF\xDF"),
+                TagTerminatedMatcher::new(b"<A", b"\xFF\xDF"),
+                TagTerminatedMatcher::new(b"<STYLE", b"\xFF\xDF\xDF\xDF\xDF\xDF"),
+                TagTerminatedMatcher::new(b"<TITLE", b"\xFF\xDF\xDF\xDF\xDF\xDF"),
+                TagTerminatedMatcher::new(b"<B", b"\xFF\xDF"),
+                TagTerminatedMatcher::new(b"<BODY", b"\xFF\xDF\xDF\xDF\xDF"),
+                TagTerminatedMatcher::new(b"<BR", b"\xFF\xDF\xDF"),
+                TagTerminatedMatcher::new(b"<P", b"\xFF\xDF"),
+                TagTerminatedMatcher::new(b"<!--", b"\xFF\xFF\xFF\xFF"),
+            ),
+            scriptable_patterns: vec!(
+                ByteMatcher { skip_whitespace: true,
+                              ..ByteMatcher::new(b"<?xml", b"\xFF\xFF\xFF\xFF\xFF",
+                                                 ("text", "xml")) },
+                ByteMatcher::new(b"%PDF-", b"\xFF\xFF\xFF\xFF\xFF", ("application", "pdf")),
+            ),
+            unknown_patterns: vec!(
+                ByteMatcher::new(b"%!PS-Adobe-", b"\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF",
+                                 ("application", "postscript")),
+                ByteMatcher::new(b"\xFE\xFF", b"\xFF\xFF", ("text", "plain")),
+                ByteMatcher::new(b"\xFF\xFE", b"\xFF\xFF", ("text", "plain")),
+                ByteMatcher::new(b"\xEF\xBB\xBF", b"\xFF\xFF\xFF", ("text", "plain")),
+            ),
+            // http://mimesniff.spec.whatwg.org/#image-type-pattern-matching-algorithm
+            image_patterns: vec!(
+                ByteMatcher::new(b"\x00\x00\x01\x00", b"\xFF\xFF\xFF\xFF", ("image", "x-icon")),
+                ByteMatcher::new(b"\x00\x00\x02\x00", b"\xFF\xFF\xFF\xFF", ("image", "x-icon")),
+                ByteMatcher::new(b"BM", b"\xFF\xFF", ("image", "bmp")),
+                ByteMatcher::new(b"GIF87a", b"\xFF\xFF\xFF\xFF\xFF\xFF", ("image", "gif")),
+                ByteMatcher::new(b"GIF89a", b"\xFF\xFF\xFF\xFF\xFF\xFF", ("image", "gif")),
+                ByteMatcher::new(b"RIFF\x00\x00\x00\x00WEBPVP",
+                                 b"\xFF\xFF\xFF\xFF\x00\x00\x00\x00\xFF\xFF\xFF\xFF\xFF\xFF",
+                                 ("image", "webp")),
+                ByteMatcher::new(b"\x89PNG\r\n\x1A\n", b"\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF",
+                                 ("image", "png")),
+                ByteMatcher::new(b"\xFF\xD8\xFF", b"\xFF\xFF\xFF", ("image", "jpeg")),
+            ),
+            // http://mimesniff.spec.whatwg.org/#audio-or-video-type-pattern-matching-algorithm
+            audio_video_patterns: vec!(
+                ByteMatcher::new(b"\x1A\x45\xDF\xA3", b"\xFF\xFF\xFF\xFF", ("video", "webm")),
+                ByteMatcher::new(b".snd", b"\xFF\xFF\xFF\xFF", ("audio", "basic")),
+                ByteMatcher::new(b"FORM\x00\x00\x00\x00AIFF",
+                                 b"\xFF\xFF\xFF\xFF\x00\x00\x00\x00\xFF\xFF\xFF\xFF",
+                                 ("audio", "aiff")),
+                ByteMatcher::new(b"ID3", b"\xFF\xFF\xFF", ("audio", "mpeg")),
+                ByteMatcher::new(b"OggS\x00", b"\xFF\xFF\xFF\xFF\xFF", ("application", "ogg")),
+                ByteMatcher::new(b"MThd\x00\x00\x00\x06",
+                                 b"\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF", ("audio", "midi")),
+                ByteMatcher::new(b"RIFF\x00\x00\x00\x00AVI ",
+                                 b"\xFF\xFF\xFF\xFF\x00\x00\x00\x00\xFF\xFF\xFF\xFF",
+                                 ("video", "avi")),
+                ByteMatcher::new(b"RIFF\x00\x00\x00\x00WAVE",
+                                 b"\xFF\xFF\xFF\xFF\x00\x00\x00\x00\xFF\xFF\xFF\xFF",
+                                 ("audio", "wave")),
+            ),
+            // http://mimesniff.spec.whatwg.org/#matching-a-font-type-pattern
+            font_patterns: vec!(
+                ByteMatcher::new(b"\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\
+                                   \x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\
+                                   \x00\x00LP",
+                                 b"\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xFF\xFF",
+                                 ("application", "vnd.ms-fontobject")),
+                ByteMatcher::new(b"\x00\x01\x00\x00", b"\xFF\xFF\xFF\xFF", ("font", "ttf")),
+                ByteMatcher::new(b"OTTO", b"\xFF\xFF\xFF\xFF", ("font", "otf")),
+                ByteMatcher::new(b"ttcf", b"\xFF\xFF\xFF\xFF", ("font", "collection")),
+                ByteMatcher::new(b"wOFF", b"\xFF\xFF\xFF\xFF", ("font", "woff")),
+                ByteMatcher::new(b"wOF2", b"\xFF\xFF\xFF\xFF", ("font", "woff2")),
+            ),
+            // http://mimesniff.spec.whatwg.org/#archive-type-pattern-matching-algorithm
+            archive_patterns: vec!(
+                ByteMatcher::new(b"\x1F\x8B\x08", b"\xFF\xFF\xFF", ("application", "x-gzip")),
+                ByteMatcher::new(b"PK\x03\x04", b"\xFF\xFF\xFF\xFF", ("application", "zip")),
+                ByteMatcher::new(b"Rar \x1A\x07\x00",
+                                 b"\xFF\xFF\xFF\xFF\xFF\xFF\xFF", ("application", "x-rar-compressed")),
+            ),
+        }
+    }
+
+    /// Determine the computed MIME type of a resource.
+    /// http://mimesniff.spec.whatwg.org/#mime-type-sniffing-algorithm
+    ///
+    /// `data` is the resource header, i.e. at most the first `RESOURCE_HEADER_SIZE` bytes.
+    pub fn classify(&self, no_sniff: bool, check_for_apache_bug: bool,
+                    supplied_type: &Option<ContentType>, data: &[u8]) -> Option<ContentType> {
+        let data = if data.len() > RESOURCE_HEADER_SIZE {
+            data.slice_to(RESOURCE_HEADER_SIZE)
+        } else {
+            data
+        };
+
+        let (type_, subtype) = match *supplied_type {
+            None => return self.sniff_unknown_type(!no_sniff, data),
+            Some((ref type_, ref subtype)) => (type_.as_slice(), subtype.as_slice()),
+        };
+        if is_unknown_type(type_, subtype) {
+            return self.sniff_unknown_type(!no_sniff, data)
+        }
+        if no_sniff {
+            return supplied_type.clone()
+        }
+        if check_for_apache_bug {
+            return self.sniff_text_or_binary(data)
+        }
+        if is_xml_type(type_, subtype) {
+            return supplied_type.clone()
+        }
+        if type_ == "text" && subtype == "html" {
+            return self.sniff_feed_or_html(data).or_else(|| supplied_type.clone())
+        }
+        if type_ == "image" {
+            return self.sniff_image(data).or_else(|| supplied_type.clone())
+        }
+        if type_ == "audio" || type_ == "video" {
+            return self.sniff_audio_video(data).or_else(|| supplied_type.clone())
+        }
+        supplied_type.clone()
+    }
+
+    /// http://mimesniff.spec.whatwg.org/#rules-for-identifying-an-unknown-mime-type
+    fn sniff_unknown_type(&self, sniff_scriptable: bool, data: &[u8]) -> Option<ContentType> {
+        if sniff_scriptable {
+            let scriptable = self.html_patterns.iter().filter_map(|m| m.classify(data)).next()
+                .or_else(|| self.scriptable_patterns.iter().filter_map(|m| m.classify(data)).next());
+            if scriptable.is_some() {
+                return scriptable
+            }
+        }
+        self.unknown_patterns.iter().filter_map(|m| m.classify(data)).next()
+            .or_else(|| self.sniff_image(data))
+            .or_else(|| self.sniff_audio_video(data))
+            .or_else(|| self.sniff_archive(data))
+            .or_else(|| self.sniff_text_or_binary(data))
+    }
+
+    /// http://mimesniff.spec.whatwg.org/#rules-for-text-or-binary
+    fn sniff_text_or_binary(&self, data: &[u8]) -> Option<ContentType> {
+        let bom = data.starts_with(b"\xFE\xFF") || data.starts_with(b"\xFF\xFE") ||
+                  data.starts_with(b"\xEF\xBB\xBF");
+        if bom || !data.iter().any(|&byte| is_binary_data_byte(byte)) {
+            Some(content_type("text", "plain"))
+        } else {
+            Some(content_type("application", "octet-stream"))
+        }
+    }
+
+    /// http://mimesniff.spec.whatwg.org/#image-type-pattern-matching-algorithm
+    pub fn sniff_image(&self, data: &[u8]) -> Option<ContentType> {
+        self.image_patterns.iter().filter_map(|m| m.classify(data)).next()
+    }
+
+    /// http://mimesniff.spec.whatwg.org/#audio-or-video-type-pattern-matching-algorithm
+    pub fn sniff_audio_video(&self, data: &[u8]) -> Option<ContentType> {
+        self.audio_video_patterns.iter().filter_map(|m| m.classify(data)).next().or_else(|| {
+            if matches_mp4_signature(data) {
+                Some(content_type("video", "mp4"))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// http://mimesniff.spec.whatwg.org/#matching-a-font-type-pattern
+    pub fn sniff_font(&self, data: &[u8]) -> Option<ContentType> {
+        self.font_patterns.iter().filter_map(|m| m.classify(data)).next()
+    }
+
+    /// http://mimesniff.spec.whatwg.org/#archive-type-pattern-matching-algorithm
+    fn sniff_archive(&self, data: &[u8]) -> Option<ContentType> {
+        self.archive_patterns.iter().filter_map(|m| m.classify(data)).next()
+    }
+
+    /// Distinguish RSS and Atom feeds that are mislabeled as HTML.
+    /// http://mimesniff.spec.whatwg.org/#rules-for-distinguishing-if-a-resource-is-a-feed-or-html
+    fn sniff_feed_or_html(&self, data: &[u8]) -> Option<ContentType> {
+        let mut i = if data.starts_with(b"\xEF\xBB\xBF") { 3 } else { 0 };
+        loop {
+            while i < data.len() && is_whitespace_byte(data[i]) {
+                i += 1;
+            }
+            if i >= data.len() || data[i] != b'<' {
+                return None
+            }
+            i += 1;
+            let rest = data.slice_from(i);
+            if rest.starts_with(b"!--") {
+                i = match find_after(data, i + 3, b"-->") { Some(i) => i, None => return None };
+            } else if rest.starts_with(b"!") {
+                i = match find_after(data, i + 1, b">") { Some(i) => i, None => return None };
+            } else if rest.starts_with(b"?") {
+                i = match find_after(data, i + 1, b"?>") { Some(i) => i, None => return None };
+            } else if rest.starts_with(b"rss") {
+                return Some(content_type("application", "rss+xml"))
+            } else if rest.starts_with(b"feed") {
+                return Some(content_type("application", "atom+xml"))
+            } else if rest.starts_with(b"rdf:RDF") {
+                if find_after(data, i, b"http://purl.org/rss/1.0/").is_some() &&
+                        find_after(data, i, b"http://www.w3.org/1999/02/22-rdf-syntax-ns#").is_some() {
+                    return Some(content_type("application", "rss+xml"))
+                }
+                return None
+            } else {
+                return None
+            }
+        }
+    }
+}
+
+/// Returns the index just past the first occurrence of `pattern` in `data` at or after `start`.
+fn find_after(data: &[u8], start: uint, pattern: &[u8]) -> Option<uint> {
+    if start > data.len() {
+        return None
+    }
+    data.slice_from(start).windows(pattern.len()).position(|window| window == pattern)
+        .map(|position| start + position + pattern.len())
+}
+
+/// http://mimesniff.spec.whatwg.org/#signature-for-mp4
+fn matches_mp4_signature(data: &[u8]) -> bool {
+    if data.len() < 12 {
+        return false
+    }
+    let box_size = (data[0] as uint << 24) | (data[1] as uint << 16) |
+                   (data[2] as uint << 8) | data[3] as uint;
+    if data.len() < box_size || box_size % 4 != 0 || box_size < 12 {
+        return false
+    }
+    if data.slice(4, 8) != b"ftyp" {
+        return false
+    }
+    if data.slice(8, 11) == b"mp4" {
+        return true
+    }
+    let mut bytes_read = 16;
+    while bytes_read + 3 <= box_size {
+        if data.slice(bytes_read, bytes_read + 3) == b"mp4" {
+            return true
+        }
+        bytes_read += 4;
+    }
+    false
+}
+
+/// http://mimesniff.spec.whatwg.org/#unknown-mime-type
+fn is_unknown_type(type_: &str, subtype: &str) -> bool {
+    (type_.eq_ignore_ascii_case("unknown") && subtype.eq_ignore_ascii_case("unknown")) ||
+    (type_.eq_ignore_ascii_case("application") && subtype.eq_ignore_ascii_case("unknown")) ||
+    (type_ == "*" && subtype == "*")
+}
+
+/// http://mimesniff.spec.whatwg.org/#xml-mime-type
+fn is_xml_type(type_: &str, subtype: &str) -> bool {
+    subtype.to_ascii_lower().as_slice().ends_with("+xml") ||
+    ((type_.eq_ignore_ascii_case("text") || type_.eq_ignore_ascii_case("application")) &&
+     subtype.eq_ignore_ascii_case("xml"))
+}
+
+/// Whether a raw `Content-Type` header value triggers the check for Apache's habit of labelling
+/// everything as text.
+/// http://mimesniff.spec.whatwg.org/#check-for-apache-bug-flag
+pub fn is_apache_bug_content_type(content_type: &str) -> bool {
+    match content_type {
+        "text/plain" |
+        "text/plain; charset=ISO-8859-1" |
+        "text/plain; charset=iso-8859-1" |
+        "text/plain; charset=UTF-8" => true,
+        _ => false,
+    }
+}
+
+/// Whether `MIMEClassifier::classify` looks at the resource header for these inputs, as opposed
+/// to returning the supplied type whatever the body starts with.
+pub fn needs_resource_header(no_sniff: bool, check_for_apache_bug: bool,
+                             supplied_type: &Option<ContentType>) -> bool {
+    let (type_, subtype) = match *supplied_type {
+        None => return true,
+        Some((ref type_, ref subtype)) => (type_.as_slice(), subtype.as_slice()),
+    };
+    if is_unknown_type(type_, subtype) {
+        return true
+    }
+    if no_sniff {
+        return false
+    }
+    if check_for_apache_bug {
+        return true
+    }
+    !is_xml_type(type_, subtype) &&
+        ((type_ == "text" && subtype == "html") ||
+         type_ == "image" || type_ == "audio" || type_ == "video")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MIMEClassifier, ContentType, needs_resource_header};
+
+    fn ct(type_: &str, subtype: &str) -> Option<ContentType> {
+        Some((type_.to_string(), subtype.to_string()))
+    }
+
+    #[test]
+    fn test_sniff_unknown() {
+        let classifier = MIMEClassifier::new();
+        assert_eq!(classifier.classify(false, false, &None, b"  <!doctype html><p>hi"), ct("text", "html"));
+        assert_eq!(classifier.classify(false, false, &None, b"<p>hi"), ct("text", "html"));
+        assert_eq!(classifier.classify(false, false, &None, b"<pre>hi"), ct("text", "plain"));
+        assert_eq!(classifier.classify(false, false, &None, b"<?xml version=\"1.0\"?>"), ct("text", "xml"));
+        assert_eq!(classifier.classify(false, false, &None, b"%PDF-1.4"), ct("application", "pdf"));
+        assert_eq!(classifier.classify(false, false, &None, b"GIF89a\x01\x00"), ct("image", "gif"));
+        assert_eq!(classifier.classify(false, false, &None, b"\x1F\x8B\x08\x00"), ct("application", "x-gzip"));
+        assert_eq!(classifier.classify(false, false, &None, b"plain text"), ct("text", "plain"));
+        assert_eq!(classifier.classify(false, false, &None, b"\x00\x01binary"),
+                   ct("application", "octet-stream"));
+    }
+
+    #[test]
+    fn test_no_sniff() {
+        let classifier = MIMEClassifier::new();
+        // Scriptable types are not sniffed for unlabeled resources served with `nosniff`.
+        assert_eq!(classifier.classify(true, false, &None, b"<html>"), ct("text", "plain"));
+        assert_eq!(classifier.classify(true, false, &ct("text", "plain"), b"\x89PNG\r\n\x1A\n"),
+                   ct("text", "plain"));
+    }
+
+    #[test]
+    fn test_sniff_supplied() {
+        let classifier = MIMEClassifier::new();
+        assert_eq!(classifier.classify(false, true, &ct("text", "plain"), b"\x00\x01\x02"),
+                   ct("application", "octet-stream"));
+        assert_eq!(classifier.classify(false, false, &ct("text", "plain"), b"\x00\x01\x02"),
+                   ct("text", "plain"));
+        assert_eq!(classifier.classify(false, false, &ct("image", "gif"), b"\x89PNG\r\n\x1A\n"),
+                   ct("image", "png"));
+        assert_eq!(classifier.classify(false, false, &ct("image", "gif"), b"not an image"),
+                   ct("image", "gif"));
+        assert_eq!(classifier.classify(false, false, &ct("text", "html"), b"<!-- x --> <rss version=\"2.0\">"),
+                   ct("application", "rss+xml"));
+        assert_eq!(classifier.classify(false, false, &ct("text", "html"), b"<feed>"),
+                   ct("application", "atom+xml"));
+        assert_eq!(classifier.classify(false, false, &ct("text", "html"), b"<html>"), ct("text", "html"));
+    }
+
+    #[test]
+    fn test_sniff_mp4_and_fonts() {
+        let classifier = MIMEClassifier::new();
+        let mp4 = b"\x00\x00\x00\x18ftypisom\x00\x00\x00\x00mp41isom";
+        assert_eq!(classifier.classify(false, false, &None, mp4), ct("video", "mp4"));
+        assert_eq!(classifier.sniff_font(b"wOFF\x00\x01"), ct("font", "woff"));
+        assert_eq!(classifier.sniff_font(b"OTTO\x00\x01"), ct("font", "otf"));
+    }
+
+    #[test]
+    fn test_needs_resource_header() {
+        assert!(needs_resource_header(false, false, &None));
+        assert!(needs_resource_header(true, false, &ct("application", "unknown")));
+        assert!(needs_resource_header(false, false, &ct("text", "html")));
+        assert!(needs_resource_header(false, false, &ct("image", "gif")));
+        assert!(needs_resource_header(false, true, &ct("text", "plain")));
+        assert!(!needs_resource_header(true, false, &ct("text", "html")));
+        assert!(!needs_resource_header(false, false, &ct("text", "css")));
+        assert!(!needs_resource_header(false, false, &ct("image", "svg+xml")));
+        assert!(!needs_resource_header(false, false, &ct("application", "javascript")));
+    }
+}
@@ -0,0 +1,92 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! [Mixed content](http://www.w3.org/TR/mixed-content/) classification: insecure loads made on
+//! behalf of documents that were delivered securely.
+
+use fetch::request::{Context, Image, ImageSet, Favicon, Audio, Video, Prefetch};
+
+use url::Url;
+
+#[deriving(Clone, PartialEq, Show)]
+pub enum MixedContent {
+    /// The load is secure, or the document isn't.
+    NotMixed,
+    /// Passive content, such as images, which is loaded with a warning.
+    OptionallyBlockable,
+    /// Active content, such as scripts, stylesheets, frames and XHR, which is blocked.
+    Blockable,
+}
+
+/// Whether `url` is delivered over a secure channel, or doesn't go over the network.
+/// http://www.w3.org/TR/mixed-content/#a-priori-authenticated-url
+pub fn is_a_priori_authenticated(url: &Url) -> bool {
+    match url.scheme.as_slice() {
+        "https" | "wss" | "data" | "blob" | "file" | "about" => true,
+        _ => false,
+    }
+}
+
+/// Classify a load of `url` made for `context` on behalf of the document at `client_url`.
+/// http://www.w3.org/TR/mixed-content/#categories
+pub fn classify(client_url: &Url, url: &Url, context: Context) -> MixedContent {
+    match client_url.scheme.as_slice() {
+        "https" | "wss" => {}
+        _ => return NotMixed,
+    }
+    if is_a_priori_authenticated(url) {
+        return NotMixed
+    }
+    match context {
+        Image | ImageSet | Favicon | Audio | Video | Prefetch => OptionallyBlockable,
+        _ => Blockable,
+    }
+}
+
+/// Check a load of `url` made for `context` on behalf of the document at `client_url`, returning
+/// an error if it must be blocked. Passive mixed content is allowed, with a warning.
+pub fn check(client_url: &Url, url: &Url, context: Context) -> Result<(), String> {
+    match classify(client_url, url, context) {
+        NotMixed => Ok(()),
+        OptionallyBlockable => {
+            warn!("Mixed content: the page at '{:s}' was loaded over HTTPS, but requested an \
+                   insecure {} '{:s}'. This content should also be served over HTTPS.",
+                  client_url.serialize(), context, url.serialize());
+            Ok(())
+        }
+        Blockable => {
+            let message = format!("Mixed content: the page at '{:s}' was loaded over HTTPS, but \
+                                   requested an insecure {} '{:s}'. This request has been \
+                                   blocked.", client_url.serialize(), context, url.serialize());
+            warn!("{:s}", message);
+            Err(message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify, NotMixed, OptionallyBlockable, Blockable};
+    use fetch::request::{Image, Script, Style, XMLHttpRequest, IFrame, Video};
+    use url::Url;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn test_classify() {
+        let secure = url("https://example.com/");
+        let insecure = url("http://example.com/");
+        assert_eq!(classify(&secure, &url("http://cdn.com/a.js"), Script), Blockable);
+        assert_eq!(classify(&secure, &url("http://cdn.com/a.css"), Style), Blockable);
+        assert_eq!(classify(&secure, &url("http://cdn.com/"), XMLHttpRequest), Blockable);
+        assert_eq!(classify(&secure, &url("http://cdn.com/"), IFrame), Blockable);
+        assert_eq!(classify(&secure, &url("http://cdn.com/a.png"), Image), OptionallyBlockable);
+        assert_eq!(classify(&secure, &url("http://cdn.com/a.webm"), Video), OptionallyBlockable);
+        assert_eq!(classify(&secure, &url("https://cdn.com/a.js"), Script), NotMixed);
+        assert_eq!(classify(&secure, &url("data:text/javascript,1"), Script), NotMixed);
+        assert_eq!(classify(&insecure, &url("http://cdn.com/a.js"), Script), NotMixed);
+    }
+}
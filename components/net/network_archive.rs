@@ -0,0 +1,345 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Recording HTTP responses to an archive file, and answering loads from it.
+//!
+//! With `--record-network`, the response to every HTTP load is written to the archive soon
+//! after it completes. With `--replay-network`, HTTP loads are answered from the archive alone, and a
+//! load that wasn't recorded fails, so that a test can't silently depend on the network.
+
+use resource_task::{LoadData, LoadResponse, Metadata, Payload, Done, start_sending_opt};
+
+use http::headers::HeaderEnum;
+use http::headers::response::HeaderCollection as ResponseHeaderCollection;
+use http::status::Ok as StatusOk;
+use serialize::base64::{FromBase64, ToBase64, STANDARD};
+use serialize::json;
+use servo_util::task::spawn_named;
+use std::ascii::StrAsciiExt;
+use std::collections::hashmap::HashMap;
+use std::comm::Select;
+use std::io::{File, IoResult, Timer};
+use std::io::fs;
+use std::num::FromPrimitive;
+use std::time::duration::Duration;
+use url::Url;
+
+/// How long the recorder waits for more responses before writing the archive, so that the
+/// responses to a page's loads are written together.
+static WRITE_DELAY_MS: i64 = 500;
+
+/// A load and its response, as stored in the archive.
+#[deriving(Clone, Encodable, Decodable)]
+pub struct ArchivedResponse {
+    method: String,
+    url: String,
+    /// The request body, base64 encoded.
+    request_body: Option<String>,
+    final_url: String,
+    content_type: Option<(String, String)>,
+    charset: Option<String>,
+    headers: Option<Vec<(String, String)>>,
+    status: u16,
+    /// The response body, base64 encoded.
+    body: String,
+    /// The error the load finished with, if it failed.
+    error: Option<String>,
+}
+
+impl ArchivedResponse {
+    fn new(load_data: &LoadData, metadata: &Metadata) -> ArchivedResponse {
+        ArchivedResponse {
+            method: format!("{}", load_data.method),
+            url: load_data.url.serialize_no_fragment(),
+            request_body: load_data.data.as_ref().map(|data| data.as_slice().to_base64(STANDARD)),
+            final_url: metadata.final_url.serialize(),
+            content_type: metadata.content_type.clone(),
+            charset: metadata.charset.clone(),
+            headers: metadata.headers.as_ref().map(|headers| {
+                headers.iter().map(|header| (header.header_name(), header.header_value())).collect()
+            }),
+            status: metadata.status.code(),
+            body: String::new(),
+            error: None,
+        }
+    }
+
+    fn key(&self) -> String {
+        archive_key(self.method.as_slice(), self.url.as_slice(), &self.request_body)
+    }
+
+    /// The values of the response headers with the given name.
+    pub fn header_values(&self, name: &str) -> Vec<String> {
+        match self.headers {
+            Some(ref headers) => {
+                headers.iter().filter(|&&(ref header_name, _)| {
+                    header_name.as_slice().eq_ignore_ascii_case(name)
+                }).map(|&(_, ref value)| value.clone()).collect()
+            }
+            None => vec!(),
+        }
+    }
+
+    pub fn final_url(&self) -> Url {
+        Url::parse(self.final_url.as_slice()).unwrap()
+    }
+
+    /// Send the recorded response to the consumer of a load.
+    pub fn send(&self, start_chan: Sender<LoadResponse>) {
+        let mut metadata = Metadata::default(self.final_url());
+        metadata.content_type = self.content_type.clone();
+        metadata.charset = self.charset.clone();
+        metadata.headers = self.headers.as_ref().map(|headers| {
+            let mut collection = ResponseHeaderCollection::new();
+            for &(ref name, ref value) in headers.iter() {
+                let _ = collection.insert_raw(name.clone(), value.as_bytes());
+            }
+            collection
+        });
+        metadata.status = FromPrimitive::from_u16(self.status).unwrap_or(StatusOk);
+
+        let progress_chan = match start_sending_opt(start_chan, metadata) {
+            Ok(progress_chan) => progress_chan,
+            Err(()) => return,
+        };
+        let body = self.body.as_slice().from_base64().unwrap_or(vec!());
+        if !body.is_empty() && progress_chan.send_opt(Payload(body)).is_err() {
+            return
+        }
+        let _ = progress_chan.send_opt(Done(match self.error {
+            Some(ref error) => Err(error.clone()),
+            None => Ok(()),
+        }));
+    }
+}
+
+fn archive_key(method: &str, url: &str, request_body: &Option<String>) -> String {
+    match *request_body {
+        Some(ref body) => format!("{:s} {:s} {:s}", method, url, body),
+        None => format!("{:s} {:s}", method, url),
+    }
+}
+
+fn load_key(load_data: &LoadData) -> String {
+    let request_body = load_data.data.as_ref().map(|data| data.as_slice().to_base64(STANDARD));
+    archive_key(format!("{}", load_data.method).as_slice(),
+                load_data.url.serialize_no_fragment().as_slice(),
+                &request_body)
+}
+
+/// Whether loads of `url` are recorded and replayed.
+pub fn is_archived(url: &Url) -> bool {
+    match url.scheme.as_slice() {
+        "http" | "https" => true,
+        _ => false,
+    }
+}
+
+/// The responses in an archive, to be replayed.
+pub struct NetworkArchive {
+    /// The responses recorded for each load, in the order they were recorded.
+    responses: HashMap<String, Vec<ArchivedResponse>>,
+}
+
+impl NetworkArchive {
+    pub fn open(path: &Path) -> Result<NetworkArchive, String> {
+        let contents = match File::open(path).read_to_string() {
+            Ok(contents) => contents,
+            Err(e) => return Err(format!("couldn't read {}: {}", path.display(), e)),
+        };
+        let archived: Vec<ArchivedResponse> = match json::decode(contents.as_slice()) {
+            Ok(archived) => archived,
+            Err(e) => return Err(format!("couldn't parse {}: {}", path.display(), e)),
+        };
+        let mut responses = HashMap::new();
+        for response in archived.into_iter() {
+            responses.find_or_insert_with(response.key(), |_| vec!()).push(response);
+        }
+        Ok(NetworkArchive {
+            responses: responses,
+        })
+    }
+
+    /// Find the response to replay for a load. When a load was recorded more than once, its
+    /// responses are replayed in turn, the last one being repeated.
+    pub fn replay(&mut self, load_data: &LoadData) -> Option<ArchivedResponse> {
+        let responses = match self.responses.find_mut(&load_key(load_data)) {
+            Some(responses) => responses,
+            None => return None,
+        };
+        if responses.len() > 1 {
+            responses.remove(0)
+        } else {
+            responses.last().map(|response| response.clone())
+        }
+    }
+}
+
+/// Start a task that writes the responses sent to it to the archive at `path`. The archive is
+/// rewritten once no more responses have arrived for `WRITE_DELAY_MS`, and when recording
+/// stops, so that it is complete even if Servo doesn't exit cleanly.
+pub fn start_recording(path: Path) -> Sender<ArchivedResponse> {
+    let (recorder, responses) = channel();
+    spawn_named("NetworkRecorder", proc() {
+        let mut archived: Vec<ArchivedResponse> = vec!();
+        let mut timer = Timer::new().unwrap();
+        loop {
+            match responses.recv_opt() {
+                Ok(response) => archived.push(response),
+                Err(()) => return,
+            }
+
+            // Collect the responses that follow closely, and write them all at once.
+            let mut stopped = false;
+            loop {
+                let timeout = timer.oneshot(Duration::milliseconds(WRITE_DELAY_MS));
+                let select = Select::new();
+                let mut response_handle = select.handle(&responses);
+                let mut timeout_handle = select.handle(&timeout);
+                unsafe {
+                    response_handle.add();
+                    timeout_handle.add();
+                }
+                if select.wait() == timeout_handle.id() {
+                    break
+                }
+                match response_handle.recv_opt() {
+                    Ok(response) => archived.push(response),
+                    Err(()) => {
+                        stopped = true;
+                        break
+                    }
+                }
+            }
+
+            match write_archive(&path, &archived) {
+                Ok(()) => {}
+                Err(e) => error!("network archive: couldn't write {}: {}", path.display(), e),
+            }
+            if stopped {
+                return
+            }
+        }
+    });
+    recorder
+}
+
+/// Replace the archive at `path` with `archived`. The responses are written to a temporary
+/// file that is renamed over the archive, so that the archive is never left half written.
+fn write_archive(path: &Path, archived: &Vec<ArchivedResponse>) -> IoResult<()> {
+    let temp_path = path.with_filename(format!(".{}.tmp", path.filename_display()));
+    try!(File::create(&temp_path).write_str(json::encode(archived).as_slice()));
+    fs::rename(&temp_path, path)
+}
+
+/// Create a channel for a loader to send its `LoadResponse` to. The response is forwarded to
+/// `start_chan` as it arrives, and sent to `recorder` once it is complete.
+pub fn record(load_data: &LoadData, start_chan: Sender<LoadResponse>,
+              recorder: Sender<ArchivedResponse>) -> Sender<LoadResponse> {
+    let (record_chan, record_port) = channel();
+    let load_data = load_data.clone();
+    spawn_named("NetworkRecordingTask", proc() {
+        let LoadResponse { metadata, progress_port } = match record_port.recv_opt() {
+            Ok(response) => response,
+            Err(()) => return,
+        };
+        let mut archived = ArchivedResponse::new(&load_data, &metadata);
+        let progress_chan = match start_sending_opt(start_chan, metadata) {
+            Ok(progress_chan) => progress_chan,
+            Err(()) => return,
+        };
+        let mut body = vec!();
+        for msg in progress_port.iter() {
+            match msg {
+                Payload(data) => {
+                    body.push_all(data.as_slice());
+                    if progress_chan.send_opt(Payload(data)).is_err() {
+                        return
+                    }
+                }
+                Done(result) => {
+                    archived.body = body.as_slice().to_base64(STANDARD);
+                    archived.error = result.clone().err();
+                    let _ = recorder.send_opt(archived);
+                    let _ = progress_chan.send_opt(Done(result));
+                    return
+                }
+            }
+        }
+    });
+    record_chan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArchivedResponse, NetworkArchive, load_key, write_archive};
+    use resource_task::{LoadData, Metadata, Payload, Done};
+    use serialize::base64::{ToBase64, STANDARD};
+    use serialize::json;
+    use std::collections::hashmap::HashMap;
+    use std::io::{File, TempDir};
+    use std::io::fs::readdir;
+    use url::Url;
+
+    #[test]
+    fn test_replay_order() {
+        let url = Url::parse("http://example.com/counter").unwrap();
+        let load_data = LoadData::new(url.clone());
+        let mut first = ArchivedResponse::new(&load_data, &Metadata::default(url.clone()));
+        first.body = "MQ==".to_string();
+        let mut second = first.clone();
+        second.body = "Mg==".to_string();
+        let mut responses = HashMap::new();
+        responses.insert(load_key(&load_data), vec!(first, second));
+        let mut archive = NetworkArchive { responses: responses };
+
+        assert_eq!(archive.replay(&load_data).unwrap().body, "MQ==".to_string());
+        assert_eq!(archive.replay(&load_data).unwrap().body, "Mg==".to_string());
+        assert_eq!(archive.replay(&load_data).unwrap().body, "Mg==".to_string());
+
+        let mut post = LoadData::new(url);
+        post.data = Some(b"x".to_vec());
+        assert!(archive.replay(&post).is_none());
+    }
+
+    #[test]
+    fn test_archive_file() {
+        let url = Url::parse("http://example.com/page.html").unwrap();
+        let load_data = LoadData::new(url.clone());
+        let mut metadata = Metadata::default(url);
+        metadata.content_type = Some(("text".to_string(), "html".to_string()));
+        let mut archived = ArchivedResponse::new(&load_data, &metadata);
+        archived.body = b"<p>".to_base64(STANDARD);
+
+        let dir = TempDir::new("network-archive").unwrap();
+        let path = dir.path().join("archive.json");
+        File::create(&path).write_str(json::encode(&vec!(archived)).as_slice()).unwrap();
+        let mut archive = NetworkArchive::open(&path).unwrap();
+
+        let (start_chan, start_port) = channel();
+        archive.replay(&load_data).unwrap().send(start_chan);
+        let response = start_port.recv();
+        assert_eq!(response.metadata.content_type, metadata.content_type);
+        assert_eq!(response.metadata.status.code(), 200);
+        assert_eq!(response.progress_port.recv(), Payload(b"<p>".to_vec()));
+        assert_eq!(response.progress_port.recv(), Done(Ok(())));
+    }
+
+    #[test]
+    fn test_write_archive() {
+        let url = Url::parse("http://example.com/").unwrap();
+        let load_data = LoadData::new(url.clone());
+        let archived = ArchivedResponse::new(&load_data, &Metadata::default(url));
+
+        let dir = TempDir::new("network-archive").unwrap();
+        let path = dir.path().join("archive.json");
+        File::create(&path).write_str("[]").unwrap();
+        write_archive(&path, &vec!(archived.clone(), archived)).unwrap();
+
+        // Only the archive is left, holding both responses.
+        assert_eq!(readdir(dir.path()).unwrap(), vec!(path.clone()));
+        let archive = NetworkArchive::open(&path).unwrap();
+        assert_eq!(archive.responses.find(&load_key(&load_data)).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,156 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Structured events describing the loads made by the resource task, for network monitors and
+//! HAR logs.
+//!
+//! Events are only generated while something is observing them: an observer registered with
+//! `AddNetworkObserver`, or the HAR log requested with `--har`.
+
+use load_scheduler::LOAD_CANCELLED;
+use resource_task::{LoadData, LoadGroupId, LoadId, LoadPriority, LoadResponse, Metadata};
+use resource_task::{Payload, Done, ResourceTask, ReportNetworkEvent, start_sending_opt};
+
+use http::headers::HeaderEnum;
+use http::headers::request::HeaderCollection as RequestHeaderCollection;
+use http::headers::response::HeaderCollection as ResponseHeaderCollection;
+use http::method::Method;
+use servo_util::task::spawn_named;
+use time;
+use time::Timespec;
+use url::Url;
+
+/// A request as dispatched by the resource task.
+#[deriving(Clone)]
+pub struct NetworkRequest {
+    pub url: Url,
+    pub method: Method,
+    pub headers: Vec<(String, String)>,
+    /// The size of the request body, in bytes.
+    pub body_size: uint,
+    pub priority: LoadPriority,
+    pub group: Option<LoadGroupId>,
+    /// The wall-clock time at which the load started.
+    pub started: Timespec,
+}
+
+impl NetworkRequest {
+    pub fn new(load_data: &LoadData) -> NetworkRequest {
+        NetworkRequest {
+            url: load_data.url.clone(),
+            method: load_data.method.clone(),
+            headers: request_headers(&load_data.headers),
+            body_size: load_data.data.as_ref().map_or(0, |data| data.len()),
+            priority: load_data.priority,
+            group: load_data.group,
+            started: time::get_time(),
+        }
+    }
+}
+
+/// The status and headers of a response.
+#[deriving(Clone)]
+pub struct NetworkResponse {
+    /// The URL of the response, after any redirects.
+    pub url: Url,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub content_type: Option<(String, String)>,
+}
+
+impl NetworkResponse {
+    fn new(metadata: &Metadata) -> NetworkResponse {
+        NetworkResponse {
+            url: metadata.final_url.clone(),
+            status: metadata.status.code(),
+            headers: metadata.headers.as_ref().map_or(vec!(), response_headers),
+            content_type: metadata.content_type.clone(),
+        }
+    }
+}
+
+#[deriving(Clone)]
+pub enum NetworkEventKind {
+    /// The load has been dispatched to a loader.
+    RequestStarted(NetworkRequest),
+    /// The loader followed a redirect with the given status and headers to a new URL.
+    Redirected(Url, u16, Vec<(String, String)>),
+    /// The response headers have arrived.
+    ResponseStarted(NetworkResponse),
+    /// The first bytes of the response body have arrived.
+    FirstByte,
+    /// The load has finished after receiving the given number of body bytes, successfully or
+    /// with an error.
+    LoadCompleted(uint, Result<(), String>),
+}
+
+#[deriving(Clone)]
+pub struct NetworkEvent {
+    pub load: LoadId,
+    /// When the event happened, in nanoseconds from an arbitrary point, as given by
+    /// `time::precise_time_ns`.
+    pub time: u64,
+    pub kind: NetworkEventKind,
+}
+
+impl NetworkEvent {
+    pub fn new(load: LoadId, kind: NetworkEventKind) -> NetworkEvent {
+        NetworkEvent {
+            load: load,
+            time: time::precise_time_ns(),
+            kind: kind,
+        }
+    }
+}
+
+pub fn request_headers(headers: &RequestHeaderCollection) -> Vec<(String, String)> {
+    headers.iter().map(|header| (header.header_name(), header.header_value())).collect()
+}
+
+pub fn response_headers(headers: &ResponseHeaderCollection) -> Vec<(String, String)> {
+    headers.iter().map(|header| (header.header_name(), header.header_value())).collect()
+}
+
+/// Create a channel for a loader to send its `LoadResponse` to. The response is forwarded to
+/// `start_chan`, and its progress reported to the resource task.
+pub fn observe(load: LoadId, start_chan: Sender<LoadResponse>, resource_task: ResourceTask)
+               -> Sender<LoadResponse> {
+    let (observer_chan, observer_port) = channel();
+    spawn_named("NetworkObserverTask", proc() {
+        let report = |kind: NetworkEventKind| {
+            let _ = resource_task.send_opt(ReportNetworkEvent(NetworkEvent::new(load, kind)));
+        };
+        let LoadResponse { metadata, progress_port } = match observer_port.recv_opt() {
+            Ok(response) => response,
+            Err(()) => return report(LoadCompleted(0, Err("no response".to_string()))),
+        };
+        report(ResponseStarted(NetworkResponse::new(&metadata)));
+        let progress_chan = match start_sending_opt(start_chan, metadata) {
+            Ok(progress_chan) => progress_chan,
+            Err(()) => return report(LoadCompleted(0, Err(LOAD_CANCELLED.to_string()))),
+        };
+
+        let mut size = 0;
+        for msg in progress_port.iter() {
+            match msg {
+                Payload(data) => {
+                    if size == 0 && !data.is_empty() {
+                        report(FirstByte);
+                    }
+                    size += data.len();
+                    if progress_chan.send_opt(Payload(data)).is_err() {
+                        return report(LoadCompleted(size, Err(LOAD_CANCELLED.to_string())));
+                    }
+                }
+                Done(result) => {
+                    report(LoadCompleted(size, result.clone()));
+                    let _ = progress_chan.send_opt(Done(result));
+                    return
+                }
+            }
+        }
+        report(LoadCompleted(size, Err("the loader went away".to_string())));
+    });
+    observer_chan
+}
@@ -5,12 +5,42 @@
 //! A task that takes a URL and streams back the binary data.
 
 use about_loader;
+use about_loader::AboutContext;
+use blob_loader;
+use blob_loader::{BlobData, BlobURLStore};
+use connection_pool::{Checkout, ConnectionPool};
+use cookie::{CookieSource, HTTP};
+use cookie_storage::CookieStorage;
 use data_loader;
+use fetch::cors_cache::{CORSCacheSender, CORSCacheTask, ExitMsg};
+use fetch::request::Context;
 use file_loader;
+use har::HarLog;
+use hsts::HSTSList;
+use http_auth::{AuthCache, AuthChallenge, AuthRequest, Credentials};
+use http_cache::{CachedResource, HttpCache};
 use http_loader;
+use image_cache_task::ImageCacheTask;
+use load_scheduler::LoadScheduler;
+use mixed_content;
+use network_archive;
+use network_archive::{ArchivedResponse, NetworkArchive};
+use network_events;
+use network_events::{NetworkEvent, NetworkRequest, RequestStarted};
+use proxy::{ProxiedStream, ProxyConfig};
+use referrer_policy::ReferrerPolicy;
+use sniffer_task;
+use websocket;
+use websocket::{WebSocketCommand, WebSocketEvent, WebSocketRequest};
 
+use std::ascii::StrAsciiExt;
+use std::collections::hashmap::HashMap;
 use std::comm::{channel, Receiver, Sender};
+use std::sync::atomics::{AtomicUint, INIT_ATOMIC_UINT, SeqCst};
 use std::task::TaskBuilder;
+use servo_util::opts::{Opts, LiveNetwork, RecordNetwork, ReplayNetwork};
+use servo_util::task::spawn_named;
+use servo_util::time::TimeProfilerChan;
 use http::headers::content_type::MediaType;
 use http::headers::response::HeaderCollection as ResponseHeaderCollection;
 use http::headers::request::HeaderCollection as RequestHeaderCollection;
@@ -24,39 +54,141 @@ use http::status::Status;
 pub enum ControlMsg {
     /// Request the data associated with a particular URL
     Load(LoadData, Sender<LoadResponse>),
+    /// Stop a load. A load that hasn't started yet fails straight away; one in progress stops
+    /// forwarding data and finishes with an error
+    Cancel(LoadId),
+    /// Cancel every load made on behalf of the given group
+    CancelLoadGroup(LoadGroupId),
+    /// Raise the priority of the loads of a URL that haven't started yet
+    PrioritizeLoad(Url, LoadPriority),
+    /// Sent by the sniffer task of a load once it has stopped forwarding data, so that queued
+    /// loads can start
+    LoadFinished(LoadId),
+    /// Store a cookie from a `Set-Cookie` string for the given originating URL
+    SetCookieForUrl(Url, String, CookieSource),
+    /// Retrieve the serialized cookies that should be sent to the given URL
+    GetCookiesForUrl(Url, Sender<Option<String>>, CookieSource),
+    /// Look up a stored response for the given URL in the HTTP cache
+    GetCachedResource(Url, Sender<Option<CachedResource>>),
+    /// Store a complete response in the HTTP cache, replacing any previous entry
+    StoreCachedResource(Url, CachedResource),
+    /// Remove any stored response for the given URL from the HTTP cache
+    InvalidateCachedResource(Url),
+    /// Retrieve a handle to the CORS preflight cache shared by all fetches
+    GetCORSCache(Sender<CORSCacheSender>),
+    /// Retrieve the stored credentials to send preemptively with a request for the given URL,
+    /// either to the origin server or, if one is given, to the proxy
+    GetAuthorization(Url, Option<Url>, Method, Sender<Option<String>>),
+    /// Answer a 401 or 407 response with stored credentials, or with credentials supplied by
+    /// the embedder. `None` is sent if there are none
+    Authenticate(AuthChallenge, Sender<Option<String>>),
+    /// Store the credentials the embedder supplied for a challenge, and answer it with them
+    SetCredentials(AuthChallenge, Option<Credentials>, Sender<Option<String>>),
+    /// Check out a connection to the server with the given connection pool key, waiting if
+    /// the server's connection limit has been reached
+    GetConnection(String, Sender<Checkout>),
+    /// Return a checked out connection to the pool, or `None` if it has been closed
+    ReleaseConnection(String, Option<ProxiedStream>),
+    /// Send an event describing the progress of each load from now on to the given channel
+    AddNetworkObserver(Sender<NetworkEvent>),
+    /// Sent by loaders to describe the progress of a load to the network observers
+    ReportNetworkEvent(NetworkEvent),
+    /// Give the time profiler's metrics to the about:profile page
+    SetTimeProfiler(TimeProfilerChan),
+    /// Give the image cache's contents to the about:cache page
+    SetImageCache(ImageCacheTask),
+    /// Store the `Strict-Transport-Security` header value received from the given URL
+    SetHSTSEntryForUrl(Url, String),
+    /// Retrieve the URL to request instead of the given one, which is its secure equivalent if
+    /// its host is known to require secure connections
+    GetSecureUrl(Url, Sender<Url>),
+    /// Open a WebSocket connection, reporting its progress to the sender and taking commands
+    /// from the receiver
+    ConnectWebSocket(WebSocketRequest, Sender<WebSocketEvent>, Receiver<WebSocketCommand>),
+    /// Make the given blob URL resolve to the contents of a blob
+    AddBlobURL(Url, BlobData),
+    /// Stop the given blob URL from resolving
+    RevokeBlobURL(Url),
     Exit
 }
 
+/// Identifies a load, so that it can be cancelled.
+#[deriving(Clone, PartialEq, Eq, Hash, Show)]
+pub struct LoadId(pub uint);
+
+static mut NEXT_LOAD_ID: AtomicUint = INIT_ATOMIC_UINT;
+
+impl LoadId {
+    pub fn next() -> LoadId {
+        unsafe { LoadId(NEXT_LOAD_ID.fetch_add(1, SeqCst)) }
+    }
+}
+
+/// Identifies the loads made on behalf of a single pipeline, so that they can be cancelled
+/// together when it exits.
+#[deriving(Clone, PartialEq, Eq, Hash, Show)]
+pub struct LoadGroupId(pub uint);
+
+/// How urgently a load is needed. Loads are started in this order when they can't all start at
+/// once, and image loads and prefetches are throttled so that they don't compete with the loads
+/// that block rendering.
+#[deriving(Clone, PartialEq, Eq, PartialOrd, Ord, Show)]
+pub enum LoadPriority {
+    DocumentPriority,
+    StylesheetPriority,
+    ScriptPriority,
+    FontPriority,
+    ImagePriority,
+    PrefetchPriority,
+}
+
 #[deriving(Clone)]
 pub struct LoadData {
+    pub id: LoadId,
     pub url: Url,
     pub method: Method,
     pub headers: RequestHeaderCollection,
     pub data: Option<Vec<u8>>,
-    pub cors: Option<ResourceCORSData>
+    /// Whether HTTP redirects are followed by the loader, rather than returned to the consumer
+    pub follow_redirects: bool,
+    /// Whether cookies are sent with the request and stored from the response
+    pub send_credentials: bool,
+    pub priority: LoadPriority,
+    /// The group to cancel this load with, if any
+    pub group: Option<LoadGroupId>,
+    /// The URL of the document the load is made for, if any. Insecure loads made for a secure
+    /// document are mixed content.
+    pub client_url: Option<Url>,
+    /// What the load is for, which decides whether mixed content is blocked
+    pub context: Option<Context>,
+    /// The URL of the document that made the load, sent in the `Referer` header as its
+    /// referrer policy allows
+    pub referrer: Option<Url>,
+    pub referrer_policy: Option<ReferrerPolicy>,
 }
 
 impl LoadData {
     pub fn new(url: Url) -> LoadData {
         LoadData {
+            id: LoadId::next(),
             url: url,
             method: Get,
             headers: RequestHeaderCollection::new(),
             data: None,
-            cors: None
+            follow_redirects: true,
+            send_credentials: true,
+            priority: DocumentPriority,
+            group: None,
+            client_url: None,
+            context: None,
+            referrer: None,
+            referrer_policy: None,
         }
     }
 }
 
-#[deriving(Clone)]
-pub struct ResourceCORSData {
-    /// CORS Preflight flag
-    pub preflight: bool,
-    /// Origin of CORS Request
-    pub origin: Url
-}
-
 /// Metadata about a loaded resource, such as is obtained from HTTP headers.
+#[deriving(Clone)]
 pub struct Metadata {
     /// Final URL after redirects.
     pub final_url: Url,
@@ -146,8 +278,14 @@ pub fn start_sending_opt(start_chan: Sender<LoadResponse>, metadata: Metadata) -
 /// Convenience function for synchronously loading a whole resource.
 pub fn load_whole_resource(resource_task: &ResourceTask, url: Url)
         -> Result<(Metadata, Vec<u8>), String> {
+    load_whole_resource_with(resource_task, LoadData::new(url))
+}
+
+/// Like `load_whole_resource`, for a load with non-default parameters.
+pub fn load_whole_resource_with(resource_task: &ResourceTask, load_data: LoadData)
+        -> Result<(Metadata, Vec<u8>), String> {
     let (start_chan, start_port) = channel();
-    resource_task.send(Load(LoadData::new(url), start_chan));
+    resource_task.send(Load(load_data, start_chan));
     let response = start_port.recv();
 
     let mut buf = vec!();
@@ -163,64 +301,465 @@ pub fn load_whole_resource(resource_task: &ResourceTask, url: Url)
 /// Handle to a resource task
 pub type ResourceTask = Sender<ControlMsg>;
 
+/// A loader for URLs of a particular scheme, supplied by the embedder.
+///
+/// The contract is the same as for the built-in `factory` functions: `load` is called on the
+/// resource task for every matching `Load` message and must respond on `start_chan`, so any
+/// blocking work should happen in a separate task.
+pub trait ProtocolHandler {
+    fn load(&self, load_data: LoadData, start_chan: Sender<LoadResponse>);
+}
+
+/// What a `RequestInterceptor` decides to do with a load.
+pub enum InterceptResult {
+    /// Carry on with the (possibly rewritten) load.
+    ContinueLoad(LoadData),
+    /// Fail the load with the given error.
+    BlockLoad(String),
+    /// Respond with the given metadata and body without consulting any loader.
+    SynthesizeResponse(Metadata, Vec<u8>),
+}
+
+/// A hook, supplied by the embedder, that sees every load before it is dispatched to a loader.
+pub trait RequestInterceptor {
+    fn intercept(&self, load_data: LoadData) -> InterceptResult;
+}
+
+/// A hook, supplied by the embedder, for asking the user for credentials when a server or proxy
+/// responds with a challenge that the credential store can't answer.
+///
+/// `prompt_for_credentials` is called on the resource task, so it must not block; the answer,
+/// `None` if the user declines, is sent on `response_chan` whenever it is available.
+pub trait AuthPrompt {
+    fn prompt_for_credentials(&self, request: AuthRequest,
+                              response_chan: Sender<Option<Credentials>>);
+}
+
+/// The protocol handlers, request interceptors and authentication prompt registered by the
+/// embedder.
+pub struct ResourceHandlers {
+    protocol_handlers: HashMap<String, Box<ProtocolHandler + Send>>,
+    interceptors: Vec<Box<RequestInterceptor + Send>>,
+    auth_prompt: Option<Box<AuthPrompt + Send>>,
+}
+
+impl ResourceHandlers {
+    pub fn new() -> ResourceHandlers {
+        ResourceHandlers {
+            protocol_handlers: HashMap::new(),
+            interceptors: vec!(),
+            auth_prompt: None,
+        }
+    }
+
+    /// Handle loads of URLs with the given scheme. A registered handler takes precedence over
+    /// the built-in loader for that scheme.
+    pub fn register_protocol_handler(&mut self, scheme: &str,
+                                     handler: Box<ProtocolHandler + Send>) {
+        self.protocol_handlers.insert(scheme.to_ascii_lower(), handler);
+    }
+
+    /// Add an interceptor. Interceptors are consulted in the order they were added, each one
+    /// seeing the load as rewritten by the previous ones.
+    pub fn add_request_interceptor(&mut self, interceptor: Box<RequestInterceptor + Send>) {
+        self.interceptors.push(interceptor);
+    }
+
+    /// Ask the user for credentials through `prompt`. Without a prompt, challenges that the
+    /// credential store can't answer are passed on to the consumer of the load.
+    pub fn set_auth_prompt(&mut self, prompt: Box<AuthPrompt + Send>) {
+        self.auth_prompt = Some(prompt);
+    }
+
+    pub fn has_auth_prompt(&self) -> bool {
+        self.auth_prompt.is_some()
+    }
+}
+
 /// Create a ResourceTask
-pub fn new_resource_task(user_agent: Option<String>) -> ResourceTask {
+pub fn new_resource_task(opts: &Opts, handlers: ResourceHandlers) -> ResourceTask {
     let (setup_chan, setup_port) = channel();
+    let resource_task = setup_chan.clone();
+    let opts = opts.clone();
     let builder = TaskBuilder::new().named("ResourceManager");
     builder.spawn(proc() {
-        ResourceManager::new(setup_port, user_agent).start();
+        ResourceManager::new(setup_port, &opts, handlers, resource_task).start();
     });
     setup_chan
 }
 
+fn start_cors_cache_task() -> CORSCacheSender {
+    let mut cors_cache_task = CORSCacheTask::new();
+    let cors_cache = cors_cache_task.get_sender();
+    spawn_named("CORSCacheTask", proc() cors_cache_task.run());
+    cors_cache
+}
+
 struct ResourceManager {
     from_client: Receiver<ControlMsg>,
     user_agent: Option<String>,
+    /// The value of the `Accept-Encoding` header sent with HTTP requests.
+    accept_encoding: String,
+    cookie_storage: CookieStorage,
+    /// The hosts that must only be reached over HTTPS.
+    hsts_list: HSTSList,
+    /// The blob URLs created by script.
+    blob_urls: BlobURLStore,
+    auth_cache: AuthCache,
+    http_cache: HttpCache,
+    cors_cache: CORSCacheSender,
+    connection_pool: ConnectionPool,
+    scheduler: LoadScheduler,
+    proxy_config: ProxyConfig,
+    /// The archive that HTTP loads are answered from, in replay mode.
+    network_archive: Option<NetworkArchive>,
+    /// The task that HTTP responses are recorded by, in record mode.
+    network_recorder: Option<Sender<ArchivedResponse>>,
+    handlers: ResourceHandlers,
+    network_observers: Vec<Sender<NetworkEvent>>,
+    /// The log of network events to write when the resource task exits, if `--har` was given.
+    har_log: Option<HarLog>,
+    /// The options shown by about:config.
+    opts: Opts,
+    time_profiler: Option<TimeProfilerChan>,
+    image_cache: Option<ImageCacheTask>,
+    /// A handle to this resource task, given to loaders that need to consult the cookie store,
+    /// the credential store, the HTTP cache or the connection pool.
+    resource_task: ResourceTask,
 }
 
 
 impl ResourceManager {
-    fn new(from_client: Receiver<ControlMsg>, user_agent: Option<String>) -> ResourceManager {
+    fn new(from_client: Receiver<ControlMsg>, opts: &Opts, handlers: ResourceHandlers,
+           resource_task: ResourceTask) -> ResourceManager {
+        let (network_archive, network_recorder) = match opts.network_archive {
+            LiveNetwork => (None, None),
+            RecordNetwork(ref path) => {
+                (None, Some(network_archive::start_recording(Path::new(path.as_slice()))))
+            }
+            ReplayNetwork(ref path) => {
+                match NetworkArchive::open(&Path::new(path.as_slice())) {
+                    Ok(archive) => (Some(archive), None),
+                    Err(e) => fail!("network archive: {}", e),
+                }
+            }
+        };
+        let hsts_list = match opts.hsts_preload_file {
+            Some(ref path) => {
+                match HSTSList::from_preload_file(&Path::new(path.as_slice())) {
+                    Ok(list) => list,
+                    Err(e) => fail!("HSTS preload list: {:s}", e),
+                }
+            }
+            None => HSTSList::new(),
+        };
         ResourceManager {
             from_client: from_client,
-            user_agent: user_agent,
+            user_agent: opts.user_agent.clone(),
+            accept_encoding: http_loader::accept_encoding(opts.accept_encoding.as_slice()),
+            cookie_storage: CookieStorage::new(),
+            hsts_list: hsts_list,
+            blob_urls: BlobURLStore::new(),
+            auth_cache: AuthCache::new(),
+            http_cache: HttpCache::new(),
+            cors_cache: start_cors_cache_task(),
+            connection_pool: ConnectionPool::new(opts),
+            scheduler: LoadScheduler::new(),
+            proxy_config: ProxyConfig::new(opts),
+            network_archive: network_archive,
+            network_recorder: network_recorder,
+            handlers: handlers,
+            network_observers: vec!(),
+            har_log: opts.har_file.as_ref().map(|path| HarLog::new(Path::new(path.as_slice()))),
+            opts: opts.clone(),
+            time_profiler: None,
+            image_cache: None,
+            resource_task: resource_task,
         }
     }
 }
 
 
 impl ResourceManager {
-    fn start(&self) {
+    fn start(&mut self) {
         loop {
             match self.from_client.recv() {
               Load(load_data, start_chan) => {
-                self.load(load_data, start_chan)
+                self.scheduler.schedule(load_data, start_chan);
+                self.start_scheduled_loads();
+              }
+              Cancel(id) => {
+                self.scheduler.cancel(id);
+              }
+              CancelLoadGroup(group) => {
+                self.scheduler.cancel_group(group);
+              }
+              PrioritizeLoad(url, priority) => {
+                self.scheduler.prioritize(&url, priority);
+                self.start_scheduled_loads();
+              }
+              LoadFinished(id) => {
+                self.scheduler.finished(id);
+                self.start_scheduled_loads();
+              }
+              SetCookieForUrl(request, cookie, source) => {
+                self.cookie_storage.set_cookie_for_url(&request, cookie.as_slice(), source);
+              }
+              GetCookiesForUrl(url, consumer, source) => {
+                consumer.send(self.cookie_storage.cookies_for_url(&url, source));
+              }
+              GetCachedResource(url, consumer) => {
+                consumer.send(self.http_cache.get(&url));
+              }
+              StoreCachedResource(url, resource) => {
+                self.http_cache.store(url, resource);
+              }
+              InvalidateCachedResource(url) => {
+                self.http_cache.invalidate(&url);
+              }
+              GetCORSCache(consumer) => {
+                consumer.send(self.cors_cache.clone());
+              }
+              GetAuthorization(url, proxy, method, consumer) => {
+                consumer.send(self.auth_cache.authorization(&url, proxy.as_ref(), &method));
+              }
+              Authenticate(challenge, consumer) => {
+                self.authenticate(challenge, consumer)
+              }
+              SetCredentials(challenge, credentials, consumer) => {
+                consumer.send(credentials.and_then(|credentials| {
+                    self.auth_cache.store(&challenge, credentials)
+                }));
+              }
+              GetConnection(key, consumer) => {
+                self.connection_pool.checkout(key, consumer);
+              }
+              ReleaseConnection(key, stream) => {
+                self.connection_pool.release(key, stream);
+              }
+              AddNetworkObserver(observer) => {
+                self.network_observers.push(observer);
+              }
+              ReportNetworkEvent(event) => {
+                self.report_network_event(event);
+              }
+              SetTimeProfiler(time_profiler) => {
+                self.time_profiler = Some(time_profiler);
+              }
+              SetImageCache(image_cache) => {
+                self.image_cache = Some(image_cache);
+              }
+              SetHSTSEntryForUrl(url, value) => {
+                self.hsts_list.update_from_header(&url, value.as_slice());
+              }
+              GetSecureUrl(url, consumer) => {
+                consumer.send(self.hsts_list.secure_url(&url));
+              }
+              ConnectWebSocket(request, events, commands) => {
+                let cookies = self.cookie_storage.cookies_for_url(&websocket::http_url(&request.url),
+                                                                  HTTP);
+                websocket::connect(request, cookies, events, commands, self.resource_task.clone());
+              }
+              AddBlobURL(url, data) => {
+                self.blob_urls.add(&url, data);
+              }
+              RevokeBlobURL(url) => {
+                self.blob_urls.revoke(&url);
               }
               Exit => {
+                self.cors_cache.send(ExitMsg);
+                match self.har_log {
+                    Some(ref har_log) => {
+                        match har_log.write() {
+                            Ok(()) => {}
+                            Err(e) => error!("resource_task: couldn't write HAR log: {}", e),
+                        }
+                    }
+                    None => {}
+                }
                 break
               }
             }
         }
     }
 
-    fn load(&self, load_data: LoadData, start_chan: Sender<LoadResponse>) {
+    fn authenticate(&mut self, challenge: AuthChallenge, consumer: Sender<Option<String>>) {
+        match self.auth_cache.respond(&challenge) {
+            Some(authorization) => return consumer.send(Some(authorization)),
+            None => {}
+        }
+        let (request, prompt) = match (challenge.auth_request(), &self.handlers.auth_prompt) {
+            (Some(request), &Some(ref prompt)) => (request, prompt),
+            _ => return consumer.send(None),
+        };
+
+        // Wait for the user's answer in another task, so that other loads aren't held up.
+        let (response_chan, response_port) = channel();
+        prompt.prompt_for_credentials(request, response_chan);
+        let resource_task = self.resource_task.clone();
+        spawn_named("AuthPromptTask", proc() {
+            let credentials = match response_port.recv_opt() {
+                Ok(credentials) => credentials,
+                Err(_) => None,
+            };
+            resource_task.send(SetCredentials(challenge, credentials, consumer));
+        });
+    }
+
+    fn about_context(&self) -> AboutContext {
+        AboutContext {
+            opts: self.opts.clone(),
+            http_cache: self.http_cache.summary(),
+            time_profiler: self.time_profiler.clone(),
+            image_cache: self.image_cache.clone(),
+        }
+    }
+
+    fn is_observed(&self) -> bool {
+        !self.network_observers.is_empty() || self.har_log.is_some()
+    }
+
+    fn report_network_event(&mut self, event: NetworkEvent) {
+        match self.har_log {
+            Some(ref mut har_log) => har_log.observe(&event),
+            None => {}
+        }
+        self.network_observers.retain(|observer| observer.send_opt(event.clone()).is_ok());
+    }
+
+    fn start_scheduled_loads(&mut self) {
+        loop {
+            match self.scheduler.next_load() {
+                Some((load_data, start_chan, cancel_port)) => {
+                    self.load(load_data, start_chan, cancel_port)
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn load(&mut self, load_data: LoadData, start_chan: Sender<LoadResponse>,
+            cancel_port: Receiver<()>) {
         let mut load_data = load_data;
         load_data.headers.user_agent = self.user_agent.clone();
+        if load_data.headers.accept_encoding.is_none() {
+            load_data.headers.accept_encoding = Some(self.accept_encoding.clone());
+        }
+
+        // Every response passes through a sniffer task, which determines the content type and
+        // stops the load when it is cancelled.
+        let id = load_data.id;
+        let resource_task = self.resource_task.clone();
+        let start_chan = sniffer_task::new_sniffer_task(load_data.url.clone(), start_chan,
+                                                        cancel_port, proc() {
+            let _ = resource_task.send_opt(LoadFinished(id));
+        });
 
-        let loader = match load_data.url.scheme.as_slice() {
-            "file" => file_loader::factory,
-            "http" | "https" => http_loader::factory,
-            "data" => data_loader::factory,
-            "about" => about_loader::factory,
+        for interceptor in self.handlers.interceptors.iter() {
+            let url = load_data.url.clone();
+            match interceptor.intercept(load_data) {
+                ContinueLoad(data) => load_data = data,
+                BlockLoad(reason) => {
+                    debug!("resource_task: load of {:s} blocked: {:s}", url.serialize(), reason);
+                    start_sending(start_chan, Metadata::default(url)).send(Done(Err(reason)));
+                    return
+                }
+                SynthesizeResponse(metadata, body) => {
+                    debug!("resource_task: synthesized response for {:s}", url.serialize());
+                    let progress_chan = start_sending(start_chan, metadata);
+                    progress_chan.send(Payload(body));
+                    progress_chan.send(Done(Ok(())));
+                    return
+                }
+            }
+        }
+
+        // Interceptors see the URL the load was made with, before it is upgraded by HSTS or
+        // checked for mixed content.
+        load_data.url = self.hsts_list.secure_url(&load_data.url);
+        match (&load_data.client_url, load_data.context) {
+            (&Some(ref client_url), Some(context)) => {
+                match mixed_content::check(client_url, &load_data.url, context) {
+                    Ok(()) => {}
+                    Err(message) => {
+                        start_sending(start_chan, Metadata::default(load_data.url.clone()))
+                            .send(Done(Err(message)));
+                        return
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let start_chan = if self.is_observed() {
+            let request = NetworkRequest::new(&load_data);
+            self.report_network_event(NetworkEvent::new(load_data.id, RequestStarted(request)));
+            network_events::observe(load_data.id, start_chan, self.resource_task.clone())
+        } else {
+            start_chan
+        };
+
+        if network_archive::is_archived(&load_data.url) {
+            match self.network_archive {
+                Some(ref mut archive) => {
+                    match archive.replay(&load_data) {
+                        Some(response) => {
+                            debug!("resource_task: replaying {:s}", load_data.url.serialize());
+                            if load_data.send_credentials {
+                                let url = response.final_url();
+                                for cookie in response.header_values("set-cookie").iter() {
+                                    self.cookie_storage.set_cookie_for_url(&url, cookie.as_slice(),
+                                                                           HTTP);
+                                }
+                            }
+                            response.send(start_chan);
+                        }
+                        None => {
+                            error!("network archive: no response recorded for {} {:s}",
+                                   load_data.method, load_data.url.serialize());
+                            start_sending(start_chan, Metadata::default(load_data.url))
+                                .send(Done(Err("not in the network archive".to_string())));
+                        }
+                    }
+                    return
+                }
+                None => {}
+            }
+        }
+        let start_chan = match self.network_recorder {
+            Some(ref recorder) if network_archive::is_archived(&load_data.url) => {
+                network_archive::record(&load_data, start_chan, recorder.clone())
+            }
+            _ => start_chan,
+        };
+
+        debug!("resource_task: loading url: {:s}", load_data.url.serialize());
+        let scheme = load_data.url.scheme.clone();
+        match self.handlers.protocol_handlers.find(&scheme) {
+            Some(handler) => return handler.load(load_data, start_chan),
+            None => {}
+        }
+        match scheme.as_slice() {
+            "file" => file_loader::factory(load_data, start_chan),
+            "http" | "https" => {
+                http_loader::factory(load_data, start_chan, self.resource_task.clone(),
+                                     self.proxy_config.clone())
+            }
+            "data" => data_loader::factory(load_data, start_chan),
+            "blob" => {
+                let data = self.blob_urls.get(&load_data.url);
+                blob_loader::factory(load_data, start_chan, data)
+            }
+            "about" if about_loader::is_diagnostic_page(&load_data.url) => {
+                about_loader::diagnostic_page_factory(load_data, start_chan, self.about_context())
+            }
+            "about" => about_loader::factory(load_data, start_chan),
             _ => {
                 debug!("resource_task: no loader for scheme {:s}", load_data.url.scheme);
                 start_sending(start_chan, Metadata::default(load_data.url))
                     .send(Done(Err("no loader for scheme".to_string())));
-                return
             }
-        };
-        debug!("resource_task: loading url: {:s}", load_data.url.serialize());
-        loader(load_data, start_chan);
+        }
     }
 }
 
@@ -252,15 +791,37 @@ impl Iterator<Vec<u8>> for ProgressMsgPortIterator {
     }
 }
 
+#[cfg(test)]
+use servo_util::opts::default_opts;
+
 #[test]
 fn test_exit() {
-    let resource_task = new_resource_task(None);
+    let resource_task = new_resource_task(&default_opts(), ResourceHandlers::new());
+    resource_task.send(Exit);
+}
+
+#[test]
+fn test_cookies() {
+    use cookie::{HTTP, NonHTTP};
+
+    let resource_task = new_resource_task(&default_opts(), ResourceHandlers::new());
+    let url = Url::parse("http://example.com/foo").unwrap();
+    resource_task.send(SetCookieForUrl(url.clone(), "a=1; HttpOnly".to_string(), HTTP));
+    resource_task.send(SetCookieForUrl(url.clone(), "b=2".to_string(), NonHTTP));
+
+    let (tx, rx) = channel();
+    resource_task.send(GetCookiesForUrl(url.clone(), tx, HTTP));
+    assert_eq!(rx.recv(), Some("a=1; b=2".to_string()));
+
+    let (tx, rx) = channel();
+    resource_task.send(GetCookiesForUrl(url, tx, NonHTTP));
+    assert_eq!(rx.recv(), Some("b=2".to_string()));
     resource_task.send(Exit);
 }
 
 #[test]
 fn test_bad_scheme() {
-    let resource_task = new_resource_task(None);
+    let resource_task = new_resource_task(&default_opts(), ResourceHandlers::new());
     let (start_chan, start) = channel();
     let url = Url::parse("bogus://whatever").unwrap();
     resource_task.send(Load(LoadData::new(url), start_chan));
@@ -271,3 +832,208 @@ fn test_bad_scheme() {
     }
     resource_task.send(Exit);
 }
+
+#[cfg(test)]
+struct BundledAssetHandler;
+
+#[cfg(test)]
+impl ProtocolHandler for BundledAssetHandler {
+    fn load(&self, load_data: LoadData, start_chan: Sender<LoadResponse>) {
+        let progress_chan = start_sending(start_chan, Metadata::default(load_data.url.clone()));
+        progress_chan.send(Payload(load_data.url.serialize_path().unwrap().into_bytes()));
+        progress_chan.send(Done(Ok(())));
+    }
+}
+
+#[test]
+fn test_protocol_handler() {
+    let mut handlers = ResourceHandlers::new();
+    handlers.register_protocol_handler("app", box BundledAssetHandler);
+    let resource_task = new_resource_task(&default_opts(), handlers);
+    let url = Url::parse("app://bundle/index.html").unwrap();
+    match load_whole_resource(&resource_task, url) {
+        Ok((_, body)) => assert_eq!(body, b"/index.html".to_vec()),
+        Err(e) => fail!("load failed: {}", e),
+    }
+    resource_task.send(Exit);
+}
+
+#[cfg(test)]
+struct EndlessHandler;
+
+#[cfg(test)]
+impl ProtocolHandler for EndlessHandler {
+    fn load(&self, load_data: LoadData, start_chan: Sender<LoadResponse>) {
+        use std::io::timer::sleep;
+        use std::time::duration::Duration;
+
+        let progress_chan = start_sending(start_chan, Metadata::default(load_data.url));
+        spawn(proc() {
+            // Keep sending data until nobody is listening.
+            while progress_chan.send_opt(Payload(Vec::from_elem(1024, 0u8))).is_ok() {
+                sleep(Duration::milliseconds(10));
+            }
+        });
+    }
+}
+
+#[test]
+fn test_cancel_load() {
+    use load_scheduler::LOAD_CANCELLED;
+
+    let mut handlers = ResourceHandlers::new();
+    handlers.register_protocol_handler("endless", box EndlessHandler);
+    let resource_task = new_resource_task(&default_opts(), handlers);
+    let load_data = LoadData::new(Url::parse("endless://stream").unwrap());
+    let id = load_data.id;
+    let (start_chan, start_port) = channel();
+    resource_task.send(Load(load_data, start_chan));
+    let response = start_port.recv();
+    match response.progress_port.recv() {
+        Payload(_) => {}
+        Done(result) => fail!("load finished early: {}", result),
+    }
+
+    resource_task.send(Cancel(id));
+    loop {
+        match response.progress_port.recv() {
+            Payload(_) => {}
+            Done(result) => {
+                assert_eq!(result, Err(LOAD_CANCELLED.to_string()));
+                break
+            }
+        }
+    }
+    resource_task.send(Exit);
+}
+
+#[test]
+fn test_network_archive_miss() {
+    use servo_util::opts::ReplayNetwork;
+    use std::io::{File, TempDir};
+
+    let dir = TempDir::new("network-archive").unwrap();
+    let path = dir.path().join("archive.json");
+    File::create(&path).write_str("[]").unwrap();
+    let mut opts = default_opts();
+    opts.network_archive = ReplayNetwork(path.as_str().unwrap().to_string());
+    let resource_task = new_resource_task(&opts, ResourceHandlers::new());
+
+    // The load fails rather than reaching the network.
+    let url = Url::parse("http://servo.invalid/").unwrap();
+    match load_whole_resource(&resource_task, url) {
+        Ok(_) => fail!("load missing from the archive succeeded"),
+        Err(e) => assert_eq!(e, "not in the network archive".to_string()),
+    }
+    resource_task.send(Exit);
+}
+
+#[cfg(test)]
+struct TestInterceptor;
+
+#[cfg(test)]
+impl RequestInterceptor for TestInterceptor {
+    fn intercept(&self, load_data: LoadData) -> InterceptResult {
+        match load_data.url.host().unwrap().serialize().as_slice() {
+            "blocked.test" => BlockLoad("blocked".to_string()),
+            "synthesized.test" => {
+                SynthesizeResponse(Metadata::default(load_data.url), b"synthesized".to_vec())
+            }
+            "rewritten.test" => {
+                let mut load_data = load_data;
+                load_data.url = Url::parse("data:,rewritten").unwrap();
+                ContinueLoad(load_data)
+            }
+            _ => ContinueLoad(load_data),
+        }
+    }
+}
+
+#[test]
+fn test_request_interceptor() {
+    let mut handlers = ResourceHandlers::new();
+    handlers.add_request_interceptor(box TestInterceptor);
+    let resource_task = new_resource_task(&default_opts(), handlers);
+
+    let url = Url::parse("http://blocked.test/").unwrap();
+    match load_whole_resource(&resource_task, url) {
+        Ok(_) => fail!("blocked load succeeded"),
+        Err(e) => assert_eq!(e, "blocked".to_string()),
+    }
+
+    let url = Url::parse("http://synthesized.test/").unwrap();
+    match load_whole_resource(&resource_task, url) {
+        Ok((metadata, body)) => {
+            assert_eq!(metadata.final_url.serialize(), "http://synthesized.test/".to_string());
+            assert_eq!(body, b"synthesized".to_vec());
+        }
+        Err(e) => fail!("load failed: {}", e),
+    }
+
+    let url = Url::parse("http://rewritten.test/").unwrap();
+    match load_whole_resource(&resource_task, url) {
+        Ok((_, body)) => assert_eq!(body, b"rewritten".to_vec()),
+        Err(e) => fail!("load failed: {}", e),
+    }
+    resource_task.send(Exit);
+}
+
+#[test]
+fn test_request_interceptor_before_hsts_and_mixed_content() {
+    use fetch::request::Script;
+
+    let mut handlers = ResourceHandlers::new();
+    handlers.add_request_interceptor(box TestInterceptor);
+    let resource_task = new_resource_task(&default_opts(), handlers);
+
+    // The interceptor sees the insecure URL, not its HSTS upgrade.
+    let secure_url = Url::parse("https://synthesized.test/").unwrap();
+    resource_task.send(SetHSTSEntryForUrl(secure_url, "max-age=1000".to_string()));
+    let url = Url::parse("http://synthesized.test/").unwrap();
+    match load_whole_resource(&resource_task, url) {
+        Ok((metadata, _)) => {
+            assert_eq!(metadata.final_url.serialize(), "http://synthesized.test/".to_string());
+        }
+        Err(e) => fail!("load failed: {}", e),
+    }
+
+    // An intercepted load is answered by the interceptor, not blocked as mixed content.
+    let mut load_data = LoadData::new(Url::parse("http://blocked.test/").unwrap());
+    load_data.client_url = Some(Url::parse("https://secure.test/").unwrap());
+    load_data.context = Some(Script);
+    match load_whole_resource_with(&resource_task, load_data) {
+        Ok(_) => fail!("blocked load succeeded"),
+        Err(e) => assert_eq!(e, "blocked".to_string()),
+    }
+    resource_task.send(Exit);
+}
+
+/// Start a loopback HTTP server that answers each incoming connection with the next of
+/// `responses`, and reports the raw request it received.
+#[cfg(test)]
+pub fn spawn_test_server(responses: Vec<Vec<u8>>) -> (Url, Receiver<String>) {
+    use std::io::{Acceptor, Listener};
+    use std::io::net::tcp::TcpListener;
+    use std::str;
+
+    let mut listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+    let port = listener.socket_name().unwrap().port;
+    let (request_chan, request_port) = channel();
+    spawn(proc() {
+        let mut acceptor = listener.listen().unwrap();
+        for response in responses.into_iter() {
+            let mut stream = acceptor.accept().unwrap();
+            let mut request = vec!();
+            while !str::from_utf8(request.as_slice()).map_or(false, |r| r.contains("\r\n\r\n")) {
+                let mut buf = [0u8, ..1024];
+                match stream.read(&mut buf) {
+                    Ok(len) => request.push_all(buf.slice_to(len)),
+                    Err(_) => break,
+                }
+            }
+            request_chan.send(String::from_utf8(request).unwrap());
+            stream.write(response.as_slice()).unwrap();
+        }
+    });
+    (Url::parse(format!("http://127.0.0.1:{}/", port).as_slice()).unwrap(), request_port)
+}
@@ -0,0 +1,287 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A task that sits between a loader and its consumer, and determines the content type of the
+//! resource from its first bytes before the `LoadResponse` is forwarded.
+
+use http_cache::get_header;
+use load_scheduler::LOAD_CANCELLED;
+use mime_classifier::{MIMEClassifier, RESOURCE_HEADER_SIZE, is_apache_bug_content_type};
+use mime_classifier::needs_resource_header;
+use resource_task::{LoadResponse, Metadata, ProgressMsg, Payload, Done, start_sending_opt};
+
+use std::ascii::StrAsciiExt;
+use std::comm::Select;
+use std::io::Timer;
+use std::time::duration::Duration;
+use servo_util::task::spawn_named;
+use url::Url;
+
+/// How long to wait for the resource header before sniffing whatever has arrived so far.
+static SNIFFING_TIMEOUT_MS: i64 = 100;
+
+/// Create a channel for a loader to send its `LoadResponse` to. The response is forwarded to
+/// `start_chan` once enough of the body has arrived to sniff its content type, after
+/// `SNIFFING_TIMEOUT_MS`, or straight away if its content type doesn't need sniffing.
+///
+/// A message on `cancel_port` stops the load: the consumer is sent an error, and dropping the
+/// loader's ports lets it notice that it should stop too. `on_finish` runs once nothing more
+/// will be forwarded.
+pub fn new_sniffer_task(url: Url, start_chan: Sender<LoadResponse>, cancel_port: Receiver<()>,
+                        on_finish: proc(): Send) -> Sender<LoadResponse> {
+    let (sniffer_chan, sniffer_port) = channel();
+    spawn_named("SnifferTask", proc() {
+        sniff_and_forward(url, start_chan, sniffer_port, cancel_port);
+        on_finish();
+    });
+    sniffer_chan
+}
+
+fn sniff_and_forward(url: Url, start_chan: Sender<LoadResponse>,
+                     sniffer_port: Receiver<LoadResponse>, cancel_port: Receiver<()>) {
+    let response = match recv_unless_cancelled(&sniffer_port, &cancel_port) {
+        Some(Ok(response)) => response,
+        Some(Err(())) => return,
+        None => return send_cancelled(start_chan, Metadata::default(url)),
+    };
+    let LoadResponse { metadata, progress_port } = response;
+    let (no_sniff, check_for_apache_bug) = sniffing_flags(&metadata);
+
+    // Buffer messages until we have the resource header, the load has finished or we have
+    // waited long enough.
+    let mut buffered = vec!();
+    let mut resource_header = vec!();
+    let mut done = false;
+    if needs_resource_header(no_sniff, check_for_apache_bug, &metadata.content_type) {
+        let mut timer = Timer::new().unwrap();
+        let timeout_port = timer.oneshot(Duration::milliseconds(SNIFFING_TIMEOUT_MS));
+        while resource_header.len() < RESOURCE_HEADER_SIZE {
+            match recv_before_timeout(&progress_port, &cancel_port, &timeout_port) {
+                Received(Ok(Payload(data))) => {
+                    resource_header.push_all(data.as_slice());
+                    buffered.push(Payload(data));
+                }
+                Received(Ok(Done(result))) => {
+                    buffered.push(Done(result));
+                    done = true;
+                    break;
+                }
+                Received(Err(())) => {
+                    done = true;
+                    break;
+                }
+                TimedOut => {
+                    debug!("sniffer: timed out waiting for the resource header of {:s}",
+                           metadata.final_url.serialize());
+                    break;
+                }
+                Cancelled => return send_cancelled(start_chan, metadata),
+            }
+        }
+    }
+
+    let metadata = sniff(metadata, no_sniff, check_for_apache_bug, resource_header.as_slice());
+    let progress_chan = match start_sending_opt(start_chan, metadata) {
+        Ok(progress_chan) => progress_chan,
+        Err(_) => return,
+    };
+    for msg in buffered.into_iter() {
+        if progress_chan.send_opt(msg).is_err() {
+            return
+        }
+    }
+    if done {
+        return
+    }
+
+    // Forward the rest of the body. If the consumer goes away, dropping `progress_port`
+    // lets the loader notice that the load has been cancelled.
+    loop {
+        let msg: ProgressMsg = match recv_unless_cancelled(&progress_port, &cancel_port) {
+            Some(Ok(msg)) => msg,
+            Some(Err(())) => return,
+            None => {
+                let _ = progress_chan.send_opt(Done(Err(LOAD_CANCELLED.to_string())));
+                return
+            }
+        };
+        let finished = match msg {
+            Done(_) => true,
+            Payload(_) => false,
+        };
+        if progress_chan.send_opt(msg).is_err() || finished {
+            return
+        }
+    }
+}
+
+/// Wait for a message on `port`, unless the load is cancelled first, in which case `None` is
+/// returned.
+fn recv_unless_cancelled<T: Send>(port: &Receiver<T>, cancel_port: &Receiver<()>)
+                                  -> Option<Result<T, ()>> {
+    let select = Select::new();
+    let mut port_handle = select.handle(port);
+    let mut cancel_handle = select.handle(cancel_port);
+    unsafe {
+        port_handle.add();
+        cancel_handle.add();
+    }
+    if select.wait() == port_handle.id() {
+        Some(port_handle.recv_opt())
+    } else {
+        None
+    }
+}
+
+/// What waiting for part of the resource header ended with.
+enum HeaderWait<T> {
+    Received(Result<T, ()>),
+    TimedOut,
+    Cancelled,
+}
+
+/// Wait for a message on `port`, unless the load is cancelled or `timeout_port` fires first.
+fn recv_before_timeout<T: Send>(port: &Receiver<T>, cancel_port: &Receiver<()>,
+                                timeout_port: &Receiver<()>) -> HeaderWait<T> {
+    let select = Select::new();
+    let mut port_handle = select.handle(port);
+    let mut cancel_handle = select.handle(cancel_port);
+    let mut timeout_handle = select.handle(timeout_port);
+    unsafe {
+        port_handle.add();
+        cancel_handle.add();
+        timeout_handle.add();
+    }
+    let id = select.wait();
+    if id == port_handle.id() {
+        Received(port_handle.recv_opt())
+    } else if id == timeout_handle.id() {
+        TimedOut
+    } else {
+        Cancelled
+    }
+}
+
+fn send_cancelled(start_chan: Sender<LoadResponse>, metadata: Metadata) {
+    debug!("sniffer: load of {:s} cancelled", metadata.final_url.serialize());
+    match start_sending_opt(start_chan, metadata) {
+        Ok(progress_chan) => {
+            let _ = progress_chan.send_opt(Done(Err(LOAD_CANCELLED.to_string())));
+        }
+        Err(()) => {}
+    }
+}
+
+/// Whether the response opted out of sniffing with `X-Content-Type-Options: nosniff`, and
+/// whether its `Content-Type` header triggers the check for the Apache bug.
+fn sniffing_flags(metadata: &Metadata) -> (bool, bool) {
+    match metadata.headers {
+        Some(ref headers) => {
+            let no_sniff = get_header(headers, "x-content-type-options").map_or(false, |value| {
+                value.as_slice().trim().eq_ignore_ascii_case("nosniff")
+            });
+            let apache_bug = get_header(headers, "content-type").map_or(false, |value| {
+                is_apache_bug_content_type(value.as_slice())
+            });
+            (no_sniff, apache_bug)
+        }
+        None => (false, false),
+    }
+}
+
+/// Replace the content type of `metadata` with the computed MIME type of the resource.
+fn sniff(mut metadata: Metadata, no_sniff: bool, check_for_apache_bug: bool,
+         resource_header: &[u8]) -> Metadata {
+    let classifier = MIMEClassifier::new();
+    let content_type = classifier.classify(no_sniff, check_for_apache_bug,
+                                           &metadata.content_type, resource_header);
+    if content_type != metadata.content_type {
+        debug!("sniffer: {:s} sniffed as {}", metadata.final_url.serialize(), content_type);
+    }
+    metadata.content_type = content_type;
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use resource_task::{new_resource_task, load_whole_resource, spawn_test_server};
+    use resource_task::{ResourceHandlers, LoadData, Load, Payload, Done, Exit};
+    use servo_util::opts::default_opts;
+    use url::Url;
+
+    #[test]
+    fn test_sniffing() {
+        let unlabeled = b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n<!DOCTYPE html>".to_vec();
+        let no_sniff = b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\
+                         X-Content-Type-Options: nosniff\r\n\
+                         Connection: close\r\n\r\n\x89PNG\r\n\x1A\n".to_vec();
+        let (url, _requests) = spawn_test_server(vec!(unlabeled, no_sniff));
+        let resource_task = new_resource_task(&default_opts(), ResourceHandlers::new());
+
+        let (metadata, _) = load_whole_resource(&resource_task, url.clone()).unwrap();
+        assert_eq!(metadata.content_type, Some(("text".to_string(), "html".to_string())));
+
+        let (metadata, body) = load_whole_resource(&resource_task, url).unwrap();
+        assert_eq!(metadata.content_type, Some(("text".to_string(), "plain".to_string())));
+        assert_eq!(body.as_slice(), b"\x89PNG\r\n\x1A\n");
+        resource_task.send(Exit);
+    }
+
+    #[test]
+    fn test_forward_before_resource_header() {
+        use std::io::{Acceptor, Listener};
+        use std::io::net::tcp::TcpListener;
+        use std::str;
+
+        // The server sends the start of each body and then holds the connection open until it
+        // is told to go on, so the resource header never completes.
+        let mut listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+        let port = listener.socket_name().unwrap().port;
+        let (release_chan, release_port) = channel::<()>();
+        spawn(proc() {
+            let mut acceptor = listener.listen().unwrap();
+            let responses = [
+                "HTTP/1.1 200 OK\r\nContent-Type: text/css\r\nContent-Length: 1000\r\n\r\np {}",
+                "HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\n<p>",
+            ];
+            for response in responses.iter() {
+                let mut stream = acceptor.accept().unwrap();
+                let mut request = vec!();
+                while !str::from_utf8(request.as_slice()).map_or(false, |r| r.contains("\r\n\r\n")) {
+                    let mut buf = [0u8, ..1024];
+                    match stream.read(&mut buf) {
+                        Ok(len) => request.push_all(buf.slice_to(len)),
+                        Err(_) => break,
+                    }
+                }
+                stream.write(response.as_bytes()).unwrap();
+                release_port.recv();
+            }
+        });
+
+        let resource_task = new_resource_task(&default_opts(), ResourceHandlers::new());
+        let url = Url::parse(format!("http://127.0.0.1:{}/", port).as_slice()).unwrap();
+
+        // A type that is never sniffed is forwarded without waiting for the body, and one that
+        // is sniffed once the timeout has passed.
+        let expected = [(("text", "css"), b"p {}"), (("text", "html"), b"<p>")];
+        for &((type_, subtype), body) in expected.iter() {
+            let (start_chan, start_port) = channel();
+            resource_task.send(Load(LoadData::new(url.clone()), start_chan));
+            let response = start_port.recv();
+            assert_eq!(response.metadata.content_type,
+                       Some((type_.to_string(), subtype.to_string())));
+            match response.progress_port.recv() {
+                Payload(data) => assert_eq!(data.as_slice(), body),
+                Done(result) => fail!("unexpected end of load: {}", result),
+            }
+            release_chan.send(());
+            match response.progress_port.recv() {
+                Done(result) => assert!(result.is_err()),
+                Payload(_) => fail!("unexpected payload"),
+            }
+        }
+        resource_task.send(Exit);
+    }
+}
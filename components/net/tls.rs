@@ -0,0 +1,109 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Making TLS connections that verify the server's certificate, for the connections that aren't
+//! made by rust-http: tunnels through a proxy and secure WebSockets.
+
+use openssl::nid;
+use openssl::ssl::{Ssl, SslContext, SslStream, SslVerifyPeer, Sslv23};
+use std::ascii::StrAsciiExt;
+use std::io::{IoError, IoResult, OtherIoError};
+use std::io::fs::PathExtensions;
+use std::io::net::ip::IpAddr;
+use std::io::net::tcp::TcpStream;
+
+/// Where the system keeps the certificate authorities that servers are verified against, on
+/// the platforms we know of.
+static CA_BUNDLES: [&'static str, ..4] = [
+    "/etc/ssl/certs/ca-certificates.crt",
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    "/etc/ssl/cert.pem",
+    "/usr/local/etc/openssl/cert.pem",
+];
+
+fn tls_error(desc: &'static str, detail: Option<String>) -> IoError {
+    IoError {
+        kind: OtherIoError,
+        desc: desc,
+        detail: detail,
+    }
+}
+
+/// A TLS context that verifies the server's certificate against the system's certificate
+/// authorities.
+fn verifying_context() -> IoResult<SslContext> {
+    let mut context = try!(SslContext::new(Sslv23).map_err(|e| {
+        tls_error("failed to create SSL context", Some(e.to_string()))
+    }));
+    context.set_verify(SslVerifyPeer, None);
+    let ca_bundle = CA_BUNDLES.iter().map(|path| Path::new(*path)).find(|path| path.exists());
+    let ca_bundle = match ca_bundle {
+        Some(ca_bundle) => ca_bundle,
+        None => return Err(tls_error("no certificate authorities to verify the server with",
+                                     None)),
+    };
+    match context.set_CA_file(&ca_bundle) {
+        Some(e) => Err(tls_error("failed to load certificate authorities", Some(e.to_string()))),
+        None => Ok(context),
+    }
+}
+
+/// Make a TLS connection to `server_name` over `stream`, sending the name with SNI unless it
+/// is an IP address, and checking that the server's certificate was issued for it.
+pub fn connect(stream: TcpStream, server_name: &str) -> IoResult<SslStream<TcpStream>> {
+    let context = try!(verifying_context());
+    let ssl = try!(Ssl::new(&context).map_err(|e| {
+        tls_error("failed to create SSL session", Some(e.to_string()))
+    }));
+    if from_str::<IpAddr>(server_name).is_none() {
+        try!(ssl.set_hostname(server_name).map_err(|e| {
+            tls_error("failed to set the SSL server name", Some(e.to_string()))
+        }));
+    }
+    let stream = try!(SslStream::new_from(ssl, stream).map_err(|e| {
+        tls_error("SSL handshake failed", Some(e.to_string()))
+    }));
+    let certificate_name = stream.get_peer_certificate().and_then(|certificate| {
+        certificate.subject_name().text_by_nid(nid::CN).map(|name| name.to_string())
+    });
+    match certificate_name {
+        Some(ref name) if matches_host_name(name.as_slice(), server_name) => Ok(stream),
+        _ => Err(tls_error("server certificate does not match the host",
+                           Some(server_name.to_string()))),
+    }
+}
+
+/// Whether the name in a certificate names `host`. A leading `*.` matches exactly one label
+/// of a domain name.
+/// http://tools.ietf.org/html/rfc6125#section-6.4
+fn matches_host_name(name: &str, host: &str) -> bool {
+    let name = name.to_ascii_lower();
+    let host = host.to_ascii_lower();
+    if name == host {
+        return true
+    }
+    if !name.as_slice().starts_with("*.") || from_str::<IpAddr>(host.as_slice()).is_some() {
+        return false
+    }
+    match host.as_slice().find('.') {
+        Some(dot) if dot > 0 => host.as_slice().slice_from(dot) == name.as_slice().slice_from(1),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches_host_name;
+
+    #[test]
+    fn test_matches_host_name() {
+        assert!(matches_host_name("servo.org", "servo.org"));
+        assert!(matches_host_name("Servo.ORG", "servo.org"));
+        assert!(matches_host_name("*.servo.org", "www.servo.org"));
+        assert!(!matches_host_name("*.servo.org", "servo.org"));
+        assert!(!matches_host_name("*.servo.org", "a.www.servo.org"));
+        assert!(!matches_host_name("*.0.0.1", "127.0.0.1"));
+        assert!(!matches_host_name("servo.org", "evil.example"));
+    }
+}
@@ -0,0 +1,891 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A client for the [WebSocket protocol](http://tools.ietf.org/html/rfc6455).
+//!
+//! Connections are started by the resource task when it receives `ConnectWebSocket`. Once the
+//! opening handshake is done, one task reads frames from the server and reports whole messages
+//! to the consumer, while another writes the messages sent by the consumer along with the
+//! control frames the reader answers with. A TLS stream can't be shared between those tasks,
+//! so for `wss` URLs a third task owns it and passes data to and from the other two.
+
+use cookie::HTTP;
+use resource_task::{ResourceTask, SetCookieForUrl};
+use tls;
+
+use openssl::crypto::hash::{hash, SHA1};
+use openssl::ssl::SslStream;
+use serialize::base64::{ToBase64, STANDARD};
+use servo_util::task::spawn_named;
+use std::ascii::StrAsciiExt;
+use std::comm::{Select, Empty, Disconnected};
+use std::io::{ChanReader, ChanWriter, IoError, IoResult, InvalidInput, OtherIoError, TimedOut};
+use std::io::TcpStream;
+use std::rand;
+use url::Url;
+
+/// Appended to the key sent by the client to compute the value the server must accept it with.
+static ACCEPT_GUID: &'static str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The largest message accepted from the server, in bytes.
+static MAX_MESSAGE_SIZE: uint = 64 * 1024 * 1024;
+
+/// The largest response to the opening handshake, in bytes.
+static MAX_HANDSHAKE_SIZE: uint = 16 * 1024;
+
+/// How long the task that owns a TLS stream waits for data from the server before it checks
+/// for data to send.
+static TLS_POLL_MS: u64 = 20;
+
+// Status codes, from section 7.4.1.
+pub static NORMAL_CLOSURE: u16 = 1000;
+pub static GOING_AWAY: u16 = 1001;
+pub static PROTOCOL_ERROR: u16 = 1002;
+pub static NO_STATUS_RECEIVED: u16 = 1005;
+pub static ABNORMAL_CLOSURE: u16 = 1006;
+pub static INVALID_PAYLOAD: u16 = 1007;
+pub static MESSAGE_TOO_BIG: u16 = 1009;
+
+/// A connection to open, sent to the resource task with `ConnectWebSocket`.
+#[deriving(Clone)]
+pub struct WebSocketRequest {
+    pub url: Url,
+    /// The subprotocols offered to the server, in order of preference.
+    pub protocols: Vec<String>,
+    /// The serialized origin of the document or worker opening the connection.
+    pub origin: String,
+}
+
+/// The progress of a connection, as reported to its consumer.
+pub enum WebSocketEvent {
+    /// The opening handshake succeeded, with the given subprotocol and extensions.
+    WebSocketOpened(String, String),
+    WebSocketTextMessage(String),
+    WebSocketBinaryMessage(Vec<u8>),
+    /// The connection was failed because of the given error. `WebSocketClosed` follows.
+    WebSocketFailed(String),
+    /// The connection is closed, cleanly or not, with the given status code and reason. This is
+    /// the last event of each connection.
+    WebSocketClosed(bool, u16, String),
+}
+
+/// The messages a consumer sends to its connection.
+pub enum WebSocketCommand {
+    SendText(String),
+    SendBinary(Vec<u8>),
+    /// Start the closing handshake, with an optional status code and a reason.
+    CloseConnection(Option<u16>, String),
+}
+
+#[deriving(Clone, PartialEq, Show)]
+pub enum Opcode {
+    ContinuationFrame,
+    TextFrame,
+    BinaryFrame,
+    CloseFrame,
+    PingFrame,
+    PongFrame,
+}
+
+impl Opcode {
+    fn from_u8(value: u8) -> Option<Opcode> {
+        match value {
+            0x0 => Some(ContinuationFrame),
+            0x1 => Some(TextFrame),
+            0x2 => Some(BinaryFrame),
+            0x8 => Some(CloseFrame),
+            0x9 => Some(PingFrame),
+            0xA => Some(PongFrame),
+            _ => None,
+        }
+    }
+
+    fn to_u8(&self) -> u8 {
+        match *self {
+            ContinuationFrame => 0x0,
+            TextFrame => 0x1,
+            BinaryFrame => 0x2,
+            CloseFrame => 0x8,
+            PingFrame => 0x9,
+            PongFrame => 0xA,
+        }
+    }
+
+    fn is_control(&self) -> bool {
+        match *self {
+            CloseFrame | PingFrame | PongFrame => true,
+            ContinuationFrame | TextFrame | BinaryFrame => false,
+        }
+    }
+}
+
+#[deriving(Clone, PartialEq, Show)]
+pub struct Frame {
+    /// Whether this is the last frame of its message.
+    pub fin: bool,
+    pub opcode: Opcode,
+    /// The unmasked payload.
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(opcode: Opcode, payload: Vec<u8>) -> Frame {
+        Frame {
+            fin: true,
+            opcode: opcode,
+            payload: payload,
+        }
+    }
+
+    /// A close frame with the given status code and reason. The reason is only sent along with
+    /// a code.
+    pub fn close(code: Option<u16>, reason: &str) -> Frame {
+        let payload = match code {
+            Some(code) => {
+                let mut payload = vec!((code >> 8) as u8, code as u8);
+                payload.push_all(reason.as_bytes());
+                payload
+            }
+            None => vec!(),
+        };
+        Frame::new(CloseFrame, payload)
+    }
+}
+
+fn protocol_error(desc: &'static str) -> IoError {
+    IoError {
+        kind: InvalidInput,
+        desc: desc,
+        detail: None,
+    }
+}
+
+/// Write `frame`, masking its payload with `mask` if given. Clients must mask every frame they
+/// send, and servers must not.
+pub fn write_frame(writer: &mut Writer, frame: &Frame, mask: Option<[u8, ..4]>) -> IoResult<()> {
+    let fin = if frame.fin { 0x80 } else { 0 };
+    try!(writer.write_u8(fin | frame.opcode.to_u8()));
+
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    let len = frame.payload.len();
+    if len < 126 {
+        try!(writer.write_u8(mask_bit | len as u8));
+    } else if len <= 0xFFFF {
+        try!(writer.write_u8(mask_bit | 126));
+        try!(writer.write_be_u16(len as u16));
+    } else {
+        try!(writer.write_u8(mask_bit | 127));
+        try!(writer.write_be_u64(len as u64));
+    }
+
+    match mask {
+        Some(mask) => {
+            try!(writer.write(mask.as_slice()));
+            let masked: Vec<u8> = frame.payload.iter().enumerate().map(|(i, &byte)| {
+                byte ^ mask[i % 4]
+            }).collect();
+            writer.write(masked.as_slice())
+        }
+        None => writer.write(frame.payload.as_slice()),
+    }
+}
+
+/// Read a frame, failing with an `InvalidInput` error if it breaks the protocol. `masked` says
+/// whether the peer must mask its frames, which is the case when reading as a server.
+pub fn read_frame(reader: &mut Reader, masked: bool) -> IoResult<Frame> {
+    let first = try!(reader.read_u8());
+    let second = try!(reader.read_u8());
+
+    // No extensions are negotiated, so the reserved bits must be clear.
+    if first & 0x70 != 0 {
+        return Err(protocol_error("reserved bits set"))
+    }
+    let opcode = match Opcode::from_u8(first & 0x0F) {
+        Some(opcode) => opcode,
+        None => return Err(protocol_error("unknown opcode")),
+    };
+    let fin = first & 0x80 != 0;
+    if (second & 0x80 != 0) != masked {
+        return Err(protocol_error("unexpected masking"))
+    }
+
+    let len = match second & 0x7F {
+        126 => try!(reader.read_be_u16()) as u64,
+        127 => try!(reader.read_be_u64()),
+        len => len as u64,
+    };
+    if opcode.is_control() && (len > 125 || !fin) {
+        return Err(protocol_error("invalid control frame"))
+    }
+    if len > MAX_MESSAGE_SIZE as u64 {
+        return Err(IoError {
+            kind: OtherIoError,
+            desc: "message too big",
+            detail: None,
+        })
+    }
+
+    let mask = if masked { Some(try!(reader.read_exact(4))) } else { None };
+    let mut payload = try!(reader.read_exact(len as uint));
+    match mask {
+        Some(mask) => {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask.as_slice()[i % 4];
+            }
+        }
+        None => {}
+    }
+    Ok(Frame {
+        fin: fin,
+        opcode: opcode,
+        payload: payload,
+    })
+}
+
+/// The value of `Sec-WebSocket-Accept` the server must answer the given key with.
+pub fn accept_value(key: &str) -> String {
+    let mut input = key.to_string();
+    input.push_str(ACCEPT_GUID);
+    hash(SHA1, input.as_bytes()).as_slice().to_base64(STANDARD)
+}
+
+/// The HTTP URL that shares cookies with a WebSocket URL.
+pub fn http_url(url: &Url) -> Url {
+    let mut url = url.clone();
+    url.scheme = match url.scheme.as_slice() {
+        "wss" => "https".to_string(),
+        _ => "http".to_string(),
+    };
+    url
+}
+
+fn random_mask() -> [u8, ..4] {
+    [rand::random(), rand::random(), rand::random(), rand::random()]
+}
+
+/// Whether a server may close the connection with `code`. Codes below 1000 are unused, codes
+/// up to 2999 are reserved for the protocol, and some of the protocol's codes only describe a
+/// connection locally.
+/// http://tools.ietf.org/html/rfc6455#section-7.4
+fn is_valid_close_code(code: u16) -> bool {
+    match code {
+        1005 | 1006 | 1015 => false,
+        1000..1014 => true,
+        3000..4999 => true,
+        _ => false,
+    }
+}
+
+/// A connection to a WebSocket server.
+enum Connection {
+    PlainConnection(TcpStream),
+    SecureConnection(SslStream<TcpStream>),
+}
+
+impl Reader for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        match *self {
+            PlainConnection(ref mut stream) => stream.read(buf),
+            SecureConnection(ref mut stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Writer for Connection {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        match *self {
+            PlainConnection(ref mut stream) => stream.write(buf),
+            SecureConnection(ref mut stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        match *self {
+            PlainConnection(ref mut stream) => stream.flush(),
+            SecureConnection(ref mut stream) => stream.flush(),
+        }
+    }
+}
+
+impl Connection {
+    /// Split the connection into the halves used by the reader and the writer task.
+    fn split(self) -> (Box<Reader + Send>, Box<Writer + Send>) {
+        match self {
+            PlainConnection(stream) => {
+                (box stream.clone() as Box<Reader + Send>,
+                 box PlainWriter(stream) as Box<Writer + Send>)
+            }
+            SecureConnection(stream) => {
+                let (incoming_chan, incoming_port) = channel();
+                let (outgoing_chan, outgoing_port) = channel();
+                spawn_named("WebSocketTLSTask", proc() {
+                    relay_tls(stream, incoming_chan, outgoing_port)
+                });
+                (box ChanReader::new(incoming_port) as Box<Reader + Send>,
+                 box ChanWriter::new(outgoing_chan) as Box<Writer + Send>)
+            }
+        }
+    }
+}
+
+/// The writing half of a plain connection, which shuts down the sending side of the connection
+/// once the writer task is done with it.
+struct PlainWriter(TcpStream);
+
+impl Writer for PlainWriter {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        let PlainWriter(ref mut stream) = *self;
+        stream.write(buf)
+    }
+}
+
+impl Drop for PlainWriter {
+    fn drop(&mut self) {
+        let PlainWriter(ref mut stream) = *self;
+        let _ = stream.close_write();
+    }
+}
+
+/// Own a TLS stream for the reader and writer tasks: send the data received from the server to
+/// `incoming`, and the data received from `outgoing` to the server, until the server closes
+/// the connection or the reader goes away.
+fn relay_tls(mut stream: SslStream<TcpStream>, incoming: Sender<Vec<u8>>,
+             outgoing: Receiver<Vec<u8>>) {
+    let mut writer_done = false;
+    let mut buf = [0u8, ..4096];
+    loop {
+        while !writer_done {
+            match outgoing.try_recv() {
+                Ok(data) => {
+                    if stream.write(data.as_slice()).and_then(|()| stream.flush()).is_err() {
+                        return
+                    }
+                }
+                Err(Empty) => break,
+                Err(Disconnected) => {
+                    writer_done = true;
+                    let _ = stream.get_inner().close_write();
+                }
+            }
+        }
+
+        stream.get_inner().set_read_timeout(Some(TLS_POLL_MS));
+        match stream.read(&mut buf) {
+            Ok(len) => {
+                if incoming.send_opt(buf.slice_to(len).to_vec()).is_err() {
+                    return
+                }
+            }
+            Err(ref e) if e.kind == TimedOut => {}
+            Err(_) => return,
+        }
+    }
+}
+
+/// The status code and the headers of the response to the opening handshake.
+struct HandshakeResponse {
+    status: Option<u16>,
+    headers: Vec<(String, String)>,
+}
+
+impl HandshakeResponse {
+    fn parse(response: &str) -> HandshakeResponse {
+        let mut lines = response.lines();
+        let status = lines.next().and_then(|line| line.split(' ').nth(1))
+                                 .and_then(|code| from_str::<u16>(code));
+        let headers = lines.filter_map(|line| {
+            line.find(':').map(|colon| {
+                (line.slice_to(colon).trim().to_string(), line.slice_from(colon + 1).trim().to_string())
+            })
+        }).collect();
+        HandshakeResponse {
+            status: status,
+            headers: headers,
+        }
+    }
+
+    fn header<'a>(&'a self, name: &str) -> Option<&'a str> {
+        self.headers.iter().find(|&&(ref header, _)| {
+            header.as_slice().eq_ignore_ascii_case(name)
+        }).map(|&(_, ref value)| value.as_slice())
+    }
+}
+
+/// Perform the opening handshake, returning the connection along with the subprotocol and the
+/// extensions chosen by the server.
+fn open(request: &WebSocketRequest, cookies: Option<String>, resource_task: &ResourceTask)
+        -> Result<(Connection, String, String), String> {
+    let url = &request.url;
+    let secure = match url.scheme.as_slice() {
+        "ws" => false,
+        "wss" => true,
+        scheme => return Err(format!("unsupported scheme {:s}", scheme)),
+    };
+    let host = match url.serialize_host() {
+        Some(host) => host,
+        None => return Err("no host".to_string()),
+    };
+    let port = url.port().unwrap_or(if secure { 443 } else { 80 });
+    let stream = match TcpStream::connect(host.as_slice(), port) {
+        Ok(stream) => stream,
+        Err(e) => return Err(format!("couldn't connect: {}", e)),
+    };
+    let mut stream = if secure {
+        let server_name = url.domain().map_or(host.clone(), |domain| domain.to_string());
+        match tls::connect(stream, server_name.as_slice()) {
+            Ok(stream) => SecureConnection(stream),
+            Err(e) => return Err(format!("couldn't make a secure connection: {}", e)),
+        }
+    } else {
+        PlainConnection(stream)
+    };
+
+    let key = Vec::from_fn(16, |_| rand::random::<u8>()).as_slice().to_base64(STANDARD);
+    let mut target = url.serialize_path().unwrap_or("/".to_string());
+    match url.query {
+        Some(ref query) => {
+            target.push_char('?');
+            target.push_str(query.as_slice());
+        }
+        None => {}
+    }
+    let authority = match url.port() {
+        Some(port) => format!("{:s}:{}", host, port),
+        None => host.clone(),
+    };
+    let mut handshake = format!("GET {:s} HTTP/1.1\r\n\
+                                 Host: {:s}\r\n\
+                                 Upgrade: websocket\r\n\
+                                 Connection: Upgrade\r\n\
+                                 Sec-WebSocket-Key: {:s}\r\n\
+                                 Sec-WebSocket-Version: 13\r\n\
+                                 Origin: {:s}\r\n",
+                                target, authority, key, request.origin);
+    if !request.protocols.is_empty() {
+        handshake.push_str(format!("Sec-WebSocket-Protocol: {:s}\r\n",
+                                   request.protocols.connect(", ")).as_slice());
+    }
+    match cookies {
+        Some(cookies) => handshake.push_str(format!("Cookie: {:s}\r\n", cookies).as_slice()),
+        None => {}
+    }
+    handshake.push_str("\r\n");
+    match stream.write(handshake.as_bytes()) {
+        Ok(()) => {}
+        Err(e) => return Err(format!("couldn't send the handshake: {}", e)),
+    }
+
+    // Read the response a byte at a time, so that no frame sent after it is consumed.
+    let mut response = vec!();
+    while !response.as_slice().ends_with(b"\r\n\r\n") {
+        if response.len() > MAX_HANDSHAKE_SIZE {
+            return Err("handshake response too long".to_string())
+        }
+        match stream.read_byte() {
+            Ok(byte) => response.push(byte),
+            Err(e) => return Err(format!("couldn't read the handshake response: {}", e)),
+        }
+    }
+    let response = HandshakeResponse::parse(String::from_utf8_lossy(response.as_slice()).as_slice());
+
+    if response.status != Some(101) {
+        return Err(format!("unexpected status {}", response.status))
+    }
+    if !response.header("Upgrade").map_or(false, |value| value.eq_ignore_ascii_case("websocket")) {
+        return Err("missing Upgrade header".to_string())
+    }
+    let upgrade = response.header("Connection").map_or(false, |value| {
+        value.split(',').any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+    });
+    if !upgrade {
+        return Err("missing Connection header".to_string())
+    }
+    if response.header("Sec-WebSocket-Accept") != Some(accept_value(key.as_slice()).as_slice()) {
+        return Err("wrong Sec-WebSocket-Accept header".to_string())
+    }
+    // No extensions are offered, so the server can't use any.
+    if response.header("Sec-WebSocket-Extensions").is_some() {
+        return Err("unexpected extensions".to_string())
+    }
+    let protocol = match response.header("Sec-WebSocket-Protocol") {
+        Some(protocol) if request.protocols.iter().any(|p| p.as_slice() == protocol) => {
+            protocol.to_string()
+        }
+        Some(protocol) => return Err(format!("unexpected subprotocol {:s}", protocol)),
+        None => String::new(),
+    };
+
+    for &(ref name, ref value) in response.headers.iter() {
+        if name.as_slice().eq_ignore_ascii_case("Set-Cookie") {
+            resource_task.send(SetCookieForUrl(http_url(url), value.clone(), HTTP));
+        }
+    }
+    Ok((stream, protocol, String::new()))
+}
+
+/// Start a task that opens the connection described by `request`, reporting its progress to
+/// `events` and acting on the commands received from `commands`.
+pub fn connect(request: WebSocketRequest,
+               cookies: Option<String>,
+               events: Sender<WebSocketEvent>,
+               commands: Receiver<WebSocketCommand>,
+               resource_task: ResourceTask) {
+    spawn_named("WebSocketTask", proc() {
+        let (stream, protocol, extensions) = match open(&request, cookies, &resource_task) {
+            Ok(connection) => connection,
+            Err(e) => {
+                debug!("websocket: couldn't connect to {:s}: {:s}", request.url.serialize(), e);
+                let _ = events.send_opt(WebSocketFailed(e));
+                let _ = events.send_opt(WebSocketClosed(false, ABNORMAL_CLOSURE, String::new()));
+                return
+            }
+        };
+        let _ = events.send_opt(WebSocketOpened(protocol, extensions));
+
+        let (reply_chan, reply_port) = channel();
+        let (reader, writer) = stream.split();
+        spawn_named("WebSocketReader", proc() {
+            read_messages(reader, events, reply_chan)
+        });
+        write_frames(writer, commands, reply_port);
+    });
+}
+
+/// Read frames until the connection is closed, reporting whole messages to `events` and sending
+/// the frames to answer with to `replies`.
+fn read_messages(mut stream: Box<Reader + Send>,
+                 events: Sender<WebSocketEvent>,
+                 replies: Sender<Frame>) {
+    // Fail the connection: tell the server why, and report the error.
+    let fail = |code: u16, error: String| {
+        let _ = replies.send_opt(Frame::close(Some(code), ""));
+        let _ = events.send_opt(WebSocketFailed(error));
+        let _ = events.send_opt(WebSocketClosed(false, ABNORMAL_CLOSURE, String::new()));
+    };
+
+    // The type and the data received so far of a fragmented message.
+    let mut message: Option<(Opcode, Vec<u8>)> = None;
+    loop {
+        let frame = match read_frame(&mut *stream, false) {
+            Ok(frame) => frame,
+            Err(ref e) if e.kind == InvalidInput => {
+                return fail(PROTOCOL_ERROR, e.desc.to_string())
+            }
+            Err(ref e) if e.kind == OtherIoError => {
+                return fail(MESSAGE_TOO_BIG, e.desc.to_string())
+            }
+            Err(_) => {
+                // The connection was dropped without a closing handshake.
+                let _ = events.send_opt(WebSocketClosed(false, ABNORMAL_CLOSURE, String::new()));
+                return
+            }
+        };
+
+        let (opcode, data) = match (frame.opcode, message.take()) {
+            (PingFrame, pending) => {
+                message = pending;
+                let _ = replies.send_opt(Frame::new(PongFrame, frame.payload));
+                continue
+            }
+            (PongFrame, pending) => {
+                message = pending;
+                continue
+            }
+            (CloseFrame, _) => {
+                let (code, reason) = match frame.payload.as_slice() {
+                    [] => (NO_STATUS_RECEIVED, String::new()),
+                    [_] => return fail(PROTOCOL_ERROR, "invalid close frame".to_string()),
+                    payload => {
+                        let code = (payload[0] as u16 << 8) | payload[1] as u16;
+                        if !is_valid_close_code(code) {
+                            return fail(PROTOCOL_ERROR, format!("invalid close code {}", code))
+                        }
+                        match String::from_utf8(payload.slice_from(2).to_vec()) {
+                            Ok(reason) => (code, reason),
+                            Err(_) => {
+                                return fail(INVALID_PAYLOAD, "invalid close reason".to_string())
+                            }
+                        }
+                    }
+                };
+                // Answer with the same code, unless this is the answer to our own close frame,
+                // in which case the writer drops it.
+                let echoed_code = if code == NO_STATUS_RECEIVED { None } else { Some(code) };
+                let _ = replies.send_opt(Frame::close(echoed_code, ""));
+                let _ = events.send_opt(WebSocketClosed(true, code, reason));
+                return
+            }
+            (TextFrame, None) | (BinaryFrame, None) => (frame.opcode, frame.payload),
+            (ContinuationFrame, Some((opcode, mut data))) => {
+                if data.len() + frame.payload.len() > MAX_MESSAGE_SIZE {
+                    return fail(MESSAGE_TOO_BIG, "message too big".to_string())
+                }
+                data.push_all(frame.payload.as_slice());
+                (opcode, data)
+            }
+            (ContinuationFrame, None) => {
+                return fail(PROTOCOL_ERROR, "unexpected continuation frame".to_string())
+            }
+            (_, Some(_)) => {
+                return fail(PROTOCOL_ERROR, "expected a continuation frame".to_string())
+            }
+        };
+
+        if !frame.fin {
+            message = Some((opcode, data));
+            continue
+        }
+        let event = match opcode {
+            TextFrame => match String::from_utf8(data) {
+                Ok(text) => WebSocketTextMessage(text),
+                Err(_) => return fail(INVALID_PAYLOAD, "invalid UTF-8 in a text message".to_string()),
+            },
+            _ => WebSocketBinaryMessage(data),
+        };
+        if events.send_opt(event).is_err() {
+            // Nobody is listening anymore; the writer closes the connection.
+            return
+        }
+    }
+}
+
+/// Write the messages sent by the consumer and the replies of the reader until the reader is
+/// done. Dropping `stream` then tells the server that nothing more will be sent.
+fn write_frames(mut stream: Box<Writer + Send>,
+                commands: Receiver<WebSocketCommand>,
+                replies: Receiver<Frame>) {
+    let mut close_sent = false;
+    let mut consumer_gone = false;
+    loop {
+        let frame = if consumer_gone {
+            match replies.recv_opt() {
+                Ok(frame) => frame,
+                Err(()) => break,
+            }
+        } else {
+            let select = Select::new();
+            let mut command_handle = select.handle(&commands);
+            let mut reply_handle = select.handle(&replies);
+            unsafe {
+                command_handle.add();
+                reply_handle.add();
+            }
+            let id = select.wait();
+            if id == command_handle.id() {
+                match command_handle.recv_opt() {
+                    Ok(SendText(text)) => Frame::new(TextFrame, text.into_bytes()),
+                    Ok(SendBinary(data)) => Frame::new(BinaryFrame, data),
+                    Ok(CloseConnection(code, reason)) => Frame::close(code, reason.as_slice()),
+                    Err(()) => {
+                        // The consumer went away without closing the connection.
+                        consumer_gone = true;
+                        Frame::close(Some(GOING_AWAY), "")
+                    }
+                }
+            } else {
+                match reply_handle.recv_opt() {
+                    Ok(frame) => frame,
+                    Err(()) => break,
+                }
+            }
+        };
+
+        // Nothing may follow a close frame.
+        if close_sent {
+            continue
+        }
+        if write_frame(&mut *stream, &frame, Some(random_mask())).is_err() {
+            break
+        }
+        if frame.opcode == CloseFrame {
+            close_sent = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Frame, TextFrame, BinaryFrame, CloseFrame, PingFrame};
+    use super::{WebSocketRequest, WebSocketOpened, WebSocketTextMessage, WebSocketBinaryMessage};
+    use super::{WebSocketClosed, WebSocketFailed, SendText, SendBinary, CloseConnection};
+    use super::{accept_value, connect, is_valid_close_code, read_frame, write_frame};
+
+    use std::io::{Acceptor, Listener, MemReader, MemWriter};
+    use std::io::net::tcp::{TcpListener, TcpStream};
+    use std::str;
+    use url::Url;
+
+    #[test]
+    fn test_accept_value() {
+        // The example from section 1.3.
+        assert_eq!(accept_value("dGhlIHNhbXBsZSBub25jZQ=="),
+                   "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=".to_string());
+    }
+
+    #[test]
+    fn test_frame_round_trip() {
+        let frames = vec!(Frame::new(TextFrame, b"hello".to_vec()),
+                          Frame::new(BinaryFrame, Vec::from_elem(70000, 7u8)),
+                          Frame::new(PingFrame, vec!()),
+                          Frame::close(Some(1000), "bye"));
+        for frame in frames.into_iter() {
+            for &masked in [false, true].iter() {
+                let mask = if masked { Some([1, 2, 3, 4]) } else { None };
+                let mut writer = MemWriter::new();
+                write_frame(&mut writer, &frame, mask).unwrap();
+                let mut reader = MemReader::new(writer.unwrap());
+                assert_eq!(read_frame(&mut reader, masked).unwrap(), frame);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unmasked_client_frame() {
+        let mut writer = MemWriter::new();
+        write_frame(&mut writer, &Frame::new(TextFrame, b"hello".to_vec()), None).unwrap();
+        assert!(read_frame(&mut MemReader::new(writer.unwrap()), true).is_err());
+    }
+
+    /// Answer the opening handshake on `stream`, then echo each data frame until the client
+    /// closes the connection.
+    fn echo(mut stream: TcpStream) {
+        let mut request = vec!();
+        while !request.as_slice().ends_with(b"\r\n\r\n") {
+            request.push(stream.read_byte().unwrap());
+        }
+        let request = str::from_utf8(request.as_slice()).unwrap().to_string();
+        let key = request.as_slice().lines().find(|line| {
+            line.starts_with("Sec-WebSocket-Key:")
+        }).unwrap().slice_from("Sec-WebSocket-Key:".len()).trim().to_string();
+        let response = format!("HTTP/1.1 101 Switching Protocols\r\n\
+                                Upgrade: websocket\r\n\
+                                Connection: Upgrade\r\n\
+                                Sec-WebSocket-Accept: {:s}\r\n\
+                                Sec-WebSocket-Protocol: echo\r\n\r\n",
+                               accept_value(key.as_slice()));
+        stream.write(response.as_bytes()).unwrap();
+
+        loop {
+            let frame = read_frame(&mut stream, true).unwrap();
+            write_frame(&mut stream, &frame, None).unwrap();
+            if frame.opcode == CloseFrame {
+                return
+            }
+        }
+    }
+
+    fn start_echo_server() -> Url {
+        let listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+        let port = listener.socket_name().unwrap().port;
+        let mut acceptor = listener.listen().unwrap();
+        spawn(proc() {
+            echo(acceptor.accept().unwrap());
+        });
+        Url::parse(format!("ws://127.0.0.1:{}/echo", port).as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_echo() {
+        let url = start_echo_server();
+        let (resource_task, _resource_port) = channel();
+        let (events_chan, events) = channel();
+        let (commands, commands_port) = channel();
+        let request = WebSocketRequest {
+            url: url,
+            protocols: vec!("chat".to_string(), "echo".to_string()),
+            origin: "http://127.0.0.1".to_string(),
+        };
+        connect(request, None, events_chan, commands_port, resource_task);
+
+        match events.recv() {
+            WebSocketOpened(protocol, _) => assert_eq!(protocol, "echo".to_string()),
+            _ => fail!("expected the connection to open"),
+        }
+        commands.send(SendText("hello".to_string()));
+        match events.recv() {
+            WebSocketTextMessage(text) => assert_eq!(text, "hello".to_string()),
+            _ => fail!("expected a text message"),
+        }
+        commands.send(SendBinary(vec!(1, 2, 3)));
+        match events.recv() {
+            WebSocketBinaryMessage(data) => assert_eq!(data, vec!(1, 2, 3)),
+            _ => fail!("expected a binary message"),
+        }
+        commands.send(CloseConnection(Some(1000), "bye".to_string()));
+        match events.recv() {
+            WebSocketClosed(clean, code, reason) => {
+                assert!(clean);
+                assert_eq!(code, 1000);
+                assert_eq!(reason, "bye".to_string());
+            }
+            _ => fail!("expected the connection to close"),
+        }
+    }
+
+    #[test]
+    fn test_close_codes() {
+        assert!(is_valid_close_code(1000));
+        assert!(is_valid_close_code(1011));
+        assert!(is_valid_close_code(3000));
+        assert!(is_valid_close_code(4999));
+        for &code in [0u16, 999, 1005, 1006, 1015, 1016, 2999, 5000].iter() {
+            assert!(!is_valid_close_code(code));
+        }
+
+        // A close frame from the server with a code it may not send fails the connection.
+        let url = start_echo_server();
+        let (resource_task, _resource_port) = channel();
+        let (events_chan, events) = channel();
+        let (commands, commands_port) = channel();
+        let request = WebSocketRequest {
+            url: url,
+            protocols: vec!("echo".to_string()),
+            origin: "http://127.0.0.1".to_string(),
+        };
+        connect(request, None, events_chan, commands_port, resource_task);
+        match events.recv() {
+            WebSocketOpened(..) => {}
+            _ => fail!("expected the connection to open"),
+        }
+        commands.send(CloseConnection(Some(1005), String::new()));
+        match events.recv() {
+            WebSocketFailed(_) => {}
+            _ => fail!("expected the connection to fail"),
+        }
+        match events.recv() {
+            WebSocketClosed(clean, code, _) => {
+                assert!(!clean);
+                assert_eq!(code, 1006);
+            }
+            _ => fail!("expected the connection to close"),
+        }
+    }
+
+    #[test]
+    fn test_connection_refused() {
+        // Nothing listens on the port once the listener is dropped.
+        let port = TcpListener::bind("127.0.0.1", 0).unwrap().socket_name().unwrap().port;
+        let url = Url::parse(format!("ws://127.0.0.1:{}/", port).as_slice()).unwrap();
+        let (resource_task, _resource_port) = channel();
+        let (events_chan, events) = channel();
+        let (_commands, commands_port) = channel();
+        let request = WebSocketRequest {
+            url: url,
+            protocols: vec!(),
+            origin: "http://127.0.0.1".to_string(),
+        };
+        connect(request, None, events_chan, commands_port, resource_task);
+        match events.recv() {
+            WebSocketFailed(_) => {}
+            _ => fail!("expected the connection to fail"),
+        }
+        match events.recv() {
+            WebSocketClosed(clean, code, _) => {
+                assert!(!clean);
+                assert_eq!(code, 1006);
+            }
+            _ => fail!("expected the connection to close"),
+        }
+    }
+}
@@ -579,7 +579,41 @@ def getJSToNativeConversionTemplate(type, descriptorProvider, failureCode=None,
         raise TypeError("Can't handle array arguments yet")
 
     if type.isSequence():
-        raise TypeError("Can't handle sequence arguments yet")
+        innerType = type.inner
+        if (innerType.isGeckoInterface() or innerType.isDictionary() or
+            innerType.isSequence() or innerType.isCallback()):
+            raise TypeError("Can't handle sequences of %s yet" % innerType)
+
+        (_, _, innerDeclType, _) = getJSToNativeConversionTemplate(
+            innerType, descriptorProvider, isMember="Sequence")
+        declType = CGWrapper(innerDeclType, pre="Vec<", post=">")
+        if type.nullable():
+            declType = CGWrapper(declType, pre="Option<", post=" >")
+
+        notSequence = ("throw_type_error(cx, \"%s is not a sequence.\");\n"
+                       "%s" % (firstCap(sourceDescription), exceptionCode))
+        templateBody = ("match FromJSValConvertible::from_jsval(cx, ${val}, ()) {\n"
+                        "    Ok(value) => value,\n"
+                        "    Err(()) => {\n"
+                        "%s\n"
+                        "    },\n"
+                        "}" % CGIndenter(CGGeneric(notSequence), 8).define())
+
+        # The elements are only kept alive by the array, so unions of DOM objects are
+        # converted from a copy that script can't change, kept in the argument's slot.
+        if innerType.isUnion() and any(t.isGeckoInterface() for t in innerType.flatMemberTypes):
+            if isMember:
+                raise TypeError("Can't handle sequences of %s outside of arguments yet" %
+                                innerType)
+            templateBody = ("{\n"
+                            "    if copy_sequence_in_place(cx, &mut ${val}).is_err() {\n"
+                            "%s\n"
+                            "    }\n"
+                            "%s\n"
+                            "}" % (CGIndenter(CGGeneric(notSequence), 8).define(),
+                                   CGIndenter(CGGeneric(templateBody), 4).define()))
+
+        return handleOptional(templateBody, declType, handleDefaultNull("None"))
 
     if type.isUnion():
         declType = CGGeneric(union_native_type(type))
@@ -2513,7 +2547,13 @@ class CGStaticMethod(CGAbstractStaticBindingMethod):
     def generate_code(self):
         nativeName = CGSpecializedMethod.makeNativeName(self.descriptor,
                                                         self.method)
-        return CGMethodCall([], nativeName, True, self.descriptor, self.method)
+        preamble = CGGeneric("""\
+let global = global_object_for_js_object(JS_CALLEE(cx, vp).to_object());
+let global = global.root();
+""")
+        call = CGMethodCall(["&global.root_ref()"], nativeName, True, self.descriptor,
+                            self.method)
+        return CGList([preamble, call])
 
 
 class CGGenericGetter(CGAbstractBindingMethod):
@@ -4542,6 +4582,7 @@ class CGBindingRoot(CGThing):
             'dom::bindings::callback::{CallSetup,ExceptionHandling}',
             'dom::bindings::callback::{WrapCallThisObject}',
             'dom::bindings::conversions::{FromJSValConvertible, ToJSValConvertible}',
+            'dom::bindings::conversions::copy_sequence_in_place',
             'dom::bindings::conversions::IDLInterface',
             'dom::bindings::conversions::{Default, Empty}',
             'dom::bindings::codegen::{PrototypeList, RegisterBindings, UnionTypes}',
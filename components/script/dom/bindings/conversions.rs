@@ -18,12 +18,15 @@ use js::jsapi::{JS_ValueToUint16, JS_ValueToNumber, JS_ValueToBoolean};
 use js::jsapi::{JS_ValueToString, JS_GetStringCharsAndLength};
 use js::jsapi::{JS_NewUCStringCopyN, JS_NewStringCopyN};
 use js::jsapi::{JS_WrapValue};
+use js::jsapi::{JS_IsArrayObject, JS_GetArrayLength, JS_GetElement, JS_SetElement};
+use js::jsapi::{JS_NewArrayObject, JS_AddObjectRoot, JS_RemoveObjectRoot};
 use js::jsval::JSVal;
 use js::jsval::{UndefinedValue, NullValue, BooleanValue, Int32Value, UInt32Value};
 use js::jsval::{StringValue, ObjectValue, ObjectOrNullValue};
 use js::glue::RUST_JS_NumberValue;
 use libc;
 use std::default;
+use std::ptr;
 use std::slice;
 
 use dom::bindings::codegen::PrototypeList;
@@ -367,6 +370,69 @@ impl<X: default::Default, T: FromJSValConvertible<X>> FromJSValConvertible<()> f
     }
 }
 
+/// Converts a JS array to a sequence, converting each of its elements in turn.
+impl<X: default::Default, T: FromJSValConvertible<X>> FromJSValConvertible<()> for Vec<T> {
+    fn from_jsval(cx: *mut JSContext, value: JSVal, _: ()) -> Result<Vec<T>, ()> {
+        if !value.is_object() {
+            return Err(());
+        }
+        let obj = value.to_object();
+        let mut length = 0u32;
+        unsafe {
+            if JS_IsArrayObject(cx, obj) == 0 || JS_GetArrayLength(cx, obj, &mut length) == 0 {
+                return Err(());
+            }
+        }
+        let mut result = Vec::with_capacity(length as uint);
+        for index in range(0, length) {
+            let mut element = UndefinedValue();
+            if unsafe { JS_GetElement(cx, obj, index, &mut element) } == 0 {
+                return Err(());
+            }
+            let option: X = default::Default::default();
+            result.push(try!(FromJSValConvertible::from_jsval(cx, element, option)));
+        }
+        Ok(result)
+    }
+}
+
+/// Replace the array in `value` with a copy of it, so that the elements of the copy stay
+/// alive while they are used, even if script changes the original array. Only the
+/// conversions of sequences that contain DOM objects need this. `value` must be rooted, such
+/// as an argument of the call.
+pub fn copy_sequence_in_place(cx: *mut JSContext, value: &mut JSVal) -> Result<(), ()> {
+    if !value.is_object() {
+        return Err(());
+    }
+    let obj = value.to_object();
+    let mut length = 0u32;
+    unsafe {
+        if JS_IsArrayObject(cx, obj) == 0 || JS_GetArrayLength(cx, obj, &mut length) == 0 {
+            return Err(());
+        }
+        let mut copy = JS_NewArrayObject(cx, 0, ptr::null_mut());
+        if copy.is_null() {
+            return Err(());
+        }
+        // Getters on the original array can run script, and hence the GC.
+        JS_AddObjectRoot(cx, &mut copy);
+        let mut result = Ok(());
+        for index in range(0, length) {
+            let mut element = UndefinedValue();
+            if JS_GetElement(cx, obj, index, &mut element) == 0 ||
+               JS_SetElement(cx, copy, index, &mut element) == 0 {
+                result = Err(());
+                break;
+            }
+        }
+        if result.is_ok() {
+            *value = ObjectValue(&*copy);
+        }
+        JS_RemoveObjectRoot(cx, &mut copy);
+        result
+    }
+}
+
 impl ToJSValConvertible for *mut JSObject {
     fn to_jsval(&self, cx: *mut JSContext) -> JSVal {
         let mut wrapped = ObjectOrNullValue(*self);
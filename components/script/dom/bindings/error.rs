@@ -26,6 +26,7 @@ pub enum Error {
     NotFound,
     HierarchyRequest,
     InvalidCharacter,
+    NoModificationAllowed,
     NotSupported,
     InvalidState,
     Syntax,
@@ -47,7 +47,9 @@
 
 use dom::bindings::utils::{Reflector, Reflectable};
 use dom::node::Node;
+use dom::filereader::{FileReader, TrustedFileReaderAddress};
 use dom::xmlhttprequest::{XMLHttpRequest, TrustedXHRAddress};
+use dom::websocket::{WebSocket, TrustedWebSocketAddress};
 use dom::worker::{Worker, TrustedWorkerAddress};
 use js::jsapi::JSObject;
 use layout_interface::TrustedNodeAddress;
@@ -157,6 +159,24 @@ impl JS<Worker> {
     }
 }
 
+impl JS<WebSocket> {
+    pub unsafe fn from_trusted_websocket_address(inner: TrustedWebSocketAddress) -> JS<WebSocket> {
+        let TrustedWebSocketAddress(addr) = inner;
+        JS {
+            ptr: addr as *const WebSocket
+        }
+    }
+}
+
+impl JS<FileReader> {
+    pub unsafe fn from_trusted_filereader_address(inner: TrustedFileReaderAddress) -> JS<FileReader> {
+        let TrustedFileReaderAddress(addr) = inner;
+        JS {
+            ptr: addr as *const FileReader
+        }
+    }
+}
+
 impl<T: Reflectable> JS<T> {
     /// Create a new JS-owned value wrapped from a raw Rust pointer.
     pub unsafe fn from_raw(raw: *const T) -> JS<T> {
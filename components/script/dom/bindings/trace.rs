@@ -32,15 +32,19 @@ use js::jsval::JSVal;
 
 use libc;
 use std::rc::Rc;
+use std::sync::Arc;
 use std::cell::{Cell, RefCell};
 
 use url::Url;
 use msg::constellation_msg::{PipelineId, SubpageId, WindowSizeData};
+use net::csp::ContentSecurityPolicy;
 use net::image_cache_task::ImageCacheTask;
+use net::referrer_policy::ReferrerPolicy;
 use script_traits::ScriptControlChan;
 use std::collections::hashmap::HashMap;
 use collections::hash::Hash;
-use style::PropertyDeclarationBlock;
+use style::{PropertyDeclarationBlock, PseudoElement, DeclarationBlockSource};
+use style::MediaQueryList as MediaQueries;
 use std::comm::{Receiver, Sender};
 use hubbub::hubbub::QuirksMode;
 use string_cache::{Atom, Namespace};
@@ -53,7 +57,7 @@ use script_traits::UntrustedNodeAddress;
 use servo_msg::compositor_msg::ScriptListener;
 use servo_msg::constellation_msg::ConstellationChan;
 use servo_util::smallvec::{SmallVec1, SmallVec};
-use layout_interface::{LayoutRPC, LayoutChan};
+use layout_interface::{LayoutRPC, LayoutChan, StylesheetId};
 use dom::bindings::utils::WindowProxyHandler;
 
 impl<T: Reflectable> JSTraceable for JS<T> {
@@ -193,9 +197,11 @@ untraceable!(uint, u8, u16, u32, u64)
 untraceable!(int, i8, i16, i32, i64)
 untraceable!(Sender<T>)
 untraceable!(Receiver<T>)
+untraceable!(Arc<T>)
 untraceable!(ImageCacheTask, ScriptControlChan)
 untraceable!(Atom, Namespace, Timer)
-untraceable!(PropertyDeclarationBlock)
+untraceable!(PropertyDeclarationBlock, PseudoElement, DeclarationBlockSource)
+untraceable!(MediaQueries)
 // These three are interdependent, if you plan to put jsmanaged data
 // in one of these make sure it is propagated properly to containing structs
 untraceable!(SubpageId, WindowSizeData, PipelineId)
@@ -203,9 +209,11 @@ untraceable!(QuirksMode)
 untraceable!(Cx)
 untraceable!(ResponseHeaderCollection, RequestHeaderCollection, Method)
 untraceable!(ConstellationChan)
-untraceable!(LayoutChan)
+untraceable!(LayoutChan, StylesheetId)
 untraceable!(WindowProxyHandler)
 untraceable!(UntrustedNodeAddress)
+untraceable!(ContentSecurityPolicy)
+untraceable!(ReferrerPolicy)
 
 impl<'a> JSTraceable for &'a str {
     #[inline]
@@ -3,11 +3,20 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use dom::bindings::codegen::InheritTypes::FileDerived;
-use dom::bindings::global::GlobalRef;
-use dom::bindings::js::Temporary;
+use dom::bindings::codegen::Bindings::BlobBinding;
+use dom::bindings::codegen::Bindings::BlobBinding::{BlobMethods, BlobPropertyBag};
+use dom::bindings::codegen::UnionTypes::BlobOrString::{BlobOrString, eBlob, eString};
+use dom::bindings::global::{GlobalField, GlobalRef};
+use dom::bindings::js::{JSRef, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
 use dom::bindings::error::Fallible;
-use dom::bindings::codegen::Bindings::BlobBinding;
+
+use servo_net::blob_loader::BlobData;
+use servo_util::str::DOMString;
+
+use std::ascii::StrAsciiExt;
+use std::cmp::{min, max};
+use std::sync::Arc;
 
 #[jstraceable]
 pub enum BlobType {
@@ -19,25 +28,95 @@ pub enum BlobType {
 #[must_root]
 pub struct Blob {
     reflector_: Reflector,
-    type_: BlobType
+    type_: BlobType,
+    global: GlobalField,
+    bytes: Arc<Vec<u8>>,
+    type_string: DOMString,
+}
+
+/// A `type` given to a blob is dropped if it isn't printable ASCII, and lowercased otherwise.
+fn normalize_type(type_string: &str) -> DOMString {
+    if type_string.chars().any(|c| c < ' ' || c > '~') {
+        "".to_string()
+    } else {
+        type_string.to_ascii_lower()
+    }
 }
 
 impl Blob {
-    pub fn new_inherited() -> Blob {
+    pub fn new_inherited(global: &GlobalRef, type_: BlobType, bytes: Arc<Vec<u8>>,
+                         type_string: &str) -> Blob {
         Blob {
             reflector_: Reflector::new(),
-            type_: BlobTypeId
+            type_: type_,
+            global: GlobalField::from_rooted(global),
+            bytes: bytes,
+            type_string: normalize_type(type_string),
         }
     }
 
-    pub fn new(global: &GlobalRef) -> Temporary<Blob> {
-        reflect_dom_object(box Blob::new_inherited(),
+    pub fn new(global: &GlobalRef, bytes: Vec<u8>, type_string: &str) -> Temporary<Blob> {
+        reflect_dom_object(box Blob::new_inherited(global, BlobTypeId, Arc::new(bytes), type_string),
                            global,
                            BlobBinding::Wrap)
     }
 
-    pub fn Constructor(global: &GlobalRef) -> Fallible<Temporary<Blob>> {
-        Ok(Blob::new(global))
+    // http://dev.w3.org/2006/webapi/FileAPI/#constructorBlob
+    pub fn Constructor(global: &GlobalRef, blob_parts: Option<Vec<BlobOrString>>,
+                       options: &BlobPropertyBag) -> Fallible<Temporary<Blob>> {
+        let mut bytes = vec!();
+        for part in blob_parts.unwrap_or(vec!()).iter() {
+            match *part {
+                eString(ref string) => bytes.push_all(string.as_bytes()),
+                eBlob(ref blob) => bytes.push_all(blob.root().bytes.as_slice()),
+            }
+        }
+        Ok(Blob::new(global, bytes, options.type_.as_slice()))
+    }
+}
+
+pub trait BlobHelpers {
+    /// The contents of this blob, to be shared with the resource task or read out.
+    fn data(self) -> BlobData;
+}
+
+impl<'a> BlobHelpers for JSRef<'a, Blob> {
+    fn data(self) -> BlobData {
+        BlobData {
+            bytes: self.bytes.clone(),
+            content_type: self.type_string.clone(),
+        }
+    }
+}
+
+impl<'a> BlobMethods for JSRef<'a, Blob> {
+    fn Size(self) -> u64 {
+        self.bytes.len() as u64
+    }
+
+    fn Type(self) -> DOMString {
+        self.type_string.clone()
+    }
+
+    // http://dev.w3.org/2006/webapi/FileAPI/#dfn-slice
+    fn Slice(self, start: Option<i64>, end: Option<i64>,
+             content_type: Option<DOMString>) -> Temporary<Blob> {
+        let size = self.bytes.len() as i64;
+        let relative = |position: i64| {
+            if position < 0 {
+                max(size + position, 0)
+            } else {
+                min(position, size)
+            }
+        };
+        let relative_start = relative(start.unwrap_or(0));
+        let relative_end = relative(end.unwrap_or(size));
+        let span = max(relative_end - relative_start, 0);
+        let bytes = self.bytes.as_slice().slice(relative_start as uint,
+                                               (relative_start + span) as uint);
+        let content_type = content_type.unwrap_or("".to_string());
+        let global = self.global.root();
+        Blob::new(&global.root_ref(), bytes.to_vec(), content_type.as_slice())
     }
 }
 
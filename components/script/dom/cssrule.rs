@@ -0,0 +1,134 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::CSSRuleBinding;
+use dom::bindings::codegen::Bindings::CSSRuleBinding::{CSSRuleConstants, CSSRuleMethods};
+use dom::bindings::codegen::InheritTypes::{CSSRuleCast, CSSStyleRuleCast};
+use dom::bindings::global;
+use dom::bindings::js::{JSRef, MutNullableJS, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::cssstylerule::{CSSStyleRule, CSSStyleRuleHelpers};
+use dom::cssstylesheet::{CSSStyleSheet, CSSStyleSheetHelpers};
+use dom::window::Window;
+use servo_util::str::DOMString;
+use style::{RuleSource, StyleRuleSource, AtRuleSource};
+
+use std::ascii::StrAsciiExt;
+
+#[jstraceable]
+pub enum CSSRuleTypeId {
+    CSSStyleRuleTypeId,
+    /// Rules other than style rules are only exposed as their text, as written.
+    CSSAtRuleTypeId(DOMString),
+}
+
+// http://dev.w3.org/csswg/cssom/#the-cssrule-interface
+#[jstraceable]
+#[must_root]
+pub struct CSSRule {
+    reflector_: Reflector,
+    pub type_id: CSSRuleTypeId,
+    parent_stylesheet: MutNullableJS<CSSStyleSheet>,
+}
+
+impl CSSRule {
+    pub fn new_inherited(type_id: CSSRuleTypeId, parent_stylesheet: JSRef<CSSStyleSheet>)
+                         -> CSSRule {
+        CSSRule {
+            reflector_: Reflector::new(),
+            type_id: type_id,
+            parent_stylesheet: MutNullableJS::new(Some(parent_stylesheet)),
+        }
+    }
+
+    fn new(window: JSRef<Window>, type_id: CSSRuleTypeId,
+           parent_stylesheet: JSRef<CSSStyleSheet>) -> Temporary<CSSRule> {
+        reflect_dom_object(box CSSRule::new_inherited(type_id, parent_stylesheet),
+                           &global::Window(window),
+                           CSSRuleBinding::Wrap)
+    }
+
+    /// Creates the object for a rule of `parent_stylesheet`.
+    pub fn new_from_source(window: JSRef<Window>, parent_stylesheet: JSRef<CSSStyleSheet>,
+                           source: RuleSource) -> Temporary<CSSRule> {
+        match source {
+            StyleRuleSource(selectors, declarations) => {
+                let rule = CSSStyleRule::new(window, parent_stylesheet, selectors, declarations);
+                CSSRuleCast::from_temporary(rule)
+            }
+            AtRuleSource(text) => CSSRule::new(window, CSSAtRuleTypeId(text), parent_stylesheet),
+        }
+    }
+}
+
+pub trait CSSRuleHelpers {
+    fn source(self) -> RuleSource;
+    fn set_parent_stylesheet(self, parent_stylesheet: Option<JSRef<CSSStyleSheet>>);
+    fn changed(self);
+}
+
+impl<'a> CSSRuleHelpers for JSRef<'a, CSSRule> {
+    fn source(self) -> RuleSource {
+        match self.type_id {
+            CSSStyleRuleTypeId => {
+                let rule: JSRef<CSSStyleRule> = CSSStyleRuleCast::to_ref(self).unwrap();
+                StyleRuleSource(rule.selectors(), rule.declarations())
+            }
+            CSSAtRuleTypeId(ref text) => AtRuleSource(text.clone()),
+        }
+    }
+
+    fn set_parent_stylesheet(self, parent_stylesheet: Option<JSRef<CSSStyleSheet>>) {
+        self.parent_stylesheet.assign(parent_stylesheet);
+    }
+
+    /// Updates the stylesheet of this rule after script changed it. Rules that were deleted
+    /// from their stylesheet no longer apply.
+    fn changed(self) {
+        match self.parent_stylesheet.get() {
+            Some(sheet) => sheet.root().changed(),
+            None => {}
+        }
+    }
+}
+
+impl<'a> CSSRuleMethods for JSRef<'a, CSSRule> {
+    fn Type(self) -> u16 {
+        match self.type_id {
+            CSSStyleRuleTypeId => CSSRuleConstants::STYLE_RULE,
+            CSSAtRuleTypeId(ref text) => {
+                let name: String = text.as_slice().slice_from(1).chars().take_while(|&c| {
+                    c.is_alphanumeric() || c == '-'
+                }).collect();
+                match name.as_slice().to_ascii_lower().as_slice() {
+                    "import" => CSSRuleConstants::IMPORT_RULE,
+                    "media" => CSSRuleConstants::MEDIA_RULE,
+                    "font-face" => CSSRuleConstants::FONT_FACE_RULE,
+                    "page" => CSSRuleConstants::PAGE_RULE,
+                    "namespace" => CSSRuleConstants::NAMESPACE_RULE,
+                    _ => 0,
+                }
+            }
+        }
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssrule-csstext
+    fn CssText(self) -> DOMString {
+        self.source().serialize()
+    }
+
+    fn SetCssText(self, _value: DOMString) {
+        // Setting cssText does nothing.
+    }
+
+    fn GetParentStyleSheet(self) -> Option<Temporary<CSSStyleSheet>> {
+        self.parent_stylesheet.get()
+    }
+}
+
+impl Reflectable for CSSRule {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
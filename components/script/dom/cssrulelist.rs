@@ -0,0 +1,62 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::CSSRuleListBinding;
+use dom::bindings::codegen::Bindings::CSSRuleListBinding::CSSRuleListMethods;
+use dom::bindings::global;
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::cssrule::CSSRule;
+use dom::cssstylesheet::{CSSStyleSheet, CSSStyleSheetHelpers};
+use dom::window::Window;
+
+// http://dev.w3.org/csswg/cssom/#the-cssrulelist-interface
+#[jstraceable]
+#[must_root]
+pub struct CSSRuleList {
+    reflector_: Reflector,
+    /// The stylesheet whose rules this lists, as they change.
+    sheet: JS<CSSStyleSheet>,
+}
+
+impl CSSRuleList {
+    fn new_inherited(sheet: JSRef<CSSStyleSheet>) -> CSSRuleList {
+        CSSRuleList {
+            reflector_: Reflector::new(),
+            sheet: JS::from_rooted(sheet),
+        }
+    }
+
+    pub fn new(window: JSRef<Window>, sheet: JSRef<CSSStyleSheet>) -> Temporary<CSSRuleList> {
+        reflect_dom_object(box CSSRuleList::new_inherited(sheet),
+                           &global::Window(window), CSSRuleListBinding::Wrap)
+    }
+}
+
+impl<'a> CSSRuleListMethods for JSRef<'a, CSSRuleList> {
+    fn Length(self) -> u32 {
+        self.sheet.root().rules().len() as u32
+    }
+
+    fn Item(self, index: u32) -> Option<Temporary<CSSRule>> {
+        let rules = self.sheet.root().rules();
+        if index < rules.len() as u32 {
+            Some(Temporary::new(rules[index as uint].clone()))
+        } else {
+            None
+        }
+    }
+
+    fn IndexedGetter(self, index: u32, found: &mut bool) -> Option<Temporary<CSSRule>> {
+        let rule = self.Item(index);
+        *found = rule.is_some();
+        rule
+    }
+}
+
+impl Reflectable for CSSRuleList {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
@@ -0,0 +1,266 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::CSSStyleDeclarationBinding;
+use dom::bindings::codegen::Bindings::CSSRuleBinding::CSSRuleMethods;
+use dom::bindings::codegen::Bindings::CSSStyleDeclarationBinding::CSSStyleDeclarationMethods;
+use dom::bindings::codegen::InheritTypes::{CSSRuleCast, NodeCast};
+use dom::bindings::error::{ErrorResult, Fallible, NoModificationAllowed};
+use dom::bindings::global;
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::cssrule::CSSRule;
+use dom::cssstylerule::{CSSStyleRule, CSSStyleRuleHelpers};
+use dom::cssstylesheet::CSSStyleSheetHelpers;
+use dom::element::{Element, AttributeHandlers};
+use dom::node::{Node, NodeHelpers};
+use dom::window::Window;
+use servo_util::str::DOMString;
+use style::{COMPUTED_LONGHANDS, DeclarationBlockSource, PseudoElement};
+
+use std::ascii::StrAsciiExt;
+use url::Url;
+
+/// What a `CSSStyleDeclaration` reads its declarations from and writes them to.
+#[jstraceable]
+#[must_root]
+pub enum CSSStyleOwner {
+    /// The `style` attribute of an element.
+    InlineStyleOwner(JS<Element>),
+    /// The computed values of an element or of one of its pseudo-elements, which are read-only.
+    ComputedStyleOwner(JS<Element>, Option<PseudoElement>),
+    /// The declarations of a style rule.
+    RuleStyleOwner(JS<CSSStyleRule>),
+}
+
+// http://dev.w3.org/csswg/cssom/#the-cssstyledeclaration-interface
+#[jstraceable]
+#[must_root]
+pub struct CSSStyleDeclaration {
+    reflector_: Reflector,
+    window: JS<Window>,
+    owner: CSSStyleOwner,
+}
+
+/// The priorities `setProperty()` accepts.
+fn parse_priority(priority: &str) -> Option<bool> {
+    match priority.to_ascii_lower().as_slice() {
+        "" => Some(false),
+        "important" => Some(true),
+        _ => None,
+    }
+}
+
+impl CSSStyleDeclaration {
+    fn new_inherited(window: JSRef<Window>, owner: CSSStyleOwner) -> CSSStyleDeclaration {
+        CSSStyleDeclaration {
+            reflector_: Reflector::new(),
+            window: JS::from_rooted(window),
+            owner: owner,
+        }
+    }
+
+    pub fn new(window: JSRef<Window>, owner: CSSStyleOwner) -> Temporary<CSSStyleDeclaration> {
+        reflect_dom_object(box CSSStyleDeclaration::new_inherited(window, owner),
+                           &global::Window(window),
+                           CSSStyleDeclarationBinding::Wrap)
+    }
+}
+
+trait PrivateCSSStyleDeclarationHelpers {
+    fn base_url(self) -> Url;
+    fn declarations(self) -> DeclarationBlockSource;
+    fn set_declarations(self, block: DeclarationBlockSource);
+    fn check_writable(self) -> ErrorResult;
+}
+
+impl<'a> PrivateCSSStyleDeclarationHelpers for JSRef<'a, CSSStyleDeclaration> {
+    /// The URL that relative URLs in the declarations resolve against: that of the stylesheet
+    /// of a rule, or else that of the document.
+    fn base_url(self) -> Url {
+        let sheet = match self.owner {
+            RuleStyleOwner(ref rule) => {
+                let rule = rule.root();
+                let cssrule: JSRef<CSSRule> = CSSRuleCast::from_ref(*rule);
+                cssrule.GetParentStyleSheet()
+            }
+            InlineStyleOwner(_) | ComputedStyleOwner(..) => None,
+        };
+        match sheet {
+            Some(sheet) => sheet.root().base_url(),
+            None => self.window.root().page().get_url(),
+        }
+    }
+
+    fn declarations(self) -> DeclarationBlockSource {
+        match self.owner {
+            InlineStyleOwner(ref element) => {
+                let element = element.root();
+                let css = element.get_string_attribute("style");
+                DeclarationBlockSource::parse(css.as_slice(), &self.base_url())
+            }
+            ComputedStyleOwner(..) => DeclarationBlockSource::new(),
+            RuleStyleOwner(ref rule) => rule.root().declarations(),
+        }
+    }
+
+    fn set_declarations(self, block: DeclarationBlockSource) {
+        match self.owner {
+            InlineStyleOwner(ref element) => {
+                // Setting the attribute reparses it, and restyles the element.
+                element.root().set_string_attribute("style", block.serialize());
+            }
+            ComputedStyleOwner(..) => unreachable!(),
+            RuleStyleOwner(ref rule) => rule.root().set_declarations(block),
+        }
+    }
+
+    fn check_writable(self) -> ErrorResult {
+        match self.owner {
+            ComputedStyleOwner(..) => Err(NoModificationAllowed),
+            InlineStyleOwner(_) | RuleStyleOwner(_) => Ok(()),
+        }
+    }
+}
+
+macro_rules! css_properties(
+    ( $( [$getter:ident, $setter:ident, $cssprop:expr] )* ) => (
+        $(
+            fn $getter(self) -> Fallible<DOMString> {
+                Ok(self.GetPropertyValue($cssprop.to_string()))
+            }
+
+            fn $setter(self, value: DOMString) -> ErrorResult {
+                self.SetProperty($cssprop.to_string(), value, "".to_string())
+            }
+        )*
+    );
+)
+
+impl<'a> CSSStyleDeclarationMethods for JSRef<'a, CSSStyleDeclaration> {
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-csstext
+    fn CssText(self) -> DOMString {
+        self.declarations().serialize()
+    }
+
+    fn SetCssText(self, value: DOMString) -> ErrorResult {
+        try!(self.check_writable());
+        let block = DeclarationBlockSource::parse(value.as_slice(), &self.base_url());
+        self.set_declarations(block);
+        Ok(())
+    }
+
+    fn Length(self) -> u32 {
+        match self.owner {
+            ComputedStyleOwner(..) => COMPUTED_LONGHANDS.len() as u32,
+            InlineStyleOwner(_) | RuleStyleOwner(_) => self.declarations().len() as u32,
+        }
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-item
+    fn Item(self, index: u32) -> DOMString {
+        match self.owner {
+            // The computed style has a declaration for each longhand it can serialize.
+            ComputedStyleOwner(..) => {
+                if index < COMPUTED_LONGHANDS.len() as u32 {
+                    COMPUTED_LONGHANDS[index as uint].to_string()
+                } else {
+                    "".to_string()
+                }
+            }
+            InlineStyleOwner(_) | RuleStyleOwner(_) => {
+                let block = self.declarations();
+                if index < block.len() as u32 {
+                    block.declarations[index as uint].name.clone()
+                } else {
+                    "".to_string()
+                }
+            }
+        }
+    }
+
+    fn IndexedGetter(self, index: u32, found: &mut bool) -> DOMString {
+        *found = index < self.Length();
+        self.Item(index)
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-getpropertyvalue
+    fn GetPropertyValue(self, property: DOMString) -> DOMString {
+        let value = match self.owner {
+            ComputedStyleOwner(ref element, ref pseudo_element) => {
+                let element = element.root();
+                let node: JSRef<Node> = NodeCast::from_ref(*element);
+                node.get_computed_style(pseudo_element.clone(), property.as_slice())
+            }
+            InlineStyleOwner(_) | RuleStyleOwner(_) => {
+                self.declarations().value(property.as_slice())
+            }
+        };
+        value.unwrap_or("".to_string())
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-getpropertypriority
+    fn GetPropertyPriority(self, property: DOMString) -> DOMString {
+        if self.declarations().important(property.as_slice()) {
+            "important".to_string()
+        } else {
+            "".to_string()
+        }
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-setproperty
+    fn SetProperty(self, property: DOMString, value: DOMString, priority: DOMString)
+                   -> ErrorResult {
+        try!(self.check_writable());
+        if value.is_empty() {
+            return self.RemoveProperty(property).map(|_| ())
+        }
+        let important = match parse_priority(priority.as_slice()) {
+            Some(important) => important,
+            None => return Ok(()),
+        };
+        let mut block = self.declarations();
+        if block.set(property.as_slice(), value.as_slice(), important, &self.base_url()) {
+            self.set_declarations(block);
+        }
+        Ok(())
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-setpropertyvalue
+    fn SetPropertyValue(self, property: DOMString, value: DOMString) -> ErrorResult {
+        let priority = self.GetPropertyPriority(property.clone());
+        self.SetProperty(property, value, priority)
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-setpropertypriority
+    fn SetPropertyPriority(self, property: DOMString, priority: DOMString) -> ErrorResult {
+        try!(self.check_writable());
+        let value = match self.declarations().value(property.as_slice()) {
+            Some(value) => value,
+            None => return Ok(()),
+        };
+        self.SetProperty(property, value, priority)
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-removeproperty
+    fn RemoveProperty(self, property: DOMString) -> Fallible<DOMString> {
+        try!(self.check_writable());
+        let mut block = self.declarations();
+        let length = block.len();
+        // Removing a shorthand that wasn't declared may still remove its longhands.
+        let value = block.remove(property.as_slice());
+        if block.len() != length {
+            self.set_declarations(block);
+        }
+        Ok(value.unwrap_or("".to_string()))
+    }
+
+    css_properties_accessors!(css_properties)
+}
+
+impl Reflectable for CSSStyleDeclaration {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
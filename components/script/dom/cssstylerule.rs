@@ -0,0 +1,127 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::CSSRuleBinding::CSSRuleMethods;
+use dom::bindings::codegen::Bindings::CSSStyleRuleBinding;
+use dom::bindings::codegen::Bindings::CSSStyleRuleBinding::CSSStyleRuleMethods;
+use dom::bindings::codegen::InheritTypes::{CSSRuleCast, CSSStyleRuleDerived};
+use dom::bindings::global;
+use dom::bindings::js::{JS, JSRef, MutNullableJS, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::cssrule::{CSSRule, CSSRuleHelpers, CSSStyleRuleTypeId};
+use dom::cssstyledeclaration::{CSSStyleDeclaration, RuleStyleOwner};
+use dom::cssstylesheet::{CSSStyleSheet, CSSStyleSheetHelpers};
+use dom::window::Window;
+use servo_util::str::DOMString;
+use style::{DeclarationBlockSource, parse_selector_source};
+
+use std::cell::RefCell;
+use std::default::Default;
+
+// http://dev.w3.org/csswg/cssom/#the-cssstylerule-interface
+#[jstraceable]
+#[must_root]
+pub struct CSSStyleRule {
+    pub cssrule: CSSRule,
+    window: JS<Window>,
+    /// The selectors of the rule, as written.
+    selectors: RefCell<DOMString>,
+    declarations: RefCell<DeclarationBlockSource>,
+    style_decl: MutNullableJS<CSSStyleDeclaration>,
+}
+
+impl CSSStyleRuleDerived for CSSRule {
+    fn is_cssstylerule(&self) -> bool {
+        match self.type_id {
+            CSSStyleRuleTypeId => true,
+            _ => false,
+        }
+    }
+}
+
+impl CSSStyleRule {
+    fn new_inherited(window: JSRef<Window>, parent_stylesheet: JSRef<CSSStyleSheet>,
+                     selectors: DOMString, declarations: DeclarationBlockSource)
+                     -> CSSStyleRule {
+        CSSStyleRule {
+            cssrule: CSSRule::new_inherited(CSSStyleRuleTypeId, parent_stylesheet),
+            window: JS::from_rooted(window),
+            selectors: RefCell::new(selectors),
+            declarations: RefCell::new(declarations),
+            style_decl: Default::default(),
+        }
+    }
+
+    pub fn new(window: JSRef<Window>, parent_stylesheet: JSRef<CSSStyleSheet>,
+               selectors: DOMString, declarations: DeclarationBlockSource)
+               -> Temporary<CSSStyleRule> {
+        let rule = CSSStyleRule::new_inherited(window, parent_stylesheet, selectors,
+                                               declarations);
+        reflect_dom_object(box rule, &global::Window(window), CSSStyleRuleBinding::Wrap)
+    }
+}
+
+pub trait CSSStyleRuleHelpers {
+    fn selectors(self) -> DOMString;
+    fn declarations(self) -> DeclarationBlockSource;
+    fn set_declarations(self, declarations: DeclarationBlockSource);
+}
+
+impl<'a> CSSStyleRuleHelpers for JSRef<'a, CSSStyleRule> {
+    fn selectors(self) -> DOMString {
+        self.selectors.borrow().clone()
+    }
+
+    fn declarations(self) -> DeclarationBlockSource {
+        self.declarations.borrow().clone()
+    }
+
+    fn set_declarations(self, declarations: DeclarationBlockSource) {
+        *self.declarations.borrow_mut() = declarations;
+        let cssrule: JSRef<CSSRule> = CSSRuleCast::from_ref(self);
+        cssrule.changed();
+    }
+}
+
+impl<'a> CSSStyleRuleMethods for JSRef<'a, CSSStyleRule> {
+    fn SelectorText(self) -> DOMString {
+        self.selectors()
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstylerule-selectortext
+    fn SetSelectorText(self, value: DOMString) {
+        // The namespace prefixes the selectors may use are those of the stylesheet.
+        let cssrule: JSRef<CSSRule> = CSSRuleCast::from_ref(self);
+        let (rules, url) = match cssrule.GetParentStyleSheet() {
+            Some(sheet) => {
+                let sheet = sheet.root();
+                (sheet.rule_sources(), sheet.base_url())
+            }
+            None => (vec!(), self.window.root().page().get_url()),
+        };
+        match parse_selector_source(value.as_slice(), rules.as_slice(), &url) {
+            Ok(selectors) => {
+                *self.selectors.borrow_mut() = selectors;
+                cssrule.changed();
+            }
+            // Invalid selectors are ignored.
+            Err(()) => {}
+        }
+    }
+
+    fn Style(self) -> Temporary<CSSStyleDeclaration> {
+        if self.style_decl.get().is_none() {
+            let window = self.window.root();
+            let owner = RuleStyleOwner(JS::from_rooted(self));
+            self.style_decl.assign(Some(CSSStyleDeclaration::new(*window, owner)));
+        }
+        self.style_decl.get().unwrap()
+    }
+}
+
+impl Reflectable for CSSStyleRule {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        self.cssrule.reflector()
+    }
+}
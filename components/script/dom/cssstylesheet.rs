@@ -0,0 +1,158 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::CSSStyleSheetBinding;
+use dom::bindings::codegen::Bindings::CSSStyleSheetBinding::CSSStyleSheetMethods;
+use dom::bindings::codegen::InheritTypes::{CSSStyleSheetDerived, HTMLLinkElementCast};
+use dom::bindings::codegen::InheritTypes::HTMLStyleElementCast;
+use dom::bindings::error::{ErrorResult, Fallible, IndexSize, Syntax};
+use dom::bindings::global;
+use dom::bindings::js::{JS, JSRef, MutNullableJS, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::cssrule::{CSSRule, CSSRuleHelpers};
+use dom::cssrulelist::CSSRuleList;
+use dom::document::DocumentHelpers;
+use dom::element::Element;
+use dom::htmllinkelement::LinkElementHelpers;
+use dom::htmlstyleelement::StyleElementHelpers;
+use dom::node::document_from_node;
+use dom::stylesheet::StyleSheet;
+use dom::window::Window;
+use servo_util::str::DOMString;
+use style::{RuleSource, parse_rule_sources, parse_rule_source, serialize_rule_sources};
+use url::Url;
+
+use std::cell::RefCell;
+use std::default::Default;
+
+// http://dev.w3.org/csswg/cssom/#the-cssstylesheet-interface
+#[jstraceable]
+#[must_root]
+pub struct CSSStyleSheet {
+    pub stylesheet: StyleSheet,
+    window: JS<Window>,
+    /// The URL that the URLs in the stylesheet are relative to.
+    base_url: Url,
+    rules: RefCell<Vec<JS<CSSRule>>>,
+    rule_list: MutNullableJS<CSSRuleList>,
+}
+
+impl CSSStyleSheetDerived for StyleSheet {
+    fn is_cssstylesheet(&self) -> bool {
+        // CSS is the only style sheet language.
+        true
+    }
+}
+
+impl CSSStyleSheet {
+    fn new_inherited(window: JSRef<Window>, owner_node: JSRef<Element>, base_url: Url)
+                     -> CSSStyleSheet {
+        CSSStyleSheet {
+            stylesheet: StyleSheet::new_inherited(owner_node),
+            window: JS::from_rooted(window),
+            base_url: base_url,
+            rules: RefCell::new(vec!()),
+            rule_list: Default::default(),
+        }
+    }
+
+    /// Creates the stylesheet of `owner_node`, whose text is `css` and whose URLs are relative
+    /// to `base_url`.
+    pub fn new(window: JSRef<Window>, owner_node: JSRef<Element>, css: &str, base_url: Url)
+               -> Temporary<CSSStyleSheet> {
+        let sheet = reflect_dom_object(box CSSStyleSheet::new_inherited(window, owner_node,
+                                                                        base_url),
+                                       &global::Window(window),
+                                       CSSStyleSheetBinding::Wrap).root();
+        let rules = parse_rule_sources(css, &sheet.base_url).into_iter().map(|source| {
+            JS::from_rooted(CSSRule::new_from_source(window, *sheet, source))
+        }).collect();
+        *sheet.rules.borrow_mut() = rules;
+        Temporary::from_rooted(*sheet)
+    }
+}
+
+pub trait CSSStyleSheetHelpers {
+    fn base_url(self) -> Url;
+    fn rules(self) -> Vec<JS<CSSRule>>;
+    fn rule_sources(self) -> Vec<RuleSource>;
+    fn changed(self);
+}
+
+impl<'a> CSSStyleSheetHelpers for JSRef<'a, CSSStyleSheet> {
+    fn base_url(self) -> Url {
+        self.base_url.clone()
+    }
+
+    fn rules(self) -> Vec<JS<CSSRule>> {
+        self.rules.borrow().clone()
+    }
+
+    fn rule_sources(self) -> Vec<RuleSource> {
+        self.rules.borrow().iter().map(|rule| rule.root().source()).collect()
+    }
+
+    /// Hands the rules to layout again after script changed them, unless the stylesheet no
+    /// longer applies because its owner left the document or was given new text.
+    fn changed(self) {
+        let owner_node = self.stylesheet.owner_node().root();
+        let this = Some(Temporary::from_rooted(self));
+        let css = serialize_rule_sources(self.rule_sources().as_slice());
+        match (HTMLStyleElementCast::to_ref(*owner_node),
+               HTMLLinkElementCast::to_ref(*owner_node)) {
+            (Some(style), _) if style.sheet() == this => style.update_stylesheet(css.as_slice()),
+            (_, Some(link)) if link.sheet() == this => {
+                link.update_stylesheet(css.as_slice(), self.base_url.clone())
+            }
+            _ => return,
+        }
+        let document = document_from_node(*owner_node).root();
+        document.reflow();
+    }
+}
+
+impl<'a> CSSStyleSheetMethods for JSRef<'a, CSSStyleSheet> {
+    fn CssRules(self) -> Temporary<CSSRuleList> {
+        if self.rule_list.get().is_none() {
+            let window = self.window.root();
+            self.rule_list.assign(Some(CSSRuleList::new(*window, self)));
+        }
+        self.rule_list.get().unwrap()
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstylesheet-insertrule
+    fn InsertRule(self, rule: DOMString, index: u32) -> Fallible<u32> {
+        if index as uint > self.rules.borrow().len() {
+            return Err(IndexSize)
+        }
+        let window = self.window.root();
+        let source = match parse_rule_source(rule.as_slice(),
+                                             self.rule_sources().as_slice(),
+                                             &self.base_url) {
+            Ok(source) => source,
+            Err(()) => return Err(Syntax),
+        };
+        let rule = CSSRule::new_from_source(*window, self, source);
+        self.rules.borrow_mut().insert(index as uint, JS::from_rooted(rule));
+        self.changed();
+        Ok(index)
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstylesheet-deleterule
+    fn DeleteRule(self, index: u32) -> ErrorResult {
+        if index as uint >= self.rules.borrow().len() {
+            return Err(IndexSize)
+        }
+        let rule = self.rules.borrow_mut().remove(index as uint).unwrap();
+        rule.root().set_parent_stylesheet(None);
+        self.changed();
+        Ok(())
+    }
+}
+
+impl Reflectable for CSSStyleSheet {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        self.stylesheet.reflector()
+    }
+}
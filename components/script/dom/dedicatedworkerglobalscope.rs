@@ -13,13 +13,15 @@ use dom::bindings::utils::{Reflectable, Reflector};
 use dom::eventtarget::{EventTarget, EventTargetHelpers};
 use dom::eventtarget::WorkerGlobalScopeTypeId;
 use dom::messageevent::MessageEvent;
+use dom::filereader::FileReader;
+use dom::websocket::WebSocket;
 use dom::worker::{Worker, TrustedWorkerAddress};
 use dom::workerglobalscope::DedicatedGlobalScope;
 use dom::workerglobalscope::WorkerGlobalScope;
 use dom::xmlhttprequest::XMLHttpRequest;
 use script_task::{ScriptTask, ScriptChan};
 use script_task::{ScriptMsg, DOMMessage, XHRProgressMsg, WorkerRelease};
-use script_task::WorkerPostMessage;
+use script_task::{WorkerPostMessage, WebSocketEventMsg, FileReaderMsg};
 use script_task::StackRootTLS;
 
 use servo_net::resource_task::{ResourceTask, load_whole_resource};
@@ -141,6 +143,12 @@ impl DedicatedWorkerGlobalScope {
                     Ok(WorkerRelease(addr)) => {
                         Worker::handle_release(addr)
                     },
+                    Ok(WebSocketEventMsg(addr, event)) => {
+                        WebSocket::handle_event(addr, event)
+                    },
+                    Ok(FileReaderMsg(addr, result)) => {
+                        FileReader::handle_result(addr, result)
+                    },
                     Ok(_) => fail!("Unexpected message"),
                     Err(_) => break,
                 }
@@ -49,11 +49,18 @@ use dom::nodelist::NodeList;
 use dom::text::Text;
 use dom::processinginstruction::ProcessingInstruction;
 use dom::range::Range;
+use dom::stylesheetlist::StyleSheetList;
 use dom::treewalker::TreeWalker;
 use dom::uievent::UIEvent;
 use dom::window::{Window, WindowHelpers};
 use html::hubbub_html_parser::build_element_from_tag;
+use layout_interface::StylesheetId;
 use hubbub::hubbub::{QuirksMode, NoQuirks, LimitedQuirks, FullQuirks};
+use servo_net::cookie::NonHTTP;
+use servo_net::csp::ContentSecurityPolicy;
+use servo_net::fetch::request::Context;
+use servo_net::referrer_policy::ReferrerPolicy;
+use servo_net::resource_task::{GetCookiesForUrl, SetCookieForUrl};
 use servo_util::namespace;
 use servo_util::str::{DOMString, split_html_space_chars};
 
@@ -86,6 +93,12 @@ pub struct Document {
     pub encoding_name: RefCell<DOMString>,
     pub is_html_document: bool,
     url: Url,
+    /// The policies that restrict what the document can load and run.
+    content_security_policy: RefCell<ContentSecurityPolicy>,
+    /// The referrer sent with the request for the document, if any.
+    referrer: RefCell<Option<DOMString>>,
+    /// How much of the document's URL is sent with the requests it makes.
+    referrer_policy: Cell<Option<ReferrerPolicy>>,
     quirks_mode: Cell<QuirksMode>,
     images: MutNullableJS<HTMLCollection>,
     embeds: MutNullableJS<HTMLCollection>,
@@ -94,6 +107,9 @@ pub struct Document {
     scripts: MutNullableJS<HTMLCollection>,
     anchors: MutNullableJS<HTMLCollection>,
     applets: MutNullableJS<HTMLCollection>,
+    stylesheets: MutNullableJS<StyleSheetList>,
+    /// The id of the next stylesheet handed to layout by an element of the document.
+    next_stylesheet_id: Cell<uint>,
 }
 
 impl DocumentDerived for EventTarget {
@@ -171,6 +187,18 @@ pub trait DocumentHelpers<'a> {
     fn register_named_element(self, element: JSRef<Element>, id: Atom);
     fn load_anchor_href(self, href: DOMString);
     fn find_fragment_node(self, fragid: DOMString) -> Option<Temporary<Element>>;
+    fn content_security_policy(self) -> ContentSecurityPolicy;
+    fn set_content_security_policy(self, policy: ContentSecurityPolicy);
+    fn add_content_security_policy(self, header: &str, report_only: bool);
+    fn add_meta_content_security_policy(self, value: &str);
+    fn allows_load(self, context: Context, url: &Url) -> bool;
+    fn allows_inline(self, context: Context, nonce: Option<&str>, content: Option<&str>) -> bool;
+    fn allows_eval(self) -> bool;
+    fn allows_connection(self, url: &Url) -> bool;
+    fn set_referrer(self, referrer: DOMString);
+    fn referrer_policy(self) -> Option<ReferrerPolicy>;
+    fn set_referrer_policy(self, policy: Option<ReferrerPolicy>);
+    fn new_stylesheet_id(self) -> StylesheetId;
 }
 
 impl<'a> DocumentHelpers<'a> for JSRef<'a, Document> {
@@ -271,6 +299,82 @@ impl<'a> DocumentHelpers<'a> for JSRef<'a, Document> {
         window.load_url(href);
     }
 
+    /// A copy of the document's policies, for the tasks that load on its behalf.
+    fn content_security_policy(self) -> ContentSecurityPolicy {
+        self.content_security_policy.borrow().clone()
+    }
+
+    fn set_content_security_policy(self, policy: ContentSecurityPolicy) {
+        *self.content_security_policy.borrow_mut() = policy;
+    }
+
+    /// Add the policies of a `Content-Security-Policy` or
+    /// `Content-Security-Policy-Report-Only` header.
+    fn add_content_security_policy(self, header: &str, report_only: bool) {
+        self.content_security_policy.borrow_mut().add_header(header, report_only);
+    }
+
+    /// Add the policy of a `<meta http-equiv="Content-Security-Policy">` element.
+    fn add_meta_content_security_policy(self, value: &str) {
+        self.content_security_policy.borrow_mut().add_meta_policy(value);
+    }
+
+    /// Whether the document's policies allow loading `url` for `context`, reporting any
+    /// violations.
+    fn allows_load(self, context: Context, url: &Url) -> bool {
+        let result = self.content_security_policy.borrow().check_load(context, url);
+        let window = self.window.root();
+        window.page().report_csp_violations(result.violations);
+        result.allowed
+    }
+
+    /// Whether the document's policies allow running inline script or applying inline style,
+    /// reporting any violations.
+    fn allows_inline(self, context: Context, nonce: Option<&str>, content: Option<&str>) -> bool {
+        let result = self.content_security_policy.borrow().check_inline(context, nonce, content);
+        let window = self.window.root();
+        window.page().report_csp_violations(result.violations);
+        result.allowed
+    }
+
+    /// Whether the document's policies allow evaluating strings as code, reporting any
+    /// violations.
+    fn allows_eval(self) -> bool {
+        let result = self.content_security_policy.borrow().check_eval();
+        let window = self.window.root();
+        window.page().report_csp_violations(result.violations);
+        result.allowed
+    }
+
+    /// Whether the document's policies allow a WebSocket connection to `url`, reporting any
+    /// violations.
+    fn allows_connection(self, url: &Url) -> bool {
+        let result = self.content_security_policy.borrow().check_connect(url);
+        let window = self.window.root();
+        window.page().report_csp_violations(result.violations);
+        result.allowed
+    }
+
+    fn set_referrer(self, referrer: DOMString) {
+        *self.referrer.borrow_mut() = Some(referrer);
+    }
+
+    /// The policy from the document's `Referrer-Policy` header or its last valid
+    /// `<meta name=referrer>`, if any.
+    fn referrer_policy(self) -> Option<ReferrerPolicy> {
+        self.referrer_policy.get()
+    }
+
+    fn set_referrer_policy(self, policy: Option<ReferrerPolicy>) {
+        self.referrer_policy.set(policy);
+    }
+
+    fn new_stylesheet_id(self) -> StylesheetId {
+        let id = self.next_stylesheet_id.get();
+        self.next_stylesheet_id.set(id + 1);
+        StylesheetId(id)
+    }
+
     /// Attempt to find a named element in this page's document.
     /// https://html.spec.whatwg.org/multipage/#the-indicated-part-of-the-document
     fn find_fragment_node(self, fragid: DOMString) -> Option<Temporary<Element>> {
@@ -313,6 +417,9 @@ impl Document {
                 }
             },
             last_modified: RefCell::new(None),
+            content_security_policy: RefCell::new(ContentSecurityPolicy::new(url.clone())),
+            referrer: RefCell::new(None),
+            referrer_policy: Cell::new(None),
             url: url,
             // http://dom.spec.whatwg.org/#concept-document-quirks
             quirks_mode: Cell::new(NoQuirks),
@@ -326,6 +433,8 @@ impl Document {
             scripts: Default::default(),
             anchors: Default::default(),
             applets: Default::default(),
+            stylesheets: Default::default(),
+            next_stylesheet_id: Cell::new(0),
         }
     }
 
@@ -354,6 +463,7 @@ impl Reflectable for Document {
 trait PrivateDocumentHelpers {
     fn createNodeList(self, callback: |node: JSRef<Node>| -> bool) -> Temporary<NodeList>;
     fn get_html_element(self) -> Option<Temporary<HTMLHtmlElement>>;
+    fn is_cookie_averse(self) -> bool;
 }
 
 impl<'a> PrivateDocumentHelpers for JSRef<'a, Document> {
@@ -388,6 +498,14 @@ impl<'a> PrivateDocumentHelpers for JSRef<'a, Document> {
             _ => None,
         }
     }
+
+    /// http://www.whatwg.org/html/#cookie-averse-document-object
+    fn is_cookie_averse(self) -> bool {
+        match self.url().scheme.as_slice() {
+            "http" | "https" => false,
+            _ => true,
+        }
+    }
 }
 
 impl<'a> DocumentMethods for JSRef<'a, Document> {
@@ -612,6 +730,11 @@ impl<'a> DocumentMethods for JSRef<'a, Document> {
     }
 
     // http://www.whatwg.org/html/#dom-document-lastmodified
+    // https://html.spec.whatwg.org/multipage/#dom-document-referrer
+    fn Referrer(self) -> DOMString {
+        self.referrer.borrow().clone().unwrap_or(String::new())
+    }
+
     fn LastModified(self) -> DOMString {
         match *self.last_modified.borrow() {
             Some(ref t) => t.clone(),
@@ -619,6 +742,29 @@ impl<'a> DocumentMethods for JSRef<'a, Document> {
         }
     }
 
+    // http://www.whatwg.org/html/#dom-document-cookie
+    fn Cookie(self) -> DOMString {
+        if !self.is_cookie_averse() {
+            let window = self.window.root();
+            let (tx, rx) = channel();
+            window.page().resource_task.send(GetCookiesForUrl(self.url().clone(), tx, NonHTTP));
+            match rx.recv() {
+                Some(cookies) => return cookies,
+                None => {}
+            }
+        }
+        "".to_string()
+    }
+
+    // http://www.whatwg.org/html/#dom-document-cookie
+    fn SetCookie(self, cookie: DOMString) {
+        if self.is_cookie_averse() {
+            return
+        }
+        let window = self.window.root();
+        window.page().resource_task.send(SetCookieForUrl(self.url().clone(), cookie, NonHTTP));
+    }
+
     // http://dom.spec.whatwg.org/#dom-document-createrange
     fn CreateRange(self) -> Temporary<Range> {
         Range::new(self)
@@ -850,6 +996,14 @@ impl<'a> DocumentMethods for JSRef<'a, Document> {
         self.applets.get().unwrap()
     }
 
+    fn StyleSheets(self) -> Temporary<StyleSheetList> {
+        if self.stylesheets.get().is_none() {
+            let window = self.window.root();
+            self.stylesheets.assign(Some(StyleSheetList::new(*window, self)));
+        }
+        self.stylesheets.get().unwrap()
+    }
+
     fn Location(self) -> Temporary<Location> {
         let window = self.window.root();
         window.Location()
@@ -26,6 +26,7 @@ pub enum EventPhase {
 #[deriving(PartialEq)]
 #[jstraceable]
 pub enum EventTypeId {
+    CloseEventTypeId,
     CustomEventTypeId,
     HTMLEventTypeId,
     KeyEventTypeId,
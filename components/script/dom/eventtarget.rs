@@ -35,7 +35,9 @@ pub enum ListenerPhase {
 #[deriving(PartialEq)]
 #[jstraceable]
 pub enum EventTargetTypeId {
+    FileReaderTypeId,
     NodeTargetTypeId(NodeTypeId),
+    WebSocketTypeId,
     WindowTypeId,
     WorkerTypeId,
     WorkerGlobalScopeTypeId(WorkerGlobalScopeId),
@@ -7,7 +7,7 @@ use dom::bindings::codegen::Bindings::FileBinding::FileMethods;
 use dom::bindings::global::GlobalRef;
 use dom::bindings::js::{JSRef, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
-use dom::blob::{Blob, BlobType, FileTypeId};
+use dom::blob::{Blob, BlobHelpers, BlobType, FileTypeId};
 use servo_util::str::DOMString;
 
 #[jstraceable]
@@ -19,18 +19,18 @@ pub struct File {
 }
 
 impl File {
-    fn new_inherited(_file_bits: JSRef<Blob>, name: DOMString) -> File {
+    fn new_inherited(global: &GlobalRef, file_bits: JSRef<Blob>, name: DOMString) -> File {
+        let data = file_bits.data();
         File {
-            blob: Blob::new_inherited(),
+            blob: Blob::new_inherited(global, FileTypeId, data.bytes,
+                                      data.content_type.as_slice()),
             name: name,
             type_: FileTypeId
         }
-        // XXXManishearth Once Blob is able to store data
-        // the relevant subfields of file_bits should be copied over
     }
 
     pub fn new(global: &GlobalRef, file_bits: JSRef<Blob>, name: DOMString) -> Temporary<File> {
-        reflect_dom_object(box File::new_inherited(file_bits, name),
+        reflect_dom_object(box File::new_inherited(global, file_bits, name),
                            global,
                            FileBinding::Wrap)
     }
@@ -0,0 +1,310 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
+use dom::bindings::codegen::Bindings::FileReaderBinding;
+use dom::bindings::codegen::Bindings::FileReaderBinding::FileReaderMethods;
+use dom::bindings::codegen::InheritTypes::{EventCast, EventTargetCast};
+use dom::bindings::error::{ErrorResult, Fallible, InvalidState};
+use dom::bindings::global::{GlobalRef, GlobalField};
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::blob::{Blob, BlobHelpers};
+use dom::event::Event;
+use dom::eventtarget::{EventTarget, EventTargetHelpers, FileReaderTypeId};
+use dom::progressevent::ProgressEvent;
+use html::encoding::{extract_encoding_from_content, sniff_bom};
+use script_task::{ScriptChan, FileReaderMsg};
+
+use servo_net::blob_loader::BlobData;
+use servo_util::str::DOMString;
+use servo_util::task::spawn_named;
+
+use encoding::all::UTF_8;
+use encoding::label::encoding_from_whatwg_label;
+use encoding::types::{DecodeReplace, Encoding, EncodingRef};
+use js::jsapi::{JS_AddObjectRoot, JS_RemoveObjectRoot};
+use serialize::base64::{ToBase64, STANDARD};
+
+use libc::c_void;
+use std::cell::{Cell, RefCell};
+
+pub struct TrustedFileReaderAddress(pub *const c_void);
+untraceable!(TrustedFileReaderAddress)
+
+#[deriving(PartialEq)]
+#[jstraceable]
+enum FileReaderReadyState {
+    Empty = 0,
+    Loading = 1,
+    Done = 2,
+}
+
+/// The outcome of a read, computed off the script task.
+pub struct FileReaderResult {
+    /// The read this is the result of; reads that were aborted or superseded are dropped.
+    generation: uint,
+    /// The size of the blob that was read.
+    total: u64,
+    result: DOMString,
+}
+
+/// How the contents of a blob are turned into `result`.
+enum ReadFormat {
+    Text(Option<DOMString>),
+    DataURL,
+}
+
+#[jstraceable]
+#[must_root]
+pub struct FileReader {
+    eventtarget: EventTarget,
+    refcount: Cell<uint>,
+    global: GlobalField,
+    ready_state: Cell<FileReaderReadyState>,
+    result: RefCell<Option<DOMString>>,
+    /// Incremented by every read and abort, so that the results of earlier reads are ignored.
+    generation: Cell<uint>,
+}
+
+// http://dev.w3.org/2006/webapi/FileAPI/#enctype
+fn read_as_text(data: &BlobData, label: Option<DOMString>) -> DOMString {
+    let encoding = label.and_then(|label| encoding_from_whatwg_label(label.as_slice()))
+                        .or_else(|| extract_encoding_from_content(data.content_type.as_slice()))
+                        .unwrap_or(UTF_8 as EncodingRef);
+    let bytes = data.bytes.as_slice();
+    let (encoding, bytes) = match sniff_bom(bytes) {
+        Some((encoding, bom_len)) => (encoding, bytes.slice_from(bom_len)),
+        None => (encoding, bytes),
+    };
+    encoding.decode(bytes, DecodeReplace).unwrap()
+}
+
+// http://dev.w3.org/2006/webapi/FileAPI/#readAsDataURL
+fn read_as_data_url(data: &BlobData) -> DOMString {
+    let content_type = if data.content_type.is_empty() {
+        "application/octet-stream"
+    } else {
+        data.content_type.as_slice()
+    };
+    format!("data:{:s};base64,{:s}", content_type, data.bytes.as_slice().to_base64(STANDARD))
+}
+
+impl FileReader {
+    fn new_inherited(global: &GlobalRef) -> FileReader {
+        FileReader {
+            eventtarget: EventTarget::new_inherited(FileReaderTypeId),
+            refcount: Cell::new(0),
+            global: GlobalField::from_rooted(global),
+            ready_state: Cell::new(Empty),
+            result: RefCell::new(None),
+            generation: Cell::new(0),
+        }
+    }
+
+    pub fn new(global: &GlobalRef) -> Temporary<FileReader> {
+        reflect_dom_object(box FileReader::new_inherited(global),
+                           global,
+                           FileReaderBinding::Wrap)
+    }
+
+    pub fn Constructor(global: &GlobalRef) -> Fallible<Temporary<FileReader>> {
+        Ok(FileReader::new(global))
+    }
+
+    pub fn handle_result(address: TrustedFileReaderAddress, result: FileReaderResult) {
+        let reader = unsafe { JS::from_trusted_filereader_address(address).root() };
+        (*reader).loaded(result);
+        reader.release();
+    }
+
+    // Creates a trusted address to the object, and roots it. Always pair this with a release()
+    pub fn addref(&self) -> TrustedFileReaderAddress {
+        let refcount = self.refcount.get();
+        if refcount == 0 {
+            let cx = self.global.root().root_ref().get_cx();
+            unsafe {
+                JS_AddObjectRoot(cx, self.reflector().rootable());
+            }
+        }
+        self.refcount.set(refcount + 1);
+        TrustedFileReaderAddress(self as *const FileReader as *const c_void)
+    }
+
+    pub fn release(&self) {
+        let refcount = self.refcount.get();
+        assert!(refcount > 0)
+        self.refcount.set(refcount - 1);
+        if refcount == 1 {
+            let cx = self.global.root().root_ref().get_cx();
+            unsafe {
+                JS_RemoveObjectRoot(cx, self.reflector().rootable());
+            }
+        }
+    }
+}
+
+trait PrivateFileReaderHelpers {
+    fn read(self, blob: JSRef<Blob>, format: ReadFormat) -> ErrorResult;
+    fn loaded(self, result: FileReaderResult);
+    fn dispatch_progress_event(self, type_: &str, loaded: u64, total: u64);
+}
+
+impl<'a> PrivateFileReaderHelpers for JSRef<'a, FileReader> {
+    // http://dev.w3.org/2006/webapi/FileAPI/#readingOnThreads
+    fn read(self, blob: JSRef<Blob>, format: ReadFormat) -> ErrorResult {
+        if self.ready_state.get() == Loading {
+            return Err(InvalidState)
+        }
+        self.ready_state.set(Loading);
+        *self.result.borrow_mut() = None;
+        let generation = self.generation.get() + 1;
+        self.generation.set(generation);
+
+        // The reader is kept alive until the result of the read has been delivered.
+        let address = self.addref();
+        let data = blob.data();
+        let global = self.global.root();
+        let ScriptChan(script_chan) = global.root_ref().script_chan().clone();
+        spawn_named("FileReaderTask", proc() {
+            let result = match format {
+                Text(label) => read_as_text(&data, label),
+                DataURL => read_as_data_url(&data),
+            };
+            let result = FileReaderResult {
+                generation: generation,
+                total: data.bytes.len() as u64,
+                result: result,
+            };
+            let _ = script_chan.send_opt(FileReaderMsg(address, result));
+        });
+        Ok(())
+    }
+
+    fn loaded(self, result: FileReaderResult) {
+        if result.generation != self.generation.get() || self.ready_state.get() != Loading {
+            return
+        }
+        self.dispatch_progress_event("loadstart", 0, result.total);
+        self.dispatch_progress_event("progress", result.total, result.total);
+        // The handlers above may have aborted this read or started another.
+        if result.generation != self.generation.get() {
+            return
+        }
+        self.ready_state.set(Done);
+        *self.result.borrow_mut() = Some(result.result);
+        self.dispatch_progress_event("load", result.total, result.total);
+        if self.ready_state.get() == Done {
+            self.dispatch_progress_event("loadend", result.total, result.total);
+        }
+    }
+
+    fn dispatch_progress_event(self, type_: &str, loaded: u64, total: u64) {
+        let global = self.global.root();
+        let event = ProgressEvent::new(&global.root_ref(), type_.to_string(), false, false,
+                                       true, loaded, total).root();
+        let target: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        let event: JSRef<Event> = EventCast::from_ref(*event);
+        target.dispatch_event_with_target(None, event).ok();
+    }
+}
+
+impl<'a> FileReaderMethods for JSRef<'a, FileReader> {
+    fn ReadAsText(self, blob: JSRef<Blob>, label: Option<DOMString>) -> ErrorResult {
+        self.read(blob, Text(label))
+    }
+
+    fn ReadAsDataURL(self, blob: JSRef<Blob>) -> ErrorResult {
+        self.read(blob, DataURL)
+    }
+
+    // http://dev.w3.org/2006/webapi/FileAPI/#dfn-abort
+    fn Abort(self) {
+        if self.ready_state.get() != Loading {
+            *self.result.borrow_mut() = None;
+            return
+        }
+        self.ready_state.set(Done);
+        *self.result.borrow_mut() = None;
+        self.generation.set(self.generation.get() + 1);
+        self.dispatch_progress_event("abort", 0, 0);
+        if self.ready_state.get() == Done {
+            self.dispatch_progress_event("loadend", 0, 0);
+        }
+    }
+
+    fn ReadyState(self) -> u16 {
+        self.ready_state.get() as u16
+    }
+
+    fn GetResult(self) -> Option<DOMString> {
+        self.result.borrow().clone()
+    }
+
+    fn GetOnloadstart(self) -> Option<EventHandlerNonNull> {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.get_event_handler_common("loadstart")
+    }
+
+    fn SetOnloadstart(self, listener: Option<EventHandlerNonNull>) {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.set_event_handler_common("loadstart", listener)
+    }
+
+    fn GetOnprogress(self) -> Option<EventHandlerNonNull> {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.get_event_handler_common("progress")
+    }
+
+    fn SetOnprogress(self, listener: Option<EventHandlerNonNull>) {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.set_event_handler_common("progress", listener)
+    }
+
+    fn GetOnload(self) -> Option<EventHandlerNonNull> {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.get_event_handler_common("load")
+    }
+
+    fn SetOnload(self, listener: Option<EventHandlerNonNull>) {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.set_event_handler_common("load", listener)
+    }
+
+    fn GetOnabort(self) -> Option<EventHandlerNonNull> {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.get_event_handler_common("abort")
+    }
+
+    fn SetOnabort(self, listener: Option<EventHandlerNonNull>) {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.set_event_handler_common("abort", listener)
+    }
+
+    fn GetOnerror(self) -> Option<EventHandlerNonNull> {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.get_event_handler_common("error")
+    }
+
+    fn SetOnerror(self, listener: Option<EventHandlerNonNull>) {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.set_event_handler_common("error", listener)
+    }
+
+    fn GetOnloadend(self) -> Option<EventHandlerNonNull> {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.get_event_handler_common("loadend")
+    }
+
+    fn SetOnloadend(self, listener: Option<EventHandlerNonNull>) {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.set_event_handler_common("loadend", listener)
+    }
+}
+
+impl Reflectable for FileReader {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        self.eventtarget.reflector()
+    }
+}
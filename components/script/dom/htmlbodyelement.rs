@@ -10,13 +10,14 @@ use dom::bindings::codegen::InheritTypes::EventTargetCast;
 use dom::bindings::codegen::InheritTypes::{HTMLBodyElementDerived, HTMLElementCast};
 use dom::bindings::js::{JSRef, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector};
-use dom::document::Document;
+use dom::document::{Document, DocumentHelpers};
 use dom::element::HTMLBodyElementTypeId;
 use dom::eventtarget::{EventTarget, NodeTargetTypeId, EventTargetHelpers};
 use dom::htmlelement::HTMLElement;
-use dom::node::{Node, ElementNodeTypeId, window_from_node};
+use dom::node::{Node, ElementNodeTypeId, document_from_node, window_from_node};
 use dom::virtualmethods::VirtualMethods;
 
+use servo_net::fetch::request::Script;
 use servo_util::str::DOMString;
 use string_cache::Atom;
 
@@ -71,6 +72,11 @@ impl<'a> VirtualMethods for JSRef<'a, HTMLBodyElement> {
         }
 
         if name.as_slice().starts_with("on") {
+            // HTMLElement has already reported any violation of the Content Security Policy.
+            let document = document_from_node(*self).root();
+            if !document.content_security_policy().check_inline(Script, None, None).allowed {
+                return
+            }
             static forwarded_events: &'static [&'static str] =
                 &["onfocus", "onload", "onscroll", "onafterprint", "onbeforeprint",
                   "onbeforeunload", "onhashchange", "onlanguagechange", "onmessage",
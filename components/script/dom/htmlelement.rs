@@ -9,21 +9,26 @@ use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLFrameSetElementDerived};
 use dom::bindings::codegen::InheritTypes::EventTargetCast;
 use dom::bindings::codegen::InheritTypes::{HTMLElementDerived, HTMLBodyElementDerived};
-use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::js::{JS, JSRef, MutNullableJS, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector};
-use dom::document::Document;
+use dom::cssstyledeclaration::{CSSStyleDeclaration, InlineStyleOwner};
+use dom::document::{Document, DocumentHelpers};
 use dom::element::{Element, ElementTypeId, ElementTypeId_, HTMLElementTypeId};
 use dom::eventtarget::{EventTarget, EventTargetHelpers, NodeTargetTypeId};
-use dom::node::{Node, ElementNodeTypeId, window_from_node};
+use dom::node::{Node, ElementNodeTypeId, document_from_node, window_from_node};
 use dom::virtualmethods::VirtualMethods;
 
+use servo_net::fetch::request::Script;
 use servo_util::str::DOMString;
 use string_cache::Atom;
 
+use std::default::Default;
+
 #[jstraceable]
 #[must_root]
 pub struct HTMLElement {
-    pub element: Element
+    pub element: Element,
+    style_decl: MutNullableJS<CSSStyleDeclaration>,
 }
 
 impl HTMLElementDerived for EventTarget {
@@ -39,7 +44,8 @@ impl HTMLElementDerived for EventTarget {
 impl HTMLElement {
     pub fn new_inherited(type_id: ElementTypeId, tag_name: DOMString, prefix: Option<DOMString>, document: JSRef<Document>) -> HTMLElement {
         HTMLElement {
-            element: Element::new_inherited(type_id, tag_name, ns!(HTML), prefix, document)
+            element: Element::new_inherited(type_id, tag_name, ns!(HTML), prefix, document),
+            style_decl: Default::default(),
         }
     }
 
@@ -62,6 +68,16 @@ impl<'a> PrivateHTMLElementHelpers for JSRef<'a, HTMLElement> {
 }
 
 impl<'a> HTMLElementMethods for JSRef<'a, HTMLElement> {
+    fn Style(self) -> Temporary<CSSStyleDeclaration> {
+        if self.style_decl.get().is_none() {
+            let window = window_from_node(self).root();
+            let element: JSRef<Element> = ElementCast::from_ref(self);
+            let owner = InlineStyleOwner(JS::from_rooted(element));
+            self.style_decl.assign(Some(CSSStyleDeclaration::new(*window, owner)));
+        }
+        self.style_decl.get().unwrap()
+    }
+
     fn GetOnclick(self) -> Option<EventHandlerNonNull> {
         let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
         eventtarget.get_event_handler_common("click")
@@ -102,6 +118,12 @@ impl<'a> VirtualMethods for JSRef<'a, HTMLElement> {
         }
 
         if name.as_slice().starts_with("on") {
+            // Event handler attributes are inline script as far as the Content Security Policy
+            // is concerned.
+            let document = document_from_node(*self).root();
+            if !document.allows_inline(Script, None, None) {
+                return
+            }
             let window = window_from_node(*self).root();
             let (cx, url, reflector) = (window.get_cx(),
                                         window.get_url(),
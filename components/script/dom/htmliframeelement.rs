@@ -9,12 +9,12 @@ use dom::bindings::codegen::InheritTypes::{NodeCast, ElementCast};
 use dom::bindings::codegen::InheritTypes::{HTMLElementCast, HTMLIFrameElementDerived};
 use dom::bindings::js::{JSRef, Temporary, OptionalRootable};
 use dom::bindings::utils::{Reflectable, Reflector};
-use dom::document::Document;
+use dom::document::{Document, DocumentHelpers};
 use dom::element::{HTMLIFrameElementTypeId, Element};
 use dom::element::AttributeHandlers;
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlelement::HTMLElement;
-use dom::node::{Node, NodeHelpers, ElementNodeTypeId, window_from_node};
+use dom::node::{Node, NodeHelpers, ElementNodeTypeId, document_from_node, window_from_node};
 use dom::virtualmethods::VirtualMethods;
 use dom::window::Window;
 use page::IterablePage;
@@ -107,8 +107,12 @@ impl<'a> HTMLIFrameElementHelpers for JSRef<'a, HTMLIFrameElement> {
             subpage_id: subpage_id,
         }));
 
+        // The constellation checks the document's policy before it loads the frame.
+        let document = document_from_node(self).root();
         let ConstellationChan(ref chan) = page.constellation_chan;
-        chan.send(LoadIframeUrlMsg(url, page.id, subpage_id, sandboxed));
+        chan.send(LoadIframeUrlMsg(url, page.id, subpage_id, sandboxed,
+                                   document.content_security_policy(),
+                                   document.referrer_policy()));
     }
 }
 
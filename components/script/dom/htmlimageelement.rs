@@ -8,14 +8,16 @@ use dom::bindings::codegen::Bindings::HTMLImageElementBinding::HTMLImageElementM
 use dom::bindings::codegen::InheritTypes::{NodeCast, ElementCast, HTMLElementCast, HTMLImageElementDerived};
 use dom::bindings::js::{JS, JSRef, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector};
-use dom::document::Document;
+use dom::document::{Document, DocumentHelpers};
 use dom::element::{Element, HTMLImageElementTypeId};
 use dom::element::AttributeHandlers;
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlelement::HTMLElement;
 use dom::node::{Node, ElementNodeTypeId, NodeHelpers, window_from_node};
 use dom::virtualmethods::VirtualMethods;
+use servo_net::fetch::request::Image;
 use servo_net::image_cache_task;
+use servo_net::mixed_content;
 use servo_util::geometry::to_px;
 use servo_util::str::DOMString;
 use string_cache::Atom;
@@ -57,6 +59,12 @@ impl<'a> PrivateHTMLImageElementHelpers for JSRef<'a, HTMLImageElement> {
                 let img_url = UrlParser::new().base_url(base_url).parse(src.as_slice());
                 // FIXME: handle URL parse errors more gracefully.
                 let img_url = img_url.unwrap();
+                if !document.allows_load(Image, &img_url) {
+                    *self.image.borrow_mut() = None;
+                    return
+                }
+                // Insecure images in secure documents are loaded, with a warning.
+                let _ = mixed_content::check(document.url(), &img_url, Image);
                 *self.image.borrow_mut() = Some(img_url.clone());
 
                 // inform the image cache to load this, but don't store a
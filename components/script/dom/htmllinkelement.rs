@@ -4,27 +4,37 @@
 
 use dom::attr::AttrHelpers;
 use dom::bindings::codegen::Bindings::HTMLLinkElementBinding;
-use dom::bindings::codegen::InheritTypes::HTMLLinkElementDerived;
-use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLElementCast};
-use dom::bindings::js::{JSRef, Temporary, OptionalRootable};
+use dom::bindings::codegen::InheritTypes::{HTMLLinkElementCast, HTMLLinkElementDerived};
+use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLElementCast, NodeCast};
+use dom::bindings::js::{JSRef, MutNullableJS, Temporary, OptionalRootable};
 use dom::bindings::utils::{Reflectable, Reflector};
-use dom::document::Document;
+use dom::cssstylesheet::CSSStyleSheet;
+use dom::document::{Document, DocumentHelpers};
 use dom::element::{AttributeHandlers, Element, HTMLLinkElementTypeId};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlelement::HTMLElement;
-use dom::node::{Node, NodeHelpers, ElementNodeTypeId, window_from_node};
+use dom::node::{Node, NodeHelpers, ElementNodeTypeId, document_from_node, window_from_node};
 use dom::virtualmethods::VirtualMethods;
-use layout_interface::{LayoutChan, LoadStylesheetMsg};
+use layout_interface::{AddStylesheetMsg, LayoutChan, LoadStylesheetMsg, RemoveStylesheetMsg};
+use layout_interface::StylesheetId;
 use servo_util::str::{DOMString, HTML_SPACE_CHARACTERS};
+use style::Stylesheet;
 
+use encoding::label::encoding_from_whatwg_label;
 use std::ascii::StrAsciiExt;
-use url::UrlParser;
+use std::cell::Cell;
+use std::default::Default;
+use url::{Url, UrlParser};
 use string_cache::Atom;
 
 #[jstraceable]
 #[must_root]
 pub struct HTMLLinkElement {
     pub htmlelement: HTMLElement,
+    /// The id of the stylesheet layout was asked to load, while the element is in a document.
+    stylesheet_id: Cell<Option<StylesheetId>>,
+    /// The stylesheet layout loaded last, once it has handed its text back.
+    sheet: MutNullableJS<CSSStyleSheet>,
 }
 
 impl HTMLLinkElementDerived for EventTarget {
@@ -36,7 +46,9 @@ impl HTMLLinkElementDerived for EventTarget {
 impl HTMLLinkElement {
     fn new_inherited(localName: DOMString, prefix: Option<DOMString>, document: JSRef<Document>) -> HTMLLinkElement {
         HTMLLinkElement {
-            htmlelement: HTMLElement::new_inherited(HTMLLinkElementTypeId, localName, prefix, document)
+            htmlelement: HTMLElement::new_inherited(HTMLLinkElementTypeId, localName, prefix, document),
+            stylesheet_id: Cell::new(None),
+            sheet: Default::default(),
         }
     }
 
@@ -45,6 +57,24 @@ impl HTMLLinkElement {
         let element = HTMLLinkElement::new_inherited(localName, prefix, document);
         Node::reflect_node(box element, document, HTMLLinkElementBinding::Wrap)
     }
+
+    /// Gives the `<link>` element of `document` that asked for the stylesheet with the given id
+    /// the text layout loaded from `url`.
+    pub fn handle_stylesheet_loaded(document: JSRef<Document>, id: StylesheetId, url: Url,
+                                    css: &str) {
+        let root: JSRef<Node> = NodeCast::from_ref(document);
+        let link = root.traverse_preorder().filter_map(|node| {
+            HTMLLinkElementCast::to_ref(node)
+        }).find(|link| link.stylesheet_id.get() == Some(id.clone()));
+        let link = match link {
+            Some(link) => link,
+            // The element left the document, and its stylesheet was removed.
+            None => return,
+        };
+        let window = window_from_node(link).root();
+        let element: JSRef<Element> = ElementCast::from_ref(link);
+        link.sheet.assign(Some(CSSStyleSheet::new(*window, element, css, url)));
+    }
 }
 
 fn get_attr(element: JSRef<Element>, name: &str) -> Option<String> {
@@ -107,10 +137,49 @@ impl<'a> VirtualMethods for JSRef<'a, HTMLLinkElement> {
             }
         }
     }
+
+    fn unbind_from_tree(&self, tree_in_doc: bool) {
+        match self.super_type() {
+            Some(ref s) => s.unbind_from_tree(tree_in_doc),
+            _ => ()
+        }
+
+        if tree_in_doc {
+            self.remove_stylesheet();
+        }
+    }
+}
+
+pub trait LinkElementHelpers {
+    fn sheet(self) -> Option<Temporary<CSSStyleSheet>>;
+    fn update_stylesheet(self, css: &str, base_url: Url);
+}
+
+impl<'a> LinkElementHelpers for JSRef<'a, HTMLLinkElement> {
+    fn sheet(self) -> Option<Temporary<CSSStyleSheet>> {
+        self.sheet.get()
+    }
+
+    /// Gives layout the stylesheet whose text is `css`, in place of the one it loaded, after
+    /// script changed its rules. Changes are only rendered at the next reflow.
+    fn update_stylesheet(self, css: &str, base_url: Url) {
+        let id = match self.stylesheet_id.get() {
+            Some(id) => id,
+            None => return,
+        };
+        let document = document_from_node(self).root();
+        let window = window_from_node(self).root();
+        let sheet = Stylesheet::from_str(css, base_url);
+        let LayoutChan(ref layout_chan) = window.page().layout_chan;
+        layout_chan.send(AddStylesheetMsg(id, sheet, document.content_security_policy(),
+                                          document.encoding_name.borrow().clone(),
+                                          document.referrer_policy()));
+    }
 }
 
 trait PrivateHTMLLinkElementHelpers {
     fn handle_stylesheet_url(self, href: &str);
+    fn remove_stylesheet(self);
 }
 
 impl<'a> PrivateHTMLLinkElementHelpers for JSRef<'a, HTMLLinkElement> {
@@ -118,12 +187,48 @@ impl<'a> PrivateHTMLLinkElementHelpers for JSRef<'a, HTMLLinkElement> {
         let window = window_from_node(self).root();
         match UrlParser::new().base_url(&window.page().get_url()).parse(href) {
             Ok(url) => {
+                // Layout checks the document's policy, since it makes the load.
+                let document = document_from_node(self).root();
+                // http://dev.w3.org/csswg/css-syntax/#environment-encoding
+                let element: JSRef<Element> = ElementCast::from_ref(self);
+                let charset = element.get_attribute(ns!(""), "charset").root().and_then(|charset| {
+                    let charset = charset.value().as_slice().to_string();
+                    encoding_from_whatwg_label(charset.as_slice()).map(|_| charset)
+                });
+                let environment_encoding = charset.unwrap_or_else(|| {
+                    document.encoding_name.borrow().clone()
+                });
+                let id = match self.stylesheet_id.get() {
+                    Some(id) => id,
+                    None => {
+                        let id = document.new_stylesheet_id();
+                        self.stylesheet_id.set(Some(id.clone()));
+                        id
+                    }
+                };
+                // The sheet of the previous URL no longer applies once layout replaces it.
+                self.sheet.clear();
                 let LayoutChan(ref layout_chan) = window.page().layout_chan;
-                layout_chan.send(LoadStylesheetMsg(url));
+                layout_chan.send(LoadStylesheetMsg(id, url, document.content_security_policy(),
+                                                   environment_encoding,
+                                                   document.referrer_policy()));
             }
             Err(e) => debug!("Parsing url {:s} failed: {:?}", href, e)
         }
     }
+
+    fn remove_stylesheet(self) {
+        self.sheet.clear();
+        let id = match self.stylesheet_id.get() {
+            Some(id) => id,
+            None => return,
+        };
+        self.stylesheet_id.set(None);
+
+        let window = window_from_node(self).root();
+        let LayoutChan(ref layout_chan) = window.page().layout_chan;
+        layout_chan.send(RemoveStylesheetMsg(id));
+    }
 }
 
 impl Reflectable for HTMLLinkElement {
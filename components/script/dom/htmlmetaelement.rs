@@ -2,17 +2,22 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::attr::AttrHelpers;
 use dom::bindings::codegen::Bindings::HTMLMetaElementBinding;
-use dom::bindings::codegen::InheritTypes::HTMLMetaElementDerived;
-use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLElementCast, HTMLMetaElementDerived};
+use dom::bindings::js::{JSRef, Temporary, OptionalRootable};
 use dom::bindings::utils::{Reflectable, Reflector};
-use dom::document::Document;
-use dom::element::HTMLMetaElementTypeId;
+use dom::document::{Document, DocumentHelpers};
+use dom::element::{Element, AttributeHandlers, HTMLMetaElementTypeId};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlelement::HTMLElement;
-use dom::node::{Node, ElementNodeTypeId};
+use dom::node::{Node, ElementNodeTypeId, document_from_node};
+use dom::virtualmethods::VirtualMethods;
+use servo_net::referrer_policy::parse_meta;
 use servo_util::str::DOMString;
 
+use std::ascii::StrAsciiExt;
+
 #[jstraceable]
 #[must_root]
 pub struct HTMLMetaElement {
@@ -39,6 +44,52 @@ impl HTMLMetaElement {
     }
 }
 
+impl<'a> VirtualMethods for JSRef<'a, HTMLMetaElement> {
+    fn super_type<'a>(&'a self) -> Option<&'a VirtualMethods> {
+        let htmlelement: &JSRef<HTMLElement> = HTMLElementCast::from_borrowed_ref(self);
+        Some(htmlelement as &VirtualMethods)
+    }
+
+    fn bind_to_tree(&self, tree_in_doc: bool) {
+        match self.super_type() {
+            Some(ref s) => s.bind_to_tree(tree_in_doc),
+            _ => ()
+        }
+
+        if !tree_in_doc {
+            return
+        }
+
+        let element: JSRef<Element> = ElementCast::from_ref(*self);
+        let content = match element.get_attribute(ns!(""), "content").root() {
+            Some(content) => content.value().as_slice().to_string(),
+            None => return,
+        };
+        let document = document_from_node(*self).root();
+
+        // http://www.w3.org/TR/CSP2/#delivery-html-meta-element
+        let http_equiv = element.get_attribute(ns!(""), "http-equiv").root();
+        let is_csp = http_equiv.map_or(false, |http_equiv| {
+            http_equiv.value().as_slice().trim().eq_ignore_ascii_case("content-security-policy")
+        });
+        if is_csp {
+            document.add_meta_content_security_policy(content.as_slice());
+        }
+
+        // https://html.spec.whatwg.org/multipage/#meta-referrer
+        let name = element.get_attribute(ns!(""), "name").root();
+        let is_referrer = name.map_or(false, |name| {
+            name.value().as_slice().eq_ignore_ascii_case("referrer")
+        });
+        if is_referrer {
+            match parse_meta(content.as_slice()) {
+                Some(policy) => document.set_referrer_policy(Some(policy)),
+                None => {}
+            }
+        }
+    }
+}
+
 impl Reflectable for HTMLMetaElement {
     fn reflector<'a>(&'a self) -> &'a Reflector {
         self.htmlelement.reflector()
@@ -4,23 +4,33 @@
 
 use dom::bindings::codegen::Bindings::HTMLStyleElementBinding;
 use dom::bindings::codegen::Bindings::NodeBinding::NodeMethods;
-use dom::bindings::codegen::InheritTypes::{HTMLElementCast, HTMLStyleElementDerived, NodeCast};
-use dom::bindings::js::{JSRef, Temporary};
+use dom::attr::AttrHelpers;
+use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLElementCast, HTMLStyleElementDerived};
+use dom::bindings::codegen::InheritTypes::NodeCast;
+use dom::bindings::js::{JSRef, MutNullableJS, Temporary, OptionalRootable};
 use dom::bindings::utils::{Reflectable, Reflector};
-use dom::document::Document;
-use dom::element::HTMLStyleElementTypeId;
+use dom::cssstylesheet::CSSStyleSheet;
+use dom::document::{Document, DocumentHelpers};
+use dom::element::{Element, AttributeHandlers, HTMLStyleElementTypeId};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlelement::HTMLElement;
-use dom::node::{Node, NodeHelpers, ElementNodeTypeId, window_from_node};
+use dom::node::{Node, NodeHelpers, ElementNodeTypeId, document_from_node, window_from_node};
+use servo_net::fetch::request::Style;
 use dom::virtualmethods::VirtualMethods;
-use layout_interface::{AddStylesheetMsg, LayoutChan};
+use layout_interface::{AddStylesheetMsg, LayoutChan, RemoveStylesheetMsg, StylesheetId};
 use servo_util::str::DOMString;
 use style::Stylesheet;
 
+use std::cell::Cell;
+use std::default::Default;
+
 #[jstraceable]
 #[must_root]
 pub struct HTMLStyleElement {
     pub htmlelement: HTMLElement,
+    /// The id of the stylesheet layout was given, while the element is in a document.
+    stylesheet_id: Cell<Option<StylesheetId>>,
+    sheet: MutNullableJS<CSSStyleSheet>,
 }
 
 impl HTMLStyleElementDerived for EventTarget {
@@ -32,7 +42,9 @@ impl HTMLStyleElementDerived for EventTarget {
 impl HTMLStyleElement {
     fn new_inherited(localName: DOMString, prefix: Option<DOMString>, document: JSRef<Document>) -> HTMLStyleElement {
         HTMLStyleElement {
-            htmlelement: HTMLElement::new_inherited(HTMLStyleElementTypeId, localName, prefix, document)
+            htmlelement: HTMLElement::new_inherited(HTMLStyleElementTypeId, localName, prefix, document),
+            stylesheet_id: Cell::new(None),
+            sheet: Default::default(),
         }
     }
 
@@ -45,6 +57,9 @@ impl HTMLStyleElement {
 
 pub trait StyleElementHelpers {
     fn parse_own_css(self);
+    fn sheet(self) -> Option<Temporary<CSSStyleSheet>>;
+    fn update_stylesheet(self, css: &str);
+    fn remove_stylesheet(self);
 }
 
 impl<'a> StyleElementHelpers for JSRef<'a, HTMLStyleElement> {
@@ -52,13 +67,61 @@ impl<'a> StyleElementHelpers for JSRef<'a, HTMLStyleElement> {
         let node: JSRef<Node> = NodeCast::from_ref(self);
         assert!(node.is_in_doc());
 
+        let data = node.GetTextContent().expect("Element.textContent must be a string");
+
+        let element: JSRef<Element> = ElementCast::from_ref(self);
+        let nonce = element.get_attribute(ns!(""), "nonce").root();
+        let nonce = nonce.as_ref().map(|nonce| nonce.value().as_slice().to_string());
+        let document = document_from_node(self).root();
+        if !document.allows_inline(Style, nonce.as_ref().map(|nonce| nonce.as_slice()),
+                                   Some(data.as_slice())) {
+            self.remove_stylesheet();
+            return
+        }
+
         let win = window_from_node(node).root();
-        let url = win.page().get_url();
+        self.sheet.assign(Some(CSSStyleSheet::new(*win, element, data.as_slice(),
+                                                  win.page().get_url())));
+        self.update_stylesheet(data.as_slice());
+    }
 
-        let data = node.GetTextContent().expect("Element.textContent must be a string");
-        let sheet = Stylesheet::from_str(data.as_slice(), url);
+    fn sheet(self) -> Option<Temporary<CSSStyleSheet>> {
+        self.sheet.get()
+    }
+
+    /// Gives layout the stylesheet whose text is `css`, in place of the one it was last given.
+    /// Changes made by script are only rendered at the next reflow.
+    fn update_stylesheet(self, css: &str) {
+        let document = document_from_node(self).root();
+        let id = match self.stylesheet_id.get() {
+            Some(id) => id,
+            None => {
+                let id = document.new_stylesheet_id();
+                self.stylesheet_id.set(Some(id.clone()));
+                id
+            }
+        };
+
+        let win = window_from_node(self).root();
+        let sheet = Stylesheet::from_str(css, win.page().get_url());
+        // The stylesheets it imports are loaded by layout.
+        let LayoutChan(ref layout_chan) = win.page().layout_chan;
+        layout_chan.send(AddStylesheetMsg(id, sheet, document.content_security_policy(),
+                                          document.encoding_name.borrow().clone(),
+                                          document.referrer_policy()));
+    }
+
+    fn remove_stylesheet(self) {
+        self.sheet.clear();
+        let id = match self.stylesheet_id.get() {
+            Some(id) => id,
+            None => return,
+        };
+        self.stylesheet_id.set(None);
+
+        let win = window_from_node(self).root();
         let LayoutChan(ref layout_chan) = win.page().layout_chan;
-        layout_chan.send(AddStylesheetMsg(sheet));
+        layout_chan.send(RemoveStylesheetMsg(id));
     }
 }
 
@@ -90,6 +153,17 @@ impl<'a> VirtualMethods for JSRef<'a, HTMLStyleElement> {
             self.parse_own_css();
         }
     }
+
+    fn unbind_from_tree(&self, tree_in_doc: bool) {
+        match self.super_type() {
+            Some(ref s) => s.unbind_from_tree(tree_in_doc),
+            _ => ()
+        }
+
+        if tree_in_doc {
+            self.remove_stylesheet();
+        }
+    }
 }
 
 impl Reflectable for HTMLStyleElement {
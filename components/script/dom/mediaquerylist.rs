@@ -0,0 +1,118 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::callback::ReportExceptions;
+use dom::bindings::codegen::Bindings::MediaQueryListBinding;
+use dom::bindings::codegen::Bindings::MediaQueryListBinding::{MediaQueryListMethods, MediaQueryListListener};
+use dom::bindings::global;
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::window::{Window, WindowHelpers};
+use servo_util::str::DOMString;
+use style::MediaQueryList as MediaQueries;
+use style::parse_media_query_list_from_str;
+
+use std::cell::{Cell, RefCell};
+
+// http://dev.w3.org/csswg/cssom-view/#the-mediaquerylist-interface
+#[jstraceable]
+#[must_root]
+pub struct MediaQueryList {
+    reflector_: Reflector,
+    window: JS<Window>,
+    media: MediaQueries,
+    /// Whether the media queries matched when they were last evaluated, used only to detect
+    /// changes to report to the listeners.
+    matches: Cell<bool>,
+    listeners: RefCell<Vec<MediaQueryListListener>>,
+}
+
+impl MediaQueryList {
+    fn new_inherited(window: JSRef<Window>, media: MediaQueries) -> MediaQueryList {
+        let matches = media.evaluate(&window.device());
+        MediaQueryList {
+            reflector_: Reflector::new(),
+            window: JS::from_rooted(window),
+            media: media,
+            matches: Cell::new(matches),
+            listeners: RefCell::new(vec!()),
+        }
+    }
+
+    pub fn new(window: JSRef<Window>, query: &str) -> Temporary<MediaQueryList> {
+        let media = parse_media_query_list_from_str(query);
+        reflect_dom_object(box MediaQueryList::new_inherited(window, media),
+                           &global::Window(window), MediaQueryListBinding::Wrap)
+    }
+}
+
+pub trait MediaQueryListHelpers {
+    fn evaluate_and_report_changes(self);
+}
+
+impl<'a> MediaQueryListHelpers for JSRef<'a, MediaQueryList> {
+    /// Evaluates the media queries again, and calls the listeners if that changed whether they
+    /// match.
+    // http://dev.w3.org/csswg/cssom-view/#evaluate-media-queries-and-report-changes
+    fn evaluate_and_report_changes(self) {
+        let matches = self.media.evaluate(&self.window.root().device());
+        if matches == self.matches.get() {
+            return
+        }
+        self.matches.set(matches);
+        // Listeners may add or remove listeners, which only applies the next time.
+        let listeners = self.listeners.borrow().clone();
+        for listener in listeners.iter() {
+            let _ = listener.Call_(self, self, ReportExceptions);
+        }
+    }
+}
+
+impl<'a> MediaQueryListMethods for JSRef<'a, MediaQueryList> {
+    // http://dev.w3.org/csswg/cssom-view/#dom-mediaquerylist-media
+    fn Media(self) -> DOMString {
+        self.media.serialize()
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-mediaquerylist-matches
+    fn Matches(self) -> bool {
+        self.media.evaluate(&self.window.root().device())
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-mediaquerylist-addlistener
+    fn AddListener(self, listener: Option<MediaQueryListListener>) {
+        let listener = match listener {
+            Some(listener) => listener,
+            None => return,
+        };
+        if self.listeners.borrow().contains(&listener) {
+            return
+        }
+        // Changes are only reported while there are listeners, so start again from the current
+        // state when the first one is added.
+        if self.listeners.borrow().is_empty() {
+            self.matches.set(self.Matches());
+        }
+        self.listeners.borrow_mut().push(listener);
+        self.window.root().add_media_query_list(self);
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-mediaquerylist-removelistener
+    fn RemoveListener(self, listener: Option<MediaQueryListListener>) {
+        let listener = match listener {
+            Some(listener) => listener,
+            None => return,
+        };
+        self.listeners.borrow_mut().retain(|other| *other != listener);
+        if self.listeners.borrow().is_empty() {
+            self.window.root().remove_media_query_list(self);
+        }
+    }
+}
+
+impl Reflectable for MediaQueryList {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
@@ -47,11 +47,12 @@ use geom::rect::Rect;
 use html::hubbub_html_parser::build_element_from_tag;
 use layout_interface::{ContentBoxResponse, ContentBoxesResponse, LayoutRPC,
                        LayoutChan, ReapLayoutDataMsg, TrustedNodeAddress};
+use layout_interface::ComputedStyleResponse;
 use devtools_traits::NodeInfo;
 use script_traits::UntrustedNodeAddress;
 use servo_util::geometry::Au;
 use servo_util::str::{DOMString, null_str_as_empty};
-use style::{parse_selector_list_from_str, matches};
+use style::{parse_selector_list_from_str, matches, PseudoElement};
 
 use js::jsapi::{JSContext, JSObject, JSTracer, JSRuntime};
 use js::jsfriendapi;
@@ -436,6 +437,8 @@ pub trait NodeHelpers<'a> {
 
     fn get_bounding_content_box(self) -> Rect<Au>;
     fn get_content_boxes(self) -> Vec<Rect<Au>>;
+    fn get_computed_style(self, pseudo_element: Option<PseudoElement>, property: &str)
+                          -> Option<DOMString>;
 
     fn query_selector(self, selectors: DOMString) -> Fallible<Option<Temporary<Element>>>;
     fn query_selector_all(self, selectors: DOMString) -> Fallible<Temporary<NodeList>>;
@@ -678,6 +681,20 @@ impl<'a> NodeHelpers<'a> for JSRef<'a, Node> {
         rect
     }
 
+    fn get_computed_style(self, pseudo_element: Option<PseudoElement>, property: &str)
+                          -> Option<DOMString> {
+        if !self.is_in_doc() {
+            return None
+        }
+        let window = window_from_node(self).root();
+        let page = window.page();
+        let addr = self.to_trusted_node_address();
+
+        let ComputedStyleResponse(value) = page.layout().computed_style(addr, pseudo_element,
+                                                                         property);
+        value
+    }
+
     fn get_content_boxes(self) -> Vec<Rect<Au>> {
         let window = window_from_node(self).root();
         let page = window.page();
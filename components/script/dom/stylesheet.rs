@@ -0,0 +1,62 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::StyleSheetBinding::StyleSheetMethods;
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector};
+use dom::element::{Element, AttributeHandlers};
+use servo_util::str::DOMString;
+
+// http://dev.w3.org/csswg/cssom/#the-stylesheet-interface
+#[jstraceable]
+#[must_root]
+pub struct StyleSheet {
+    reflector_: Reflector,
+    /// The element whose stylesheet this is.
+    owner_node: JS<Element>,
+}
+
+impl StyleSheet {
+    pub fn new_inherited(owner_node: JSRef<Element>) -> StyleSheet {
+        StyleSheet {
+            reflector_: Reflector::new(),
+            owner_node: JS::from_rooted(owner_node),
+        }
+    }
+
+    pub fn owner_node(&self) -> Temporary<Element> {
+        Temporary::new(self.owner_node.clone())
+    }
+}
+
+impl<'a> StyleSheetMethods for JSRef<'a, StyleSheet> {
+    fn Type(self) -> DOMString {
+        "text/css".to_string()
+    }
+
+    // Only the stylesheets of <style> elements are exposed, and they have no location.
+    fn GetHref(self) -> Option<DOMString> {
+        None
+    }
+
+    fn GetOwnerNode(self) -> Option<Temporary<Element>> {
+        Some(self.owner_node())
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-stylesheet-title
+    fn GetTitle(self) -> Option<DOMString> {
+        let title = self.owner_node.root().get_string_attribute("title");
+        if title.is_empty() {
+            None
+        } else {
+            Some(title)
+        }
+    }
+}
+
+impl Reflectable for StyleSheet {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
@@ -0,0 +1,80 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::StyleSheetListBinding;
+use dom::bindings::codegen::Bindings::StyleSheetListBinding::StyleSheetListMethods;
+use dom::bindings::codegen::InheritTypes::{HTMLLinkElementCast, HTMLStyleElementCast, NodeCast};
+use dom::bindings::codegen::InheritTypes::StyleSheetCast;
+use dom::bindings::global;
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::document::Document;
+use dom::htmllinkelement::LinkElementHelpers;
+use dom::htmlstyleelement::StyleElementHelpers;
+use dom::node::{Node, NodeHelpers};
+use dom::stylesheet::StyleSheet;
+use dom::window::Window;
+
+// http://dev.w3.org/csswg/cssom/#the-stylesheetlist-interface
+#[jstraceable]
+#[must_root]
+pub struct StyleSheetList {
+    reflector_: Reflector,
+    document: JS<Document>,
+}
+
+impl StyleSheetList {
+    fn new_inherited(document: JSRef<Document>) -> StyleSheetList {
+        StyleSheetList {
+            reflector_: Reflector::new(),
+            document: JS::from_rooted(document),
+        }
+    }
+
+    pub fn new(window: JSRef<Window>, document: JSRef<Document>) -> Temporary<StyleSheetList> {
+        reflect_dom_object(box StyleSheetList::new_inherited(document),
+                           &global::Window(window), StyleSheetListBinding::Wrap)
+    }
+}
+
+trait PrivateStyleSheetListHelpers {
+    fn sheets(self) -> Vec<Temporary<StyleSheet>>;
+}
+
+impl<'a> PrivateStyleSheetListHelpers for JSRef<'a, StyleSheetList> {
+    /// The stylesheets of the document, in tree order. The stylesheet of a `<link>` element is
+    /// only there once layout has loaded it.
+    fn sheets(self) -> Vec<Temporary<StyleSheet>> {
+        let document = self.document.root();
+        let root: JSRef<Node> = NodeCast::from_ref(*document);
+        root.traverse_preorder().filter_map(|node| {
+            match HTMLStyleElementCast::to_ref(node) {
+                Some(style) => style.sheet(),
+                None => HTMLLinkElementCast::to_ref(node).and_then(|link| link.sheet()),
+            }
+        }).map(|sheet| StyleSheetCast::from_temporary(sheet)).collect()
+    }
+}
+
+impl<'a> StyleSheetListMethods for JSRef<'a, StyleSheetList> {
+    fn Length(self) -> u32 {
+        self.sheets().len() as u32
+    }
+
+    fn Item(self, index: u32) -> Option<Temporary<StyleSheet>> {
+        self.sheets().into_iter().nth(index as uint)
+    }
+
+    fn IndexedGetter(self, index: u32, found: &mut bool) -> Option<Temporary<StyleSheet>> {
+        let sheet = self.Item(index);
+        *found = sheet.is_some();
+        sheet
+    }
+}
+
+impl Reflectable for StyleSheetList {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
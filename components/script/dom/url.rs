@@ -0,0 +1,51 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::global::GlobalRef;
+use dom::bindings::js::JSRef;
+use dom::bindings::utils::{Reflectable, Reflector};
+use dom::blob::{Blob, BlobHelpers};
+
+use servo_net::fetch::request::serialize_origin;
+use servo_net::resource_task::{AddBlobURL, RevokeBlobURL};
+use servo_util::str::DOMString;
+
+use url::Url;
+use uuid;
+
+#[jstraceable]
+#[must_root]
+pub struct URL {
+    reflector_: Reflector,
+}
+
+impl URL {
+    // http://dev.w3.org/2006/webapi/FileAPI/#dfn-createObjectURL
+    pub fn CreateObjectURL(global: &GlobalRef, blob: JSRef<Blob>) -> DOMString {
+        let base = global.get_url();
+        let origin = match base.scheme.as_slice() {
+            "http" | "https" => serialize_origin(&base),
+            _ => "null".to_string(),
+        };
+        let url = format!("blob:{:s}/{:s}", origin, uuid::Uuid::new_v4().to_hyphenated_string());
+        global.resource_task().send(AddBlobURL(Url::parse(url.as_slice()).unwrap(), blob.data()));
+        url
+    }
+
+    // http://dev.w3.org/2006/webapi/FileAPI/#dfn-revokeObjectURL
+    pub fn RevokeObjectURL(global: &GlobalRef, url: DOMString) {
+        match Url::parse(url.as_slice()) {
+            Ok(ref url) if url.scheme.as_slice() == "blob" => {
+                global.resource_task().send(RevokeBlobURL(url.clone()));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Reflectable for URL {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
@@ -0,0 +1,418 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
+use dom::bindings::codegen::Bindings::WebSocketBinding;
+use dom::bindings::codegen::Bindings::WebSocketBinding::{BinaryType, WebSocketMethods};
+use dom::bindings::codegen::Bindings::WebSocketBinding::BinaryTypeValues;
+use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use dom::bindings::codegen::InheritTypes::{EventCast, EventTargetCast};
+use dom::bindings::codegen::UnionTypes::BlobOrString::{BlobOrString, eBlob, eString};
+use dom::bindings::conversions::ToJSValConvertible;
+use dom::bindings::error::{ErrorResult, Fallible, InvalidAccess, InvalidState, NotSupported};
+use dom::bindings::error::{Security, Syntax};
+use dom::bindings::global::{GlobalRef, GlobalField};
+use dom::bindings::global;
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::blob::{Blob, BlobHelpers};
+use dom::closeevent::CloseEvent;
+use dom::document::DocumentHelpers;
+use dom::event::Event;
+use dom::eventtarget::{EventTarget, EventTargetHelpers, WebSocketTypeId};
+use dom::messageevent::MessageEvent;
+use script_task::{ScriptChan, WebSocketEventMsg};
+
+use net::fetch::request::serialize_origin;
+use net::resource_task::ConnectWebSocket;
+use net::websocket::{WebSocketCommand, WebSocketEvent, WebSocketRequest};
+use net::websocket::{WebSocketOpened, WebSocketTextMessage, WebSocketBinaryMessage};
+use net::websocket::{WebSocketFailed, WebSocketClosed, ABNORMAL_CLOSURE};
+use net::websocket::{SendText, SendBinary, CloseConnection};
+use servo_util::str::DOMString;
+use servo_util::task::spawn_named;
+
+use js::jsapi::{JS_AddObjectRoot, JS_RemoveObjectRoot};
+use js::jsval::JSVal;
+
+use libc::c_void;
+use std::cell::{Cell, RefCell};
+use url::Url;
+
+pub struct TrustedWebSocketAddress(pub *const c_void);
+untraceable!(TrustedWebSocketAddress)
+
+#[deriving(PartialEq)]
+#[jstraceable]
+enum WebSocketState {
+    Connecting = 0,
+    Open = 1,
+    Closing = 2,
+    Closed = 3,
+}
+
+#[jstraceable]
+#[must_root]
+pub struct WebSocket {
+    eventtarget: EventTarget,
+    refcount: Cell<uint>,
+    global: GlobalField,
+    url: Url,
+    ready_state: Cell<WebSocketState>,
+    /// The number of bytes passed to `send()` once the connection started closing, which are
+    /// never sent.
+    buffered_amount: Cell<u32>,
+    protocol: RefCell<DOMString>,
+    extensions: RefCell<DOMString>,
+    binary_type: Cell<BinaryType>,
+    /// Whether `close()` was called before the connection was established, which fails it.
+    failed_by_close: Cell<bool>,
+    /// Sends commands to the task running the connection.
+    commands: Sender<WebSocketCommand>,
+}
+
+/// Whether `protocol` is a valid subprotocol name: a token as defined by RFC 2616.
+fn is_valid_protocol(protocol: &str) -> bool {
+    static SEPARATORS: &'static str = "()<>@,;:\\\"/[]?={}";
+    !protocol.is_empty() && protocol.chars().all(|c| {
+        c >= '\x21' && c <= '\x7E' && !SEPARATORS.contains_char(c)
+    })
+}
+
+impl WebSocket {
+    fn new_inherited(global: &GlobalRef, url: Url, commands: Sender<WebSocketCommand>)
+                     -> WebSocket {
+        WebSocket {
+            eventtarget: EventTarget::new_inherited(WebSocketTypeId),
+            refcount: Cell::new(0),
+            global: GlobalField::from_rooted(global),
+            url: url,
+            ready_state: Cell::new(Connecting),
+            buffered_amount: Cell::new(0),
+            protocol: RefCell::new("".to_string()),
+            extensions: RefCell::new("".to_string()),
+            binary_type: Cell::new(BinaryTypeValues::Blob),
+            failed_by_close: Cell::new(false),
+            commands: commands,
+        }
+    }
+
+    pub fn new(global: &GlobalRef, url: Url, commands: Sender<WebSocketCommand>)
+               -> Temporary<WebSocket> {
+        reflect_dom_object(box WebSocket::new_inherited(global, url, commands),
+                           global,
+                           WebSocketBinding::Wrap)
+    }
+
+    // http://www.whatwg.org/html/#dom-websocket
+    pub fn Constructor(global: &GlobalRef, url: DOMString, protocols: Option<DOMString>)
+                       -> Fallible<Temporary<WebSocket>> {
+        // Step 1.
+        let url = match Url::parse(url.as_slice()) {
+            Ok(url) => url,
+            Err(_) => return Err(Syntax),
+        };
+        // Steps 2-3.
+        match url.scheme.as_slice() {
+            "ws" | "wss" => {}
+            _ => return Err(Syntax),
+        }
+        if url.fragment.is_some() {
+            return Err(Syntax)
+        }
+
+        // Step 4: connections are subject to the document's connect-src directive.
+        let allowed = match *global {
+            global::Window(window) => window.Document().root().allows_connection(&url),
+            global::Worker(_) => true,
+        };
+        if !allowed {
+            return Err(Security)
+        }
+
+        // Steps 5-6.
+        let protocols: Vec<DOMString> = protocols.into_iter().collect();
+        for (i, protocol) in protocols.iter().enumerate() {
+            if !is_valid_protocol(protocol.as_slice()) ||
+               protocols.slice_to(i).contains(protocol) {
+                return Err(Syntax)
+            }
+        }
+
+        // Step 8.
+        let request = WebSocketRequest {
+            url: url.clone(),
+            protocols: protocols,
+            origin: serialize_origin(&global.get_url()),
+        };
+        let (events_chan, events_port) = channel();
+        let (commands_chan, commands_port) = channel();
+        global.resource_task().send(ConnectWebSocket(request, events_chan, commands_port));
+
+        // The object is kept alive until the connection is closed.
+        let ws = WebSocket::new(global, url, commands_chan).root();
+        let address = ws.addref();
+        let ScriptChan(script_chan) = global.script_chan().clone();
+        spawn_named("WebSocketEventTask", proc() {
+            for event in events_port.iter() {
+                let last = match event {
+                    WebSocketClosed(..) => true,
+                    _ => false,
+                };
+                if script_chan.send_opt(WebSocketEventMsg(address, event)).is_err() || last {
+                    break
+                }
+            }
+        });
+
+        Ok(Temporary::from_rooted(*ws))
+    }
+
+    pub fn handle_event(address: TrustedWebSocketAddress, event: WebSocketEvent) {
+        let ws = unsafe { JS::from_trusted_websocket_address(address).root() };
+        match event {
+            WebSocketOpened(protocol, extensions) => (*ws).open(protocol, extensions),
+            WebSocketTextMessage(text) => {
+                let global = ws.global.root();
+                let data = text.to_jsval(global.root_ref().get_cx());
+                (*ws).dispatch_message(data);
+            }
+            WebSocketBinaryMessage(bytes) => {
+                // binaryType can't be set to "arraybuffer", so binary messages are always
+                // delivered as blobs.
+                let global = ws.global.root();
+                let blob = Blob::new(&global.root_ref(), bytes, "").root();
+                let data = blob.to_jsval(global.root_ref().get_cx());
+                (*ws).dispatch_message(data);
+            }
+            WebSocketFailed(error) => {
+                debug!("websocket: connection to {:s} failed: {:s}", ws.url.serialize(), error);
+                (*ws).dispatch_simple_event("error");
+            }
+            WebSocketClosed(was_clean, code, reason) => {
+                (*ws).closed(was_clean, code, reason);
+                ws.release();
+            }
+        }
+    }
+}
+
+impl WebSocket {
+    // Creates a trusted address to the object, and roots it. Always pair this with a release()
+    pub fn addref(&self) -> TrustedWebSocketAddress {
+        let refcount = self.refcount.get();
+        if refcount == 0 {
+            let cx = self.global.root().root_ref().get_cx();
+            unsafe {
+                JS_AddObjectRoot(cx, self.reflector().rootable());
+            }
+        }
+        self.refcount.set(refcount + 1);
+        TrustedWebSocketAddress(self as *const WebSocket as *const c_void)
+    }
+
+    pub fn release(&self) {
+        let refcount = self.refcount.get();
+        assert!(refcount > 0)
+        self.refcount.set(refcount - 1);
+        if refcount == 1 {
+            let cx = self.global.root().root_ref().get_cx();
+            unsafe {
+                JS_RemoveObjectRoot(cx, self.reflector().rootable());
+            }
+        }
+    }
+}
+
+trait PrivateWebSocketHelpers {
+    fn open(self, protocol: String, extensions: String);
+    fn dispatch_message(self, data: JSVal);
+    fn dispatch_simple_event(self, type_: &str);
+    fn closed(self, was_clean: bool, code: u16, reason: String);
+}
+
+impl<'a> PrivateWebSocketHelpers for JSRef<'a, WebSocket> {
+    // http://www.whatwg.org/html/#feedback-from-the-protocol
+    fn open(self, protocol: String, extensions: String) {
+        // The connection is being failed by close().
+        if self.ready_state.get() != Connecting {
+            return
+        }
+        self.ready_state.set(Open);
+        *self.protocol.borrow_mut() = protocol;
+        *self.extensions.borrow_mut() = extensions;
+        self.dispatch_simple_event("open");
+    }
+
+    fn dispatch_message(self, data: JSVal) {
+        if self.ready_state.get() != Open {
+            return
+        }
+        let global = self.global.root();
+        let event = MessageEvent::new(&global.root_ref(), "message".to_string(), false, false,
+                                      data, serialize_origin(&self.url), "".to_string()).root();
+        let target: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        let event: JSRef<Event> = EventCast::from_ref(*event);
+        target.dispatch_event_with_target(None, event).ok();
+    }
+
+    fn dispatch_simple_event(self, type_: &str) {
+        let global = self.global.root();
+        let event = Event::new(&global.root_ref(), type_.to_string(), false, false).root();
+        let target: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        target.dispatch_event_with_target(None, *event).ok();
+    }
+
+    fn closed(self, was_clean: bool, code: u16, reason: String) {
+        self.ready_state.set(Closed);
+        let (was_clean, code, reason) = if self.failed_by_close.get() {
+            self.dispatch_simple_event("error");
+            (false, ABNORMAL_CLOSURE, "".to_string())
+        } else {
+            (was_clean, code, reason)
+        };
+        let global = self.global.root();
+        let event = CloseEvent::new(&global.root_ref(), "close".to_string(), false, false,
+                                    was_clean, code, reason).root();
+        let target: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        let event: JSRef<Event> = EventCast::from_ref(*event);
+        target.dispatch_event_with_target(None, event).ok();
+    }
+}
+
+impl<'a> WebSocketMethods for JSRef<'a, WebSocket> {
+    fn Url(self) -> DOMString {
+        self.url.serialize()
+    }
+
+    fn ReadyState(self) -> u16 {
+        self.ready_state.get() as u16
+    }
+
+    fn BufferedAmount(self) -> u32 {
+        self.buffered_amount.get()
+    }
+
+    fn GetOnopen(self) -> Option<EventHandlerNonNull> {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.get_event_handler_common("open")
+    }
+
+    fn SetOnopen(self, listener: Option<EventHandlerNonNull>) {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.set_event_handler_common("open", listener)
+    }
+
+    fn GetOnerror(self) -> Option<EventHandlerNonNull> {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.get_event_handler_common("error")
+    }
+
+    fn SetOnerror(self, listener: Option<EventHandlerNonNull>) {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.set_event_handler_common("error", listener)
+    }
+
+    fn GetOnclose(self) -> Option<EventHandlerNonNull> {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.get_event_handler_common("close")
+    }
+
+    fn SetOnclose(self, listener: Option<EventHandlerNonNull>) {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.set_event_handler_common("close", listener)
+    }
+
+    fn Extensions(self) -> DOMString {
+        self.extensions.borrow().clone()
+    }
+
+    fn Protocol(self) -> DOMString {
+        self.protocol.borrow().clone()
+    }
+
+    // http://www.whatwg.org/html/#dom-websocket-close
+    fn Close(self, code: Option<u16>, reason: Option<DOMString>) -> ErrorResult {
+        // Step 1.
+        match code {
+            Some(code) if code != 1000 && (code < 3000 || code > 4999) => {
+                return Err(InvalidAccess)
+            }
+            _ => {}
+        }
+        // Step 2.
+        let reason = reason.unwrap_or("".to_string());
+        if reason.len() > 123 {
+            return Err(Syntax)
+        }
+
+        // Step 3.
+        match self.ready_state.get() {
+            Closing | Closed => return Ok(()),
+            Connecting => self.failed_by_close.set(true),
+            Open => {}
+        }
+        self.ready_state.set(Closing);
+        let _ = self.commands.send_opt(CloseConnection(code, reason));
+        Ok(())
+    }
+
+    fn GetOnmessage(self) -> Option<EventHandlerNonNull> {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.get_event_handler_common("message")
+    }
+
+    fn SetOnmessage(self, listener: Option<EventHandlerNonNull>) {
+        let eventtarget: JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.set_event_handler_common("message", listener)
+    }
+
+    fn BinaryType(self) -> BinaryType {
+        self.binary_type.get()
+    }
+
+    // TODO: allow "arraybuffer" once the bindings support ArrayBuffer.
+    fn SetBinaryType(self, binary_type: BinaryType) -> ErrorResult {
+        match binary_type {
+            BinaryTypeValues::Blob => {
+                self.binary_type.set(binary_type);
+                Ok(())
+            }
+            BinaryTypeValues::Arraybuffer => Err(NotSupported),
+        }
+    }
+
+    // http://www.whatwg.org/html/#dom-websocket-send
+    fn Send(self, data: BlobOrString) -> ErrorResult {
+        let (command, length) = match data {
+            eString(text) => {
+                let length = text.len();
+                (SendText(text), length)
+            }
+            eBlob(blob) => {
+                let bytes = (*blob.root().data().bytes).clone();
+                let length = bytes.len();
+                (SendBinary(bytes), length)
+            }
+        };
+        match self.ready_state.get() {
+            Connecting => Err(InvalidState),
+            Open => {
+                let _ = self.commands.send_opt(command);
+                Ok(())
+            }
+            Closing | Closed => {
+                let buffered_amount = self.buffered_amount.get();
+                self.buffered_amount.set(buffered_amount + length as u32);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Reflectable for WebSocket {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        self.eventtarget.reflector()
+    }
+}
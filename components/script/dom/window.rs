@@ -6,15 +6,20 @@ use dom::bindings::codegen::Bindings::EventHandlerBinding::{OnErrorEventHandlerN
 use dom::bindings::codegen::Bindings::WindowBinding;
 use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::codegen::InheritTypes::EventTargetCast;
+use dom::bindings::conversions;
+use dom::bindings::conversions::FromJSValConvertible;
 use dom::bindings::error::{Fallible, InvalidCharacter};
 use dom::bindings::global;
-use dom::bindings::js::{MutNullableJS, JSRef, Temporary, OptionalSettable};
+use dom::bindings::js::{MutNullableJS, JS, JSRef, Temporary, OptionalSettable};
 use dom::bindings::utils::{Reflectable, Reflector};
 use dom::browsercontext::BrowserContext;
 use dom::console::Console;
-use dom::document::Document;
+use dom::cssstyledeclaration::{CSSStyleDeclaration, ComputedStyleOwner};
+use dom::document::{Document, DocumentHelpers};
+use dom::element::Element;
 use dom::eventtarget::{EventTarget, WindowTypeId, EventTargetHelpers};
 use dom::location::Location;
+use dom::mediaquerylist::{MediaQueryList, MediaQueryListHelpers};
 use dom::navigator::Navigator;
 use dom::performance::Performance;
 use dom::screen::Screen;
@@ -25,8 +30,11 @@ use script_traits::ScriptControlChan;
 
 use servo_msg::compositor_msg::ScriptListener;
 use servo_net::image_cache_task::ImageCacheTask;
+use servo_util::geometry::Au;
 use servo_util::str::{DOMString,HTML_SPACE_CHARACTERS};
 use servo_util::task::{spawn_named};
+use style::{Before, After, Device};
+use style::Screen as ScreenMedia;
 
 use js::jsapi::{JS_CallFunctionValue, JS_EvaluateUCScript};
 use js::jsapi::JSContext;
@@ -36,8 +44,10 @@ use js::jsval::{UndefinedValue, NullValue};
 use js::rust::with_compartment;
 use url::{Url, UrlParser};
 
+use geom::size::Size2D;
 use libc;
 use serialize::base64::{FromBase64, ToBase64, STANDARD};
+use std::ascii::StrAsciiExt;
 use std::collections::hashmap::HashMap;
 use std::cell::{Cell, RefCell};
 use std::cmp;
@@ -94,6 +104,8 @@ pub struct Window {
     pub navigationStart: u64,
     pub navigationStartPrecise: f64,
     screen: MutNullableJS<Screen>,
+    /// The lists returned by `matchMedia()` that have listeners, in the order they got them.
+    media_query_lists: RefCell<Vec<JS<MediaQueryList>>>,
 }
 
 impl Window {
@@ -340,6 +352,25 @@ impl<'a> WindowMethods for JSRef<'a, Window> {
         self.screen.get().unwrap()
     }
 
+    // http://dev.w3.org/csswg/cssom/#dom-window-getcomputedstyle
+    fn GetComputedStyle(self, element: JSRef<Element>, pseudo_element: Option<DOMString>)
+                        -> Temporary<CSSStyleDeclaration> {
+        let pseudo_element = pseudo_element.and_then(|pseudo_element| {
+            match pseudo_element.as_slice().to_ascii_lower().as_slice() {
+                ":before" | "::before" => Some(Before),
+                ":after" | "::after" => Some(After),
+                _ => None,
+            }
+        });
+        let owner = ComputedStyleOwner(JS::from_rooted(element), pseudo_element);
+        CSSStyleDeclaration::new(self, owner)
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-window-matchmedia
+    fn MatchMedia(self, query: DOMString) -> Temporary<MediaQueryList> {
+        MediaQueryList::new(self, query.as_slice())
+    }
+
     fn Debug(self, message: DOMString) {
         debug!("{:s}", message);
     }
@@ -373,6 +404,10 @@ pub trait WindowHelpers {
     fn load_url(self, href: DOMString);
     fn handle_fire_timer(self, timer_id: TimerId, cx: *mut JSContext);
     fn evaluate_js_with_result(self, code: &str) -> JSVal;
+    fn device(self) -> Device;
+    fn add_media_query_list(self, list: JSRef<MediaQueryList>);
+    fn remove_media_query_list(self, list: JSRef<MediaQueryList>);
+    fn evaluate_media_queries(self);
 }
 
 trait PrivateWindowHelpers {
@@ -444,23 +479,66 @@ impl<'a> WindowHelpers for JSRef<'a, Window> {
             Some(timer_handle) => timer_handle.data,
         };
 
-        // TODO: Support extra arguments. This requires passing a `*JSVal` array as `argv`.
-        with_compartment(cx, this_value, || {
-            let mut rval = NullValue();
-            unsafe {
-                JS_CallFunctionValue(cx, this_value, data.funval,
-                                     0, ptr::null_mut(), &mut rval);
-            }
-        });
+        if data.funval.is_string() {
+            let code: DOMString =
+                FromJSValConvertible::from_jsval(cx, data.funval, conversions::Default).unwrap();
+            self.evaluate_js_with_result(code.as_slice());
+        } else {
+            // TODO: Support extra arguments. This requires passing a `*JSVal` array as `argv`.
+            with_compartment(cx, this_value, || {
+                let mut rval = NullValue();
+                unsafe {
+                    JS_CallFunctionValue(cx, this_value, data.funval,
+                                         0, ptr::null_mut(), &mut rval);
+                }
+            });
+        }
 
         if !data.is_interval {
             self.active_timers.borrow_mut().remove(&timer_id);
         }
     }
+
+    /// The device that media queries are evaluated against, as in layout.
+    fn device(self) -> Device {
+        let window_size = self.page().window_size.get();
+        let viewport_size = Size2D(Au::from_frac32_px(window_size.initial_viewport.width.get()),
+                                   Au::from_frac32_px(window_size.initial_viewport.height.get()));
+        Device::new(ScreenMedia, viewport_size, window_size.device_pixel_ratio.get() as f64)
+    }
+
+    fn add_media_query_list(self, list: JSRef<MediaQueryList>) {
+        let list = JS::from_rooted(list);
+        let mut lists = self.media_query_lists.borrow_mut();
+        if !lists.contains(&list) {
+            lists.push(list);
+        }
+    }
+
+    fn remove_media_query_list(self, list: JSRef<MediaQueryList>) {
+        let list = JS::from_rooted(list);
+        self.media_query_lists.borrow_mut().retain(|other| *other != list);
+    }
+
+    /// Tells the listeners of the lists returned by `matchMedia()` whose media queries started
+    /// or stopped matching, as after a resize.
+    fn evaluate_media_queries(self) {
+        let lists = self.media_query_lists.borrow().clone();
+        for list in lists.iter() {
+            list.root().evaluate_and_report_changes();
+        }
+    }
 }
 
 impl<'a> PrivateWindowHelpers for JSRef<'a, Window> {
     fn set_timeout_or_interval(self, callback: JSVal, timeout: i32, is_interval: bool) -> i32 {
+        // A string is evaluated as code when the timer fires, unless the document's policies
+        // don't allow that.
+        // http://www.w3.org/TR/CSP2/#directive-script-src
+        if callback.is_string() && !self.Document().root().allows_eval() {
+            return 0
+        }
+
         let timeout = cmp::max(0, timeout) as u64;
         let handle = self.next_timer_handle.get();
         self.next_timer_handle.set(handle + 1);
@@ -545,6 +623,7 @@ impl Window {
             navigationStart: time::get_time().sec as u64,
             navigationStartPrecise: time::precise_time_s(),
             screen: Default::default(),
+            media_query_lists: RefCell::new(vec!()),
         };
 
         WindowBinding::Wrap(cx, win)
@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
+use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::codegen::Bindings::XMLHttpRequestBinding;
 use dom::bindings::codegen::Bindings::XMLHttpRequestBinding::XMLHttpRequestMethods;
 use dom::bindings::codegen::Bindings::XMLHttpRequestBinding::XMLHttpRequestResponseType;
@@ -13,10 +14,11 @@ use dom::bindings::conversions::ToJSValConvertible;
 use dom::bindings::error::{Error, ErrorResult, Fallible, InvalidState, InvalidAccess};
 use dom::bindings::error::{Network, Syntax, Security, Abort, Timeout};
 use dom::bindings::global::{GlobalField, GlobalRef, WorkerField};
+use dom::bindings::global;
 use dom::bindings::js::{MutNullableJS, JS, JSRef, Temporary, OptionalRootedRootable};
 use dom::bindings::str::ByteString;
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
-use dom::document::Document;
+use dom::document::{Document, DocumentHelpers};
 use dom::event::Event;
 use dom::eventtarget::{EventTarget, EventTargetHelpers, XMLHttpRequestTargetTypeId};
 use dom::progressevent::ProgressEvent;
@@ -43,8 +45,10 @@ use js::jsval::{JSVal, NullValue, UndefinedValue};
 use libc;
 use libc::c_void;
 
-use net::resource_task::{ResourceTask, ResourceCORSData, Load, LoadData, Payload, Done};
-use cors::{allow_cross_origin_request, CORSRequest, CORSMode, ForcedPreflightMode};
+use net::fetch::request::{Request, RequestMode, CORSMode, ForcedPreflightMode};
+use net::fetch::request::{CredentialsSameOrigin, Include, RefererUrl};
+use net::fetch::request::XMLHttpRequest as XHRContext;
+use net::resource_task::{ResourceTask, LoadData, Payload, Done};
 use script_task::{ScriptChan, XHRProgressMsg};
 use servo_util::str::DOMString;
 use servo_util::task::spawn_named;
@@ -187,8 +191,9 @@ impl XMLHttpRequest {
     }
 
     fn fetch(fetch_type: &SyncOrAsync, resource_task: ResourceTask,
-             mut load_data: LoadData, terminate_receiver: Receiver<Error>,
-             cors_request: Result<Option<CORSRequest>,()>) -> ErrorResult {
+             load_data: LoadData, terminate_receiver: Receiver<Error>,
+             referer_url: Url, mode: RequestMode, with_credentials: bool,
+             allowed_by_csp: bool) -> ErrorResult {
         fn notify_partial_progress(fetch_type: &SyncOrAsync, msg: XHRProgress) {
             match *fetch_type {
                 Sync(xhr) => {
@@ -201,46 +206,36 @@ impl XMLHttpRequest {
             }
         }
 
-        match cors_request {
-            Err(_) => return Err(Network), // Happens in case of cross-origin non-http URIs
-            Ok(Some(ref req)) => {
-                let response = req.http_fetch();
-                if response.network_error {
-                    return Err(Network)
-                } else {
-                    load_data.cors = Some(ResourceCORSData {
-                        preflight: req.preflight_flag,
-                        origin: req.origin.clone()
-                    })
-                }
-            },
-            _ => {}
+        if !allowed_by_csp {
+            notify_partial_progress(fetch_type, ErroredMsg(None));
+            return Err(Network)
         }
 
         // Step 10, 13
-        let (start_chan, start_port) = channel();
-        resource_task.send(Load(load_data, start_chan));
-        let response = start_port.recv();
+        let mut request = Request::new(load_data.url, XHRContext);
+        request.method = load_data.method;
+        request.headers = load_data.headers;
+        request.body = load_data.data;
+        request.unsafe_request = true;
+        request.origin = Some(referer_url.clone());
+        request.referer = RefererUrl(referer_url);
+        request.referrer_policy = load_data.referrer_policy;
+        request.mode = mode;
+        request.credentials_mode = if with_credentials { Include } else { CredentialsSameOrigin };
+        let mut response = request.fetch(&resource_task);
         match terminate_receiver.try_recv() {
             Ok(e) => return Err(e),
             _ => {}
         }
-        match cors_request {
-            Ok(Some(ref req)) => {
-                    match response.metadata.headers {
-                        Some(ref h) if allow_cross_origin_request(req, h) => {},
-                        _ => return Err(Network)
-                    }
-            },
-            _ => {}
+        if response.response.is_network_error() {
+            notify_partial_progress(fetch_type, ErroredMsg(None));
+            return Err(Network)
         }
-        // XXXManishearth Clear cache entries in case of a network error
-
         notify_partial_progress(fetch_type, HeadersReceivedMsg(
-            response.metadata.headers.clone(), response.metadata.status.clone()));
+            Some(response.response.headers.clone()), response.response.status.clone()));
         let mut buf = vec!();
         loop {
-            let progress = response.progress_port.recv();
+            let progress = response.recv();
             match terminate_receiver.try_recv() {
                 Ok(e) => return Err(e),
                 _ => {}
@@ -495,6 +490,18 @@ impl<'a> XMLHttpRequestMethods for JSRef<'a, XMLHttpRequest> {
         let resource_task = global.root_ref().resource_task();
         let mut load_data = LoadData::new(self.request_url.borrow().clone().unwrap());
         load_data.data = extracted;
+        // Workers don't have a referrer policy of their own yet.
+        load_data.referrer_policy = match global.root_ref() {
+            global::Window(window) => window.Document().root().referrer_policy(),
+            global::Worker(_) => None,
+        };
+        // A request blocked by the document's connect-src directive is a network error.
+        let allowed_by_csp = match global.root_ref() {
+            global::Window(window) => {
+                window.Document().root().allows_load(XHRContext, &load_data.url)
+            }
+            global::Worker(_) => true,
+        };
 
         // Default headers
         let ref request_headers = self.request_headers;
@@ -526,43 +533,27 @@ impl<'a> XMLHttpRequestMethods for JSRef<'a, XMLHttpRequest> {
         let (terminate_sender, terminate_receiver) = channel();
         *self.terminate_sender.borrow_mut() = Some(terminate_sender);
 
-        // CORS stuff
         let referer_url = self.global.root().root_ref().get_url();
         let mode = if self.upload_events.get() {
             ForcedPreflightMode
         } else {
             CORSMode
         };
-        let cors_request = CORSRequest::maybe_new(referer_url.clone(), load_data.url.clone(), mode,
-                                                  load_data.method.clone(), load_data.headers.clone());
-        match cors_request {
-            Ok(None) => {
-                let mut buf = String::new();
-                buf.push_str(referer_url.scheme.as_slice());
-                buf.push_str("://".as_slice());
-                referer_url.serialize_host().map(|ref h| buf.push_str(h.as_slice()));
-                referer_url.port().as_ref().map(|&p| {
-                    buf.push_str(":".as_slice());
-                    buf.push_str(format!("{:u}", p).as_slice());
-                });
-                referer_url.serialize_path().map(|ref h| buf.push_str(h.as_slice()));
-                self.request_headers.borrow_mut().referer = Some(buf);
-            },
-            Ok(Some(ref req)) => self.insert_trusted_header("origin".to_string(),
-                                                            format!("{}", req.origin)),
-            _ => {}
-        }
+        let with_credentials = self.with_credentials.get();
 
         if self.sync.get() {
             return XMLHttpRequest::fetch(&mut Sync(self), resource_task, load_data,
-                                         terminate_receiver, cors_request);
+                                         terminate_receiver, referer_url, mode, with_credentials,
+                                         allowed_by_csp);
         } else {
             let builder = TaskBuilder::new().named("XHRTask");
             self.fetch_time.set(time::now().to_timespec().sec);
             let script_chan = global.root_ref().script_chan().clone();
             builder.spawn(proc() {
                 let _ = XMLHttpRequest::fetch(&mut Async(addr.unwrap(), script_chan),
-                                              resource_task, load_data, terminate_receiver, cors_request);
+                                              resource_task, load_data, terminate_receiver,
+                                              referer_url, mode, with_credentials,
+                                              allowed_by_csp);
             });
             let timeout = self.timeout.get();
             if timeout > 0 {
@@ -702,7 +693,6 @@ trait PrivateXMLHttpRequestHelpers {
     fn release_once(self);
     fn change_ready_state(self, XMLHttpRequestState);
     fn process_partial_response(self, progress: XHRProgress);
-    fn insert_trusted_header(self, name: String, value: String);
     fn dispatch_progress_event(self, upload: bool, type_: DOMString, loaded: u64, total: Option<u64>);
     fn dispatch_upload_progress_event(self, type_: DOMString, partial_load: Option<u64>);
     fn dispatch_response_progress_event(self, type_: DOMString);
@@ -851,18 +841,6 @@ impl<'a> PrivateXMLHttpRequestHelpers for JSRef<'a, XMLHttpRequest> {
         }
     }
 
-    fn insert_trusted_header(self, name: String, value: String) {
-        // Insert a header without checking spec-compliance
-        // Use for hardcoded headers
-        let mut collection = self.request_headers.borrow_mut();
-        let value_bytes = value.into_bytes();
-        let mut reader = BufReader::new(value_bytes.as_slice());
-        let maybe_header: Option<Header> = HeaderEnum::value_from_stream(
-                                                                String::from_str(name.as_slice()),
-                                                                &mut HeaderValueByteIterator::new(&mut reader));
-        collection.insert(maybe_header.unwrap());
-    }
-
     fn dispatch_progress_event(self, upload: bool, type_: DOMString, loaded: u64, total: Option<u64>) {
         let global = self.global.root();
         let upload_target = *self.upload.root();
@@ -0,0 +1,399 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! [Determining the character encoding](https://html.spec.whatwg.org/multipage/#determining-the-character-encoding)
+//! of HTML documents, and decoding them as they arrive.
+
+use encoding::all::{UTF_8, UTF_16BE, UTF_16LE, WINDOWS_1252};
+use encoding::label::encoding_from_whatwg_label;
+use encoding::types::{Encoding, EncodingRef, RawDecoder};
+
+use std::ascii::StrAsciiExt;
+use std::str;
+
+/// The number of bytes the prescan looks at for a `<meta>` declaration.
+static PRESCAN_LENGTH: uint = 1024;
+
+/// Where the encoding of a document came from. A tentative encoding is one that was guessed.
+#[deriving(Clone, PartialEq, Show)]
+pub enum Confidence {
+    Tentative,
+    Certain,
+}
+
+/// The encoding given by a byte order mark at the start of `bytes`, and the length of the mark.
+pub fn sniff_bom(bytes: &[u8]) -> Option<(EncodingRef, uint)> {
+    match bytes {
+        [0xEF, 0xBB, 0xBF, ..] => Some((UTF_8 as EncodingRef, 3)),
+        [0xFE, 0xFF, ..] => Some((UTF_16BE as EncodingRef, 2)),
+        [0xFF, 0xFE, ..] => Some((UTF_16LE as EncodingRef, 2)),
+        _ => None,
+    }
+}
+
+fn is_utf16(encoding: EncodingRef) -> bool {
+    match encoding.whatwg_name() {
+        Some("utf-16be") | Some("utf-16le") => true,
+        _ => false,
+    }
+}
+
+fn is_space(byte: u8) -> bool {
+    byte == 0x09 || byte == 0x0A || byte == 0x0C || byte == 0x0D || byte == 0x20
+}
+
+fn to_ascii_lower(byte: u8) -> u8 {
+    if byte >= b'A' && byte <= b'Z' { byte + (b'a' - b'A') } else { byte }
+}
+
+fn is_ascii_letter(byte: u8) -> bool {
+    (byte >= b'a' && byte <= b'z') || (byte >= b'A' && byte <= b'Z')
+}
+
+/// Whether `bytes` starts with `prefix`, ignoring ASCII case.
+fn starts_with_ignoring_case(bytes: &[u8], prefix: &[u8]) -> bool {
+    bytes.len() >= prefix.len() &&
+        bytes.iter().zip(prefix.iter()).all(|(&a, &b)| to_ascii_lower(a) == b)
+}
+
+/// [Extract a character encoding from a meta element](https://html.spec.whatwg.org/multipage/#algorithm-for-extracting-a-character-encoding-from-a-meta-element)
+/// with the given `content` attribute.
+pub fn extract_encoding_from_content(content: &str) -> Option<EncodingRef> {
+    let lower = content.to_ascii_lower();
+    let bytes = lower.as_bytes();
+    let mut position = 0u;
+    loop {
+        // Steps 2-3
+        let index = match bytes.slice_from(position).windows(7).position(|w| w == b"charset") {
+            Some(index) => position + index,
+            None => return None,
+        };
+        position = index + "charset".len();
+        // Step 4
+        while position < bytes.len() && is_space(bytes[position]) {
+            position += 1;
+        }
+        // Step 5
+        if position >= bytes.len() || bytes[position] != b'=' {
+            continue
+        }
+        position += 1;
+        // Step 6
+        while position < bytes.len() && is_space(bytes[position]) {
+            position += 1;
+        }
+        if position >= bytes.len() {
+            return None
+        }
+        // Steps 7-8
+        let value = match bytes[position] {
+            quote @ b'"' | quote @ b'\'' => {
+                let rest = bytes.slice_from(position + 1);
+                match rest.iter().position(|&b| b == quote) {
+                    Some(end) => rest.slice_to(end),
+                    None => return None,
+                }
+            }
+            _ => {
+                let rest = bytes.slice_from(position);
+                let end = rest.iter().position(|&b| is_space(b) || b == b';')
+                              .unwrap_or(rest.len());
+                rest.slice_to(end)
+            }
+        };
+        return str::from_utf8(value).and_then(encoding_from_whatwg_label)
+    }
+}
+
+/// An attribute found by the prescan, with its name in lowercase.
+struct PrescanAttribute {
+    name: String,
+    value: String,
+}
+
+/// [Get an attribute](https://html.spec.whatwg.org/multipage/#concept-get-attributes-when-sniffing)
+/// starting at `*position`, leaving `*position` after it.
+fn get_attribute(bytes: &[u8], position: &mut uint) -> Option<PrescanAttribute> {
+    let len = bytes.len();
+    // Step 1
+    while *position < len && (is_space(bytes[*position]) || bytes[*position] == b'/') {
+        *position += 1;
+    }
+    // Step 2
+    if *position >= len || bytes[*position] == b'>' {
+        return None
+    }
+    // Steps 3-5
+    let mut name = vec!();
+    let mut value = vec!();
+    loop {
+        if *position >= len {
+            return None
+        }
+        match bytes[*position] {
+            b'=' if !name.is_empty() => {
+                *position += 1;
+                break
+            }
+            b if is_space(b) => {
+                // Step 6
+                while *position < len && is_space(bytes[*position]) {
+                    *position += 1;
+                }
+                if *position >= len || bytes[*position] != b'=' {
+                    return Some(PrescanAttribute {
+                        name: String::from_utf8_lossy(name.as_slice()).into_string(),
+                        value: String::new(),
+                    })
+                }
+                *position += 1;
+                break
+            }
+            b'/' | b'>' => {
+                return Some(PrescanAttribute {
+                    name: String::from_utf8_lossy(name.as_slice()).into_string(),
+                    value: String::new(),
+                })
+            }
+            b => {
+                name.push(to_ascii_lower(b));
+                *position += 1;
+            }
+        }
+    }
+    // Steps 9-10
+    while *position < len && is_space(bytes[*position]) {
+        *position += 1;
+    }
+    if *position >= len {
+        return None
+    }
+    match bytes[*position] {
+        quote @ b'"' | quote @ b'\'' => {
+            *position += 1;
+            loop {
+                if *position >= len {
+                    return None
+                }
+                let b = bytes[*position];
+                *position += 1;
+                if b == quote {
+                    break
+                }
+                value.push(to_ascii_lower(b));
+            }
+        }
+        b'>' => {}
+        _ => {
+            while *position < len && !is_space(bytes[*position]) && bytes[*position] != b'>' {
+                value.push(to_ascii_lower(bytes[*position]));
+                *position += 1;
+            }
+        }
+    }
+    Some(PrescanAttribute {
+        name: String::from_utf8_lossy(name.as_slice()).into_string(),
+        value: String::from_utf8_lossy(value.as_slice()).into_string(),
+    })
+}
+
+/// Look for the encoding declared by the `<meta>` element at `*position`, just after `<meta`.
+/// https://html.spec.whatwg.org/multipage/#prescan-a-byte-stream-to-determine-its-encoding
+fn prescan_meta(bytes: &[u8], position: &mut uint) -> Option<EncodingRef> {
+    let mut attribute_list: Vec<String> = vec!();
+    let mut got_pragma = false;
+    let mut need_pragma = None;
+    let mut charset = None;
+    loop {
+        let attribute = match get_attribute(bytes, position) {
+            Some(attribute) => attribute,
+            None => break,
+        };
+        if attribute_list.contains(&attribute.name) {
+            continue
+        }
+        attribute_list.push(attribute.name.clone());
+        match attribute.name.as_slice() {
+            "http-equiv" => {
+                if attribute.value.as_slice() == "content-type" {
+                    got_pragma = true;
+                }
+            }
+            "content" => {
+                if charset.is_none() {
+                    match extract_encoding_from_content(attribute.value.as_slice()) {
+                        Some(encoding) => {
+                            charset = Some(encoding);
+                            need_pragma = Some(true);
+                        }
+                        None => {}
+                    }
+                }
+            }
+            "charset" => {
+                if charset.is_none() {
+                    charset = encoding_from_whatwg_label(attribute.value.as_slice());
+                    need_pragma = Some(false);
+                }
+            }
+            _ => {}
+        }
+    }
+    // Steps 11-14
+    match (need_pragma, charset) {
+        (None, _) | (_, None) => None,
+        (Some(true), _) if !got_pragma => None,
+        (_, Some(encoding)) if is_utf16(encoding) => Some(UTF_8 as EncodingRef),
+        (_, Some(encoding)) if encoding.whatwg_name() == Some("x-user-defined") => {
+            Some(WINDOWS_1252 as EncodingRef)
+        }
+        (_, Some(encoding)) => Some(encoding),
+    }
+}
+
+/// [Prescan](https://html.spec.whatwg.org/multipage/#prescan-a-byte-stream-to-determine-its-encoding)
+/// the start of a document for a `<meta>` element declaring its encoding.
+pub fn prescan(bytes: &[u8]) -> Option<EncodingRef> {
+    let bytes = if bytes.len() > PRESCAN_LENGTH { bytes.slice_to(PRESCAN_LENGTH) } else { bytes };
+    let len = bytes.len();
+    let mut position = 0u;
+    while position < len {
+        let rest = bytes.slice_from(position);
+        if rest.starts_with(b"<!--") {
+            // Skip the comment, allowing the dashes to be shared: <!-->
+            match rest.slice_from(2).windows(3).position(|w| w == b"-->") {
+                Some(end) => position += 2 + end + 3,
+                None => return None,
+            }
+        } else if starts_with_ignoring_case(rest, b"<meta") &&
+                rest.len() > 5 && (is_space(rest[5]) || rest[5] == b'/') {
+            position += 5;
+            match prescan_meta(bytes, &mut position) {
+                Some(encoding) => return Some(encoding),
+                None => {}
+            }
+        } else if rest.len() > 2 && rest[0] == b'<' &&
+                (is_ascii_letter(rest[1]) || (rest[1] == b'/' && is_ascii_letter(rest[2]))) {
+            // Skip the tag name, then its attributes.
+            position += 1;
+            while position < len && !is_space(bytes[position]) && bytes[position] != b'>' {
+                position += 1;
+            }
+            while get_attribute(bytes, &mut position).is_some() {}
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"</") || rest.starts_with(b"<?") {
+            match rest.iter().position(|&b| b == b'>') {
+                Some(end) => position += end,
+                None => return None,
+            }
+        }
+        position += 1;
+    }
+    None
+}
+
+/// The encoding that a document is decoded with, and how sure we are of it.
+/// https://html.spec.whatwg.org/multipage/#determining-the-character-encoding
+pub fn determine_encoding(bytes: &[u8], transport_label: Option<&str>)
+                          -> (EncodingRef, Confidence, uint) {
+    // Step 1
+    match sniff_bom(bytes) {
+        Some((encoding, bom_length)) => return (encoding, Certain, bom_length),
+        None => {}
+    }
+    // Step 3
+    match transport_label.and_then(encoding_from_whatwg_label) {
+        Some(encoding) => return (encoding, Certain, 0),
+        None => {}
+    }
+    // Step 4
+    match prescan(bytes) {
+        Some(encoding) => return (encoding, Tentative, 0),
+        None => {}
+    }
+    // Step 9. Most of the legacy content on the web that doesn't declare its encoding is in
+    // windows-1252.
+    (WINDOWS_1252 as EncodingRef, Tentative, 0)
+}
+
+/// Decodes a document as it arrives. The first bytes are held back until there are enough of
+/// them to prescan.
+pub struct HTMLDecoder {
+    transport_label: Option<String>,
+    buffer: Vec<u8>,
+    encoding: Option<(EncodingRef, Confidence)>,
+    decoder: Option<Box<RawDecoder>>,
+}
+
+impl HTMLDecoder {
+    /// A decoder for a document whose transport layer gave `transport_label` as its encoding.
+    pub fn new(transport_label: Option<String>) -> HTMLDecoder {
+        HTMLDecoder {
+            transport_label: transport_label,
+            buffer: vec!(),
+            encoding: None,
+            decoder: None,
+        }
+    }
+
+    /// The encoding of the document, once it is known.
+    pub fn encoding(&self) -> Option<(EncodingRef, Confidence)> {
+        self.encoding
+    }
+
+    fn start_decoding(&mut self, output: &mut String) {
+        let (encoding, confidence, bom_length) = {
+            let label = self.transport_label.as_ref().map(|label| label.as_slice());
+            determine_encoding(self.buffer.as_slice(), label)
+        };
+        debug!("decoding document as {:s} ({})", encoding.name(), confidence);
+        self.encoding = Some((encoding, confidence));
+        self.decoder = Some(encoding.raw_decoder());
+        let buffer = ::std::mem::replace(&mut self.buffer, vec!());
+        self.decode(buffer.slice_from(bom_length), output);
+    }
+
+    /// Decode `input` into `output`, replacing invalid sequences with U+FFFD.
+    fn decode(&mut self, input: &[u8], output: &mut String) {
+        let decoder = self.decoder.as_mut().unwrap();
+        let mut remaining = input;
+        loop {
+            let (_, error) = decoder.raw_feed(remaining, output);
+            match error {
+                Some(error) => {
+                    output.push_char('\uFFFD');
+                    let upto = error.upto as int;
+                    remaining = remaining.slice_from(if upto > 0 { upto as uint } else { 0 });
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Decode the next chunk of the document, returning what can be decoded so far.
+    pub fn feed(&mut self, input: &[u8]) -> String {
+        let mut output = String::new();
+        if self.decoder.is_some() {
+            self.decode(input, &mut output);
+        } else {
+            self.buffer.push_all(input);
+            if self.buffer.len() >= PRESCAN_LENGTH {
+                self.start_decoding(&mut output);
+            }
+        }
+        output
+    }
+
+    /// Decode what remains of the document once all of it has arrived.
+    pub fn finish(&mut self) -> String {
+        let mut output = String::new();
+        if self.decoder.is_none() {
+            self.start_decoding(&mut output);
+        }
+        match self.decoder.as_mut().unwrap().raw_finish(&mut output) {
+            Some(_) => output.push_char('\uFFFD'),
+            None => {}
+        }
+        output
+    }
+}
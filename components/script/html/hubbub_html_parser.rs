@@ -17,14 +17,21 @@ use dom::htmlformelement::HTMLFormElement;
 use dom::htmlscriptelement::HTMLScriptElementHelpers;
 use dom::node::NodeHelpers;
 use dom::types::*;
+use html::encoding::HTMLDecoder;
 use page::Page;
 
+use encoding;
 use encoding::all::UTF_8;
-use encoding::types::{Encoding, DecodeReplace};
+use encoding::label::encoding_from_whatwg_label;
+use encoding::types::{Encoding, EncodingRef, DecodeReplace};
 
 use hubbub::hubbub;
 use hubbub::hubbub::{NullNs, HtmlNs, MathMlNs, SvgNs, XLinkNs, XmlNs, XmlNsNs};
-use servo_net::resource_task::{Load, LoadData, Payload, Done, ResourceTask, load_whole_resource};
+use servo_net::csp::ContentSecurityPolicy;
+use servo_net::fetch::request::{Request, RefererUrl};
+use servo_net::fetch::request::Script as ScriptContext;
+use servo_net::referrer_policy::{ReferrerPolicy, determine_referrer, parse_header};
+use servo_net::resource_task::{Load, LoadData, LoadGroupId, Payload, Done, ResourceTask};
 use servo_msg::constellation_msg::LoadData as MsgLoadData;
 use servo_util::str::DOMString;
 use servo_util::task::spawn_named;
@@ -64,7 +71,9 @@ pub enum HTMLInput {
 }
 
 enum JSMessage {
-    JSTaskNewFile(Url),
+    /// A script to load, the URL of the document it is loaded for, the label of the encoding
+    /// to decode it with if its response doesn't give one, and the document's referrer policy.
+    JSTaskNewFile(Url, Option<Url>, String, Option<ReferrerPolicy>),
     JSTaskNewInlineScript(String, Option<Url>),
     JSTaskExit
 }
@@ -94,20 +103,42 @@ unsafe fn from_hubbub_node<T: Reflectable>(n: hubbub::NodeDataPtr) -> Temporary<
 
 fn js_script_listener(to_parent: Sender<HtmlDiscoveryMessage>,
                       from_parent: Receiver<JSMessage>,
-                      resource_task: ResourceTask) {
+                      resource_task: ResourceTask,
+                      load_group: LoadGroupId) {
     let mut result_vec = vec!();
 
     loop {
         match from_parent.recv_opt() {
-            Ok(JSTaskNewFile(url)) => {
-                match load_whole_resource(&resource_task, url.clone()) {
+            Ok(JSTaskNewFile(url, document_url, fallback_encoding, referrer_policy)) => {
+                let mut request = Request::new(url.clone(), ScriptContext);
+                request.group = Some(load_group);
+                match document_url {
+                    Some(ref document_url) => request.referer = RefererUrl(document_url.clone()),
+                    None => {}
+                }
+                request.origin = document_url;
+                request.referrer_policy = referrer_policy;
+                match request.fetch(&resource_task).into_bytes() {
                     Err(_) => {
                         error!("error loading script {:s}", url.serialize());
                     }
                     Ok((metadata, bytes)) => {
-                        let decoded = UTF_8.decode(bytes.as_slice(), DecodeReplace).unwrap();
+                        // A byte order mark wins over the Content-Type charset, which wins over
+                        // the fallback.
+                        // https://html.spec.whatwg.org/multipage/#fetch-a-classic-script
+                        let script_encoding = metadata.charset.as_ref()
+                                               .and_then(|charset| {
+                                                   encoding_from_whatwg_label(charset.as_slice())
+                                               })
+                                               .or_else(|| {
+                                                   encoding_from_whatwg_label(
+                                                       fallback_encoding.as_slice())
+                                               })
+                                               .unwrap_or(UTF_8 as EncodingRef);
+                        let (decoded, _) = encoding::decode(bytes.as_slice(), DecodeReplace,
+                                                            script_encoding);
                         result_vec.push(JSFile {
-                            data: decoded.to_string(),
+                            data: decoded.unwrap(),
                             url: Some(metadata.final_url),
                         });
                     }
@@ -298,8 +329,9 @@ pub fn parse_html(page: &Page,
     let resource_task2 = resource_task.clone();
     let js_result_chan = discovery_chan.clone();
     let (js_chan, js_msg_port) = channel();
+    let load_group = page.load_group();
     spawn_named("parse_html:js", proc() {
-        js_script_listener(js_result_chan, js_msg_port, resource_task2.clone());
+        js_script_listener(js_result_chan, js_msg_port, resource_task2.clone(), load_group);
     });
 
     let (base_url, load_response) = match input {
@@ -307,11 +339,16 @@ pub fn parse_html(page: &Page,
             // Wait for the LoadResponse so that the parser knows the final URL.
             let (input_chan, input_port) = channel();
             let mut load_data = LoadData::new(url.clone());
+            load_data.group = Some(load_group);
             msg_load_data.map(|m| {
                 load_data.headers = m.headers;
                 load_data.method = m.method;
                 load_data.data = m.data;
+                load_data.referrer = m.referrer;
+                load_data.referrer_policy = m.referrer_policy;
             });
+            let referrer = load_data.referrer.clone();
+            let referrer_policy = load_data.referrer_policy;
             resource_task.send(Load(load_data, input_chan));
 
             let load_response = input_port.recv();
@@ -330,6 +367,41 @@ pub fn parse_html(page: &Page,
                 };
             });
 
+            // The document's Content Security Policy applies from the start of parsing, and
+            // 'self' refers to the origin of the final URL.
+            let mut policy = ContentSecurityPolicy::new(load_response.metadata.final_url.clone());
+            load_response.metadata.headers.as_ref().map(|headers| {
+                for h in headers.iter() {
+                    match h.header_name().as_slice().to_ascii_lower().as_slice() {
+                        "content-security-policy" => {
+                            policy.add_header(h.header_value().as_slice(), false)
+                        }
+                        "content-security-policy-report-only" => {
+                            policy.add_header(h.header_value().as_slice(), true)
+                        }
+                        "referrer-policy" => {
+                            match parse_header(h.header_value().as_slice()) {
+                                Some(header_policy) => {
+                                    document.set_referrer_policy(Some(header_policy))
+                                }
+                                None => {}
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            });
+            document.set_content_security_policy(policy);
+
+            // document.referrer is the referrer that was sent for the final URL.
+            let final_url = &load_response.metadata.final_url;
+            match referrer.and_then(|referrer| {
+                determine_referrer(&referrer, final_url, referrer_policy)
+            }) {
+                Some(referrer) => document.set_referrer(referrer.serialize()),
+                None => {}
+            }
+
             let base_url = load_response.metadata.final_url.clone();
 
             {
@@ -492,7 +564,21 @@ pub fn parse_html(page: &Page,
                             }
                         };
                         match url_parser.parse(src.value().as_slice()) {
-                            Ok(new_url) => js_chan2.send(JSTaskNewFile(new_url)),
+                            Ok(new_url) => {
+                                if document.allows_load(ScriptContext, &new_url) {
+                                    let charset = script_element.get_attribute(ns!(""), "charset")
+                                                                .root()
+                                                                .map(|charset| charset.Value());
+                                    let fallback_encoding = match charset {
+                                        Some(ref charset) if encoding_from_whatwg_label(
+                                            charset.as_slice()).is_some() => charset.clone(),
+                                        _ => document.encoding_name.borrow().clone(),
+                                    };
+                                    js_chan2.send(JSTaskNewFile(new_url, base_url.clone(),
+                                                                fallback_encoding,
+                                                                document.referrer_policy()))
+                                }
+                            }
                             Err(e) => debug!("Parsing url {:s} failed: {:?}", src.Value(), e)
                         };
                     }
@@ -507,7 +593,14 @@ pub fn parse_html(page: &Page,
                         }
 
                         debug!("script data = {:?}", data);
-                        js_chan2.send(JSTaskNewInlineScript(data, base_url.clone()));
+                        let nonce = script_element.get_attribute(ns!(""), "nonce").root()
+                                                  .map(|nonce| nonce.Value());
+                        let allowed = document.allows_inline(
+                            ScriptContext, nonce.as_ref().map(|nonce| nonce.as_slice()),
+                            Some(data.as_slice()));
+                        if allowed {
+                            js_chan2.send(JSTaskNewInlineScript(data, base_url.clone()));
+                        }
                     }
                 }
             }
@@ -531,17 +624,42 @@ pub fn parse_html(page: &Page,
                     let page = format!("<html><body><img src='{:s}' /></body></html>", base_url.as_ref().unwrap().serialize());
                     parser.parse_chunk(page.into_bytes().as_slice());
                 },
-                _ => loop {
-                    match load_response.progress_port.recv() {
-                        Payload(data) => {
-                            debug!("received data");
-                            parser.parse_chunk(data.as_slice());
-                        }
-                        Done(Err(err)) => {
-                            fail!("Failed to load page URL {:s}, error: {:s}", url.serialize(), err);
+                _ => {
+                    // Hubbub is given UTF-8; the document is decoded as it arrives, once enough
+                    // of it has been seen to determine its encoding.
+                    let mut decoder = HTMLDecoder::new(load_response.metadata.charset.clone());
+                    let set_encoding_name = |decoder: &HTMLDecoder| {
+                        match decoder.encoding() {
+                            Some((encoding, _)) => {
+                                let name = encoding.whatwg_name().unwrap_or(encoding.name());
+                                document.set_encoding_name(name.to_string());
+                            }
+                            None => {}
                         }
-                        Done(..) => {
-                            break;
+                    };
+                    loop {
+                        match load_response.progress_port.recv() {
+                            Payload(data) => {
+                                debug!("received data");
+                                let known = decoder.encoding().is_some();
+                                let decoded = decoder.feed(data.as_slice());
+                                if !known {
+                                    set_encoding_name(&decoder);
+                                }
+                                parser.parse_chunk(decoded.as_bytes());
+                            }
+                            Done(Err(err)) => {
+                                fail!("Failed to load page URL {:s}, error: {:s}", url.serialize(), err);
+                            }
+                            Done(..) => {
+                                let known = decoder.encoding().is_some();
+                                let decoded = decoder.finish();
+                                if !known {
+                                    set_encoding_name(&decoder);
+                                }
+                                parser.parse_chunk(decoded.as_bytes());
+                                break;
+                            }
                         }
                     }
                 }
@@ -558,7 +676,9 @@ pub fn parse_html(page: &Page,
 }
 
 fn build_parser<'a>(node: hubbub::NodeDataPtr) -> hubbub::Parser<'a> {
-    let mut parser = hubbub::Parser::new("UTF-8", false);
+    // The input is always decoded to UTF-8 before it reaches hubbub, so it must not switch
+    // encodings itself.
+    let mut parser = hubbub::Parser::new("UTF-8", true);
     parser.set_document_node(node);
     parser.enable_scripting(true);
     parser.enable_styling(true);
@@ -15,21 +15,33 @@ use geom::rect::Rect;
 use js::jsapi::JSTracer;
 use libc::c_void;
 use script_traits::{ScriptControlChan, OpaqueScriptLayoutChannel, UntrustedNodeAddress};
+pub use script_traits::StylesheetId;
 use servo_msg::constellation_msg::WindowSizeData;
+use servo_net::csp::ContentSecurityPolicy;
+use servo_net::referrer_policy::ReferrerPolicy;
 use servo_util::geometry::Au;
 use std::any::{Any, AnyRefExt};
 use std::comm::{channel, Receiver, Sender};
 use std::owned::BoxAny;
-use style::Stylesheet;
+use style::{PseudoElement, Stylesheet};
 use url::Url;
 
 /// Asynchronous messages that script can send to layout.
 pub enum Msg {
-    /// Adds the given stylesheet to the document.
-    AddStylesheetMsg(Stylesheet),
+    /// Adds the given stylesheet to the document, or replaces the one that was added with the
+    /// same id. The stylesheets it imports are loaded like the ones of `LoadStylesheetMsg`.
+    AddStylesheetMsg(StylesheetId, Stylesheet, ContentSecurityPolicy, String,
+                     Option<ReferrerPolicy>),
 
-    /// Adds the given stylesheet to the document.
-    LoadStylesheetMsg(Url),
+    /// Removes the stylesheet that was added with the given id from the document.
+    RemoveStylesheetMsg(StylesheetId),
+
+    /// Loads the stylesheet at the given URL and adds it to the document with the given id, if
+    /// the document's Content Security Policy allows it. The label names the encoding the
+    /// stylesheet is decoded with if neither it nor its response declares one, and the
+    /// document's referrer policy decides what is sent as its referrer. Its text is handed back
+    /// to script with `StylesheetLoadedMsg`.
+    LoadStylesheetMsg(StylesheetId, Url, ContentSecurityPolicy, String, Option<ReferrerPolicy>),
 
     /// Requests a reflow.
     ReflowMsg(Box<Reflow>),
@@ -68,6 +80,10 @@ pub trait LayoutRPC {
     /// Requests the node containing the point of interest
     fn hit_test(&self, node: TrustedNodeAddress, point: Point2D<f32>) -> Result<HitTestResponse, ()>;
     fn mouse_over(&self, node: TrustedNodeAddress, point: Point2D<f32>) -> Result<MouseOverResponse, ()>;
+    /// Requests the computed value of a property of an element or of one of its
+    /// pseudo-elements, as in the `getComputedStyle()` call.
+    fn computed_style(&self, node: TrustedNodeAddress, pseudo_element: Option<PseudoElement>,
+                      property: &str) -> ComputedStyleResponse;
 }
 
 /// The address of a node known to be valid. These must only be sent from content -> layout,
@@ -88,6 +104,8 @@ pub struct ContentBoxResponse(pub Rect<Au>);
 pub struct ContentBoxesResponse(pub Vec<Rect<Au>>);
 pub struct HitTestResponse(pub UntrustedNodeAddress);
 pub struct MouseOverResponse(pub Vec<UntrustedNodeAddress>);
+/// The serialized computed value, if the property is known and the element has been styled.
+pub struct ComputedStyleResponse(pub Option<String>);
 
 /// Why we're doing reflow.
 #[deriving(PartialEq, Show)]
@@ -37,6 +37,7 @@ extern crate script_traits;
 extern crate "plugins" as servo_plugins;
 extern crate "net" as servo_net;
 extern crate "util" as servo_util;
+#[phase(plugin, link)]
 extern crate style;
 extern crate sync;
 extern crate "msg" as servo_msg;
@@ -46,7 +47,6 @@ extern crate string_cache;
 #[phase(plugin)]
 extern crate string_cache_macros;
 
-pub mod cors;
 
 /// The implementation of the DOM.
 #[macro_escape]
@@ -88,8 +88,14 @@ pub mod dom {
     pub mod characterdata;
     pub mod domrect;
     pub mod domrectlist;
+    pub mod closeevent;
     pub mod comment;
     pub mod console;
+    pub mod cssrule;
+    pub mod cssrulelist;
+    pub mod cssstyledeclaration;
+    pub mod cssstylerule;
+    pub mod cssstylesheet;
     pub mod customevent;
     pub mod dedicatedworkerglobalscope;
     pub mod document;
@@ -104,6 +110,7 @@ pub mod dom {
     pub mod eventdispatcher;
     pub mod eventtarget;
     pub mod file;
+    pub mod filereader;
     pub mod formdata;
     pub mod htmlanchorelement;
     pub mod htmlappletelement;
@@ -176,6 +183,7 @@ pub mod dom {
     pub mod htmlvideoelement;
     pub mod htmlunknownelement;
     pub mod location;
+    pub mod mediaquerylist;
     pub mod messageevent;
     pub mod mouseevent;
     pub mod namednodemap;
@@ -190,13 +198,17 @@ pub mod dom {
     pub mod progressevent;
     pub mod range;
     pub mod screen;
+    pub mod stylesheet;
+    pub mod stylesheetlist;
     pub mod text;
     pub mod treewalker;
     pub mod uievent;
+    pub mod url;
     pub mod urlhelper;
     pub mod urlsearchparams;
     pub mod validitystate;
     pub mod virtualmethods;
+    pub mod websocket;
     pub mod window;
     pub mod worker;
     pub mod workerglobalscope;
@@ -211,6 +223,7 @@ pub mod dom {
 
 /// Parsers for HTML and CSS.
 pub mod html {
+    pub mod encoding;
     pub mod hubbub_html_parser;
 }
 
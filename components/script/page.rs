@@ -16,13 +16,16 @@ use layout_interface::{GetRPCMsg, LayoutChan, LayoutRPC};
 use layout_interface::{Reflow, ReflowGoal, ReflowMsg};
 use script_traits::{UntrustedNodeAddress, ScriptControlChan};
 
+use devtools_traits::{DevtoolsControlChan, ReportPageErrorMsg, PageError};
+
 use geom::point::Point2D;
 use js::rust::Cx;
 use servo_msg::compositor_msg::PerformingLayout;
 use servo_msg::compositor_msg::ScriptListener;
 use servo_msg::constellation_msg::{ConstellationChan, WindowSizeData};
 use servo_msg::constellation_msg::{PipelineId, SubpageId};
-use servo_net::resource_task::ResourceTask;
+use servo_net::csp::Violation;
+use servo_net::resource_task::{LoadGroupId, ResourceTask};
 use servo_util::str::DOMString;
 use servo_util::smallvec::{SmallVec1, SmallVec};
 use std::cell::{Cell, RefCell, Ref, RefMut};
@@ -83,6 +86,9 @@ pub struct Page {
     /// A handle for communicating messages to the constellation task.
     pub constellation_chan: ConstellationChan,
 
+    /// A handle for reporting errors about the page to the devtools server, if it's running.
+    pub devtools_chan: Option<DevtoolsControlChan>,
+
     // Child Pages.
     pub children: RefCell<Vec<Rc<Page>>>,
 
@@ -128,6 +134,7 @@ impl Page {
            window_size: WindowSizeData,
            resource_task: ResourceTask,
            constellation_chan: ConstellationChan,
+           devtools_chan: Option<DevtoolsControlChan>,
            js_context: Rc<Cx>) -> Page {
         let js_info = JSPageInfo {
             dom_static: GlobalStaticData(),
@@ -156,6 +163,7 @@ impl Page {
             last_reflow_id: Cell::new(0),
             resource_task: resource_task,
             constellation_chan: constellation_chan,
+            devtools_chan: devtools_chan,
             children: RefCell::new(vec!()),
             damaged: Cell::new(false),
             pending_reflows: Cell::new(0),
@@ -227,6 +235,32 @@ impl Iterator<Rc<Page>> for PageIterator {
 }
 
 impl Page {
+    /// The group that the loads made on behalf of this page belong to.
+    pub fn load_group(&self) -> LoadGroupId {
+        let PipelineId(id) = self.id;
+        LoadGroupId(id)
+    }
+
+    /// Report Content Security Policy violations to the devtools console of the page, or to
+    /// the log when devtools aren't running.
+    pub fn report_csp_violations(&self, violations: Vec<Violation>) {
+        for violation in violations.into_iter() {
+            let message = violation.message();
+            match self.devtools_chan {
+                Some(ref chan) => {
+                    debug!("{:s}", message);
+                    chan.send(ReportPageErrorMsg(self.id, PageError {
+                        message: message,
+                        source_name: violation.document_url.serialize(),
+                        category: "CSP".to_string(),
+                        warning: violation.report_only,
+                    }));
+                }
+                None => warn!("{:s}", message),
+            }
+        }
+    }
+
     pub fn mut_js_info<'a>(&'a self) -> RefMut<'a, Option<JSPageInfo>> {
         self.js_info.borrow_mut()
     }
@@ -8,10 +8,12 @@
 use dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use dom::bindings::codegen::Bindings::DOMRectBinding::DOMRectMethods;
 use dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
+use dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use dom::bindings::codegen::InheritTypes::{EventTargetCast, NodeCast, EventCast, ElementCast};
 use dom::bindings::conversions;
 use dom::bindings::conversions::{FromJSValConvertible, Empty};
 use dom::bindings::global;
+use dom::bindings::global::global_object_for_js_object;
 use dom::bindings::js::{JS, JSRef, RootCollection, Temporary, OptionalRootable};
 use dom::bindings::trace::JSTraceable;
 use dom::bindings::utils::Reflectable;
@@ -20,11 +22,14 @@ use dom::document::{Document, HTMLDocument, DocumentHelpers};
 use dom::element::{Element, HTMLButtonElementTypeId, HTMLInputElementTypeId};
 use dom::element::{HTMLSelectElementTypeId, HTMLTextAreaElementTypeId, HTMLOptionElementTypeId};
 use dom::event::Event;
+use dom::htmllinkelement::HTMLLinkElement;
 use dom::uievent::UIEvent;
 use dom::eventtarget::{EventTarget, EventTargetHelpers};
 use dom::node;
 use dom::node::{ElementNodeTypeId, Node, NodeHelpers};
 use dom::window::{TimerId, Window, WindowHelpers};
+use dom::websocket::{WebSocket, TrustedWebSocketAddress};
+use dom::filereader::{FileReader, FileReaderResult, TrustedFileReaderAddress};
 use dom::worker::{Worker, TrustedWorkerAddress};
 use dom::xmlhttprequest::{TrustedXHRAddress, XMLHttpRequest, XHRProgress};
 use html::hubbub_html_parser::{InputString, InputUrl, HtmlParserResult, HtmlDiscoveredScript};
@@ -41,21 +46,24 @@ use script_traits::{CompositorEvent, ResizeEvent, ReflowEvent, ClickEvent, Mouse
 use script_traits::{MouseMoveEvent, MouseUpEvent, ConstellationControlMsg, ScriptTaskFactory};
 use script_traits::{ResizeMsg, AttachLayoutMsg, LoadMsg, SendEventMsg, ResizeInactiveMsg};
 use script_traits::{ExitPipelineMsg, NewLayoutInfo, OpaqueScriptLayoutChannel, ScriptControlChan};
-use script_traits::ReflowCompleteMsg;
+use script_traits::{ReflowCompleteMsg, ReportCSPViolationsMsg, StylesheetId, StylesheetLoadedMsg};
 use servo_msg::compositor_msg::{FinishedLoading, LayerId, Loading};
 use servo_msg::compositor_msg::{ScriptListener};
 use servo_msg::constellation_msg::{ConstellationChan, LoadCompleteMsg, LoadUrlMsg, NavigationDirection};
 use servo_msg::constellation_msg::{LoadData, PipelineId, Failure, FailureMsg, WindowSizeData};
 use servo_msg::constellation_msg;
+use servo_net::csp::Violation;
 use servo_net::image_cache_task::ImageCacheTask;
-use servo_net::resource_task::ResourceTask;
+use servo_net::resource_task::{CancelLoadGroup, ResourceTask};
+use servo_net::websocket::WebSocketEvent;
 use servo_util::geometry::to_frac_px;
 use servo_util::smallvec::{SmallVec1, SmallVec};
 use servo_util::task::spawn_named_with_send_on_failure;
 
 use geom::point::Point2D;
 use js::jsapi::{JS_SetWrapObjectCallbacks, JS_SetGCZeal, JS_DEFAULT_ZEAL_FREQ, JS_GC};
-use js::jsapi::{JSContext, JSRuntime, JSTracer};
+use js::jsapi::{JSBool, JSContext, JSRuntime, JSTracer};
+use js::jsapi::{JS_SetSecurityCallbacks, JSSecurityCallbacks, JS_GetGlobalForScopeChain};
 use js::jsapi::{JS_SetGCParameter, JSGC_MAX_BYTES};
 use js::rust::{Cx, RtUtils};
 use js::rust::with_compartment;
@@ -99,6 +107,10 @@ pub enum ScriptMsg {
     WorkerPostMessage(TrustedWorkerAddress, *mut u64, size_t),
     /// Releases one reference to the Worker object (dispatched to all tasks).
     WorkerRelease(TrustedWorkerAddress),
+    /// Notifies a WebSocket object of the progress of its connection (dispatched to all tasks).
+    WebSocketEventMsg(TrustedWebSocketAddress, WebSocketEvent),
+    /// Delivers the result of a read to a FileReader object (dispatched to all tasks).
+    FileReaderMsg(TrustedFileReaderAddress, FileReaderResult),
 }
 
 /// Encapsulates internal communication within the script task.
@@ -174,6 +186,22 @@ pub struct ScriptTask {
     mouse_over_targets: RefCell<Option<Vec<JS<Node>>>>
 }
 
+static SECURITY_CALLBACKS: JSSecurityCallbacks = JSSecurityCallbacks {
+    checkObjectAccess: None,
+    contentSecurityPolicyAllows: Some(content_security_policy_allows),
+};
+
+/// Called by SpiderMonkey before it evaluates a string as code, with `eval()`, `Function()`
+/// and the like: whether the policies of the document allow that.
+unsafe extern fn content_security_policy_allows(cx: *mut JSContext) -> JSBool {
+    let global = global_object_for_js_object(JS_GetGlobalForScopeChain(cx)).root();
+    let allowed = match global.root_ref() {
+        global::Window(window) => window.Document().root().allows_eval(),
+        global::Worker(_) => true,
+    };
+    allowed as JSBool
+}
+
 /// In the event of task failure, all data on the stack runs its destructor. However, there
 /// are no reachable, owning pointers to the DOM memory, so it never gets freed by default
 /// when the script task fails. The ScriptMemoryFailsafe uses the destructor bomb pattern
@@ -305,11 +333,13 @@ impl ScriptTask {
                                       callback,
                                       Some(wrap_for_same_compartment),
                                       Some(pre_wrap));
+            JS_SetSecurityCallbacks((*js_runtime).ptr, &SECURITY_CALLBACKS);
         }
 
         let page = Page::new(id, None, layout_chan, window_size,
                              resource_task.clone(),
                              constellation_chan.clone(),
+                             devtools_chan.clone(),
                              js_context.clone());
 
         // Notify devtools that a new script global exists.
@@ -502,12 +532,17 @@ impl ScriptTask {
                 FromConstellation(ReflowCompleteMsg(id, reflow_id)) => self.handle_reflow_complete_msg(id, reflow_id),
                 FromConstellation(ResizeInactiveMsg(id, new_size)) => self.handle_resize_inactive_msg(id, new_size),
                 FromConstellation(ExitPipelineMsg(id)) => if self.handle_exit_pipeline_msg(id) { return false },
+                FromConstellation(ReportCSPViolationsMsg(id, violations)) => self.handle_csp_violations(id, violations),
+                FromConstellation(StylesheetLoadedMsg(id, stylesheet_id, url, css)) =>
+                    self.handle_stylesheet_loaded(id, stylesheet_id, url, css),
                 FromScript(ExitWindowMsg(id)) => self.handle_exit_window_msg(id),
                 FromConstellation(ResizeMsg(..)) => fail!("should have handled ResizeMsg already"),
                 FromScript(XHRProgressMsg(addr, progress)) => XMLHttpRequest::handle_xhr_progress(addr, progress),
                 FromScript(DOMMessage(..)) => fail!("unexpected message"),
                 FromScript(WorkerPostMessage(addr, data, nbytes)) => Worker::handle_message(addr, data, nbytes),
                 FromScript(WorkerRelease(addr)) => Worker::handle_release(addr),
+                FromScript(WebSocketEventMsg(addr, event)) => WebSocket::handle_event(addr, event),
+                FromScript(FileReaderMsg(addr, result)) => FileReader::handle_result(addr, result),
                 FromDevtools(EvaluateJS(id, s, reply)) => self.handle_evaluate_js(id, s, reply),
                 FromDevtools(GetRootNode(id, reply)) => self.handle_get_root_node(id, reply),
                 FromDevtools(GetDocumentElement(id, reply)) => self.handle_get_document_element(id, reply),
@@ -614,6 +649,7 @@ impl ScriptTask {
                       window_size,
                       parent_page.resource_task.clone(),
                       self.constellation_chan.clone(),
+                      self.devtools_chan.clone(),
                       self.js_context.borrow().as_ref().unwrap().clone())
         };
         parent_page.children.borrow_mut().push(Rc::new(new_page));
@@ -630,6 +666,32 @@ impl ScriptTask {
     }
 
     /// Handles a notification that reflow completed.
+    /// Handles Content Security Policy violations found by layout or the constellation.
+    fn handle_csp_violations(&self, pipeline_id: PipelineId, violations: Vec<Violation>) {
+        let page = self.page.borrow();
+        match page.find(pipeline_id) {
+            Some(page) => page.report_csp_violations(violations),
+            None => {}
+        }
+    }
+
+    fn handle_stylesheet_loaded(&self, pipeline_id: PipelineId, id: StylesheetId, url: Url,
+                                css: String) {
+        let page = self.page.borrow();
+        let page = match page.find(pipeline_id) {
+            Some(page) => page,
+            None => return,
+        };
+        let frame = page.frame();
+        match *frame {
+            Some(ref frame) => {
+                let document = frame.document.root();
+                HTMLLinkElement::handle_stylesheet_loaded(*document, id, url, css.as_slice());
+            }
+            None => {}
+        }
+    }
+
     fn handle_reflow_complete_msg(&self, pipeline_id: PipelineId, reflow_id: uint) {
         debug!("Script: Reflow {:?} complete for {:?}", reflow_id, pipeline_id);
         let mut page = self.page.borrow_mut();
@@ -932,6 +994,9 @@ impl ScriptTask {
 
                         let wintarget: JSRef<EventTarget> = EventTargetCast::from_ref(*window);
                         let _ = wintarget.dispatch_event_with_target(None, event);
+
+                        // http://dev.w3.org/csswg/cssom-view/#evaluate-media-queries-and-report-changes
+                        window.evaluate_media_queries();
                     }
                     None => ()
                 }
@@ -1068,8 +1133,17 @@ impl ScriptTask {
     /// The entry point for content to notify that a new load has been requested
     /// for the given pipeline.
     fn trigger_load(&self, pipeline_id: PipelineId, url: Url) {
+        let mut load_data = LoadData::new(url);
+        {
+            // The document that started the navigation is its referrer.
+            let page = get_page(&*self.page.borrow(), pipeline_id);
+            let frame = page.frame();
+            let document = frame.as_ref().unwrap().document.root();
+            load_data.referrer = Some(document.url().clone());
+            load_data.referrer_policy = document.referrer_policy();
+        }
         let ConstellationChan(ref const_chan) = self.constellation_chan;
-        const_chan.send(LoadUrlMsg(pipeline_id, LoadData::new(url)));
+        const_chan.send(LoadUrlMsg(pipeline_id, load_data));
     }
 
     /// The entry point for content to notify that a fragment url has been requested
@@ -1087,6 +1161,11 @@ impl ScriptTask {
 
 /// Shuts down layout for the given page tree.
 fn shut_down_layout(page_tree: &Rc<Page>, rt: *mut JSRuntime) {
+    // Stop the loads that are still running on behalf of the exiting pages.
+    for page in page_tree.iter() {
+        page.resource_task.send(CancelLoadGroup(page.load_group()));
+    }
+
     for page in page_tree.iter() {
         page.join_layout();
 
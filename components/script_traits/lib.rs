@@ -26,10 +26,12 @@ use libc::c_void;
 use servo_msg::constellation_msg::{ConstellationChan, PipelineId, Failure, WindowSizeData};
 use servo_msg::constellation_msg::{LoadData, SubpageId};
 use servo_msg::compositor_msg::ScriptListener;
+use servo_net::csp::Violation;
 use servo_net::image_cache_task::ImageCacheTask;
 use servo_net::resource_task::ResourceTask;
 use servo_util::smallvec::SmallVec1;
 use std::any::Any;
+use url::Url;
 
 use geom::point::Point2D;
 
@@ -62,8 +64,19 @@ pub enum ConstellationControlMsg {
     SendEventMsg(PipelineId, CompositorEvent),
     /// Notifies script that reflow is finished.
     ReflowCompleteMsg(PipelineId, uint),
+    /// Reports loads that the Content Security Policy of a page blocked, or would have blocked
+    /// if it were enforced.
+    ReportCSPViolationsMsg(PipelineId, Vec<Violation>),
+    /// Hands script the text of a stylesheet that layout loaded for a `<link>` element, with
+    /// the id script gave its load and the URL it was finally loaded from.
+    StylesheetLoadedMsg(PipelineId, StylesheetId, Url, String),
 }
 
+/// Identifies a stylesheet that script added to the document, so that it can replace or remove
+/// it later.
+#[deriving(Clone, PartialEq, Show)]
+pub struct StylesheetId(pub uint);
+
 /// Events from the compositor that the script task needs to know about
 pub enum CompositorEvent {
     ResizeEvent(WindowSizeData),
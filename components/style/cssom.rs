@@ -0,0 +1,451 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The source of declarations and rules as the [CSSOM](http://dev.w3.org/csswg/cssom/) exposes
+//! them. Script edits these, and serializes them back to CSS to be parsed into a `Stylesheet`
+//! or a `PropertyDeclarationBlock` whenever they change.
+
+use std::ascii::StrAsciiExt;
+use url::Url;
+
+use cssparser::{tokenize, parse_stylesheet_rules, parse_declaration_list, ToCss};
+use cssparser::ast::*;
+use errors::ErrorLoggerIterator;
+use namespaces::{NamespaceMap, parse_namespace_rule};
+use properties::{PropertyDeclaration, PropertyBitField, ValidOrIgnoredDeclaration};
+use properties::longhands_of_shorthand;
+use selectors::parse_selector_list;
+
+
+#[deriving(Clone, PartialEq, Show)]
+pub struct DeclarationSource {
+    /// The name of the property, in lower case.
+    pub name: String,
+    pub value: String,
+    pub important: bool,
+}
+
+
+/// The valid declarations of a style attribute or of a style rule, in order.
+#[deriving(Clone, PartialEq, Show)]
+pub struct DeclarationBlockSource {
+    pub declarations: Vec<DeclarationSource>,
+}
+
+
+fn is_valid_declaration(name: &str, value: &[ComponentValue], base_url: &Url) -> bool {
+    let mut result_list = vec!();
+    match PropertyDeclaration::parse(name, value, &mut result_list, base_url,
+                                     &mut PropertyBitField::new()) {
+        ValidOrIgnoredDeclaration => true,
+        _ => false,
+    }
+}
+
+
+impl DeclarationBlockSource {
+    pub fn new() -> DeclarationBlockSource {
+        DeclarationBlockSource {
+            declarations: vec!(),
+        }
+    }
+
+    pub fn parse(css: &str, base_url: &Url) -> DeclarationBlockSource {
+        DeclarationBlockSource::from_iter(tokenize(css), base_url)
+    }
+
+    fn from_iter<I: Iterator<Node>>(input: I, base_url: &Url) -> DeclarationBlockSource {
+        let mut block = DeclarationBlockSource::new();
+        for item in ErrorLoggerIterator(parse_declaration_list(input)) {
+            match item {
+                DeclAtRule(_) => {},
+                Declaration_(Declaration{ location: _, name: name, value: value, important: important }) => {
+                    if !is_valid_declaration(name.as_slice(), value.as_slice(), base_url) {
+                        continue
+                    }
+                    // As in the cascade, a later declaration of a property overrides an
+                    // earlier one, unless only the earlier one is important.
+                    let name = name.as_slice().to_ascii_lower();
+                    match block.get(name.as_slice()) {
+                        Some(declaration) if declaration.important && !important => continue,
+                        _ => {}
+                    }
+                    let value = value.iter().to_css();
+                    block.push(name, value.as_slice().trim().to_string(), important)
+                }
+            }
+        }
+        block
+    }
+
+    pub fn len(&self) -> uint {
+        self.declarations.len()
+    }
+
+    pub fn get<'a>(&'a self, name: &str) -> Option<&'a DeclarationSource> {
+        let name = name.to_ascii_lower();
+        self.declarations.iter().find(|declaration| declaration.name == name)
+    }
+
+    /// The declarations of the longhands of the shorthand `name`, if it is one and they are all
+    /// declared with the same importance.
+    fn longhand_declarations<'a>(&'a self, name: &str) -> Option<Vec<&'a DeclarationSource>> {
+        let longhands = match longhands_of_shorthand(name) {
+            Some(longhands) => longhands,
+            None => return None,
+        };
+        let mut declarations = vec!();
+        for longhand in longhands.iter() {
+            match self.get(longhand.as_slice()) {
+                Some(declaration) => declarations.push(declaration),
+                None => return None,
+            }
+        }
+        if declarations.iter().any(|declaration| {
+            declaration.important != declarations[0].important
+        }) {
+            return None
+        }
+        Some(declarations)
+    }
+
+    /// The value of the property `name`. A shorthand that wasn't declared has a value when all
+    /// of its longhands were, with the same importance.
+    /// http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-getpropertyvalue
+    pub fn value(&self, name: &str) -> Option<String> {
+        match self.get(name) {
+            Some(declaration) => return Some(declaration.value.clone()),
+            None => {}
+        }
+        self.longhand_declarations(name).and_then(|declarations| {
+            let values: Vec<&str> = declarations.iter().map(|declaration| {
+                declaration.value.as_slice()
+            }).collect();
+            serialize_shorthand(name.to_ascii_lower().as_slice(), values.as_slice())
+        })
+    }
+
+    /// Whether the property `name` is important. A shorthand that wasn't declared is when all of
+    /// its longhands are.
+    /// http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-getpropertypriority
+    pub fn important(&self, name: &str) -> bool {
+        match self.get(name) {
+            Some(declaration) => declaration.important,
+            None => match self.longhand_declarations(name) {
+                Some(declarations) => declarations[0].important,
+                None => false,
+            },
+        }
+    }
+
+    /// Sets the property `name` to `value`, if it is a valid value for it. A shorthand replaces
+    /// the declarations of its longhands.
+    pub fn set(&mut self, name: &str, value: &str, important: bool, base_url: &Url) -> bool {
+        let tokens: Vec<ComponentValue> = tokenize(value).map(|(token, _)| token).collect();
+        if !is_valid_declaration(name, tokens.as_slice(), base_url) {
+            return false
+        }
+        self.push(name.to_ascii_lower(), value.trim().to_string(), important);
+        true
+    }
+
+    /// Removes the declaration of the property `name`, and those of its longhands if it is a
+    /// shorthand. Returns the value it had.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let name = name.to_ascii_lower();
+        let value = self.get(name.as_slice()).map(|declaration| declaration.value.clone());
+        let mut names = longhands_of_shorthand(name.as_slice()).unwrap_or(vec!());
+        names.push(name);
+        self.declarations.retain(|declaration| !names.contains(&declaration.name));
+        value
+    }
+
+    fn push(&mut self, name: String, value: String, important: bool) {
+        self.remove(name.as_slice());
+        self.declarations.push(DeclarationSource {
+            name: name,
+            value: value,
+            important: important,
+        })
+    }
+
+    /// http://dev.w3.org/csswg/cssom/#serialize-a-css-declaration-block
+    pub fn serialize(&self) -> String {
+        let declarations: Vec<String> = self.declarations.iter().map(|declaration| {
+            format!("{:s}: {:s}{:s};", declaration.name, declaration.value,
+                    if declaration.important { " !important" } else { "" })
+        }).collect();
+        declarations.connect(" ")
+    }
+}
+
+
+/// Serializes the shorthand `name` from the values of its longhands, in the order
+/// `longhands_of_shorthand()` returns them, or returns `None` if no value of the shorthand sets
+/// them all to those values.
+/// http://dev.w3.org/csswg/cssom/#serialize-a-css-value
+fn serialize_shorthand(name: &str, values: &[&str]) -> Option<String> {
+    // A CSS-wide keyword can only be given to all the longhands at once.
+    let is_keyword = |value: &&str| {
+        value.eq_ignore_ascii_case("inherit") || value.eq_ignore_ascii_case("initial")
+    };
+    if values.iter().any(|value| is_keyword(value)) {
+        if values.iter().all(|value| value.eq_ignore_ascii_case(values[0])) {
+            return Some(values[0].to_string())
+        }
+        return None
+    }
+    match name {
+        "margin" | "padding" | "border-color" | "border-style" | "border-width" => {
+            // The values of the top, right, bottom and left sides, leaving out those that the
+            // parser would repeat.
+            let (top, right, bottom, left) = (values[0], values[1], values[2], values[3]);
+            let sides = if left != right {
+                values
+            } else if bottom != top {
+                values.slice_to(3)
+            } else if right != top {
+                values.slice_to(2)
+            } else {
+                values.slice_to(1)
+            };
+            Some(sides.connect(" "))
+        }
+        "border" => {
+            // The color, style and width of each side; only a border that is the same on every
+            // side is a value of `border`.
+            if values.chunks(3).any(|side| side != values.slice_to(3)) {
+                return None
+            }
+            Some(values.slice_to(3).connect(" "))
+        }
+        "font" => {
+            // The style, variant, weight, size, line height and family.
+            Some(format!("{:s} {:s} {:s} {:s}/{:s} {:s}",
+                         values[0], values[1], values[2], values[3], values[4], values[5]))
+        }
+        _ => Some(values.connect(" ")),
+    }
+}
+
+
+#[deriving(Clone, PartialEq, Show)]
+pub enum RuleSource {
+    /// A style rule: its selectors as written, and its declarations.
+    StyleRuleSource(String, DeclarationBlockSource),
+    /// Any other rule, as written.
+    AtRuleSource(String),
+}
+
+
+impl RuleSource {
+    /// http://dev.w3.org/csswg/cssom/#serialize-a-css-rule
+    pub fn serialize(&self) -> String {
+        match *self {
+            StyleRuleSource(ref selectors, ref block) if block.len() == 0 => {
+                format!("{:s} {{ }}", *selectors)
+            }
+            StyleRuleSource(ref selectors, ref block) => {
+                format!("{:s} {{ {:s} }}", *selectors, block.serialize())
+            }
+            AtRuleSource(ref text) => text.clone(),
+        }
+    }
+}
+
+
+fn serialize_block(block: &[Node]) -> String {
+    block.iter().map(|&(ref component_value, _)| component_value).to_css()
+}
+
+
+fn parse_rule_sources_with_namespaces(css: &str, namespaces: &mut NamespaceMap, base_url: &Url)
+                                      -> Vec<RuleSource> {
+    let mut rules = vec!();
+    for rule in ErrorLoggerIterator(parse_stylesheet_rules(tokenize(css))) {
+        match rule {
+            QualifiedRule_(QualifiedRule{ location: _, prelude: prelude, block: block }) => {
+                // Rules that the style system drops because of their selectors aren't exposed.
+                let selectors = prelude.iter().to_css();
+                if parse_selector_list(prelude.into_iter(), namespaces).is_ok() {
+                    rules.push(StyleRuleSource(selectors.as_slice().trim().to_string(),
+                                               DeclarationBlockSource::from_iter(
+                                                   block.into_iter(), base_url)))
+                }
+            }
+            AtRule_(rule) => {
+                let mut text = format!("@{:s}{:s}", rule.name, rule.prelude.iter().to_css());
+                match rule.block {
+                    Some(ref block) => {
+                        text.push_char('{');
+                        text.push_str(serialize_block(block.as_slice()).as_slice());
+                        text.push_char('}');
+                    }
+                    None => text.push_char(';'),
+                }
+                if rule.name.as_slice().eq_ignore_ascii_case("namespace") {
+                    parse_namespace_rule(rule, namespaces)
+                }
+                rules.push(AtRuleSource(text.as_slice().trim().to_string()))
+            }
+        }
+    }
+    rules
+}
+
+
+/// Parses the top-level rules of a stylesheet.
+pub fn parse_rule_sources(css: &str, base_url: &Url) -> Vec<RuleSource> {
+    parse_rule_sources_with_namespaces(css, &mut NamespaceMap::new(), base_url)
+}
+
+
+/// The namespace prefixes declared by the `@namespace` rules among `rules`.
+fn namespaces(rules: &[RuleSource], base_url: &Url) -> NamespaceMap {
+    let mut namespaces = NamespaceMap::new();
+    for rule in rules.iter() {
+        match *rule {
+            AtRuleSource(ref text) => {
+                parse_rule_sources_with_namespaces(text.as_slice(), &mut namespaces, base_url);
+            }
+            StyleRuleSource(..) => {}
+        }
+    }
+    namespaces
+}
+
+
+/// Parses the rule given to `insertRule()`, for a stylesheet with the given rules. Fails unless
+/// `css` is exactly one valid rule.
+pub fn parse_rule_source(css: &str, rules: &[RuleSource], base_url: &Url)
+                         -> Result<RuleSource, ()> {
+    let mut namespaces = namespaces(rules, base_url);
+    let mut parsed = parse_rule_sources_with_namespaces(css, &mut namespaces, base_url);
+    if parsed.len() != 1 {
+        return Err(())
+    }
+    Ok(parsed.pop().unwrap())
+}
+
+
+/// Parses the selectors given to `selectorText` for a rule of a stylesheet with the given
+/// rules, returning them as written if they are valid.
+pub fn parse_selector_source(css: &str, rules: &[RuleSource], base_url: &Url)
+                             -> Result<String, ()> {
+    let namespaces = namespaces(rules, base_url);
+    let tokens = tokenize(css).map(|(token, _)| token);
+    parse_selector_list(tokens, &namespaces).map(|_| css.trim().to_string())
+}
+
+
+pub fn serialize_rule_sources(rules: &[RuleSource]) -> String {
+    let rules: Vec<String> = rules.iter().map(|rule| rule.serialize()).collect();
+    rules.connect("\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{DeclarationBlockSource, StyleRuleSource, AtRuleSource};
+    use super::{parse_rule_sources, parse_rule_source, parse_selector_source};
+    use super::serialize_rule_sources;
+    use url::Url;
+
+    fn url() -> Url {
+        Url::parse("http://example.com/").unwrap()
+    }
+
+    #[test]
+    fn test_parse_declarations() {
+        let block = DeclarationBlockSource::parse(
+            "color: red; unknown: 1; width: nonsense; margin-top : 1px ; \
+             color: blue !important; color: green",
+            &url());
+        assert_eq!(block.serialize(), "margin-top: 1px; color: blue !important;".to_string());
+        assert_eq!(block.get("COLOR").map(|d| d.value.clone()), Some("blue".to_string()));
+    }
+
+    #[test]
+    fn test_set_and_remove() {
+        let mut block = DeclarationBlockSource::parse("margin-left: 1px; color: red", &url());
+        assert!(!block.set("color", "nonsense", false, &url()));
+        assert!(block.set("Color", "blue", true, &url()));
+        assert_eq!(block.serialize(), "margin-left: 1px; color: blue !important;".to_string());
+
+        // A shorthand replaces its longhands.
+        assert!(block.set("margin", "2px", false, &url()));
+        assert_eq!(block.serialize(), "color: blue !important; margin: 2px;".to_string());
+        assert!(block.set("margin-top", "3px", false, &url()));
+        assert_eq!(block.remove("margin"), Some("2px".to_string()));
+        assert_eq!(block.remove("margin"), None);
+        assert_eq!(block.serialize(), "color: blue !important;".to_string());
+    }
+
+    #[test]
+    fn test_serialize_shorthands() {
+        let block = DeclarationBlockSource::parse(
+            "margin-top: 1px; margin-right: 2px; margin-bottom: 1px; margin-left: 2px; \
+             padding-top: 1px; padding-right: 1px; padding-bottom: 1px; \
+             border-top-color: red; border-top-style: solid; border-top-width: 1px; \
+             font-style: italic; font-variant: normal; font-weight: bold; font-size: 12px; \
+             line-height: 2; font-family: serif",
+            &url());
+        assert_eq!(block.value("margin"), Some("1px 2px".to_string()));
+        assert!(!block.important("margin"));
+        // A longhand isn't declared.
+        assert_eq!(block.value("padding"), None);
+        assert_eq!(block.value("border-top"), Some("red solid 1px".to_string()));
+        assert_eq!(block.value("border"), None);
+        assert_eq!(block.value("font"), Some("italic normal bold 12px/2 serif".to_string()));
+
+        let mut block = DeclarationBlockSource::parse(
+            "margin-top: 1px; margin-right: 2px; margin-bottom: 3px; margin-left: 2px", &url());
+        assert_eq!(block.value("margin"), Some("1px 2px 3px".to_string()));
+        assert!(block.set("margin-left", "4px", true, &url()));
+        // The longhands differ in importance.
+        assert_eq!(block.value("margin"), None);
+        assert!(block.set("margin-left", "2px", false, &url()));
+        assert!(block.set("margin-top", "inherit", false, &url()));
+        // Only some of the longhands are inherited.
+        assert_eq!(block.value("margin"), None);
+        assert!(block.set("margin", "5px", true, &url()));
+        assert_eq!(block.value("margin"), Some("5px".to_string()));
+        assert!(block.important("margin"));
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = parse_rule_sources(
+            "@import 'a.css'; a > b  { color: red } ::: { color: red } \
+             @media print { a { color: blue } } p {}",
+            &url());
+        assert_eq!(rules.len(), 4);
+        match rules.as_slice()[1] {
+            StyleRuleSource(ref selectors, ref block) => {
+                assert_eq!(*selectors, "a > b".to_string());
+                assert_eq!(block.serialize(), "color: red;".to_string());
+            }
+            _ => fail!("expected a style rule"),
+        }
+        assert_eq!(serialize_rule_sources(rules.as_slice()),
+                   "@import \"a.css\";\na > b { color: red; }\n\
+                    @media print { a { color: blue } }\np { }".to_string());
+    }
+
+    #[test]
+    fn test_parse_inserted_rule() {
+        let rules = parse_rule_sources("@namespace svg url(http://www.w3.org/2000/svg);", &url());
+        assert!(parse_rule_source("svg|a { color: red }", &[], &url()).is_err());
+        assert!(parse_rule_source("a {} b {}", rules.as_slice(), &url()).is_err());
+        match parse_rule_source("svg|a { color: red }", rules.as_slice(), &url()) {
+            Ok(StyleRuleSource(ref selectors, _)) => assert_eq!(*selectors, "svg|a".to_string()),
+            _ => fail!("expected a style rule"),
+        }
+        assert_eq!(parse_rule_source("@media print {}", &[], &url()),
+                   Ok(AtRuleSource("@media print {}".to_string())));
+        assert_eq!(parse_selector_source(" svg|a, b ", rules.as_slice(), &url()),
+                   Ok("svg|a, b".to_string()));
+        assert!(parse_selector_source("a >", rules.as_slice(), &url()).is_err());
+    }
+}
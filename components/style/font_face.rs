@@ -9,21 +9,27 @@ use std::ascii::StrAsciiExt;
 use parsing_utils::{BufferedIter, ParserIter, parse_slice_comma_separated};
 use properties::longhands::font_family::parse_one_family;
 use properties::computed_values::font_family::FamilyName;
-use stylesheets::{CSSRule, CSSFontFaceRule, CSSStyleRule, CSSMediaRule};
-use media_queries::{Device, Screen};
+use stylesheets::{CSSRule, CSSFontFaceRule, CSSImportRule, CSSStyleRule, CSSMediaRule};
+use stylesheets::iter_font_face_rules;
+use media_queries::Device;
 use url::{Url, UrlParser};
 
 
 static SUPPORTED_FORMATS: &'static [&'static str] = &["truetype", "opentype"];
 
 
-pub fn iter_font_face_rules_inner(rules: &[CSSRule], callback: |family: &str, source: &Url|) {
-    let device = &Device { media_type: Screen };  // TODO, use Print when printing
+pub fn iter_font_face_rules_inner(rules: &[CSSRule], device: &Device,
+                                  callback: |family: &str, source: &Url|) {
     for rule in rules.iter() {
         match *rule {
+            CSSImportRule(ref rule) => if rule.media_queries.evaluate(device) {
+                for stylesheet in rule.stylesheet.iter() {
+                    iter_font_face_rules(stylesheet, device, |f, s| callback(f, s))
+                }
+            },
             CSSStyleRule(_) => {},
             CSSMediaRule(ref rule) => if rule.media_queries.evaluate(device) {
-                iter_font_face_rules_inner(rule.rules.as_slice(), |f, s| callback(f, s))
+                iter_font_face_rules_inner(rule.rules.as_slice(), device, |f, s| callback(f, s))
             },
             CSSFontFaceRule(ref rule) => {
                 for source in rule.sources.iter() {
@@ -34,7 +34,7 @@ extern crate "util" as servo_util;
 
 
 // Public API
-pub use stylesheets::{Stylesheet, iter_font_face_rules};
+pub use stylesheets::{Stylesheet, decode_stylesheet, iter_font_face_rules};
 pub use selector_matching::{Stylist, StylesheetOrigin, UserAgentOrigin, AuthorOrigin, UserOrigin};
 pub use selector_matching::{DeclarationBlock, matches,matches_simple_selector};
 pub use selector_matching::{RECOMMENDED_SELECTOR_BLOOM_FILTER_SIZE,SELECTOR_WHITESPACE};
@@ -43,6 +43,12 @@ pub use properties::{PropertyDeclaration, ComputedValues, computed_values, style
 pub use properties::{PropertyDeclarationBlock, parse_style_attribute};  // Style attributes
 pub use properties::{CSSFloat, DeclaredValue, PropertyDeclarationParseResult};
 pub use properties::longhands;
+pub use properties::{computed_value_to_css, COMPUTED_LONGHANDS};  // getComputedStyle()
+pub use cssom::{DeclarationBlockSource, DeclarationSource, RuleSource, StyleRuleSource};
+pub use cssom::{AtRuleSource, parse_rule_sources, parse_rule_source, parse_selector_source};
+pub use cssom::serialize_rule_sources;
+pub use media_queries::{Device, MediaType, Screen, Print, MediaQueryList};
+pub use media_queries::parse_media_query_list_from_str;  // matchMedia()
 pub use node::{TElement, TNode};
 pub use selectors::{PseudoElement, Before, After, SelectorList, parse_selector_list_from_str};
 pub use selectors::{AttrSelector, NamespaceConstraint, SpecificNamespace, AnyNamespace};
@@ -59,3 +65,4 @@ mod node;
 mod media_queries;
 mod parsing_utils;
 mod font_face;
+mod cssom;
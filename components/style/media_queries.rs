@@ -3,10 +3,14 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use std::ascii::StrAsciiExt;
-use cssparser::parse_rule_list;
+use cssparser::{parse_rule_list, tokenize};
 use cssparser::ast::*;
+use geom::size::Size2D;
 
 use errors::{ErrorLoggerIterator, log_css_error};
+use properties::longhands;
+use properties::common_types::{CSSFloat, computed, specified};
+use servo_util::geometry::Au;
 use stylesheets::{CSSRule, CSSMediaRule, parse_style_rule, parse_nested_at_rule};
 use namespaces::NamespaceMap;
 use url::Url;
@@ -18,33 +22,143 @@ pub struct MediaRule {
 }
 
 
+#[deriving(Clone)]
 pub struct MediaQueryList {
-    // "not all" is omitted from the list.
-    // An empty list never matches.
+    // An empty list never matches. Media queries that fail to parse are kept as "not all".
     media_queries: Vec<MediaQuery>
 }
 
-// For now, this is a "Level 2 MQ", ie. a media type.
+// http://www.w3.org/TR/css3-mediaqueries/#syntax
+#[deriving(Clone)]
 pub struct MediaQuery {
+    qualifier: Option<Qualifier>,
     media_type: MediaQueryType,
-    // TODO: Level 3 MQ expressions
+    expressions: Vec<Expression>,
 }
 
+#[deriving(PartialEq, Clone)]
+pub enum Qualifier {
+    Only,
+    Not,
+}
 
+#[deriving(PartialEq, Clone)]
 pub enum MediaQueryType {
     All,  // Always true
     MediaType_(MediaType),
 }
 
-#[deriving(PartialEq)]
+#[deriving(PartialEq, Clone)]
 pub enum MediaType {
     Screen,
     Print,
+    /// A media type this implementation doesn't know about, which never matches.
+    UnknownMediaType(String),
+}
+
+/// A media feature, and the value it is compared to. A feature without a value is evaluated in
+/// the boolean context.
+// http://www.w3.org/TR/css3-mediaqueries/#media1
+#[deriving(Clone)]
+pub enum Expression {
+    Width(Option<Range<specified::Length>>),
+    Height(Option<Range<specified::Length>>),
+    AspectRatio(Option<Range<AspectRatioValue>>),
+    Orientation(Option<OrientationValue>),
+    Resolution(Option<Range<ResolutionValue>>),
+    DevicePixelRatio(Option<Range<CSSFloat>>),
+    Color(Option<Range<u32>>),
+}
+
+#[deriving(Clone)]
+pub enum Range<T> {
+    Min(T),
+    Max(T),
+    Exact(T),
+}
+
+impl<T> Range<T> {
+    fn map<U>(&self, f: |&T| -> U) -> Range<U> {
+        match *self {
+            Min(ref value) => Min(f(value)),
+            Max(ref value) => Max(f(value)),
+            Exact(ref value) => Exact(f(value)),
+        }
+    }
+}
+
+impl<T: PartialOrd> Range<T> {
+    fn evaluate(&self, value: T) -> bool {
+        match *self {
+            Min(ref min) => value >= *min,
+            Max(ref max) => value <= *max,
+            Exact(ref expected) => value == *expected,
+        }
+    }
+}
+
+/// A ratio of two positive integers, as in `16/9`.
+#[deriving(PartialEq, Clone)]
+pub struct AspectRatioValue(pub u32, pub u32);
+
+impl AspectRatioValue {
+    /// The ratio as a number, for comparison.
+    fn value(&self) -> CSSFloat {
+        let AspectRatioValue(width, height) = *self;
+        width as CSSFloat / height as CSSFloat
+    }
 }
 
+#[deriving(PartialEq, Clone)]
+pub enum OrientationValue {
+    Portrait,
+    Landscape,
+}
+
+#[deriving(PartialEq, Clone)]
+pub enum ResolutionUnit {
+    Dpi,
+    Dpcm,
+    Dppx,
+}
+
+#[deriving(PartialEq, Clone)]
+pub struct ResolutionValue(pub CSSFloat, pub ResolutionUnit);
+
+impl ResolutionValue {
+    /// The resolution in dots per CSS pixel.
+    fn to_dppx(&self) -> CSSFloat {
+        match *self {
+            ResolutionValue(value, Dpi) => value / 96.,
+            ResolutionValue(value, Dpcm) => value * 2.54 / 96.,
+            ResolutionValue(value, Dppx) => value,
+        }
+    }
+}
+
+/// What media queries are evaluated against.
+#[deriving(PartialEq, Clone)]
 pub struct Device {
     pub media_type: MediaType,
-    // TODO: Level 3 MQ data: viewport size, etc.
+    /// The size of the viewport.
+    pub viewport_size: Size2D<Au>,
+    /// The number of device pixels per CSS pixel.
+    pub device_pixel_ratio: CSSFloat,
+    /// The number of bits per color component.
+    pub color_bits: u32,
+}
+
+impl Device {
+    pub fn new(media_type: MediaType, viewport_size: Size2D<Au>, device_pixel_ratio: CSSFloat)
+               -> Device {
+        Device {
+            media_type: media_type,
+            viewport_size: viewport_size,
+            device_pixel_ratio: device_pixel_ratio,
+            // Everything is rendered in 8-bit RGBA.
+            color_bits: 8,
+        }
+    }
 }
 
 
@@ -74,58 +188,415 @@ pub fn parse_media_rule(rule: AtRule, parent_rules: &mut Vec<CSSRule>,
 
 
 pub fn parse_media_query_list(input: &[ComponentValue]) -> MediaQueryList {
+    if input.skip_whitespace().next().is_none() {
+        // An empty media query list is the same as "all".
+        return MediaQueryList {
+            media_queries: vec!(MediaQuery { qualifier: None, media_type: All, expressions: vec!() })
+        }
+    }
+    let queries = input.split(|component_value| *component_value == Comma)
+                       .map(parse_media_query)
+                       .collect();
+    MediaQueryList { media_queries: queries }
+}
+
+/// Parses a media query list given as a string, as in `window.matchMedia()`.
+pub fn parse_media_query_list_from_str(input: &str) -> MediaQueryList {
+    let tokens: Vec<ComponentValue> = tokenize(input).map(|(token, _)| token).collect();
+    parse_media_query_list(tokens.as_slice())
+}
+
+/// Parses one media query of a list. Media queries that are invalid are "not all".
+fn parse_media_query(input: &[ComponentValue]) -> MediaQuery {
+    match parse_media_query_internal(input) {
+        Ok(query) => query,
+        Err(()) => MediaQuery { qualifier: Some(Not), media_type: All, expressions: vec!() },
+    }
+}
+
+fn parse_media_query_internal(input: &[ComponentValue]) -> Result<MediaQuery, ()> {
+    let tokens: Vec<&ComponentValue> = input.skip_whitespace().collect();
+    let mut tokens = tokens.as_slice();
+
+    let mut qualifier = None;
+    match tokens {
+        [&Ident(ref value), ..rest] => match value.as_slice().to_ascii_lower().as_slice() {
+            "only" => { qualifier = Some(Only); tokens = rest }
+            "not" => { qualifier = Some(Not); tokens = rest }
+            _ => {}
+        },
+        _ => {}
+    }
+
+    // A media query may start with an expression, and then it has no media type. It can't
+    // have a qualifier either.
+    let (media_type, rest) = match tokens {
+        [&Ident(ref value), ..rest] => {
+            let media_type = match value.as_slice().to_ascii_lower().as_slice() {
+                "all" => All,
+                "screen" => MediaType_(Screen),
+                "print" => MediaType_(Print),
+                // These are reserved, and can't be media types.
+                "only" | "not" | "and" | "or" => return Err(()),
+                value => MediaType_(UnknownMediaType(value.to_string())),
+            };
+            (Some(media_type), rest)
+        }
+        [&ParenthesisBlock(_), ..] if qualifier.is_none() => (None, tokens),
+        _ => return Err(()),
+    };
+
+    let mut expressions = vec!();
+    let mut expect_and = media_type.is_some();
+    for token in rest.iter() {
+        match **token {
+            Ident(ref value) if expect_and && value.as_slice().eq_ignore_ascii_case("and") => {
+                expect_and = false
+            }
+            ParenthesisBlock(ref block) if !expect_and => {
+                expressions.push(try!(parse_expression(block.as_slice())));
+                expect_and = true
+            }
+            _ => return Err(()),
+        }
+    }
+    if !expect_and {
+        // The query ended with "and".
+        return Err(())
+    }
+    Ok(MediaQuery {
+        qualifier: qualifier,
+        media_type: media_type.unwrap_or(All),
+        expressions: expressions,
+    })
+}
+
+/// Parses the contents of a `(feature: value)` expression.
+fn parse_expression(input: &[ComponentValue]) -> Result<Expression, ()> {
     let iter = &mut input.skip_whitespace();
-    let mut next = iter.next();
-    if next.is_none() {
-        return MediaQueryList{ media_queries: vec!(MediaQuery{media_type: All}) }
-    }
-    let mut queries = vec!();
-    loop {
-        let mq = match next {
-            Some(&Ident(ref value)) => {
-                match value.as_slice().to_ascii_lower().as_slice() {
-                    "screen" => Some(MediaQuery{ media_type: MediaType_(Screen) }),
-                    "print" => Some(MediaQuery{ media_type: MediaType_(Print) }),
-                    "all" => Some(MediaQuery{ media_type: All }),
-                    _ => None
-                }
-            },
-            _ => None
-        };
-        match iter.next() {
-            None => {
-                for mq in mq.into_iter() {
-                    queries.push(mq);
-                }
-                return MediaQueryList{ media_queries: queries }
-            },
-            Some(&Comma) => {
-                for mq in mq.into_iter() {
-                    queries.push(mq);
-                }
-            },
-            // Ingnore this comma-separated part
-            _ => loop {
-                match iter.next() {
-                    Some(&Comma) => break,
-                    None => return MediaQueryList{ media_queries: queries },
-                    _ => (),
-                }
-            },
-        }
-        next = iter.next();
+    let name = match iter.next() {
+        Some(&Ident(ref value)) => value.as_slice().to_ascii_lower(),
+        _ => return Err(()),
+    };
+    let value: Vec<&ComponentValue> = match iter.next() {
+        None => vec!(),
+        Some(&Colon) => {
+            let value: Vec<&ComponentValue> = iter.collect();
+            if value.is_empty() {
+                return Err(())
+            }
+            value
+        }
+        Some(_) => return Err(()),
+    };
+
+    let (prefix, feature) = if name.as_slice().starts_with("min-") {
+        (Some(true), name.as_slice().slice_from(4))
+    } else if name.as_slice().starts_with("max-") {
+        (Some(false), name.as_slice().slice_from(4))
+    } else {
+        (None, name.as_slice())
+    };
+    let value = value.as_slice();
+
+    // Parses the value of a feature that may have a "min-" or "max-" prefix.
+    fn range<T>(prefix: Option<bool>, value: &[&ComponentValue],
+                parse: |&[&ComponentValue]| -> Result<T, ()>) -> Result<Option<Range<T>>, ()> {
+        if value.is_empty() {
+            // Features with a prefix can't be evaluated in the boolean context.
+            return if prefix.is_none() { Ok(None) } else { Err(()) }
+        }
+        let value = try!(parse(value));
+        Ok(Some(match prefix {
+            Some(true) => Min(value),
+            Some(false) => Max(value),
+            None => Exact(value),
+        }))
+    }
+
+    match feature {
+        "width" => Ok(Width(try!(range(prefix, value, parse_length)))),
+        "height" => Ok(Height(try!(range(prefix, value, parse_length)))),
+        "aspect-ratio" => Ok(AspectRatio(try!(range(prefix, value, parse_aspect_ratio)))),
+        "resolution" => Ok(Resolution(try!(range(prefix, value, parse_resolution)))),
+        "device-pixel-ratio" => Ok(DevicePixelRatio(try!(range(prefix, value, parse_number)))),
+        "color" => Ok(Color(try!(range(prefix, value, parse_integer)))),
+        "orientation" if prefix.is_none() => {
+            match value {
+                [] => Ok(Orientation(None)),
+                [&Ident(ref value)] => match value.as_slice().to_ascii_lower().as_slice() {
+                    "portrait" => Ok(Orientation(Some(Portrait))),
+                    "landscape" => Ok(Orientation(Some(Landscape))),
+                    _ => Err(()),
+                },
+                _ => Err(()),
+            }
+        }
+        _ => Err(()),
+    }
+}
+
+fn parse_length(value: &[&ComponentValue]) -> Result<specified::Length, ()> {
+    match value {
+        [value] => specified::Length::parse_non_negative(value),
+        _ => Err(()),
+    }
+}
+
+fn parse_number(value: &[&ComponentValue]) -> Result<CSSFloat, ()> {
+    match value {
+        [&Number(ref value)] if value.value >= 0. => Ok(value.value),
+        _ => Err(()),
+    }
+}
+
+fn parse_integer(value: &[&ComponentValue]) -> Result<u32, ()> {
+    match value {
+        [&Number(ref value)] => match value.int_value {
+            Some(value) if value >= 0 => Ok(value as u32),
+            _ => Err(()),
+        },
+        _ => Err(()),
+    }
+}
+
+fn parse_aspect_ratio(value: &[&ComponentValue]) -> Result<AspectRatioValue, ()> {
+    match value {
+        [width, &Delim('/'), height] => {
+            let width = try!(parse_integer(&[width]));
+            let height = try!(parse_integer(&[height]));
+            if width == 0 || height == 0 {
+                return Err(())
+            }
+            Ok(AspectRatioValue(width, height))
+        }
+        _ => Err(()),
+    }
+}
+
+fn parse_resolution(value: &[&ComponentValue]) -> Result<ResolutionValue, ()> {
+    match value {
+        [&Dimension(ref value, ref unit)] if value.value > 0. => {
+            match unit.as_slice().to_ascii_lower().as_slice() {
+                "dpi" => Ok(ResolutionValue(value.value, Dpi)),
+                "dpcm" => Ok(ResolutionValue(value.value, Dpcm)),
+                "dppx" => Ok(ResolutionValue(value.value, Dppx)),
+                _ => Err(()),
+            }
+        }
+        _ => Err(()),
     }
 }
 
 
 impl MediaQueryList {
     pub fn evaluate(&self, device: &Device) -> bool {
-        self.media_queries.iter().any(|mq| {
-            match mq.media_type {
-                MediaType_(media_type) => media_type == device.media_type,
-                All => true,
+        self.media_queries.iter().any(|mq| mq.evaluate(device))
+    }
+
+    /// Serializes the media query list, as in `MediaList.mediaText`.
+    // http://dev.w3.org/csswg/cssom/#serialize-a-media-query-list
+    pub fn serialize(&self) -> String {
+        let queries: Vec<String> = self.media_queries.iter().map(|mq| mq.serialize()).collect();
+        queries.connect(", ")
+    }
+}
+
+impl MediaQuery {
+    fn evaluate(&self, device: &Device) -> bool {
+        let media_type_matches = match self.media_type {
+            MediaType_(ref media_type) => *media_type == device.media_type,
+            All => true,
+        };
+        let matches = media_type_matches && self.expressions.iter().all(|e| e.evaluate(device));
+        match self.qualifier {
+            Some(Not) => !matches,
+            _ => matches,
+        }
+    }
+
+    // http://dev.w3.org/csswg/cssom/#serialize-a-media-query
+    fn serialize(&self) -> String {
+        let mut result = String::new();
+        match self.qualifier {
+            Some(Only) => result.push_str("only "),
+            Some(Not) => result.push_str("not "),
+            None => {}
+        }
+        let media_type = match self.media_type {
+            All => "all",
+            MediaType_(Screen) => "screen",
+            MediaType_(Print) => "print",
+            MediaType_(UnknownMediaType(ref name)) => name.as_slice(),
+        };
+        // "all" is omitted before expressions, unless it has a qualifier.
+        if self.media_type != All || self.qualifier.is_some() || self.expressions.is_empty() {
+            result.push_str(media_type);
+            if !self.expressions.is_empty() {
+                result.push_str(" and ");
+            }
+        }
+        let expressions: Vec<String> = self.expressions.iter().map(|e| e.serialize()).collect();
+        result.push_str(expressions.connect(" and ").as_slice());
+        result
+    }
+}
+
+impl Expression {
+    fn evaluate(&self, device: &Device) -> bool {
+        let width = device.viewport_size.width;
+        let height = device.viewport_size.height;
+        match *self {
+            Width(None) => width != Au(0),
+            Width(Some(ref range)) => range.map(|&length| compute_length(length)).evaluate(width),
+            Height(None) => height != Au(0),
+            Height(Some(ref range)) => range.map(|&length| compute_length(length)).evaluate(height),
+            AspectRatio(None) => width != Au(0) && height != Au(0),
+            AspectRatio(Some(ref range)) => {
+                height != Au(0) &&
+                    range.map(|ratio| ratio.value()).evaluate(width.to_subpx() / height.to_subpx())
+            }
+            Orientation(None) => true,
+            Orientation(Some(orientation)) => {
+                // The viewport is portrait when it is taller than it is wide, or square.
+                (orientation == Portrait) == (height >= width)
+            }
+            Resolution(None) | DevicePixelRatio(None) => true,
+            Resolution(Some(ref range)) => {
+                range.map(|resolution| resolution.to_dppx()).evaluate(device.device_pixel_ratio)
+            }
+            DevicePixelRatio(Some(ref range)) => range.evaluate(device.device_pixel_ratio),
+            Color(None) => device.color_bits > 0,
+            Color(Some(ref range)) => range.evaluate(device.color_bits),
+        }
+    }
+
+    fn serialize(&self) -> String {
+        fn feature<T>(name: &str, range: &Option<Range<T>>, serialize: |&T| -> String) -> String {
+            match *range {
+                None => format!("({})", name),
+                Some(Min(ref value)) => format!("(min-{}: {})", name, serialize(value)),
+                Some(Max(ref value)) => format!("(max-{}: {})", name, serialize(value)),
+                Some(Exact(ref value)) => format!("({}: {})", name, serialize(value)),
+            }
+        }
+        fn length(length: &specified::Length) -> String {
+            match *length {
+                specified::Au_(value) => format!("{}px", value.to_subpx()),
+                specified::Em(value) => format!("{}em", value),
+                specified::Ex(value) => format!("{}ex", value),
+            }
+        }
+        match *self {
+            Width(ref range) => feature("width", range, length),
+            Height(ref range) => feature("height", range, length),
+            AspectRatio(ref range) => feature("aspect-ratio", range, |&AspectRatioValue(w, h)| {
+                format!("{}/{}", w, h)
+            }),
+            Orientation(None) => "(orientation)".to_string(),
+            Orientation(Some(Portrait)) => "(orientation: portrait)".to_string(),
+            Orientation(Some(Landscape)) => "(orientation: landscape)".to_string(),
+            Resolution(ref range) => feature("resolution", range, |&ResolutionValue(value, unit)| {
+                let unit = match unit {
+                    Dpi => "dpi",
+                    Dpcm => "dpcm",
+                    Dppx => "dppx",
+                };
+                format!("{}{}", value, unit)
+            }),
+            DevicePixelRatio(ref range) => {
+                feature("device-pixel-ratio", range, |value| format!("{}", value))
             }
-            // TODO: match Level 3 expressions
-        })
+            Color(ref range) => feature("color", range, |value| format!("{}", value)),
+        }
+    }
+}
+
+/// Computes a length of a media feature. Font-relative lengths are relative to the initial
+/// value of `font-size`.
+fn compute_length(length: specified::Length) -> Au {
+    computed::compute_Au_with_font_size(length, longhands::font_size::get_initial_value())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use geom::size::Size2D;
+    use servo_util::geometry::Au;
+    use super::{Device, MediaType, Screen, Print, parse_media_query_list_from_str};
+
+    fn device(media_type: MediaType, width: int, height: int, device_pixel_ratio: f64) -> Device {
+        Device::new(media_type, Size2D(Au::from_px(width), Au::from_px(height)), device_pixel_ratio)
+    }
+
+    fn matches(query: &str, device: &Device) -> bool {
+        parse_media_query_list_from_str(query).evaluate(device)
+    }
+
+    #[test]
+    fn test_media_types() {
+        let screen = device(Screen, 800, 600, 1.);
+        assert!(matches("", &screen));
+        assert!(matches("all", &screen));
+        assert!(matches("SCREEN", &screen));
+        assert!(matches("only screen", &screen));
+        assert!(!matches("print", &screen));
+        assert!(!matches("tv", &screen));
+        assert!(matches("not print", &screen));
+        assert!(matches("print, screen", &screen));
+        assert!(matches("print", &device(Print, 800, 600, 1.)));
+    }
+
+    #[test]
+    fn test_features() {
+        let screen = device(Screen, 800, 600, 2.);
+        assert!(matches("(min-width: 800px)", &screen));
+        assert!(!matches("(min-width: 801px)", &screen));
+        assert!(matches("screen and (max-width: 50em)", &screen));
+        assert!(!matches("screen and (max-width: 49em)", &screen));
+        assert!(matches("(width: 800px) and (height: 600px)", &screen));
+        assert!(matches("(width)", &screen));
+        assert!(matches("(aspect-ratio: 4/3)", &screen));
+        assert!(matches("(min-aspect-ratio: 16/9)", &device(Screen, 1920, 1080, 1.)));
+        assert!(!matches("(min-aspect-ratio: 16/9)", &screen));
+        assert!(matches("(orientation: landscape)", &screen));
+        assert!(matches("(orientation: portrait)", &device(Screen, 600, 600, 1.)));
+        assert!(matches("(min-resolution: 2dppx)", &screen));
+        assert!(matches("(resolution: 192dpi)", &screen));
+        assert!(!matches("(min-resolution: 3dppx)", &screen));
+        assert!(matches("(min-device-pixel-ratio: 1.5)", &screen));
+        assert!(matches("(color)", &screen));
+        assert!(matches("(min-color: 8)", &screen));
+        assert!(!matches("not screen and (color)", &screen));
+    }
+
+    #[test]
+    fn test_invalid_queries() {
+        let screen = device(Screen, 800, 600, 1.);
+        // Invalid media queries are "not all", and don't affect the other queries of the list.
+        assert!(!matches("screen and", &screen));
+        assert!(!matches("screen (color)", &screen));
+        assert!(!matches("not (color)", &screen));
+        assert!(!matches("(min-width)", &screen));
+        assert!(!matches("(min-orientation: portrait)", &screen));
+        assert!(!matches("(width: -1px)", &screen));
+        assert!(!matches("(aspect-ratio: 0/1)", &screen));
+        assert!(!matches("(unknown-feature)", &screen));
+        assert!(matches("(width:), screen", &screen));
+    }
+
+    #[test]
+    fn test_serialize() {
+        fn serialize(query: &str) -> String {
+            parse_media_query_list_from_str(query).serialize()
+        }
+        assert_eq!(serialize("Screen"), "screen".to_string());
+        assert_eq!(serialize("only screen  AND (MIN-WIDTH:100px)"),
+                   "only screen and (min-width: 100px)".to_string());
+        assert_eq!(serialize("(orientation:portrait) and (color)"),
+                   "(orientation: portrait) and (color)".to_string());
+        assert_eq!(serialize("print, foo bar"), "print, not all".to_string());
+        assert_eq!(serialize("(min-aspect-ratio: 16 / 9)"),
+                   "(min-aspect-ratio: 16/9)".to_string());
     }
 }
@@ -14,6 +14,7 @@ pub static DEFAULT_LINE_HEIGHT: CSSFloat = 1.14;
 
 pub mod specified {
     use std::ascii::StrAsciiExt;
+    use std::f64::consts::PI;
     use cssparser::ast;
     use cssparser::ast::*;
     use super::{Au, CSSFloat};
@@ -201,10 +202,36 @@ pub mod specified {
             }
         }
     }
+    // http://dev.w3.org/csswg/css-values/#angles
+    #[deriving(Clone, PartialEq)]
+    pub struct Angle(pub CSSFloat);  // In radians.
+    impl Angle {
+        pub fn parse(input: &ComponentValue) -> Result<Angle, ()> {
+            match input {
+                &Dimension(ref value, ref unit) => {
+                    match unit.as_slice().to_ascii_lower().as_slice() {
+                        "deg" => Ok(Angle(value.value * PI / 180.)),
+                        "grad" => Ok(Angle(value.value * PI / 200.)),
+                        "rad" => Ok(Angle(value.value)),
+                        "turn" => Ok(Angle(value.value * 2. * PI)),
+                        _ => Err(()),
+                    }
+                }
+                &Number(ref value) if value.value == 0. => Ok(Angle(0.)),
+                _ => Err(())
+            }
+        }
+        #[inline]
+        pub fn radians(self) -> CSSFloat {
+            let Angle(radians) = self;
+            radians
+        }
+    }
 }
 
 pub mod computed {
     pub use cssparser::Color as CSSColor;
+    use cssparser::RGBA;
     pub use super::super::longhands::computed_as_specified as compute_CSSColor;
     use super::*;
     use super::super::longhands;
@@ -293,6 +320,67 @@ pub mod computed {
             specified::LPN_None => LPN_None,
         }
     }
+
+    /// The serialization of a computed value, as returned by `getComputedStyle()`.
+    pub trait ToComputedCss {
+        fn to_computed_css(&self) -> String;
+    }
+
+    impl ToComputedCss for CSSFloat {
+        fn to_computed_css(&self) -> String {
+            format!("{}", *self)
+        }
+    }
+
+    impl ToComputedCss for Au {
+        fn to_computed_css(&self) -> String {
+            format!("{}px", self.to_subpx())
+        }
+    }
+
+    impl ToComputedCss for RGBA {
+        fn to_computed_css(&self) -> String {
+            fn channel(value: f32) -> u8 {
+                (value * 255.).round() as u8
+            }
+            if self.alpha == 1. {
+                format!("rgb({}, {}, {})",
+                        channel(self.red), channel(self.green), channel(self.blue))
+            } else {
+                format!("rgba({}, {}, {}, {})",
+                        channel(self.red), channel(self.green), channel(self.blue), self.alpha)
+            }
+        }
+    }
+
+    impl ToComputedCss for LengthOrPercentage {
+        fn to_computed_css(&self) -> String {
+            match *self {
+                LP_Length(value) => value.to_computed_css(),
+                LP_Percentage(value) => format!("{}%", value * 100.),
+            }
+        }
+    }
+
+    impl ToComputedCss for LengthOrPercentageOrAuto {
+        fn to_computed_css(&self) -> String {
+            match *self {
+                LPA_Length(value) => value.to_computed_css(),
+                LPA_Percentage(value) => format!("{}%", value * 100.),
+                LPA_Auto => "auto".to_string(),
+            }
+        }
+    }
+
+    impl ToComputedCss for LengthOrPercentageOrNone {
+        fn to_computed_css(&self) -> String {
+            match *self {
+                LPN_Length(value) => value.to_computed_css(),
+                LPN_Percentage(value) => format!("{}%", value * 100.),
+                LPN_None => "none".to_string(),
+            }
+        }
+    }
 }
 
 pub fn parse_url(input: &str, base_url: &Url) -> Url {
@@ -18,6 +18,7 @@ use errors::{ErrorLoggerIterator, log_css_error};
 pub use parsing_utils::*;
 pub use self::common_types::*;
 use selector_matching::DeclarationBlock;
+use self::common_types::computed::ToComputedCss;
 
 
 pub use self::property_bit_field::PropertyBitField;
@@ -34,25 +35,32 @@ def to_rust_ident(name):
         name += "_"
     return name
 
+def to_camel_case(ident):
+    camel_case, _ = re.subn(
+        "_([a-z])",
+        lambda m: m.group(1).upper(),
+        ident.strip("_").capitalize())
+    return camel_case
+
 class Longhand(object):
     def __init__(self, name, derived_from=None, experimental=False):
         self.name = name
         self.ident = to_rust_ident(name)
-        self.camel_case, _ = re.subn(
-            "_([a-z])",
-            lambda m: m.group(1).upper(),
-            self.ident.strip("_").capitalize())
+        self.camel_case = to_camel_case(self.ident)
         self.style_struct = THIS_STYLE_STRUCT
         self.experimental = experimental
         if derived_from is None:
             self.derived_from = None
         else:
             self.derived_from = [ to_rust_ident(name) for name in derived_from ]
+        # The kind of computed value, for the properties that computed_value_to_css serializes.
+        self.computed_type = None
 
 class Shorthand(object):
     def __init__(self, name, sub_properties):
         self.name = name
         self.ident = to_rust_ident(name)
+        self.camel_case = to_camel_case(self.ident)
         self.sub_properties = [LONGHANDS_BY_NAME[s] for s in sub_properties]
 
 class StyleStruct(object):
@@ -85,6 +93,15 @@ def switch_to_style_struct(name):
             THIS_STYLE_STRUCT = style_struct
             return ""
     fail()
+
+# The properties that have an attribute on CSSStyleDeclaration.
+# script/makefile.cargo generates the attributes from this list after rendering the template.
+def dom_properties():
+    return SHORTHANDS + [p for p in LONGHANDS if p.derived_from is None]
+
+# The name of the attribute in CamelCase. `float` is a reserved word in WebIDL.
+def dom_accessor_name(property):
+    return "CssFloat" if property.name == "float" else property.camel_case
 %>
 
 pub mod longhands {
@@ -164,6 +181,15 @@ pub mod longhands {
                         ${to_rust_ident(value)},
                     % endfor
                 }
+                impl super::super::computed::ToComputedCss for T {
+                    fn to_computed_css(&self) -> String {
+                        match *self {
+                            % for value in values.split():
+                                ${to_rust_ident(value)} => "${value}",
+                            % endfor
+                        }.to_string()
+                    }
+                }
             }
             pub type SpecifiedValue = computed_value::T;
             #[inline] pub fn get_initial_value() -> computed_value::T {
@@ -181,6 +207,7 @@ pub mod longhands {
                 })
             }
         </%self:single_component_value>
+        <% LONGHANDS_BY_NAME[name].computed_type = "keyword" %>
     </%def>
 
     <%def name="single_keyword(name, values, experimental=False)">
@@ -205,6 +232,7 @@ pub mod longhands {
                 specified::${type}::${parse_method}(v)
             }
         </%self:single_component_value>
+        <% LONGHANDS_BY_NAME[name].computed_type = type %>
     </%def>
 
 
@@ -241,6 +269,7 @@ pub mod longhands {
                 pub type T = super::super::border_top_style::computed_value::T;
             }
         </%self:longhand>
+        <% LONGHANDS_BY_NAME["border-%s-style" % side].computed_type = "keyword" %>
     % endfor
 
     pub fn parse_border_width(component_value: &ComponentValue, _base_url: &Url)
@@ -280,6 +309,7 @@ pub mod longhands {
                 }
             }
         </%self:longhand>
+        <% LONGHANDS_BY_NAME["border-%s-width" % side].computed_type = "Au" %>
     % endfor
 
     ${new_style_struct("PositionOffsets", is_inherited=False)}
@@ -756,6 +786,7 @@ pub mod longhands {
             }
         }
     </%self:raw_longhand>
+    <% LONGHANDS_BY_NAME["color"].computed_type = "RGBA" %>
 
     // CSS 2.1, Section 15 - Fonts
 
@@ -956,6 +987,7 @@ pub mod longhands {
             }
         }
     </%self:single_component_value>
+    <% LONGHANDS_BY_NAME["font-size"].computed_type = "Au" %>
 
     // CSS 2.1, Section 16 - Text
 
@@ -1115,6 +1147,391 @@ pub mod longhands {
     ${switch_to_style_struct("Box")}
 
     ${single_keyword("box-sizing", "content-box border-box")}
+
+    ${new_style_struct("Effects", is_inherited=False)}
+
+    // CSS Color Module Level 3
+    // http://dev.w3.org/csswg/css-color/#transparency
+    <%self:single_component_value name="opacity">
+        pub type SpecifiedValue = CSSFloat;
+        pub mod computed_value {
+            use super::super::CSSFloat;
+            pub type T = CSSFloat;
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            1.0
+        }
+        #[inline]
+        pub fn to_computed_value(value: SpecifiedValue, _: &computed::Context)
+                                 -> computed_value::T {
+            // Values outside of the range are clamped, rather than invalid.
+            if value < 0.0 {
+                0.0
+            } else if value > 1.0 {
+                1.0
+            } else {
+                value
+            }
+        }
+        pub fn from_component_value(input: &ComponentValue, _base_url: &Url)
+                                    -> Result<SpecifiedValue, ()> {
+            match *input {
+                Number(ref value) => Ok(value.value),
+                _ => Err(()),
+            }
+        }
+    </%self:single_component_value>
+    <% LONGHANDS_BY_NAME["opacity"].computed_type = "CSSFloat" %>
+
+    // CSS Transforms Module Level 1
+    // http://dev.w3.org/csswg/css-transforms/
+    <%self:longhand name="transform">
+        pub mod computed_value {
+            use geom::size::Size2D;
+            use super::super::{Au, CSSFloat};
+            use super::super::super::common_types::computed::{LengthOrPercentage, LP_Length};
+            use super::super::super::common_types::computed::LP_Percentage;
+
+            /// A 4x4 matrix, with its fields in the order of the arguments of `matrix3d()`. Points
+            /// are row vectors, so that `m41`, `m42` and `m43` are the translation.
+            #[deriving(PartialEq, Clone, Show)]
+            pub struct ComputedMatrix {
+                pub m11: CSSFloat, pub m12: CSSFloat, pub m13: CSSFloat, pub m14: CSSFloat,
+                pub m21: CSSFloat, pub m22: CSSFloat, pub m23: CSSFloat, pub m24: CSSFloat,
+                pub m31: CSSFloat, pub m32: CSSFloat, pub m33: CSSFloat, pub m34: CSSFloat,
+                pub m41: CSSFloat, pub m42: CSSFloat, pub m43: CSSFloat, pub m44: CSSFloat,
+            }
+
+            impl ComputedMatrix {
+                pub fn identity() -> ComputedMatrix {
+                    ComputedMatrix {
+                        m11: 1., m12: 0., m13: 0., m14: 0.,
+                        m21: 0., m22: 1., m23: 0., m24: 0.,
+                        m31: 0., m32: 0., m33: 1., m34: 0.,
+                        m41: 0., m42: 0., m43: 0., m44: 1.,
+                    }
+                }
+
+                /// A translation, in px.
+                pub fn translation(x: CSSFloat, y: CSSFloat, z: CSSFloat) -> ComputedMatrix {
+                    ComputedMatrix { m41: x, m42: y, m43: z, ..ComputedMatrix::identity() }
+                }
+
+                /// The matrix that applies `self`, then `other`.
+                pub fn then(&self, other: &ComputedMatrix) -> ComputedMatrix {
+                    let a = self;
+                    let b = other;
+                    ComputedMatrix {
+                        m11: a.m11 * b.m11 + a.m12 * b.m21 + a.m13 * b.m31 + a.m14 * b.m41,
+                        m12: a.m11 * b.m12 + a.m12 * b.m22 + a.m13 * b.m32 + a.m14 * b.m42,
+                        m13: a.m11 * b.m13 + a.m12 * b.m23 + a.m13 * b.m33 + a.m14 * b.m43,
+                        m14: a.m11 * b.m14 + a.m12 * b.m24 + a.m13 * b.m34 + a.m14 * b.m44,
+                        m21: a.m21 * b.m11 + a.m22 * b.m21 + a.m23 * b.m31 + a.m24 * b.m41,
+                        m22: a.m21 * b.m12 + a.m22 * b.m22 + a.m23 * b.m32 + a.m24 * b.m42,
+                        m23: a.m21 * b.m13 + a.m22 * b.m23 + a.m23 * b.m33 + a.m24 * b.m43,
+                        m24: a.m21 * b.m14 + a.m22 * b.m24 + a.m23 * b.m34 + a.m24 * b.m44,
+                        m31: a.m31 * b.m11 + a.m32 * b.m21 + a.m33 * b.m31 + a.m34 * b.m41,
+                        m32: a.m31 * b.m12 + a.m32 * b.m22 + a.m33 * b.m32 + a.m34 * b.m42,
+                        m33: a.m31 * b.m13 + a.m32 * b.m23 + a.m33 * b.m33 + a.m34 * b.m43,
+                        m34: a.m31 * b.m14 + a.m32 * b.m24 + a.m33 * b.m34 + a.m34 * b.m44,
+                        m41: a.m41 * b.m11 + a.m42 * b.m21 + a.m43 * b.m31 + a.m44 * b.m41,
+                        m42: a.m41 * b.m12 + a.m42 * b.m22 + a.m43 * b.m32 + a.m44 * b.m42,
+                        m43: a.m41 * b.m13 + a.m42 * b.m23 + a.m43 * b.m33 + a.m44 * b.m43,
+                        m44: a.m41 * b.m14 + a.m42 * b.m24 + a.m43 * b.m34 + a.m44 * b.m44,
+                    }
+                }
+            }
+
+            #[deriving(PartialEq, Clone)]
+            pub enum ComputedOperation {
+                Matrix(ComputedMatrix),
+                Translate(LengthOrPercentage, LengthOrPercentage, Au),
+                Scale(CSSFloat, CSSFloat, CSSFloat),
+                /// The axis, which isn't normalized, and the angle in radians.
+                Rotate(CSSFloat, CSSFloat, CSSFloat, CSSFloat),
+                /// The angles along the x and y axes, in radians.
+                Skew(CSSFloat, CSSFloat),
+                Perspective(Au),
+            }
+
+            /// The list of transform functions, empty for `none`.
+            pub type T = Vec<ComputedOperation>;
+
+            /// Computes the matrix of a transform, relative to the transform origin. Percentages
+            /// are relative to the size of the border box.
+            pub fn to_matrix(value: &T, border_box_size: &Size2D<Au>) -> ComputedMatrix {
+                // The transform functions apply from right to left.
+                value.iter().rev().fold(ComputedMatrix::identity(), |matrix, operation| {
+                    matrix.then(&operation_matrix(operation, border_box_size))
+                })
+            }
+
+            fn resolve(length: LengthOrPercentage, reference: Au) -> Au {
+                match length {
+                    LP_Length(length) => length,
+                    LP_Percentage(percentage) => reference.scale_by(percentage),
+                }
+            }
+
+            fn operation_matrix(operation: &ComputedOperation, border_box_size: &Size2D<Au>)
+                                -> ComputedMatrix {
+                let identity = ComputedMatrix::identity();
+                match *operation {
+                    Matrix(ref matrix) => matrix.clone(),
+                    Translate(x, y, z) => {
+                        let x = resolve(x, border_box_size.width);
+                        let y = resolve(y, border_box_size.height);
+                        ComputedMatrix::translation(x.to_subpx(), y.to_subpx(), z.to_subpx())
+                    }
+                    Scale(x, y, z) => ComputedMatrix { m11: x, m22: y, m33: z, ..identity },
+                    Rotate(x, y, z, angle) => {
+                        // http://dev.w3.org/csswg/css-transforms/#Rotate3dDefined
+                        let length = (x * x + y * y + z * z).sqrt();
+                        if length == 0. {
+                            return identity
+                        }
+                        let (x, y, z) = (x / length, y / length, z / length);
+                        let sc = (angle / 2.).sin() * (angle / 2.).cos();
+                        let sq = (angle / 2.).sin() * (angle / 2.).sin();
+                        ComputedMatrix {
+                            m11: 1. - 2. * (y * y + z * z) * sq,
+                            m12: 2. * (x * y * sq + z * sc),
+                            m13: 2. * (x * z * sq - y * sc),
+                            m21: 2. * (x * y * sq - z * sc),
+                            m22: 1. - 2. * (x * x + z * z) * sq,
+                            m23: 2. * (y * z * sq + x * sc),
+                            m31: 2. * (x * z * sq + y * sc),
+                            m32: 2. * (y * z * sq - x * sc),
+                            m33: 1. - 2. * (x * x + y * y) * sq,
+                            ..identity
+                        }
+                    }
+                    Skew(x, y) => ComputedMatrix { m12: y.tan(), m21: x.tan(), ..identity },
+                    Perspective(length) if length > Au(0) => {
+                        ComputedMatrix { m34: -1. / length.to_subpx(), ..identity }
+                    }
+                    Perspective(_) => identity,
+                }
+            }
+        }
+
+        #[deriving(Clone)]
+        pub enum SpecifiedOperation {
+            SpecifiedMatrix(ComputedMatrix),
+            SpecifiedTranslate(specified::LengthOrPercentage,
+                               specified::LengthOrPercentage,
+                               specified::Length),
+            SpecifiedScale(CSSFloat, CSSFloat, CSSFloat),
+            SpecifiedRotate(CSSFloat, CSSFloat, CSSFloat, specified::Angle),
+            SpecifiedSkew(specified::Angle, specified::Angle),
+            SpecifiedPerspective(specified::Length),
+        }
+
+        pub type SpecifiedValue = Vec<SpecifiedOperation>;
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            vec!()
+        }
+
+        pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                                 -> computed_value::T {
+            value.into_iter().map(|operation| {
+                match operation {
+                    SpecifiedMatrix(matrix) => Matrix(matrix),
+                    SpecifiedTranslate(x, y, z) => {
+                        Translate(computed::compute_LengthOrPercentage(x, context),
+                                  computed::compute_LengthOrPercentage(y, context),
+                                  computed::compute_Au(z, context))
+                    }
+                    SpecifiedScale(x, y, z) => Scale(x, y, z),
+                    SpecifiedRotate(x, y, z, angle) => Rotate(x, y, z, angle.radians()),
+                    SpecifiedSkew(x, y) => Skew(x.radians(), y.radians()),
+                    SpecifiedPerspective(length) => {
+                        Perspective(computed::compute_Au(length, context))
+                    }
+                }
+            }).collect()
+        }
+
+        /// Returns the arguments of a transform function, which must each be a single component
+        /// value, if there are as many as `min` to `max` of them.
+        fn parse_arguments<'a>(arguments: &'a [ComponentValue], min: uint, max: uint)
+                               -> Result<Vec<&'a ComponentValue>, ()> {
+            let mut result = vec!();
+            for argument in arguments.split(|component_value| *component_value == Comma) {
+                result.push(try!(one_component_value(argument)));
+            }
+            if result.len() < min || result.len() > max {
+                return Err(())
+            }
+            Ok(result)
+        }
+
+        fn number(input: &ComponentValue) -> Result<CSSFloat, ()> {
+            match *input {
+                ast::Number(ref value) => Ok(value.value),
+                _ => Err(()),
+            }
+        }
+
+        fn parse_operation(name: &str, arguments: &[ComponentValue])
+                           -> Result<SpecifiedOperation, ()> {
+            let zero = specified::LP_Length(specified::Au_(Au(0)));
+            let zero_z = specified::Au_(Au(0));
+            let zero_angle = specified::Angle(0.);
+            let length_or_percentage = specified::LengthOrPercentage::parse;
+            let angle = specified::Angle::parse;
+            let argument = || one_component_value(arguments);
+            match name.to_ascii_lower().as_slice() {
+                "matrix" => {
+                    let args = try!(parse_arguments(arguments, 6, 6));
+                    let mut values = vec!();
+                    for arg in args.iter() {
+                        values.push(try!(number(*arg)));
+                    }
+                    Ok(SpecifiedMatrix(ComputedMatrix {
+                        m11: values[0], m12: values[1],
+                        m21: values[2], m22: values[3],
+                        m41: values[4], m42: values[5],
+                        ..ComputedMatrix::identity()
+                    }))
+                }
+                "matrix3d" => {
+                    let args = try!(parse_arguments(arguments, 16, 16));
+                    let mut v = vec!();
+                    for arg in args.iter() {
+                        v.push(try!(number(*arg)));
+                    }
+                    Ok(SpecifiedMatrix(ComputedMatrix {
+                        m11: v[0], m12: v[1], m13: v[2], m14: v[3],
+                        m21: v[4], m22: v[5], m23: v[6], m24: v[7],
+                        m31: v[8], m32: v[9], m33: v[10], m34: v[11],
+                        m41: v[12], m42: v[13], m43: v[14], m44: v[15],
+                    }))
+                }
+                "translate" => {
+                    let args = try!(parse_arguments(arguments, 1, 2));
+                    let x = try!(length_or_percentage(args[0]));
+                    let y = match args.as_slice().get(1) {
+                        Some(y) => try!(length_or_percentage(*y)),
+                        None => zero,
+                    };
+                    Ok(SpecifiedTranslate(x, y, zero_z))
+                }
+                "translatex" => {
+                    let x = try!(length_or_percentage(try!(argument())));
+                    Ok(SpecifiedTranslate(x, zero, zero_z))
+                }
+                "translatey" => {
+                    let y = try!(length_or_percentage(try!(argument())));
+                    Ok(SpecifiedTranslate(zero, y, zero_z))
+                }
+                "translatez" => {
+                    let z = try!(specified::Length::parse(try!(argument())));
+                    Ok(SpecifiedTranslate(zero, zero, z))
+                }
+                "translate3d" => {
+                    let args = try!(parse_arguments(arguments, 3, 3));
+                    Ok(SpecifiedTranslate(try!(length_or_percentage(args[0])),
+                                          try!(length_or_percentage(args[1])),
+                                          try!(specified::Length::parse(args[2]))))
+                }
+                "scale" => {
+                    let args = try!(parse_arguments(arguments, 1, 2));
+                    let x = try!(number(args[0]));
+                    let y = match args.as_slice().get(1) {
+                        Some(y) => try!(number(*y)),
+                        None => x,
+                    };
+                    Ok(SpecifiedScale(x, y, 1.))
+                }
+                "scalex" => Ok(SpecifiedScale(try!(number(try!(argument()))), 1., 1.)),
+                "scaley" => Ok(SpecifiedScale(1., try!(number(try!(argument()))), 1.)),
+                "scalez" => Ok(SpecifiedScale(1., 1., try!(number(try!(argument()))))),
+                "scale3d" => {
+                    let args = try!(parse_arguments(arguments, 3, 3));
+                    Ok(SpecifiedScale(try!(number(args[0])),
+                                      try!(number(args[1])),
+                                      try!(number(args[2]))))
+                }
+                "rotate" | "rotatez" => {
+                    Ok(SpecifiedRotate(0., 0., 1., try!(angle(try!(argument())))))
+                }
+                "rotatex" => {
+                    Ok(SpecifiedRotate(1., 0., 0., try!(angle(try!(argument())))))
+                }
+                "rotatey" => {
+                    Ok(SpecifiedRotate(0., 1., 0., try!(angle(try!(argument())))))
+                }
+                "rotate3d" => {
+                    let args = try!(parse_arguments(arguments, 4, 4));
+                    Ok(SpecifiedRotate(try!(number(args[0])),
+                                       try!(number(args[1])),
+                                       try!(number(args[2])),
+                                       try!(angle(args[3]))))
+                }
+                "skew" => {
+                    let args = try!(parse_arguments(arguments, 1, 2));
+                    let x = try!(angle(args[0]));
+                    let y = match args.as_slice().get(1) {
+                        Some(y) => try!(angle(*y)),
+                        None => zero_angle,
+                    };
+                    Ok(SpecifiedSkew(x, y))
+                }
+                "skewx" => Ok(SpecifiedSkew(try!(angle(try!(argument()))), zero_angle)),
+                "skewy" => Ok(SpecifiedSkew(zero_angle, try!(angle(try!(argument()))))),
+                "perspective" => {
+                    let length = try!(specified::Length::parse_non_negative(try!(argument())));
+                    Ok(SpecifiedPerspective(length))
+                }
+                _ => Err(()),
+            }
+        }
+
+        pub fn parse(input: &[ComponentValue], _: &Url) -> Result<SpecifiedValue, ()> {
+            match one_component_value(input) {
+                Ok(&Ident(ref value)) if value.as_slice().eq_ignore_ascii_case("none") => {
+                    return Ok(vec!())
+                }
+                _ => {}
+            }
+            let mut operations = vec!();
+            for component_value in input.skip_whitespace() {
+                match *component_value {
+                    Function(ref name, ref arguments) => {
+                        operations.push(try!(parse_operation(name.as_slice(),
+                                                             arguments.as_slice())))
+                    }
+                    _ => return Err(()),
+                }
+            }
+            if operations.is_empty() {
+                return Err(())
+            }
+            Ok(operations)
+        }
+    </%self:longhand>
+
+    <%self:longhand name="transform-origin">
+        // Only the 2D part of the transform origin is supported, which is the same as a
+        // background position.
+        pub use super::background_position::{parse, to_computed_value};
+        pub type SpecifiedValue = super::background_position::SpecifiedValue;
+        pub mod computed_value {
+            pub type T = super::super::background_position::computed_value::T;
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T {
+                horizontal: computed::LP_Percentage(0.5),
+                vertical: computed::LP_Percentage(0.5),
+            }
+        }
+    </%self:longhand>
 }
 
 
@@ -1684,6 +2101,72 @@ impl PropertyDeclaration {
 }
 
 
+/// Returns the longhands set by the shorthand property `name`, or `None` if it isn't one.
+pub fn longhands_of_shorthand(name: &str) -> Option<Vec<String>> {
+    // FIXME: local variable to work around Rust #10683
+    let name_lower = name.as_slice().to_ascii_lower();
+    match name_lower.as_slice() {
+        % for shorthand in SHORTHANDS:
+            "${shorthand.name}" => Some(vec!(
+                % for sub_property in shorthand.sub_properties:
+                    "${sub_property.name}".to_string(),
+                % endfor
+            )),
+        % endfor
+        _ => None,
+    }
+}
+
+
+/// The longhands that `computed_value_to_css()` can serialize, in alphabetical order, which are
+/// the properties a computed style declaration enumerates.
+pub static COMPUTED_LONGHANDS: &'static [&'static str] = &[
+    % for property in sorted(LONGHANDS, key=lambda property: property.name):
+        % if property.computed_type is not None and property.derived_from is None:
+            "${property.name}",
+        % endif
+    % endfor
+];
+
+
+/// Serializes the computed value of the property `name`, as returned by `getComputedStyle()`.
+/// Returns `None` for unknown properties and for the ones that can't be serialized yet.
+pub fn computed_value_to_css(style: &ComputedValues, name: &str) -> Option<String> {
+    // FIXME: local variable to work around Rust #10683
+    let name_lower = name.as_slice().to_ascii_lower();
+    match name_lower.as_slice() {
+        % for property in LONGHANDS:
+            % if property.computed_type is not None and property.derived_from is None:
+                "${property.name}" => {
+                    let value = style.get_${property.style_struct.name.lower()}()
+                                     .${property.ident}.clone();
+                    % if property.computed_type == "CSSColor":
+                        Some(style.resolve_color(value).to_computed_css())
+                    % else:
+                        Some(value.to_computed_css())
+                    % endif
+                },
+            % endif
+        % endfor
+        _ => None,
+    }
+}
+
+
+/// Calls `$macro_name!` with a `[getter, setter, "property-name"]` triple for every property
+/// that has an attribute on `CSSStyleDeclaration`, so that script can implement them.
+#[macro_export]
+macro_rules! css_properties_accessors(
+    ($macro_name: ident) => (
+        $macro_name!(
+            % for property in dom_properties():
+                [Get${dom_accessor_name(property)}, Set${dom_accessor_name(property)}, "${property.name}"]
+            % endfor
+        )
+    );
+)
+
+
 pub mod style_structs {
     use super::longhands;
 
@@ -1868,6 +2351,50 @@ fn initial_writing_mode_is_empty() {
     assert_eq!(get_writing_mode(INITIAL_VALUES.get_inheritedbox()), WritingMode::empty())
 }
 
+#[test]
+fn initial_values_to_css() {
+    let style = &*INITIAL_VALUES;
+    let to_css = |name| computed_value_to_css(style, name);
+    assert_eq!(to_css("display"), Some("inline".to_string()));
+    assert_eq!(to_css("Margin-Top"), Some("0px".to_string()));
+    assert_eq!(to_css("max-width"), Some("none".to_string()));
+    assert_eq!(to_css("font-size"), Some("16px".to_string()));
+    assert_eq!(to_css("color"), Some("rgb(0, 0, 0)".to_string()));
+    assert_eq!(to_css("border-top-color"), Some("rgb(0, 0, 0)".to_string()));
+    assert_eq!(to_css("opacity"), Some("1".to_string()));
+    assert_eq!(to_css("margin"), None);
+    assert_eq!(to_css("-servo-display-for-hypothetical-box"), None);
+}
+
+#[test]
+fn transform_matrix() {
+    use geom::size::Size2D;
+    use self::common_types::computed::{LP_Length, LP_Percentage};
+    use self::longhands::transform::{mod, ComputedMatrix, Scale, Translate};
+
+    let url = Url::parse("http://example.com/").unwrap();
+    let parse = |css: &str| {
+        let tokens: Vec<ComponentValue> = tokenize(css).map(|(token, _)| token).collect();
+        transform::parse(tokens.as_slice(), &url).map(|operations| operations.len())
+    };
+    assert_eq!(parse("none"), Ok(0));
+    assert_eq!(parse("translate(1px, 2%) Scale(2) rotate(0.25turn)"), Ok(3));
+    assert_eq!(parse("matrix(1, 0, 0, 1, 10, 20) skewX(10deg)"), Ok(2));
+    assert_eq!(parse("none translate(1px)"), Err(()));
+    assert_eq!(parse("rotate(10px)"), Err(()));
+    assert_eq!(parse("skew(1deg, 2deg, 3deg)"), Err(()));
+    assert_eq!(parse("translate(1px) 2"), Err(()));
+
+    // The scale applies first, then the translation, whose percentage is of the border box.
+    let operations = vec!(Translate(LP_Length(Au::from_px(10)), LP_Percentage(0.5), Au(0)),
+                          Scale(2., 3., 1.));
+    let border_box_size = Size2D(Au::from_px(100), Au::from_px(40));
+    assert_eq!(transform::to_matrix(&operations, &border_box_size), ComputedMatrix {
+        m11: 2., m22: 3., m41: 10., m42: 20.,
+        ..ComputedMatrix::identity()
+    });
+}
+
 
 /// This only exists to limit the scope of #[allow(experimental)]
 /// FIXME: remove this when Arc::make_unique() is not experimental anymore.
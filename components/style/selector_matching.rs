@@ -15,7 +15,7 @@ use servo_util::smallvec::VecLike;
 use servo_util::sort;
 use string_cache::Atom;
 
-use media_queries::{Device, Screen};
+use media_queries::Device;
 use node::{TElement, TNode};
 use properties::{PropertyDeclaration, PropertyDeclarationBlock};
 use selectors::*;
@@ -268,27 +268,64 @@ pub struct Stylist {
     before_map: PerPseudoElementSelectorMap,
     after_map: PerPseudoElementSelectorMap,
     rules_source_order: uint,
+    /// What the media queries of the stylesheets are evaluated against.
+    device: Device,
+    /// Only taken out while its rules are added.
+    ua_stylesheet: Option<Stylesheet>,
 }
 
 impl Stylist {
     #[inline]
-    pub fn new() -> Stylist {
+    pub fn new(device: Device) -> Stylist {
         let mut stylist = Stylist {
             element_map: PerPseudoElementSelectorMap::new(),
             before_map: PerPseudoElementSelectorMap::new(),
             after_map: PerPseudoElementSelectorMap::new(),
             rules_source_order: 0u,
+            device: device,
+            ua_stylesheet: Some(Stylesheet::from_bytes(
+                read_resource_file(["user-agent.css"]).unwrap().as_slice(),
+                Url::parse("chrome:///user-agent.css").unwrap(),
+                None,
+                None)),
         };
-        let ua_stylesheet = Stylesheet::from_bytes(
-            read_resource_file(["user-agent.css"]).unwrap().as_slice(),
-            Url::parse("chrome:///user-agent.css").unwrap(),
-            None,
-            None);
-        stylist.add_stylesheet(ua_stylesheet, UserAgentOrigin);
+        stylist.add_user_agent_stylesheet();
         stylist
     }
 
-    pub fn add_stylesheet(&mut self, stylesheet: Stylesheet, origin: StylesheetOrigin) {
+    fn add_user_agent_stylesheet(&mut self) {
+        let ua_stylesheet = self.ua_stylesheet.take().unwrap();
+        self.add_stylesheet(&ua_stylesheet, UserAgentOrigin);
+        self.ua_stylesheet = Some(ua_stylesheet);
+    }
+
+    pub fn device<'a>(&'a self) -> &'a Device {
+        &self.device
+    }
+
+    /// Sets the device that media queries are evaluated against, as when the viewport is
+    /// resized. If this changes which rules of the user agent stylesheet or of
+    /// `author_stylesheets` apply, only the user agent rules are kept, the author stylesheets
+    /// have to be added again, and true is returned.
+    pub fn set_device(&mut self, device: Device, author_stylesheets: &[&Stylesheet]) -> bool {
+        let changed = {
+            let old_device = &self.device;
+            self.ua_stylesheet.iter().chain(author_stylesheets.iter().map(|&s| s)).any(|s| {
+                s.media_queries_differ(old_device, &device)
+            })
+        };
+        self.device = device;
+        if changed {
+            self.element_map = PerPseudoElementSelectorMap::new();
+            self.before_map = PerPseudoElementSelectorMap::new();
+            self.after_map = PerPseudoElementSelectorMap::new();
+            self.rules_source_order = 0;
+            self.add_user_agent_stylesheet();
+        }
+        changed
+    }
+
+    pub fn add_stylesheet(&mut self, stylesheet: &Stylesheet, origin: StylesheetOrigin) {
         let (mut element_map, mut before_map, mut after_map) = match origin {
             UserAgentOrigin => (
                 &mut self.element_map.user_agent,
@@ -332,8 +369,10 @@ impl Stylist {
             };
         );
 
-        let device = &Device { media_type: Screen };  // TODO, use Print when printing
-        iter_stylesheet_style_rules(&stylesheet, device, |style_rule| {
+        let device = &self.device;
+        // The rules of imported stylesheets come before the rules of the stylesheet importing
+        // them, in the order of its @import rules.
+        iter_stylesheet_style_rules(stylesheet, device, |style_rule| {
             append!(style_rule, normal);
             append!(style_rule, important);
             rules_source_order += 1;
@@ -341,6 +380,14 @@ impl Stylist {
         self.rules_source_order = rules_source_order;
     }
 
+    /// Forgets the rules of the author stylesheets, so that they can be added again after one
+    /// of them changed.
+    pub fn clear_author_stylesheets(&mut self) {
+        self.element_map.author = PerOriginSelectorMap::new();
+        self.before_map.author = PerOriginSelectorMap::new();
+        self.after_map.author = PerOriginSelectorMap::new();
+    }
+
     /// Returns the applicable CSS declarations for the given element. This corresponds to
     /// `ElementRuleCollector` in WebKit.
     ///
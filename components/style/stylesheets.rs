@@ -4,7 +4,7 @@
 
 use std::iter::Iterator;
 use std::ascii::StrAsciiExt;
-use url::Url;
+use url::{Url, UrlParser};
 
 use encoding::EncodingRef;
 
@@ -14,7 +14,7 @@ use selectors;
 use properties;
 use errors::{ErrorLoggerIterator, log_css_error};
 use namespaces::{NamespaceMap, parse_namespace_rule};
-use media_queries::{MediaRule, parse_media_rule};
+use media_queries::{MediaRule, MediaQueryList, parse_media_rule, parse_media_query_list};
 use media_queries;
 use font_face::{FontFaceRule, parse_font_face_rule, iter_font_face_rules_inner};
 
@@ -27,28 +27,48 @@ pub struct Stylesheet {
 
 
 pub enum CSSRule {
+    CSSImportRule(ImportRule),
     CSSStyleRule(StyleRule),
     CSSMediaRule(MediaRule),
     CSSFontFaceRule(FontFaceRule),
 }
 
 
+pub struct ImportRule {
+    pub url: Url,
+    pub media_queries: MediaQueryList,
+    /// The imported stylesheet, once it has been loaded.
+    pub stylesheet: Option<Stylesheet>,
+}
+
+
 pub struct StyleRule {
     pub selectors: Vec<selectors::Selector>,
     pub declarations: properties::PropertyDeclarationBlock,
 }
 
 
+/// Decodes the bytes of a stylesheet into its text, the way `Stylesheet::from_bytes_iter` does
+/// before parsing it.
+pub fn decode_stylesheet<I: Iterator<Vec<u8>>>(
+        mut input: I, protocol_encoding_label: Option<&str>,
+        environment_encoding: Option<EncodingRef>) -> String {
+    let mut bytes = vec!();
+    // TODO: incremental decoding and tokinization/parsing
+    for chunk in input {
+        bytes.push_all(chunk.as_slice())
+    }
+    let (string, _) = decode_stylesheet_bytes(
+        bytes.as_slice(), protocol_encoding_label, environment_encoding);
+    string
+}
+
 impl Stylesheet {
     pub fn from_bytes_iter<I: Iterator<Vec<u8>>>(
-            mut input: I, base_url: Url, protocol_encoding_label: Option<&str>,
+            input: I, base_url: Url, protocol_encoding_label: Option<&str>,
             environment_encoding: Option<EncodingRef>) -> Stylesheet {
-        let mut bytes = vec!();
-        // TODO: incremental decoding and tokinization/parsing
-        for chunk in input {
-            bytes.push_all(chunk.as_slice())
-        }
-        Stylesheet::from_bytes(bytes.as_slice(), base_url, protocol_encoding_label, environment_encoding)
+        let css = decode_stylesheet(input, protocol_encoding_label, environment_encoding);
+        Stylesheet::from_str(css.as_slice(), base_url)
     }
 
     pub fn from_bytes(
@@ -94,8 +114,7 @@ impl Stylesheet {
                                               "@import must be before any rule but @charset")
                             } else {
                                 next_state = STATE_IMPORTS;
-                                // TODO: support @import
-                                log_css_error(rule.location, "@import is not supported yet")
+                                parse_import_rule(rule, &mut rules, &base_url)
                             }
                         },
                         "namespace" => {
@@ -121,6 +140,75 @@ impl Stylesheet {
         }
         Stylesheet{ rules: rules }
     }
+
+    /// Load the stylesheets imported by this one, which was loaded from `url`, and the ones they
+    /// import in turn. An import of a stylesheet that is already being imported further up the
+    /// chain is skipped, so that cycles terminate.
+    pub fn load_imports(&mut self, url: &Url, load: |&Url| -> Option<Stylesheet>) {
+        let mut ancestors = vec!(url.clone());
+        load_imports_inner(self.rules.as_mut_slice(), &mut ancestors, load)
+    }
+
+    /// Returns whether any media query of this stylesheet or of those it imports evaluates
+    /// differently on the two devices, so that different rules apply.
+    pub fn media_queries_differ(&self, old_device: &media_queries::Device,
+                                new_device: &media_queries::Device) -> bool {
+        rules_media_queries_differ(self.rules.as_slice(), old_device, new_device)
+    }
+}
+
+
+fn load_imports_inner(rules: &mut [CSSRule], ancestors: &mut Vec<Url>,
+                      load: |&Url| -> Option<Stylesheet>) {
+    for rule in rules.iter_mut() {
+        let rule = match *rule {
+            CSSImportRule(ref mut rule) => rule,
+            // @import rules come before any other.
+            _ => return,
+        };
+        if ancestors.contains(&rule.url) {
+            debug!("Skipping cyclic @import of {:s}", rule.url.serialize());
+            continue
+        }
+        let mut stylesheet = match load(&rule.url) {
+            Some(stylesheet) => stylesheet,
+            None => continue,
+        };
+        ancestors.push(rule.url.clone());
+        load_imports_inner(stylesheet.rules.as_mut_slice(), ancestors, |url| load(url));
+        ancestors.pop();
+        rule.stylesheet = Some(stylesheet);
+    }
+}
+
+
+fn parse_import_rule(rule: AtRule, parent_rules: &mut Vec<CSSRule>, base_url: &Url) {
+    let location = rule.location;
+    macro_rules! syntax_error(
+        () => {{
+            log_css_error(location, "Invalid @import rule");
+            return
+        }};
+    );
+    if rule.block.is_some() { syntax_error!() }
+    let prelude = rule.prelude.as_slice();
+    let mut iter = prelude.iter().enumerate().skip_while(|&(_, value)| match *value {
+        WhiteSpace => true,
+        _ => false,
+    });
+    let (index, url) = match iter.next() {
+        Some((index, &URL(ref url))) | Some((index, &QuotedString(ref url))) => (index, url),
+        _ => syntax_error!(),
+    };
+    let url = match UrlParser::new().base_url(base_url).parse(url.as_slice()) {
+        Ok(url) => url,
+        Err(_) => syntax_error!(),
+    };
+    parent_rules.push(CSSImportRule(ImportRule {
+        url: url,
+        media_queries: parse_media_query_list(prelude.slice_from(index + 1)),
+        stylesheet: None,
+    }))
 }
 
 
@@ -156,6 +244,11 @@ pub fn iter_style_rules<'a>(rules: &[CSSRule], device: &media_queries::Device,
                             callback: |&StyleRule|) {
     for rule in rules.iter() {
         match *rule {
+            CSSImportRule(ref rule) => if rule.media_queries.evaluate(device) {
+                for stylesheet in rule.stylesheet.iter() {
+                    iter_style_rules(stylesheet.rules.as_slice(), device, |s| callback(s))
+                }
+            },
             CSSStyleRule(ref rule) => callback(rule),
             CSSMediaRule(ref rule) => if rule.media_queries.evaluate(device) {
                 iter_style_rules(rule.rules.as_slice(), device, |s| callback(s))
@@ -172,7 +265,102 @@ pub fn iter_stylesheet_style_rules(stylesheet: &Stylesheet, device: &media_queri
 }
 
 
+fn rules_media_queries_differ(rules: &[CSSRule], old_device: &media_queries::Device,
+                              new_device: &media_queries::Device) -> bool {
+    rules.iter().any(|rule| {
+        match *rule {
+            CSSImportRule(ref rule) => {
+                rule.media_queries.evaluate(old_device) != rule.media_queries.evaluate(new_device) ||
+                    rule.stylesheet.iter().any(|stylesheet| {
+                        stylesheet.media_queries_differ(old_device, new_device)
+                    })
+            }
+            CSSMediaRule(ref rule) => {
+                rule.media_queries.evaluate(old_device) != rule.media_queries.evaluate(new_device) ||
+                    rules_media_queries_differ(rule.rules.as_slice(), old_device, new_device)
+            }
+            CSSStyleRule(_) | CSSFontFaceRule(_) => false,
+        }
+    })
+}
+
+
 #[inline]
-pub fn iter_font_face_rules(stylesheet: &Stylesheet, callback: |family: &str, sources: &Url|) {
-    iter_font_face_rules_inner(stylesheet.rules.as_slice(), callback)
+pub fn iter_font_face_rules(stylesheet: &Stylesheet, device: &media_queries::Device,
+                            callback: |family: &str, sources: &Url|) {
+    iter_font_face_rules_inner(stylesheet.rules.as_slice(), device, callback)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Stylesheet, CSSImportRule, CSSStyleRule, iter_stylesheet_style_rules};
+    use geom::size::Size2D;
+    use media_queries::{Device, Screen};
+    use servo_util::geometry::Au;
+    use url::Url;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    fn import_urls(stylesheet: &Stylesheet) -> Vec<String> {
+        stylesheet.rules.iter().filter_map(|rule| match *rule {
+            CSSImportRule(ref rule) => Some(rule.url.serialize()),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn test_parse_import() {
+        let stylesheet = Stylesheet::from_str(
+            "@import url(a.css); @import 'b.css' print; @import; a {} @import 'c.css';",
+            url("http://example.com/css/main.css"));
+        assert_eq!(import_urls(&stylesheet),
+                   vec!("http://example.com/css/a.css".to_string(),
+                        "http://example.com/css/b.css".to_string()));
+        match stylesheet.rules.as_slice()[2] {
+            CSSStyleRule(_) => {}
+            _ => fail!("expected a style rule"),
+        }
+    }
+
+    #[test]
+    fn test_load_imports() {
+        let main_url = url("http://example.com/main.css");
+        let mut stylesheet = Stylesheet::from_str(
+            "@import 'a.css'; @import 'b.css' print; main, x, y {}", main_url.clone());
+        let mut loads = vec!();
+        stylesheet.load_imports(&main_url, |url| {
+            loads.push(url.serialize());
+            let css = match url.serialize().as_slice() {
+                "http://example.com/a.css" => "@import 'main.css'; @import 'c.css'; a, x {}",
+                "http://example.com/b.css" => "b, x, y, z {}",
+                "http://example.com/c.css" => "@import 'a.css'; c {}",
+                _ => return None,
+            };
+            Some(Stylesheet::from_str(css, url.clone()))
+        });
+        assert_eq!(loads, vec!("http://example.com/a.css".to_string(),
+                               "http://example.com/c.css".to_string(),
+                               "http://example.com/b.css".to_string()));
+
+        // Imported rules come first, and the print-only import doesn't apply on screen. Each
+        // rule is told apart by its number of selectors.
+        let mut selector_counts = vec!();
+        let device = Device::new(Screen, Size2D(Au::from_px(800), Au::from_px(600)), 1.);
+        iter_stylesheet_style_rules(&stylesheet, &device, |rule| {
+            selector_counts.push(rule.selectors.len());
+        });
+        assert_eq!(selector_counts, vec!(1, 2, 3));
+    }
+
+    #[test]
+    fn test_media_queries_differ() {
+        let url = Url::parse("http://example.com/").unwrap();
+        let stylesheet = Stylesheet::from_str("@media (min-width: 600px) { a {} }", url);
+        let device = |width| Device::new(Screen, Size2D(Au::from_px(width), Au::from_px(400)), 1.);
+        assert!(!stylesheet.media_queries_differ(&device(800), &device(700)));
+        assert!(stylesheet.media_queries_differ(&device(800), &device(500)));
+    }
 }
@@ -118,29 +118,37 @@ impl MemoryProfiler {
 
     fn handle_print_msg(&self) {
         println!("{:16s}: {:12s}", "_category_", "_size (MiB)_");
+        for &(path, nbytes) in memory_reports().iter() {
+            MemoryProfiler::print_measurement(path, nbytes);
+        }
+        println!("");
+    }
+}
 
+/// The current memory measurements, as (path, size in bytes) pairs. A size is `None` if it
+/// can't be measured on this platform.
+pub fn memory_reports() -> Vec<(&'static str, Option<u64>)> {
+    vec!(
         // Virtual and physical memory usage, as reported by the OS.
-        MemoryProfiler::print_measurement("vsize",          get_vsize());
-        MemoryProfiler::print_measurement("resident",       get_resident());
+        ("vsize",          get_vsize()),
+        ("resident",       get_resident()),
 
         // The descriptions of the jemalloc measurements are taken directly
         // from the jemalloc documentation.
 
         // Total number of bytes allocated by the application.
-        MemoryProfiler::print_measurement("heap-allocated", get_jemalloc_stat("stats.allocated"));
+        ("heap-allocated", get_jemalloc_stat("stats.allocated")),
 
         // Total number of bytes in active pages allocated by the application.
         // This is a multiple of the page size, and greater than or equal to
         // |stats.allocated|.
-        MemoryProfiler::print_measurement("heap-active",    get_jemalloc_stat("stats.active"));
+        ("heap-active",    get_jemalloc_stat("stats.active")),
 
         // Total number of bytes in chunks mapped on behalf of the application.
         // This is a multiple of the chunk size, and is at least as large as
         // |stats.active|. This does not include inactive chunks.
-        MemoryProfiler::print_measurement("heap-mapped",    get_jemalloc_stat("stats.mapped"));
-
-        println!("");
-    }
+        ("heap-mapped",    get_jemalloc_stat("stats.mapped")),
+    )
 }
 
 extern {
@@ -13,6 +13,7 @@ use geom::scale_factor::ScaleFactor;
 use geom::size::TypedSize2D;
 use layers::geometry::DevicePixel;
 use getopts;
+use std::ascii::StrAsciiExt;
 use std::cmp;
 use std::io;
 use std::os;
@@ -97,6 +98,90 @@ pub struct Opts {
 
     /// Dumps the flow tree after a layout.
     pub dump_flow_tree: bool,
+
+    /// The content codings to advertise in `Accept-Encoding` headers (`--accept-encoding`).
+    pub accept_encoding: Vec<String>,
+
+    /// The proxy to use for http URLs (`--http-proxy`, or the `http_proxy` environment variable).
+    pub http_proxy: Option<String>,
+
+    /// The proxy to use for https URLs (`--https-proxy`, or the `https_proxy` environment
+    /// variable).
+    pub https_proxy: Option<String>,
+
+    /// A comma-separated list of hosts that are never proxied (`--no-proxy`, or the `no_proxy`
+    /// environment variable).
+    pub no_proxy: Option<String>,
+
+    /// The maximum number of concurrent HTTP connections to a single host
+    /// (`--max-connections-per-host`).
+    pub max_connections_per_host: uint,
+
+    /// The number of seconds an idle HTTP connection is kept open for reuse
+    /// (`--idle-connection-timeout`).
+    pub idle_connection_timeout: u64,
+
+    /// Whether HTTP responses are recorded to, or replayed from, a network archive
+    /// (`--record-network` and `--replay-network`).
+    pub network_archive: NetworkArchiveMode,
+
+    /// A file to write a HAR log of the loads made by the resource task to on exit (`--har`).
+    pub har_file: Option<String>,
+
+    /// A JSON file listing the hosts that are only reached over HTTPS, before they say so
+    /// themselves (`--hsts-preload`).
+    pub hsts_preload_file: Option<String>,
+}
+
+/// Where the resource task gets HTTP responses from.
+#[deriving(Clone)]
+pub enum NetworkArchiveMode {
+    /// Load from the network.
+    LiveNetwork,
+    /// Load from the network, and write every response to the archive at the given path.
+    RecordNetwork(String),
+    /// Answer loads only from the archive at the given path, failing any load that wasn't
+    /// recorded.
+    ReplayNetwork(String),
+}
+
+impl Opts {
+    /// The name and value of each option, e.g. for about:config.
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        vec!(
+            ("urls", format!("{:?}", self.urls)),
+            ("render_backend", format!("{:?}", self.render_backend)),
+            ("n_render_threads", self.n_render_threads.to_string()),
+            ("cpu_painting", self.cpu_painting.to_string()),
+            ("tile_size", self.tile_size.to_string()),
+            ("device_pixels_per_px", format!("{:?}", self.device_pixels_per_px)),
+            ("time_profiler_period", format!("{:?}", self.time_profiler_period)),
+            ("memory_profiler_period", format!("{:?}", self.memory_profiler_period)),
+            ("enable_experimental", self.enable_experimental.to_string()),
+            ("layout_threads", self.layout_threads.to_string()),
+            ("exit_after_load", self.exit_after_load.to_string()),
+            ("output_file", format!("{:?}", self.output_file)),
+            ("headless", self.headless.to_string()),
+            ("hard_fail", self.hard_fail.to_string()),
+            ("bubble_inline_sizes_separately", self.bubble_inline_sizes_separately.to_string()),
+            ("show_debug_borders", self.show_debug_borders.to_string()),
+            ("enable_text_antialiasing", self.enable_text_antialiasing.to_string()),
+            ("trace_layout", self.trace_layout.to_string()),
+            ("devtools_port", format!("{:?}", self.devtools_port)),
+            ("initial_window_size", format!("{:?}", self.initial_window_size)),
+            ("user_agent", format!("{:?}", self.user_agent)),
+            ("dump_flow_tree", self.dump_flow_tree.to_string()),
+            ("accept_encoding", format!("{:?}", self.accept_encoding)),
+            ("http_proxy", format!("{:?}", self.http_proxy)),
+            ("https_proxy", format!("{:?}", self.https_proxy)),
+            ("no_proxy", format!("{:?}", self.no_proxy)),
+            ("max_connections_per_host", self.max_connections_per_host.to_string()),
+            ("idle_connection_timeout", self.idle_connection_timeout.to_string()),
+            ("network_archive", format!("{:?}", self.network_archive)),
+            ("har_file", format!("{:?}", self.har_file)),
+            ("hsts_preload_file", format!("{:?}", self.hsts_preload_file)),
+        )
+    }
 }
 
 fn print_usage(app: &str, opts: &[getopts::OptGroup]) {
@@ -135,6 +220,16 @@ pub fn from_cmdline_args(args: &[String]) -> Option<Opts> {
         getopts::optopt("", "resolution", "Set window resolution.", "800x600"),
         getopts::optopt("u", "user-agent", "Set custom user agent string", "NCSA Mosaic/1.0 (X11;SunOS 4.1.4 sun4m)"),
         getopts::optflag("", "dump-flow-tree", "Dump the flow (render) tree during each layout."),
+        getopts::optopt("", "accept-encoding", "Content codings to request from HTTP servers", "gzip,deflate"),
+        getopts::optopt("", "http-proxy", "Proxy for http URLs", "http://proxy.example:3128"),
+        getopts::optopt("", "https-proxy", "Proxy for https URLs", "http://proxy.example:3128"),
+        getopts::optopt("", "no-proxy", "Hosts to connect to without a proxy", "localhost,.example.org"),
+        getopts::optopt("", "max-connections-per-host", "Maximum concurrent HTTP connections to a host", "6"),
+        getopts::optopt("", "idle-connection-timeout", "Seconds to keep idle HTTP connections open", "60"),
+        getopts::optopt("", "record-network", "Record HTTP responses to an archive", "archive.json"),
+        getopts::optopt("", "replay-network", "Answer HTTP loads from an archive only", "archive.json"),
+        getopts::optopt("", "har", "Write a HAR log of network activity on exit", "servo.har"),
+        getopts::optopt("", "hsts-preload", "Hosts to always reach over HTTPS", "hsts_preload.json"),
         getopts::optflag("h", "help", "Print this message")
     );
 
@@ -230,6 +325,36 @@ pub fn from_cmdline_args(args: &[String]) -> Option<Opts> {
         }
     };
 
+    let accept_encoding = match opt_match.opt_str("accept-encoding") {
+        Some(codings) => {
+            codings.as_slice().split(',').map(|coding| coding.trim().to_string())
+                                         .filter(|coding| !coding.is_empty())
+                                         .collect()
+        }
+        None => default_accept_encoding(),
+    };
+
+    let max_connections_per_host = match opt_match.opt_str("max-connections-per-host") {
+        Some(max) => cmp::max(from_str(max.as_slice()).unwrap(), 1),
+        None => DEFAULT_MAX_CONNECTIONS_PER_HOST,
+    };
+
+    let idle_connection_timeout = match opt_match.opt_str("idle-connection-timeout") {
+        Some(timeout) => from_str(timeout.as_slice()).unwrap(),
+        None => DEFAULT_IDLE_CONNECTION_TIMEOUT,
+    };
+
+    let network_archive = match (opt_match.opt_str("record-network"),
+                                 opt_match.opt_str("replay-network")) {
+        (None, None) => LiveNetwork,
+        (Some(path), None) => RecordNetwork(path),
+        (None, Some(path)) => ReplayNetwork(path),
+        (Some(_), Some(_)) => {
+            args_fail("--record-network and --replay-network can't be used together");
+            return None;
+        }
+    };
+
     Some(Opts {
         urls: urls,
         render_backend: render_backend,
@@ -253,9 +378,78 @@ pub fn from_cmdline_args(args: &[String]) -> Option<Opts> {
         initial_window_size: initial_window_size,
         user_agent: opt_match.opt_str("u"),
         dump_flow_tree: opt_match.opt_present("dump-flow-tree"),
+        accept_encoding: accept_encoding,
+        http_proxy: opt_match.opt_str("http-proxy").or_else(|| proxy_env_var("http_proxy")),
+        https_proxy: opt_match.opt_str("https-proxy").or_else(|| proxy_env_var("https_proxy")),
+        no_proxy: opt_match.opt_str("no-proxy").or_else(|| proxy_env_var("no_proxy")),
+        max_connections_per_host: max_connections_per_host,
+        idle_connection_timeout: idle_connection_timeout,
+        network_archive: network_archive,
+        har_file: opt_match.opt_str("har"),
+        hsts_preload_file: opt_match.opt_str("hsts-preload"),
     })
 }
 
+pub static DEFAULT_MAX_CONNECTIONS_PER_HOST: uint = 6;
+pub static DEFAULT_IDLE_CONNECTION_TIMEOUT: u64 = 60;
+
+fn default_accept_encoding() -> Vec<String> {
+    vec!("gzip".to_string(), "deflate".to_string())
+}
+
+/// Read a proxy setting from the environment, accepting either the lowercase or the uppercase
+/// name of the variable. `HTTP_PROXY` is ignored, as it can be set by a CGI request's `Proxy`
+/// header.
+pub fn proxy_env_var(name: &str) -> Option<String> {
+    match os::getenv(name) {
+        Some(ref value) if !value.is_empty() => return Some(value.clone()),
+        _ => {}
+    }
+    if name == "http_proxy" {
+        return None;
+    }
+    os::getenv(name.to_ascii_upper().as_slice()).and_then(|value| {
+        if value.is_empty() { None } else { Some(value) }
+    })
+}
+
+/// Options suitable for running servo without a command line, e.g. in tests.
+pub fn default_opts() -> Opts {
+    Opts {
+        urls: vec!(),
+        render_backend: SkiaBackend,
+        n_render_threads: 1,
+        cpu_painting: false,
+        tile_size: 512,
+        device_pixels_per_px: None,
+        time_profiler_period: None,
+        memory_profiler_period: None,
+        enable_experimental: false,
+        layout_threads: 1,
+        exit_after_load: false,
+        output_file: None,
+        headless: true,
+        hard_fail: true,
+        bubble_inline_sizes_separately: false,
+        show_debug_borders: false,
+        enable_text_antialiasing: true,
+        trace_layout: false,
+        devtools_port: None,
+        initial_window_size: TypedSize2D(800, 600),
+        user_agent: None,
+        dump_flow_tree: false,
+        accept_encoding: default_accept_encoding(),
+        http_proxy: None,
+        https_proxy: None,
+        no_proxy: None,
+        max_connections_per_host: DEFAULT_MAX_CONNECTIONS_PER_HOST,
+        idle_connection_timeout: DEFAULT_IDLE_CONNECTION_TIMEOUT,
+        network_archive: LiveNetwork,
+        har_file: None,
+        hsts_preload_file: None,
+    }
+}
+
 static mut EXPERIMENTAL_ENABLED: bool = false;
 
 pub fn set_experimental_enabled(new_value: bool) {
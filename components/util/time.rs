@@ -58,6 +58,8 @@ pub enum TimeProfilerMsg {
     TimeMsg((TimeProfilerCategory, Option<TimerMetadata>), f64),
     /// Message used to force print the profiling metrics
     PrintMsg,
+    /// Request the profiling metrics gathered so far, e.g. for about:profile
+    GetBucketsMsg(Sender<Vec<TimeProfilerBucket>>),
     /// Tells the profiler to shut down.
     ExitMsg,
 }
@@ -114,6 +116,23 @@ impl Formatable for TimeProfilerCategory {
     }
 }
 
+/// The metrics of one category of events, as reported by `GetBucketsMsg`. Times are in
+/// milliseconds.
+#[deriving(Clone)]
+pub struct TimeProfilerBucket {
+    /// The name of the category, indented to show the categories it is part of.
+    pub category: String,
+    /// The URL, whether the first reflow and whether an iframe, for events with metadata.
+    pub url: Option<String>,
+    pub first_reflow: Option<bool>,
+    pub iframe: Option<bool>,
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    pub events: uint,
+}
+
 type TimeProfilerBuckets = TreeMap<(TimeProfilerCategory, Option<TimerMetadata>), Vec<f64>>;
 
 // back end of the profiler that handles data aggregation and performance metrics
@@ -150,6 +169,9 @@ impl TimeProfiler {
                     loop {
                         match port.recv_opt() {
                             Err(_) | Ok(ExitMsg) => break,
+                            Ok(GetBucketsMsg(chan)) => {
+                                let _ = chan.send_opt(vec!());
+                            }
                             _ => {}
                         }
                     }
@@ -199,34 +221,72 @@ impl TimeProfiler {
                 Some(TimeMsg(..)) => self.print_buckets(),
                 _ => ()
             },
+            GetBucketsMsg(chan) => {
+                // Reporting the metrics doesn't count as printing them.
+                let _ = chan.send_opt(self.buckets());
+                return true
+            }
             ExitMsg => return false,
         };
         self.last_msg = Some(msg);
         true
     }
 
+    /// The (mean, median, min, max) of a bucket's times, which are sorted in the process.
+    fn stats(data: &mut Vec<f64>) -> Option<(f64, f64, f64, f64)> {
+        data.sort_by(|a, b| {
+            if a < b {
+                Less
+            } else {
+                Greater
+            }
+        });
+        let data_len = data.len();
+        if data_len == 0 {
+            return None
+        }
+        Some((data.iter().map(|&x|x).sum() / (data_len as f64),
+              data.as_slice()[data_len / 2],
+              data.iter().fold(f64::INFINITY, |a, &b| a.min(b)),
+              data.iter().fold(-f64::INFINITY, |a, &b| a.max(b))))
+    }
+
+    fn buckets(&mut self) -> Vec<TimeProfilerBucket> {
+        let mut buckets = vec!();
+        for (&(ref category, ref meta), ref mut data) in self.buckets.iter_mut() {
+            match TimeProfiler::stats(*data) {
+                Some((mean, median, min, max)) => {
+                    buckets.push(TimeProfilerBucket {
+                        category: category.format(),
+                        url: meta.as_ref().map(|meta| meta.url.clone()),
+                        first_reflow: meta.as_ref().map(|meta| meta.first_reflow),
+                        iframe: meta.as_ref().map(|meta| meta.iframe),
+                        mean: mean,
+                        median: median,
+                        min: min,
+                        max: max,
+                        events: data.len(),
+                    })
+                }
+                None => {}
+            }
+        }
+        buckets
+    }
+
     fn print_buckets(&mut self) {
         println!("{:35s} {:14} {:9} {:30} {:15s} {:15s} {:-15s} {:-15s} {:-15s}",
                  "_category_", "_incremental?_", "_iframe?_",
                  "            _url_", "    _mean (ms)_", "  _median (ms)_",
                  "     _min (ms)_", "     _max (ms)_", "      _events_");
         for (&(ref category, ref meta), ref mut data) in self.buckets.iter_mut() {
-            data.sort_by(|a, b| {
-                if a < b {
-                    Less
-                } else {
-                    Greater
+            match TimeProfiler::stats(*data) {
+                Some((mean, median, min, max)) => {
+                    println!("{:-35s}{} {:15.4f} {:15.4f} {:15.4f} {:15.4f} {:15u}",
+                             category.format(), meta.format(), mean, median, min, max,
+                             data.len());
                 }
-            });
-            let data_len = data.len();
-            if data_len > 0 {
-                let (mean, median, min, max) =
-                    (data.iter().map(|&x|x).sum() / (data_len as f64),
-                     data.as_slice()[data_len / 2],
-                     data.iter().fold(f64::INFINITY, |a, &b| a.min(b)),
-                     data.iter().fold(-f64::INFINITY, |a, &b| a.max(b)));
-                println!("{:-35s}{} {:15.4f} {:15.4f} {:15.4f} {:15.4f} {:15u}",
-                         category.format(), meta.format(), mean, median, min, max, data_len);
+                None => {}
             }
         }
         println!("");
@@ -0,0 +1,102 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Answering HTTP authentication challenges from the embedding application, in the manner of
+//! `cef_request_handler_t::get_auth_credentials`.
+//!
+//! The port does not create browsers yet, so there is no `cef_client_t` to ask for a request
+//! handler; instead the application registers a callback directly. Without one, the user is
+//! asked through the window.
+
+use libc::{c_int, size_t};
+use servo_net::http_auth::{AuthRequest, Credentials};
+use servo_net::resource_task::{AuthPrompt, ResourceHandlers};
+use servo_util::task::spawn_named;
+use std::mem;
+use std::slice;
+use string::{cef_string_utf8_clear, cef_string_utf8_set};
+use types::cef_string_t;
+
+/// Fills in `username` and `password` and returns 1 to continue the request, or returns 0 to
+/// cancel it. Called on a task of its own, so it may block.
+pub type servo_get_auth_credentials_t = extern "C" fn(is_proxy: c_int,
+                                                      host: *const cef_string_t,
+                                                      port: c_int,
+                                                      realm: *const cef_string_t,
+                                                      username: *mut cef_string_t,
+                                                      password: *mut cef_string_t)
+                                                      -> c_int;
+
+static mut AUTH_CREDENTIALS_CALLBACK: Option<servo_get_auth_credentials_t> = None;
+
+#[no_mangle]
+pub extern "C" fn servo_set_auth_credentials_callback(callback: servo_get_auth_credentials_t) {
+    unsafe {
+        AUTH_CREDENTIALS_CALLBACK = Some(callback);
+    }
+}
+
+/// The resource handlers to run Servo with, including the registered callback if there is one.
+pub fn resource_handlers() -> ResourceHandlers {
+    let mut handlers = ResourceHandlers::new();
+    match unsafe { AUTH_CREDENTIALS_CALLBACK } {
+        Some(callback) => handlers.set_auth_prompt(box CefAuthPrompt { callback: callback }),
+        None => {}
+    }
+    handlers
+}
+
+struct CefAuthPrompt {
+    callback: servo_get_auth_credentials_t,
+}
+
+impl AuthPrompt for CefAuthPrompt {
+    fn prompt_for_credentials(&self, request: AuthRequest,
+                              response_chan: Sender<Option<Credentials>>) {
+        let callback = self.callback;
+        spawn_named("CefAuthPrompt", proc() {
+            response_chan.send(get_credentials(callback, &request));
+        });
+    }
+}
+
+fn get_credentials(callback: servo_get_auth_credentials_t, request: &AuthRequest)
+                   -> Option<Credentials> {
+    let host = new_cef_string(request.url.serialize_host().unwrap_or(String::new()).as_slice());
+    let realm = new_cef_string(request.realm.as_slice());
+    let port = request.url.port_or_default().unwrap_or(0);
+    let mut username = new_cef_string("");
+    let mut password = new_cef_string("");
+    let result = callback(request.is_proxy as c_int, &host, port as c_int, &realm,
+                          &mut username, &mut password);
+    let credentials = if result != 0 {
+        Some(Credentials {
+            username: from_cef_string(&username),
+            password: from_cef_string(&password),
+        })
+    } else {
+        None
+    };
+    for string in [host, realm, username, password].iter_mut() {
+        cef_string_utf8_clear(string);
+    }
+    credentials
+}
+
+fn new_cef_string(value: &str) -> cef_string_t {
+    let mut string: cef_string_t = unsafe { mem::zeroed() };
+    cef_string_utf8_set(value.as_ptr(), value.len() as size_t, &mut string, 1);
+    string
+}
+
+fn from_cef_string(string: &cef_string_t) -> String {
+    if string.str.is_null() {
+        return String::new()
+    }
+    unsafe {
+        slice::raw::buf_as_slice(string.str as *const u8, string.length as uint, |bytes| {
+            String::from_utf8_lossy(bytes).into_string()
+        })
+    }
+}
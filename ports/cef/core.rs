@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 
+use auth;
 use azure;
 use command_line::command_line_init;
 use eutil::fptr_is_null;
@@ -72,9 +73,18 @@ pub extern "C" fn cef_run_message_loop() {
         initial_window_size: TypedSize2D(800, 600),
         user_agent: None,
         dump_flow_tree: false,
+        accept_encoding: vec!("gzip".to_string(), "deflate".to_string()),
+        http_proxy: opts::proxy_env_var("http_proxy"),
+        https_proxy: opts::proxy_env_var("https_proxy"),
+        no_proxy: opts::proxy_env_var("no_proxy"),
+        max_connections_per_host: opts::DEFAULT_MAX_CONNECTIONS_PER_HOST,
+        idle_connection_timeout: opts::DEFAULT_IDLE_CONNECTION_TIMEOUT,
+        network_archive: opts::LiveNetwork,
+        har_file: None,
+        hsts_preload_file: None,
     };
     native::start(0, 0 as *const *const u8, proc() {
-       servo::run(opts);
+       servo::run_with_resource_handlers(opts, auth::resource_handlers());
     });
 }
 
@@ -44,6 +44,7 @@ extern crate core_graphics;
 #[cfg(target_os="macos")]
 extern crate core_text;
 
+pub mod auth;
 pub mod browser;
 pub mod command_line;
 pub mod core;
@@ -16,6 +16,7 @@ extern crate debug;
 
 extern crate compositing;
 extern crate devtools;
+extern crate devtools_traits;
 extern crate rustuv;
 extern crate "net" as servo_net;
 extern crate "msg" as servo_msg;
@@ -40,7 +41,11 @@ use script::dom::bindings::codegen::RegisterBindings;
 #[cfg(not(test))]
 use servo_net::image_cache_task::ImageCacheTask;
 #[cfg(not(test))]
-use servo_net::resource_task::new_resource_task;
+use devtools_traits::NetworkEventMsg;
+#[cfg(not(test))]
+use servo_net::resource_task::{new_resource_task, ResourceHandlers, AddNetworkObserver};
+#[cfg(not(test))]
+use servo_net::resource_task::{SetTimeProfiler, SetImageCache};
 #[cfg(not(test))]
 use gfx::font_cache_task::FontCacheTask;
 #[cfg(not(test))]
@@ -49,6 +54,8 @@ use servo_util::time::TimeProfiler;
 use servo_util::memory::MemoryProfiler;
 #[cfg(not(test))]
 use servo_util::opts;
+#[cfg(not(test))]
+use servo_util::task::spawn_named;
 
 #[cfg(not(test))]
 use green::GreenTaskBuilder;
@@ -85,6 +92,13 @@ pub extern "C" fn android_start(argc: int, argv: *const *const u8) -> int {
 
 #[cfg(not(test))]
 pub fn run(opts: opts::Opts) {
+    run_with_resource_handlers(opts, ResourceHandlers::new())
+}
+
+/// Run Servo, loading resources with the protocol handlers, request interceptors and
+/// authentication prompt registered by the embedder in addition to the built-in loaders.
+#[cfg(not(test))]
+pub fn run_with_resource_handlers(opts: opts::Opts, resource_handlers: ResourceHandlers) {
     ::servo_util::opts::set_experimental_enabled(opts.enable_experimental);
     RegisterBindings::RegisterProxyHandlers();
 
@@ -93,6 +107,11 @@ pub fn run(opts: opts::Opts) {
     let mut pool = green::SchedPool::new(pool_config);
 
     let (compositor_port, compositor_chan) = CompositorChan::new();
+    // Unless the embedder asks for credentials itself, ask the user through the window.
+    let mut resource_handlers = resource_handlers;
+    if !resource_handlers.has_auth_prompt() {
+        resource_handlers.set_auth_prompt(box compositor_chan.clone());
+    }
     let time_profiler_chan = TimeProfiler::create(opts.time_profiler_period);
     let memory_profiler_chan = MemoryProfiler::create(opts.memory_profiler_period);
     let devtools_chan = opts.devtools_port.map(|port| {
@@ -108,7 +127,23 @@ pub fn run(opts: opts::Opts) {
         .spawn(proc() {
         let opts = &opts_clone;
         // Create a Servo instance.
-        let resource_task = new_resource_task(opts.user_agent.clone());
+        let resource_task = new_resource_task(opts, resource_handlers);
+        // Show the loads made by the resource task in the devtools network monitor.
+        match devtools_chan {
+            Some(ref devtools_chan) => {
+                let (observer, events) = channel();
+                resource_task.send(AddNetworkObserver(observer));
+                let devtools_chan = devtools_chan.clone();
+                spawn_named("DevtoolsNetworkBridge", proc() {
+                    for event in events.iter() {
+                        if devtools_chan.send_opt(NetworkEventMsg(event)).is_err() {
+                            break
+                        }
+                    }
+                });
+            }
+            None => {}
+        }
         // If we are emitting an output file, then we need to block on
         // image load or we risk emitting an output file missing the
         // image.
@@ -117,6 +152,9 @@ pub fn run(opts: opts::Opts) {
             } else {
                 ImageCacheTask::new(resource_task.clone())
             };
+        // Let the about: pages describe the profiler's metrics and the image cache's contents.
+        resource_task.send(SetTimeProfiler(time_profiler_chan_clone.clone()));
+        resource_task.send(SetImageCache(image_cache_task.clone()));
         let font_cache_task = FontCacheTask::new(resource_task.clone());
         let constellation_chan = Constellation::<layout::layout_task::LayoutTask,
                                                  script::script_task::ScriptTask>::start(